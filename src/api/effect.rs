@@ -0,0 +1,11 @@
+// Efecto de la mezcla final. El motor procesa los efectos en el orden en que se
+// añaden, después de sumar las voces y antes de entregar el bloque.
+pub trait Effect: Send {
+    // Procesar un bloque estéreo en el sitio; ambos canales tienen la misma longitud
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]);
+
+    fn set_sample_rate(&mut self, sample_rate: f32);
+
+    // Vaciar el estado interno (colas, líneas de retardo) sin cambiar los ajustes
+    fn reset(&mut self) {}
+}
@@ -0,0 +1,194 @@
+use std::sync::Arc;
+use std::time::Instant;
+use super::effect::Effect;
+use super::params::ParameterError;
+use super::patch::Patch;
+use super::voices::VoiceManager;
+use crate::audio::stats::{block_peak, EngineStats};
+use crate::midi::{ALL_NOTES_OFF_CC, ALL_SOUND_OFF_CC};
+
+// Tamaño del bloque interno; los bloques más largos se procesan por partes
+const MAX_BLOCK_FRAMES: usize = 512;
+
+// Motor completo: voces, patch y cadena de efectos, sin dispositivo de audio ni GUI.
+// Quien lo incrusta llama a `process` desde su propio callback de audio.
+pub struct SynthEngine {
+    patch: Patch,
+    voices: VoiceManager,
+    effects: Vec<Box<dyn Effect>>,
+    // Buffers de trabajo reservados una sola vez
+    interleaved: Vec<f32>,
+    stats: Arc<EngineStats>,
+}
+
+impl SynthEngine {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut patch = Patch::default();
+        patch.fx.reverb.prepare(sample_rate);
+        Self {
+            patch,
+            voices: VoiceManager::new(sample_rate),
+            effects: Vec::new(),
+            interleaved: vec![0.0; MAX_BLOCK_FRAMES * 2],
+            stats: Arc::new(EngineStats::new()),
+        }
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.voices.sample_rate()
+    }
+
+    // Recalcula las particiones de la reverb: mejor llamarla fuera del callback de audio
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.voices.set_sample_rate(sample_rate);
+        for effect in self.effects.iter_mut() {
+            effect.set_sample_rate(sample_rate);
+        }
+        self.patch.fx.reverb.prepare(sample_rate);
+        self.voices.apply_patch(&self.patch);
+    }
+
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+
+    // Los cambios se aplican también a las notas que ya suenan. Si el patch no trae las
+    // particiones de la reverb para esta frecuencia (`ReverbSettings::prepare`) se
+    // calculan aquí, así que conviene prepararlo antes si se cambia desde el callback
+    pub fn set_patch(&mut self, mut patch: Patch) {
+        patch.fx.reverb.prepare(self.sample_rate());
+        self.patch = patch;
+        self.voices.apply_patch(&self.patch);
+    }
+
+    pub fn parameter(&self, id: u32) -> Result<f32, ParameterError> {
+        self.patch.parameter(id)
+    }
+
+    pub fn set_parameter(&mut self, id: u32, value: f32) -> Result<(), ParameterError> {
+        self.patch.set_parameter(id, value)?;
+        self.voices.apply_patch(&self.patch);
+        Ok(())
+    }
+
+    // Velocidad de 0.0 a 1.0; una velocidad nula equivale a soltar la nota
+    pub fn note_on(&mut self, note: u8, velocity: f32) {
+        let velocity = self.patch.velocity_response.apply(velocity);
+        if velocity > 0.0 {
+            self.voices.note_on(note, velocity, &self.patch);
+        } else {
+            self.voices.note_off(note);
+        }
+    }
+
+    pub fn note_off(&mut self, note: u8) {
+        self.voices.note_off(note);
+    }
+
+    // CC123 (All Notes Off) y CC120 (All Sound Off) silencian el motor; el resto son
+    // fuentes de la matriz de modulación o RPN (CC101/100/6/38)
+    pub fn control_change(&mut self, cc: u8, value: u8) {
+        match cc {
+            ALL_NOTES_OFF_CC => self.all_notes_off(),
+            ALL_SOUND_OFF_CC => self.reset(),
+            _ => {
+                self.voices.control_change(cc, value);
+            }
+        }
+    }
+
+    pub fn channel_pressure(&mut self, value: u8) {
+        self.voices.channel_pressure(value);
+    }
+
+    // De 0 a 16383 con el centro en 8192
+    pub fn pitch_bend(&mut self, value: u16) {
+        self.voices.pitch_bend(value);
+    }
+
+    pub fn poly_pressure(&mut self, note: u8, value: u8) {
+        self.voices.poly_pressure(note, value);
+    }
+
+    // Mensaje MIDI de canal completo, con su byte de estado, tal como lo entregan los
+    // formatos de plugin. El canal no importa; los mensajes de sistema se ignoran.
+    pub fn midi_message(&mut self, message: &[u8]) {
+        let Some(&status) = message.first() else {
+            return;
+        };
+        let data1 = message.get(1).copied().unwrap_or(0);
+        let data2 = message.get(2).copied().unwrap_or(0);
+        match status & 0xF0 {
+            0x80 => self.note_off(data1),
+            0x90 => self.note_on(data1, data2 as f32 / 127.0),
+            0xA0 => self.poly_pressure(data1, data2),
+            0xB0 => self.control_change(data1, data2),
+            0xD0 => self.channel_pressure(data1),
+            0xE0 => self.pitch_bend(((data2 as u16) << 7) | data1 as u16),
+            _ => {}
+        }
+    }
+
+    pub fn all_notes_off(&mut self) {
+        self.voices.all_notes_off();
+    }
+
+    // Silencio en unos milisegundos sin esperar a la liberación de cada voz
+    pub fn all_sound_off(&mut self) {
+        self.voices.kill_all();
+    }
+
+    // Silencio inmediato: corta las voces y vacía las colas de los efectos
+    pub fn reset(&mut self) {
+        self.voices.reset();
+        for effect in self.effects.iter_mut() {
+            effect.reset();
+        }
+    }
+
+    pub fn voices(&self) -> &VoiceManager {
+        &self.voices
+    }
+
+    pub fn voices_mut(&mut self) -> &mut VoiceManager {
+        &mut self.voices
+    }
+
+    // Voces, tiempo de proceso y pico de salida, que `process` actualiza en cada llamada.
+    // Se puede guardar una copia del `Arc` y leerla desde otro hilo sin bloquear el audio.
+    pub fn stats(&self) -> Arc<EngineStats> {
+        self.stats.clone()
+    }
+
+    pub fn add_effect(&mut self, mut effect: Box<dyn Effect>) {
+        effect.set_sample_rate(self.sample_rate());
+        self.effects.push(effect);
+    }
+
+    pub fn clear_effects(&mut self) {
+        self.effects.clear();
+    }
+
+    // Renderizar un bloque estéreo en dos canales separados de la misma longitud
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let started = Instant::now();
+        let frames = left.len().min(right.len());
+        let mut start = 0;
+        while start < frames {
+            let end = (start + MAX_BLOCK_FRAMES).min(frames);
+            let block = &mut self.interleaved[..(end - start) * 2];
+            let stolen = self.voices.render(self.patch.volume, block);
+            self.stats.add_stolen(stolen);
+            for (i, frame) in block.chunks_exact(2).enumerate() {
+                left[start + i] = frame[0];
+                right[start + i] = frame[1];
+            }
+            for effect in self.effects.iter_mut() {
+                effect.process(&mut left[start..end], &mut right[start..end]);
+            }
+            start = end;
+        }
+        let peak = block_peak(&left[..frames]).max(block_peak(&right[..frames]));
+        self.stats.record_callback(started.elapsed(), frames, self.sample_rate(), self.voices.active_voices(), peak);
+    }
+}
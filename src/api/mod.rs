@@ -0,0 +1,45 @@
+// API pública para incrustar el motor en otros proyectos de Rust, sin pasar por el
+// plugin VST3 ni por la interfaz gráfica.
+//
+// Estabilidad: los tipos que se exportan desde este módulo siguen versionado
+// semántico. Un cambio incompatible en ellos solo se publica con una versión mayor
+// del crate. El resto de módulos son internos y pueden cambiar en cualquier versión.
+mod effect;
+mod engine;
+mod params;
+mod patch;
+mod voices;
+
+pub use effect::Effect;
+pub use engine::SynthEngine;
+pub use params::{ParameterError, ParameterGroup, ParameterInfo, ParameterKind, PARAMETERS};
+pub(crate) use params::fx_slot_of;
+pub use patch::Patch;
+pub use voices::VoiceManager;
+
+// Tipos del motor que forman parte de un patch o de una voz
+pub use crate::dsp::wavetable::ADDITIVE_HARMONICS;
+pub use crate::dsp::{
+    BandFilterSettings, BandMode, ChipChannel, DutyCycle, FilterSettings, FilterSlope, FilterType, FormantSettings, GlideMode,
+    GlideSettings,
+    LfoSettings, LfoShape, LfoTrigger, ModCurve, ModDestination, ModMatrix, ModRoute, ModSource, Note, OscillatorSettings,
+    OscillatorSource, ParaphonicSettings, ParaphonicTrigger, PhaseMode, Quality, UnisonSettings, VectorSettings, VoicePanMode, VoicePanSettings, Vowel, WaveType,
+    MAX_OSCILLATORS, MAX_UNISON_VOICES,
+};
+pub use crate::audio::stats::{EngineStats, StatsSnapshot};
+pub use crate::dsp::modulation::MAX_MOD_ROUTES;
+pub use crate::dsp::fx::{EffectType, FxSettings, FxSlotSettings, MAX_FX_SLOTS};
+pub use crate::dsp::fx::compressor::CompressorSettings;
+pub use crate::dsp::fx::delay::{DelayDivision, DelaySettings};
+pub use crate::dsp::fx::distortion::{DistortionCurve, DistortionSettings};
+pub use crate::dsp::fx::eq::EqSettings;
+pub use crate::dsp::fx::impulse::ImpulseResponse;
+pub use crate::dsp::fx::reverb::{ReverbKernel, ReverbSettings};
+pub use crate::dsp::{SaturationMode, SaturationSettings};
+pub use crate::midi::velocity::{VelocityCurve, VelocityResponse};
+pub use crate::structs::envelope::AdsrSettings;
+pub use crate::structs::mseg::{MsegDestination, MsegPoint, MsegSettings, MAX_MSEG_POINTS};
+pub use crate::structs::split::SplitSettings;
+
+// Bloques de procesado reutilizables para efectos propios
+pub use crate::dsp::{Biquad, BiquadCoefficients};
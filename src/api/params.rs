@@ -0,0 +1,708 @@
+use std::fmt;
+use crate::dsp::chip::MAX_CHIP_BIT_DEPTH;
+use crate::dsp::filters::{BandMode, FilterSlope, FilterType, MAX_BAND_Q, MAX_CUTOFF, MIN_BAND_Q, MIN_CUTOFF};
+use crate::dsp::lfo::{LfoShape, LfoTrigger, MAX_LFO_PITCH_DEPTH, MAX_LFO_RATE, MIN_LFO_RATE};
+use crate::dsp::fx::compressor::{
+    MAX_COMPRESSOR_ATTACK, MAX_COMPRESSOR_MAKEUP, MAX_COMPRESSOR_RATIO, MAX_COMPRESSOR_RELEASE, MIN_COMPRESSOR_ATTACK,
+    MIN_COMPRESSOR_RELEASE, MIN_COMPRESSOR_THRESHOLD,
+};
+use crate::dsp::fx::delay::{DelayDivision, MAX_DELAY_FEEDBACK, MAX_DELAY_TIME, MIN_DELAY_TIME};
+use crate::dsp::fx::distortion::{DistortionCurve, MAX_DISTORTION_DRIVE, MAX_DISTORTION_TRIM};
+use crate::dsp::fx::eq::MAX_EQ_GAIN;
+use crate::dsp::fx::reverb::MAX_PRE_DELAY;
+use crate::dsp::fx::MAX_FX_SLOTS;
+use crate::dsp::saturation::{SaturationMode, MAX_SATURATION_DRIVE};
+use crate::dsp::{
+    ChipChannel, DutyCycle, EffectType, GlideMode, ModCurve, ModDestination, ModSource, ParaphonicTrigger, PhaseMode,
+    VoicePanMode, WaveType, MAX_GLIDE_TIME, MAX_OSCILLATORS, MAX_OSCILLATOR_DETUNE, MAX_UNISON_DETUNE, MAX_UNISON_VOICES,
+};
+use crate::midi::velocity::VelocityCurve;
+use crate::parameters::ParameterScale;
+use crate::structs::envelope::{MAX_ATTACK_TIME, MAX_DECAY_TIME, MAX_RELEASE_TIME, MIN_STAGE_TIME};
+use crate::structs::mseg::MsegDestination;
+use super::patch::Patch;
+use self::ParameterGroup::{Envelope, Filter, Fx, Global, Modulation, Oscillators};
+
+// Descripción de un parámetro automatizable del motor. Es el único registro: el plugin
+// VST3, el LV2, el AU y la API leen de aquí el nombre, el rango y la escala de cada uno.
+// Los identificadores son estables: no se reutilizan ni se renumeran entre versiones.
+#[derive(Clone, Copy, Debug)]
+pub struct ParameterInfo {
+    pub id: u32,
+    pub name: &'static str,
+    // Nombre corto para pantallas pequeñas y superficies de control
+    pub short_name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub unit: &'static str,
+    // Los tiempos y frecuencias se reparten en escala logarítmica sobre el rango normalizado
+    pub logarithmic: bool,
+    pub kind: ParameterKind,
+    pub group: ParameterGroup,
+}
+
+// Tipo de valor de un parámetro, que decide cómo lo muestra y lo redondea el host
+#[derive(Clone, Copy, Debug)]
+pub enum ParameterKind {
+    Continuous,
+    // De 0.0 al máximo, mostrado en porcentaje
+    Percent,
+    // De -1.0 a 1.0, mostrado en porcentaje con signo
+    Bipolar,
+    // Panorama de -1.0 (izquierda) a 1.0 (derecha)
+    Pan,
+    Toggle,
+    Integer,
+    // Lista de opciones de 0 a max, con el nombre de cada índice
+    Choice(fn(usize) -> &'static str),
+}
+
+// Sección del sintetizador a la que pertenece un parámetro; los hosts la muestran como
+// grupo (unidad VST3, clump del AU)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterGroup {
+    Oscillators = 1,
+    Envelope,
+    Filter,
+    Modulation,
+    Fx,
+    Global,
+}
+
+impl ParameterGroup {
+    pub const ALL: [ParameterGroup; 6] = [
+        ParameterGroup::Oscillators,
+        ParameterGroup::Envelope,
+        ParameterGroup::Filter,
+        ParameterGroup::Modulation,
+        ParameterGroup::Fx,
+        ParameterGroup::Global,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParameterGroup::Oscillators => "Osciladores",
+            ParameterGroup::Envelope => "Envolvente",
+            ParameterGroup::Filter => "Filtro",
+            ParameterGroup::Modulation => "Modulación",
+            ParameterGroup::Fx => "Efectos",
+            ParameterGroup::Global => "Global",
+        }
+    }
+}
+
+impl ParameterInfo {
+    pub fn find(id: u32) -> Option<&'static ParameterInfo> {
+        PARAMETERS.iter().find(|info| info.id == id)
+    }
+
+    // Valor real a partir de un valor normalizado (0..1), como lo envía un host
+    pub fn from_normalized(&self, value: f32) -> f32 {
+        ParameterScale::from(self).to_plain(value as f64) as f32
+    }
+
+    pub fn to_normalized(&self, value: f32) -> f32 {
+        ParameterScale::from(self).to_normalized(value as f64) as f32
+    }
+
+    // Pasos discretos del rango: opciones menos una en las listas, 1 en los
+    // interruptores y 0 en los continuos
+    pub fn step_count(&self) -> u32 {
+        match self.kind {
+            ParameterKind::Toggle => 1,
+            ParameterKind::Integer | ParameterKind::Choice(_) => (self.max - self.min) as u32,
+            _ => 0,
+        }
+    }
+}
+
+const fn continuous(
+    id: u32, name: &'static str, short_name: &'static str, group: ParameterGroup, min: f32, max: f32, default: f32,
+    unit: &'static str,
+) -> ParameterInfo {
+    ParameterInfo { id, name, short_name, min, max, default, unit, logarithmic: false, kind: ParameterKind::Continuous, group }
+}
+
+const fn log(
+    id: u32, name: &'static str, short_name: &'static str, group: ParameterGroup, min: f32, max: f32, default: f32,
+    unit: &'static str,
+) -> ParameterInfo {
+    ParameterInfo { id, name, short_name, min, max, default, unit, logarithmic: true, kind: ParameterKind::Continuous, group }
+}
+
+const fn percent(
+    id: u32, name: &'static str, short_name: &'static str, group: ParameterGroup, max: f32, default: f32,
+) -> ParameterInfo {
+    ParameterInfo {
+        id, name, short_name, min: 0.0, max, default, unit: "", logarithmic: false, kind: ParameterKind::Percent, group,
+    }
+}
+
+const fn bipolar(
+    id: u32, name: &'static str, short_name: &'static str, group: ParameterGroup, default: f32,
+) -> ParameterInfo {
+    ParameterInfo {
+        id, name, short_name, min: -1.0, max: 1.0, default, unit: "", logarithmic: false, kind: ParameterKind::Bipolar, group,
+    }
+}
+
+const fn pan(id: u32, name: &'static str, short_name: &'static str, group: ParameterGroup) -> ParameterInfo {
+    ParameterInfo {
+        id, name, short_name, min: -1.0, max: 1.0, default: 0.0, unit: "", logarithmic: false, kind: ParameterKind::Pan, group,
+    }
+}
+
+const fn toggle(
+    id: u32, name: &'static str, short_name: &'static str, group: ParameterGroup, default: bool,
+) -> ParameterInfo {
+    let default = if default { 1.0 } else { 0.0 };
+    ParameterInfo {
+        id, name, short_name, min: 0.0, max: 1.0, default, unit: "", logarithmic: false, kind: ParameterKind::Toggle, group,
+    }
+}
+
+const fn integer(
+    id: u32, name: &'static str, short_name: &'static str, group: ParameterGroup, min: i32, max: i32, default: i32,
+    unit: &'static str,
+) -> ParameterInfo {
+    ParameterInfo {
+        id, name, short_name, min: min as f32, max: max as f32, default: default as f32, unit, logarithmic: false,
+        kind: ParameterKind::Integer, group,
+    }
+}
+
+const fn choice(
+    id: u32, name: &'static str, short_name: &'static str, group: ParameterGroup, names: fn(usize) -> &'static str,
+    count: usize, default: usize,
+) -> ParameterInfo {
+    ParameterInfo {
+        id, name, short_name, min: 0.0, max: (count - 1) as f32, default: default as f32, unit: "", logarithmic: false,
+        kind: ParameterKind::Choice(names), group,
+    }
+}
+
+// Los parámetros de las ranuras del rack se registran a partir de este ID, uno por cada
+// FxSlotParameter en cada ranura, para que no cambien al añadir parámetros fijos
+const FX_SLOT_PARAMETER_BASE: u32 = 1000;
+
+// Parámetros automatizables de cada ranura del rack
+#[derive(Clone, Copy, PartialEq)]
+enum FxSlotParameter {
+    Bypass,
+    Mix,
+}
+
+impl FxSlotParameter {
+    const ALL: [FxSlotParameter; 2] = [FxSlotParameter::Bypass, FxSlotParameter::Mix];
+
+    // Ranura y parámetro a partir de un ID, o None si no es de una ranura
+    fn from_id(id: u32) -> Option<(usize, FxSlotParameter)> {
+        let offset = id.checked_sub(FX_SLOT_PARAMETER_BASE)? as usize;
+        let slot = offset / Self::ALL.len();
+        (slot < MAX_FX_SLOTS).then(|| (slot, Self::ALL[offset % Self::ALL.len()]))
+    }
+}
+
+// Ranura del rack a la que pertenece un parámetro (su bypass o su mezcla), o None si no
+// es de una ranura
+pub(crate) fn fx_slot_of(id: u32) -> Option<usize> {
+    FxSlotParameter::from_id(id).map(|(slot, _)| slot)
+}
+
+// Registro de parámetros con valores en sus unidades reales (no normalizados), en el
+// orden en que los lista el host. Los de las ranuras del rack se registran todos aunque
+// estén vacías, para que la lista no cambie mientras el host la tiene.
+pub const PARAMETERS: &[ParameterInfo] = &[
+    choice(0, "Osc 1 Wave", "Wave1", Oscillators, |index| WaveType::ALL[index].as_str(), WaveType::ALL.len(), 0),
+    log(1, "Attack", "Atk", Envelope, MIN_STAGE_TIME, MAX_ATTACK_TIME, 0.01, "s"),
+    log(2, "Decay", "Dec", Envelope, MIN_STAGE_TIME, MAX_DECAY_TIME, 0.1, "s"),
+    percent(3, "Sustain", "Sus", Envelope, 1.0, 0.7),
+    log(4, "Release", "Rel", Envelope, MIN_STAGE_TIME, MAX_RELEASE_TIME, 0.3, "s"),
+    percent(5, "Wavetable Position", "WT Pos", Oscillators, 1.0, 0.0),
+    continuous(6, "Wavetable Sweep", "WT Swp", Oscillators, -2.0, 2.0, 0.0, "pos/s"),
+    integer(7, "Osc 1 Octave", "Oct1", Oscillators, -2, 2, 0, "oct"),
+    integer(8, "Osc 2 Octave", "Oct2", Oscillators, -2, 2, 0, "oct"),
+    percent(9, "Osc 1 Drift", "Drft1", Oscillators, 1.0, 0.0),
+    percent(10, "Osc 2 Drift", "Drft2", Oscillators, 1.0, 0.0),
+    choice(11, "Osc 1 Phase Mode", "Phs1", Oscillators, |index| PhaseMode::ALL[index].as_str(), PhaseMode::ALL.len(), 0),
+    choice(12, "Osc 2 Phase Mode", "Phs2", Oscillators, |index| PhaseMode::ALL[index].as_str(), PhaseMode::ALL.len(), 0),
+    percent(13, "Voice Drive", "Drive", Global, 1.0, 0.0),
+    percent(14, "Voice Drive Velocity", "DrvVel", Global, 1.0, 0.0),
+    percent(15, "Voice Filter Env Depth", "FEnv", Filter, 1.0, 0.0),
+    integer(16, "Oscillator Count", "Oscs", Oscillators, 1, MAX_OSCILLATORS as i32, 2, ""),
+    pan(17, "Osc 1 Pan", "Pan1", Oscillators),
+    pan(18, "Osc 2 Pan", "Pan2", Oscillators),
+    percent(19, "Osc 1 Fold", "Fold1", Oscillators, 1.0, 0.0),
+    percent(20, "Osc 2 Fold", "Fold2", Oscillators, 1.0, 0.0),
+    // Los ajustes de chip se aplican a los cuatro osciladores a la vez
+    choice(21, "Chip Channel", "ChipCh", Oscillators, |index| ChipChannel::ALL[index].as_str(), ChipChannel::ALL.len(), 0),
+    choice(22, "Chip Duty Cycle", "Duty", Oscillators, |index| DutyCycle::ALL[index].as_str(), DutyCycle::ALL.len(), 2),
+    integer(23, "Chip Bit Depth", "Bits", Oscillators, 1, MAX_CHIP_BIT_DEPTH as i32, 4, "bits"),
+    toggle(24, "Vector Mode", "Vector", Oscillators, false),
+    percent(25, "Vector X", "VecX", Oscillators, 1.0, 0.5),
+    percent(26, "Vector Y", "VecY", Oscillators, 1.0, 0.5),
+    log(27, "Filter Cutoff", "Cutoff", Filter, MIN_CUTOFF, MAX_CUTOFF, MAX_CUTOFF, "Hz"),
+    percent(28, "Filter Resonance", "Reso", Filter, 1.0, 0.0),
+    choice(29, "Filter Type", "FltType", Filter, |index| FilterType::ALL[index].as_str(), FilterType::ALL.len(), 0),
+    bipolar(30, "Filter Env Amount", "FltEnv", Filter, 0.0),
+    log(31, "Filter Env Attack", "FAtk", Filter, MIN_STAGE_TIME, MAX_ATTACK_TIME, 0.01, "s"),
+    log(32, "Filter Env Decay", "FDec", Filter, MIN_STAGE_TIME, MAX_DECAY_TIME, 0.1, "s"),
+    percent(33, "Filter Env Sustain", "FSus", Filter, 1.0, 0.7),
+    log(34, "Filter Env Release", "FRel", Filter, MIN_STAGE_TIME, MAX_RELEASE_TIME, 0.3, "s"),
+    toggle(35, "Band Filter", "Band", Filter, false),
+    choice(36, "Band Mode", "Band Mode", Filter, |index| BandMode::ALL[index].as_str(), BandMode::ALL.len(), 0),
+    log(37, "Band Frequency", "Band Freq", Filter, MIN_CUTOFF, MAX_CUTOFF, 1000.0, "Hz"),
+    log(38, "Band Q", "Band Q", Filter, MIN_BAND_Q, MAX_BAND_Q, 1.0, ""),
+    choice(39, "Filter Slope", "Slope", Filter, |index| FilterSlope::ALL[index].as_str(), FilterSlope::ALL.len(), 1),
+    log(40, "Master Low Pass", "Master LP", Global, MIN_CUTOFF, MAX_CUTOFF, MAX_CUTOFF, "Hz"),
+    log(41, "Master High Pass", "Master HP", Global, MIN_CUTOFF, MAX_CUTOFF, MIN_CUTOFF, "Hz"),
+    toggle(42, "Formant Filter", "Formant", Filter, false),
+    percent(43, "Formant Morph", "Vowel", Filter, 1.0, 0.0),
+    choice(44, "LFO Shape", "LFO Shape", Modulation, |index| LfoShape::ALL[index].as_str(), LfoShape::ALL.len(), 0),
+    log(45, "LFO Rate", "LFO Rate", Modulation, MIN_LFO_RATE, MAX_LFO_RATE, 5.0, "Hz"),
+    bipolar(46, "Filter Velocity", "Flt Vel", Filter, 0.0),
+    percent(47, "Filter LFO", "Flt LFO", Filter, 1.0, 0.0),
+    choice(48, "Velocity Curve", "Vel Curve", Global, |index| VelocityCurve::ALL[index].as_str(), VelocityCurve::ALL.len(), 0),
+    percent(49, "Velocity Sensitivity", "Vel Sens", Global, 1.0, 1.0),
+    toggle(50, "MSEG", "MSEG", Modulation, false),
+    choice(
+        51, "MSEG Destination", "MSEG Dest", Modulation, |index| MsegDestination::ALL[index].as_str(),
+        MsegDestination::ALL.len(), 1,
+    ),
+    bipolar(52, "MSEG Amount", "MSEG Amt", Modulation, 0.5),
+    percent(53, "Amp Env Key Tracking", "Amp KeyTrk", Envelope, 1.0, 0.0),
+    percent(54, "Filter Env Key Tracking", "Flt KeyTrk", Filter, 1.0, 0.0),
+    continuous(55, "LFO Pitch Depth", "Vibrato", Modulation, 0.0, MAX_LFO_PITCH_DEPTH, 0.0, "st"),
+    choice(56, "LFO Trigger", "LFO Trig", Modulation, |index| LfoTrigger::ALL[index].as_str(), LfoTrigger::ALL.len(), 0),
+    continuous(57, "LFO Start Phase", "LFO Phase", Modulation, 0.0, 360.0, 0.0, "°"),
+    // Primera ruta de la matriz de modulación
+    integer(58, "Mod CC", "Mod CC", Modulation, 0, 127, 1, "CC"),
+    choice(
+        59, "Mod Destination", "Mod Dest", Modulation, |index| ModDestination::ALL[index].as_str(),
+        ModDestination::ALL.len(), 4,
+    ),
+    bipolar(60, "Mod Amount", "Mod Amt", Modulation, 0.5),
+    choice(61, "Mod Source", "Mod Src", Modulation, |index| ModSource::ALL[index].as_str(), ModSource::ALL.len(), 0),
+    percent(62, "Mod Random Depth", "Rnd Depth", Modulation, 1.0, 1.0),
+    choice(63, "Mod Curve", "Mod Curve", Modulation, |index| ModCurve::ALL[index].as_str(), ModCurve::ALL.len(), 0),
+    toggle(64, "LFO One Shot", "LFO 1Shot", Modulation, false),
+    toggle(65, "Delay", "Delay", Fx, false),
+    log(66, "Delay Time", "Dly Time", Fx, MIN_DELAY_TIME, MAX_DELAY_TIME, 375.0, "ms"),
+    toggle(67, "Delay Sync", "Dly Sync", Fx, false),
+    choice(68, "Delay Division", "Dly Div", Fx, |index| DelayDivision::ALL[index].as_str(), DelayDivision::ALL.len(), 3),
+    continuous(69, "Delay BPM", "Dly BPM", Fx, 30.0, 300.0, 120.0, "BPM"),
+    percent(70, "Delay Feedback", "Dly Fdbk", Fx, MAX_DELAY_FEEDBACK, 0.4),
+    percent(71, "Delay Damping", "Dly Damp", Fx, 1.0, 0.3),
+    toggle(72, "Delay Ping-Pong", "Dly PPong", Fx, false),
+    percent(73, "Delay Mix", "Dly Mix", Fx, 1.0, 0.3),
+    toggle(74, "Distortion", "Dist", Fx, false),
+    choice(
+        75, "Distortion Curve", "Dist Crv", Fx, |index| DistortionCurve::ALL[index].as_str(), DistortionCurve::ALL.len(), 0,
+    ),
+    continuous(76, "Distortion Drive", "Dist Drv", Fx, 0.0, MAX_DISTORTION_DRIVE, 12.0, "dB"),
+    continuous(77, "Distortion Trim", "Dist Trim", Fx, -MAX_DISTORTION_TRIM, MAX_DISTORTION_TRIM, -6.0, "dB"),
+    percent(78, "Distortion Mix", "Dist Mix", Fx, 1.0, 1.0),
+    toggle(79, "EQ", "EQ", Fx, false),
+    log(80, "EQ Low Freq", "EQ Lo Frq", Fx, MIN_CUTOFF, MAX_CUTOFF, 120.0, "Hz"),
+    continuous(81, "EQ Low Gain", "EQ Lo Gain", Fx, -MAX_EQ_GAIN, MAX_EQ_GAIN, 0.0, "dB"),
+    log(82, "EQ Mid Freq", "EQ Mid Frq", Fx, MIN_CUTOFF, MAX_CUTOFF, 1000.0, "Hz"),
+    continuous(83, "EQ Mid Gain", "EQ Mid Gain", Fx, -MAX_EQ_GAIN, MAX_EQ_GAIN, 0.0, "dB"),
+    log(84, "EQ Mid Q", "EQ Mid Q", Fx, MIN_BAND_Q, MAX_BAND_Q, 1.0, ""),
+    log(85, "EQ High Freq", "EQ Hi Frq", Fx, MIN_CUTOFF, MAX_CUTOFF, 8000.0, "Hz"),
+    continuous(86, "EQ High Gain", "EQ Hi Gain", Fx, -MAX_EQ_GAIN, MAX_EQ_GAIN, 0.0, "dB"),
+    toggle(87, "Compressor", "Comp", Fx, false),
+    continuous(88, "Compressor Threshold", "Comp Thr", Fx, MIN_COMPRESSOR_THRESHOLD, 0.0, -18.0, "dB"),
+    log(89, "Compressor Ratio", "Comp Ratio", Fx, 1.0, MAX_COMPRESSOR_RATIO, 4.0, ":1"),
+    log(90, "Compressor Attack", "Comp Atk", Fx, MIN_COMPRESSOR_ATTACK, MAX_COMPRESSOR_ATTACK, 10.0, "ms"),
+    log(91, "Compressor Release", "Comp Rel", Fx, MIN_COMPRESSOR_RELEASE, MAX_COMPRESSOR_RELEASE, 150.0, "ms"),
+    continuous(92, "Compressor Makeup", "Comp Gain", Fx, 0.0, MAX_COMPRESSOR_MAKEUP, 0.0, "dB"),
+    toggle(93, "Reverb", "Reverb", Fx, false),
+    continuous(94, "Reverb Pre-Delay", "Rev PreDly", Fx, 0.0, MAX_PRE_DELAY, 10.0, "ms"),
+    percent(95, "Reverb Mix", "Rev Mix", Fx, 1.0, 0.25),
+    choice(96, "Saturation", "Sat", Global, |index| SaturationMode::ALL[index].as_str(), SaturationMode::ALL.len(), 0),
+    continuous(97, "Saturation Drive", "Sat Drive", Global, 0.0, MAX_SATURATION_DRIVE, 0.0, "dB"),
+    // Onda de los osciladores 2 a 4; la del primero es el parámetro 0
+    choice(98, "Osc 2 Wave", "Wave2", Oscillators, |index| WaveType::ALL[index].as_str(), WaveType::ALL.len(), 0),
+    choice(99, "Osc 3 Wave", "Wave3", Oscillators, |index| WaveType::ALL[index].as_str(), WaveType::ALL.len(), 0),
+    choice(100, "Osc 4 Wave", "Wave4", Oscillators, |index| WaveType::ALL[index].as_str(), WaveType::ALL.len(), 0),
+    // Volumen y desafinación de cada oscilador, para que el modo vectorial pueda
+    // ajustar los cuatro desde el host
+    percent(101, "Osc 1 Volume", "Vol1", Oscillators, 1.0, 1.0),
+    percent(102, "Osc 2 Volume", "Vol2", Oscillators, 1.0, 0.5),
+    percent(103, "Osc 3 Volume", "Vol3", Oscillators, 1.0, 0.5),
+    percent(104, "Osc 4 Volume", "Vol4", Oscillators, 1.0, 0.5),
+    continuous(105, "Osc 1 Detune", "Dtn1", Oscillators, -MAX_OSCILLATOR_DETUNE, MAX_OSCILLATOR_DETUNE, 0.0, "st"),
+    continuous(106, "Osc 2 Detune", "Dtn2", Oscillators, -MAX_OSCILLATOR_DETUNE, MAX_OSCILLATOR_DETUNE, 0.0, "st"),
+    continuous(107, "Osc 3 Detune", "Dtn3", Oscillators, -MAX_OSCILLATOR_DETUNE, MAX_OSCILLATOR_DETUNE, 0.0, "st"),
+    continuous(108, "Osc 4 Detune", "Dtn4", Oscillators, -MAX_OSCILLATOR_DETUNE, MAX_OSCILLATOR_DETUNE, 0.0, "st"),
+    percent(109, "Volume", "Vol", Global, 1.0, 0.5),
+    // Reparto de las notas entre las voces: mono y legato, glide, unísono, panorama por
+    // voz, modo parafónico y teclado dividido
+    toggle(110, "Legato", "Legato", Global, false),
+    toggle(111, "Legato Retrigger Amp", "Leg Amp", Global, false),
+    toggle(112, "Legato Retrigger Mod", "Leg Mod", Global, false),
+    choice(113, "Glide Mode", "Glide", Global, |index| GlideMode::ALL[index].as_str(), GlideMode::ALL.len(), 0),
+    log(114, "Glide Time", "Glide Time", Global, MIN_STAGE_TIME, MAX_GLIDE_TIME, 0.1, "s"),
+    integer(115, "Unison Voices", "Unison", Global, 1, MAX_UNISON_VOICES as i32, 1, ""),
+    continuous(116, "Unison Detune", "Uni Dtn", Global, 0.0, MAX_UNISON_DETUNE, 0.2, "st"),
+    percent(117, "Unison Spread", "Uni Sprd", Global, 1.0, 0.5),
+    choice(118, "Voice Pan Mode", "VPan Mode", Global, |index| VoicePanMode::ALL[index].as_str(), VoicePanMode::ALL.len(), 0),
+    pan(119, "Voice Pan", "VPan", Global),
+    percent(120, "Voice Pan Spread", "VPan Sprd", Global, 1.0, 0.5),
+    toggle(121, "Paraphonic", "Para", Envelope, false),
+    choice(
+        122, "Paraphonic Trigger", "Para Trig", Envelope, |index| ParaphonicTrigger::ALL[index].as_str(),
+        ParaphonicTrigger::ALL.len(), 0,
+    ),
+    toggle(123, "Split", "Split", Global, false),
+    integer(124, "Split Note", "Split Note", Global, 0, 127, 60, ""),
+    choice(125, "Lower Wave", "Low Wave", Oscillators, |index| WaveType::ALL[index].as_str(), WaveType::ALL.len(), 3),
+    percent(126, "Lower Volume", "Low Vol", Global, 1.0, 1.0),
+    percent(127, "Upper Volume", "Up Vol", Global, 1.0, 1.0),
+    // Ranuras del rack de efectos
+    toggle(1000, "FX 1 Bypass", "FX1 Byp", Fx, false),
+    percent(1001, "FX 1 Mix", "FX1 Mix", Fx, 1.0, 1.0),
+    toggle(1002, "FX 2 Bypass", "FX2 Byp", Fx, false),
+    percent(1003, "FX 2 Mix", "FX2 Mix", Fx, 1.0, 1.0),
+    toggle(1004, "FX 3 Bypass", "FX3 Byp", Fx, false),
+    percent(1005, "FX 3 Mix", "FX3 Mix", Fx, 1.0, 1.0),
+    toggle(1006, "FX 4 Bypass", "FX4 Byp", Fx, false),
+    percent(1007, "FX 4 Mix", "FX4 Mix", Fx, 1.0, 1.0),
+    toggle(1008, "FX 5 Bypass", "FX5 Byp", Fx, false),
+    percent(1009, "FX 5 Mix", "FX5 Mix", Fx, 1.0, 1.0),
+    toggle(1010, "FX 6 Bypass", "FX6 Byp", Fx, false),
+    percent(1011, "FX 6 Mix", "FX6 Mix", Fx, 1.0, 1.0),
+    toggle(1012, "FX 7 Bypass", "FX7 Byp", Fx, false),
+    percent(1013, "FX 7 Mix", "FX7 Mix", Fx, 1.0, 1.0),
+    toggle(1014, "FX 8 Bypass", "FX8 Byp", Fx, false),
+    percent(1015, "FX 8 Mix", "FX8 Mix", Fx, 1.0, 1.0),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterError {
+    UnknownId(u32),
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterError::UnknownId(id) => write!(f, "parámetro desconocido: {}", id),
+        }
+    }
+}
+
+impl std::error::Error for ParameterError {}
+
+// Índice de una forma de onda en la lista de opciones de los parámetros de onda
+fn wave_index(wave_type: WaveType) -> f32 {
+    WaveType::ALL.iter().position(|wave| *wave == wave_type).unwrap_or(0) as f32
+}
+
+impl Patch {
+    pub fn parameter(&self, id: u32) -> Result<f32, ParameterError> {
+        // Una ranura vacía devuelve el valor por defecto de una ranura nueva
+        if let Some((slot, parameter)) = FxSlotParameter::from_id(id) {
+            let slot = self.fx.slots.get(slot);
+            return Ok(match parameter {
+                FxSlotParameter::Bypass => if slot.is_some_and(|slot| slot.bypass) { 1.0 } else { 0.0 },
+                FxSlotParameter::Mix => slot.map_or(1.0, |slot| slot.mix),
+            });
+        }
+        let value = match id {
+            0 => wave_index(self.oscillators[0].wave_type),
+            1 => self.envelope.attack,
+            2 => self.envelope.decay,
+            3 => self.envelope.sustain,
+            4 => self.envelope.release,
+            5 => self.wavetable_position,
+            6 => self.wavetable_sweep,
+            7 => self.oscillators[0].octave as f32,
+            8 => self.oscillators[1].octave as f32,
+            9 => self.oscillators[0].drift,
+            10 => self.oscillators[1].drift,
+            11 => self.oscillators[0].phase_mode as u8 as f32,
+            12 => self.oscillators[1].phase_mode as u8 as f32,
+            13 => self.voice_drive,
+            14 => self.voice_drive_velocity,
+            15 => self.voice_filter_env_depth,
+            16 => self.oscillator_count.clamp(1, MAX_OSCILLATORS) as f32,
+            17 => self.oscillators[0].pan,
+            18 => self.oscillators[1].pan,
+            19 => self.oscillators[0].fold,
+            20 => self.oscillators[1].fold,
+            21 => self.oscillators[0].chip_channel as u8 as f32,
+            22 => self.oscillators[0].duty_cycle as u8 as f32,
+            23 => self.oscillators[0].bit_depth.clamp(1, MAX_CHIP_BIT_DEPTH) as f32,
+            24 => if self.vector.enabled { 1.0 } else { 0.0 },
+            25 => self.vector.x,
+            26 => self.vector.y,
+            27 => self.filter.cutoff,
+            28 => self.filter.resonance,
+            29 => self.filter.filter_type as u8 as f32,
+            30 => self.filter.env_amount,
+            31 => self.filter_envelope.attack,
+            32 => self.filter_envelope.decay,
+            33 => self.filter_envelope.sustain,
+            34 => self.filter_envelope.release,
+            35 => if self.filter.band.enabled { 1.0 } else { 0.0 },
+            36 => self.filter.band.mode as u8 as f32,
+            37 => self.filter.band.frequency,
+            38 => self.filter.band.q,
+            39 => self.filter.slope as u8 as f32,
+            40 => self.master_filter.lowpass_cutoff,
+            41 => self.master_filter.highpass_cutoff,
+            42 => if self.filter.formant.enabled { 1.0 } else { 0.0 },
+            43 => self.filter.formant.morph,
+            44 => self.lfo.shape as u8 as f32,
+            45 => self.lfo.rate,
+            46 => self.filter.velocity_amount,
+            47 => self.filter.lfo_amount,
+            48 => self.velocity_response.curve as u8 as f32,
+            49 => self.velocity_response.sensitivity,
+            50 => if self.mseg.enabled { 1.0 } else { 0.0 },
+            51 => self.mseg.destination as u8 as f32,
+            52 => self.mseg.amount,
+            53 => self.envelope.key_tracking,
+            54 => self.filter_envelope.key_tracking,
+            55 => self.lfo.pitch_depth,
+            56 => self.lfo.trigger as u8 as f32,
+            // El LFO guarda la fase como fracción de ciclo; el parámetro va en grados
+            57 => self.lfo.start_phase * 360.0,
+            58 => self.mod_matrix.routes[0].cc as f32,
+            59 => self.mod_matrix.routes[0].destination as u8 as f32,
+            60 => self.mod_matrix.routes[0].amount,
+            61 => self.mod_matrix.routes[0].source as u8 as f32,
+            62 => self.mod_matrix.random_depth,
+            63 => self.mod_matrix.routes[0].curve as u8 as f32,
+            64 => if self.lfo.one_shot { 1.0 } else { 0.0 },
+            65 => if self.fx.contains(EffectType::Delay) { 1.0 } else { 0.0 },
+            66 => self.fx.delay.time,
+            67 => if self.fx.delay.tempo_sync { 1.0 } else { 0.0 },
+            68 => self.fx.delay.division as u8 as f32,
+            69 => self.fx.delay.bpm,
+            70 => self.fx.delay.feedback,
+            71 => self.fx.delay.damping,
+            72 => if self.fx.delay.ping_pong { 1.0 } else { 0.0 },
+            73 => self.fx.delay.mix,
+            74 => if self.fx.contains(EffectType::Distortion) { 1.0 } else { 0.0 },
+            75 => self.fx.distortion.curve as u8 as f32,
+            76 => self.fx.distortion.drive,
+            77 => self.fx.distortion.trim,
+            78 => self.fx.distortion.mix,
+            79 => if self.fx.contains(EffectType::Eq) { 1.0 } else { 0.0 },
+            80 => self.fx.eq.low_frequency,
+            81 => self.fx.eq.low_gain,
+            82 => self.fx.eq.mid_frequency,
+            83 => self.fx.eq.mid_gain,
+            84 => self.fx.eq.mid_q,
+            85 => self.fx.eq.high_frequency,
+            86 => self.fx.eq.high_gain,
+            87 => if self.fx.contains(EffectType::Compressor) { 1.0 } else { 0.0 },
+            88 => self.fx.compressor.threshold,
+            89 => self.fx.compressor.ratio,
+            90 => self.fx.compressor.attack,
+            91 => self.fx.compressor.release,
+            92 => self.fx.compressor.makeup,
+            93 => if self.fx.contains(EffectType::Reverb) { 1.0 } else { 0.0 },
+            94 => self.fx.reverb.pre_delay,
+            95 => self.fx.reverb.mix,
+            96 => self.saturation.mode as u8 as f32,
+            97 => self.saturation.drive,
+            98..=100 => wave_index(self.oscillators[id as usize - 97].wave_type),
+            101..=104 => self.oscillators[id as usize - 101].volume,
+            105..=108 => self.oscillators[id as usize - 105].detune,
+            109 => self.volume,
+            110 => if self.legato { 1.0 } else { 0.0 },
+            111 => if self.legato_retrigger_amp { 1.0 } else { 0.0 },
+            112 => if self.legato_retrigger_mod { 1.0 } else { 0.0 },
+            113 => self.glide.mode as u8 as f32,
+            114 => self.glide.time,
+            115 => self.unison.voice_count() as f32,
+            116 => self.unison.detune,
+            117 => self.unison.spread,
+            118 => self.voice_pan.mode as u8 as f32,
+            119 => self.voice_pan.pan,
+            120 => self.voice_pan.spread,
+            121 => if self.paraphonic.enabled { 1.0 } else { 0.0 },
+            122 => self.paraphonic.trigger as u8 as f32,
+            123 => if self.split.enabled { 1.0 } else { 0.0 },
+            124 => self.split.split_note as f32,
+            125 => wave_index(self.split.lower_wave_type),
+            126 => self.split.lower_volume,
+            127 => self.split.upper_volume,
+            _ => return Err(ParameterError::UnknownId(id)),
+        };
+        Ok(value)
+    }
+
+    // Tomar de `source` todo lo que no es un parámetro registrado (orden del rack,
+    // respuesta al impulso, armónicos, calidad, rutas y puntos de la matriz y del MSEG...)
+    // sin tocar los valores de los parámetros, que llegan del host con su automatización.
+    // Es lo que el editor del plugin cambia sin pasar por el host.
+    pub(crate) fn merge_unregistered(&mut self, source: &Patch) {
+        let mut values = [0.0; PARAMETERS.len()];
+        for (value, info) in values.iter_mut().zip(PARAMETERS) {
+            *value = self.parameter(info.id).unwrap_or(info.default);
+        }
+        self.clone_from(source);
+        for (value, info) in values.iter().zip(PARAMETERS) {
+            let _ = self.set_parameter(info.id, *value);
+        }
+    }
+
+    // Los valores fuera de rango se recortan a los límites del parámetro. Cambiar el
+    // bypass o la mezcla de una ranura vacía del rack no hace nada.
+    pub fn set_parameter(&mut self, id: u32, value: f32) -> Result<(), ParameterError> {
+        let info = ParameterInfo::find(id).ok_or(ParameterError::UnknownId(id))?;
+        let value = value.clamp(info.min, info.max);
+        if let Some((slot, parameter)) = FxSlotParameter::from_id(id) {
+            if let Some(slot) = self.fx.slots.get_mut(slot) {
+                match parameter {
+                    FxSlotParameter::Bypass => slot.bypass = value >= 0.5,
+                    FxSlotParameter::Mix => slot.mix = value,
+                }
+            }
+            return Ok(());
+        }
+        match id {
+            0 => self.oscillators[0].wave_type = WaveType::ALL[value.round() as usize],
+            1 => self.envelope.attack = value,
+            2 => self.envelope.decay = value,
+            3 => self.envelope.sustain = value,
+            4 => self.envelope.release = value,
+            5 => self.wavetable_position = value,
+            6 => self.wavetable_sweep = value,
+            7 => self.oscillators[0].octave = value.round() as i32,
+            8 => self.oscillators[1].octave = value.round() as i32,
+            9 => self.oscillators[0].drift = value,
+            10 => self.oscillators[1].drift = value,
+            11 => self.oscillators[0].phase_mode = PhaseMode::ALL[value.round() as usize],
+            12 => self.oscillators[1].phase_mode = PhaseMode::ALL[value.round() as usize],
+            13 => self.voice_drive = value,
+            14 => self.voice_drive_velocity = value,
+            15 => self.voice_filter_env_depth = value,
+            16 => self.oscillator_count = value.round() as usize,
+            17 => self.oscillators[0].pan = value,
+            18 => self.oscillators[1].pan = value,
+            19 => self.oscillators[0].fold = value,
+            20 => self.oscillators[1].fold = value,
+            21 => {
+                let channel = ChipChannel::ALL[value.round() as usize];
+                for osc in self.oscillators.iter_mut() {
+                    osc.chip_channel = channel;
+                }
+            }
+            22 => {
+                let duty = DutyCycle::ALL[value.round() as usize];
+                for osc in self.oscillators.iter_mut() {
+                    osc.duty_cycle = duty;
+                }
+            }
+            23 => {
+                let bit_depth = value.round() as u8;
+                for osc in self.oscillators.iter_mut() {
+                    osc.bit_depth = bit_depth;
+                }
+            }
+            24 => self.vector.enabled = value >= 0.5,
+            25 => self.vector.x = value,
+            26 => self.vector.y = value,
+            27 => self.filter.cutoff = value,
+            28 => self.filter.resonance = value,
+            29 => self.filter.filter_type = FilterType::ALL[value.round() as usize],
+            30 => self.filter.env_amount = value,
+            31 => self.filter_envelope.attack = value,
+            32 => self.filter_envelope.decay = value,
+            33 => self.filter_envelope.sustain = value,
+            34 => self.filter_envelope.release = value,
+            35 => self.filter.band.enabled = value >= 0.5,
+            36 => self.filter.band.mode = BandMode::ALL[value.round() as usize],
+            37 => self.filter.band.frequency = value,
+            38 => self.filter.band.q = value,
+            39 => self.filter.slope = FilterSlope::ALL[value.round() as usize],
+            40 => self.master_filter.lowpass_cutoff = value,
+            41 => self.master_filter.highpass_cutoff = value,
+            42 => self.filter.formant.enabled = value >= 0.5,
+            43 => self.filter.formant.morph = value,
+            44 => self.lfo.shape = LfoShape::ALL[value.round() as usize],
+            45 => self.lfo.rate = value,
+            46 => self.filter.velocity_amount = value,
+            47 => self.filter.lfo_amount = value,
+            48 => self.velocity_response.curve = VelocityCurve::ALL[value.round() as usize],
+            49 => self.velocity_response.sensitivity = value,
+            50 => self.mseg.enabled = value >= 0.5,
+            51 => self.mseg.destination = MsegDestination::ALL[value.round() as usize],
+            52 => self.mseg.amount = value,
+            53 => self.envelope.key_tracking = value,
+            54 => self.filter_envelope.key_tracking = value,
+            55 => self.lfo.pitch_depth = value,
+            56 => self.lfo.trigger = LfoTrigger::ALL[value.round() as usize],
+            57 => self.lfo.start_phase = value / 360.0,
+            58 => self.mod_matrix.route_mut(0).cc = value.round() as u8,
+            59 => self.mod_matrix.route_mut(0).destination = ModDestination::ALL[value.round() as usize],
+            60 => self.mod_matrix.route_mut(0).amount = value,
+            61 => self.mod_matrix.route_mut(0).source = ModSource::ALL[value.round() as usize],
+            62 => self.mod_matrix.random_depth = value,
+            63 => self.mod_matrix.route_mut(0).curve = ModCurve::ALL[value.round() as usize],
+            64 => self.lfo.one_shot = value >= 0.5,
+            65 => self.fx.set_enabled(EffectType::Delay, value >= 0.5),
+            66 => self.fx.delay.time = value,
+            67 => self.fx.delay.tempo_sync = value >= 0.5,
+            68 => self.fx.delay.division = DelayDivision::ALL[value.round() as usize],
+            69 => self.fx.delay.bpm = value,
+            70 => self.fx.delay.feedback = value,
+            71 => self.fx.delay.damping = value,
+            72 => self.fx.delay.ping_pong = value >= 0.5,
+            73 => self.fx.delay.mix = value,
+            74 => self.fx.set_enabled(EffectType::Distortion, value >= 0.5),
+            75 => self.fx.distortion.curve = DistortionCurve::ALL[value.round() as usize],
+            76 => self.fx.distortion.drive = value,
+            77 => self.fx.distortion.trim = value,
+            78 => self.fx.distortion.mix = value,
+            79 => self.fx.set_enabled(EffectType::Eq, value >= 0.5),
+            80 => self.fx.eq.low_frequency = value,
+            81 => self.fx.eq.low_gain = value,
+            82 => self.fx.eq.mid_frequency = value,
+            83 => self.fx.eq.mid_gain = value,
+            84 => self.fx.eq.mid_q = value,
+            85 => self.fx.eq.high_frequency = value,
+            86 => self.fx.eq.high_gain = value,
+            87 => self.fx.set_enabled(EffectType::Compressor, value >= 0.5),
+            88 => self.fx.compressor.threshold = value,
+            89 => self.fx.compressor.ratio = value,
+            90 => self.fx.compressor.attack = value,
+            91 => self.fx.compressor.release = value,
+            92 => self.fx.compressor.makeup = value,
+            93 => self.fx.set_enabled(EffectType::Reverb, value >= 0.5),
+            94 => self.fx.reverb.pre_delay = value,
+            95 => self.fx.reverb.mix = value,
+            96 => self.saturation.mode = SaturationMode::ALL[value.round() as usize],
+            97 => self.saturation.drive = value,
+            98..=100 => self.oscillators[id as usize - 97].wave_type = WaveType::ALL[value.round() as usize],
+            101..=104 => self.oscillators[id as usize - 101].volume = value,
+            105..=108 => self.oscillators[id as usize - 105].detune = value,
+            109 => self.volume = value,
+            110 => self.legato = value >= 0.5,
+            111 => self.legato_retrigger_amp = value >= 0.5,
+            112 => self.legato_retrigger_mod = value >= 0.5,
+            113 => self.glide.mode = GlideMode::ALL[value.round() as usize],
+            114 => self.glide.time = value,
+            115 => self.unison.voices = value.round() as usize,
+            116 => self.unison.detune = value,
+            117 => self.unison.spread = value,
+            118 => self.voice_pan.mode = VoicePanMode::ALL[value.round() as usize],
+            119 => self.voice_pan.pan = value,
+            120 => self.voice_pan.spread = value,
+            121 => self.paraphonic.enabled = value >= 0.5,
+            122 => self.paraphonic.trigger = ParaphonicTrigger::ALL[value.round() as usize],
+            123 => self.split.enabled = value >= 0.5,
+            124 => self.split.split_note = value.round() as u8,
+            125 => self.split.lower_wave_type = WaveType::ALL[value.round() as usize],
+            126 => self.split.lower_volume = value,
+            127 => self.split.upper_volume = value,
+            _ => return Err(ParameterError::UnknownId(id)),
+        }
+        Ok(())
+    }
+}
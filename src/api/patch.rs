@@ -0,0 +1,120 @@
+use crate::dsp::wavetable::ADDITIVE_HARMONICS;
+use crate::midi::velocity::VelocityResponse;
+use crate::dsp::{
+    FilterSettings, FxSettings, GlideSettings, MasterFilterSettings, LfoSettings, ModMatrix, OscillatorSettings, ParaphonicSettings, Quality, SaturationSettings,
+    UnisonSettings, VectorSettings, VoicePanSettings, MAX_OSCILLATORS,
+};
+use crate::structs::envelope::AdsrSettings;
+use crate::structs::mseg::MsegSettings;
+use crate::structs::preset::Preset;
+use crate::structs::split::SplitSettings;
+
+// Sonido completo que renderiza el motor, con el mismo contenido que un preset
+#[derive(PartialEq)]
+pub struct Patch {
+    pub volume: f32,
+    pub oscillators: [OscillatorSettings; MAX_OSCILLATORS],
+    pub oscillator_count: usize,
+    pub wavetable_position: f32,
+    pub wavetable_sweep: f32,
+    pub harmonic_levels: [f32; ADDITIVE_HARMONICS],
+    // Modo mono: las notas nuevas mueven la voz que suena en lugar de añadir otra, y al
+    // soltar se vuelve a la tecla anterior. Con los retrigger, también las envolventes
+    // (amplitud) y el LFO y el MSEG (modulación) vuelven a empezar
+    pub legato: bool,
+    pub legato_retrigger_amp: bool,
+    pub legato_retrigger_mod: bool,
+    pub quality: Quality,
+    pub voice_drive: f32,
+    pub voice_drive_velocity: f32,
+    pub voice_filter_env_depth: f32,
+    pub vector: VectorSettings,
+    pub lfo: LfoSettings,
+    pub mseg: MsegSettings,
+    pub mod_matrix: ModMatrix,
+    pub filter: FilterSettings,
+    pub envelope: AdsrSettings,
+    pub filter_envelope: AdsrSettings,
+    pub split: SplitSettings,
+    pub unison: UnisonSettings,
+    pub voice_pan: VoicePanSettings,
+    pub paraphonic: ParaphonicSettings,
+    pub glide: GlideSettings,
+    pub master_filter: MasterFilterSettings,
+    pub fx: FxSettings,
+    pub saturation: SaturationSettings,
+    // Respuesta a la velocidad de las notas; es un ajuste de sesión, no del preset
+    pub velocity_response: VelocityResponse,
+}
+
+// Todo es Copy salvo el rack de efectos; `clone_from` reutiliza su memoria, para copiar
+// el patch en el hilo de audio
+impl Clone for Patch {
+    fn clone(&self) -> Self {
+        Self { fx: self.fx.clone(), ..*self }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        let mut fx = std::mem::take(&mut self.fx);
+        fx.clone_from(&source.fx);
+        *self = Self { fx, ..*source };
+    }
+}
+
+impl Default for Patch {
+    fn default() -> Self {
+        Self::from(&Preset::default())
+    }
+}
+
+impl From<&Preset> for Patch {
+    fn from(preset: &Preset) -> Self {
+        Self {
+            volume: preset.volume,
+            oscillators: preset.oscillators,
+            oscillator_count: preset.oscillator_count,
+            wavetable_position: preset.wavetable_position,
+            wavetable_sweep: preset.wavetable_sweep,
+            harmonic_levels: preset.harmonic_levels,
+            legato: preset.legato,
+            legato_retrigger_amp: preset.legato_retrigger_amp,
+            legato_retrigger_mod: preset.legato_retrigger_mod,
+            quality: preset.quality,
+            voice_drive: preset.voice_drive,
+            voice_drive_velocity: preset.voice_drive_velocity,
+            voice_filter_env_depth: preset.voice_filter_env_depth,
+            vector: preset.vector,
+            lfo: preset.lfo,
+            mseg: preset.mseg,
+            mod_matrix: preset.mod_matrix,
+            filter: preset.filter,
+            envelope: preset.envelope,
+            filter_envelope: preset.filter_envelope,
+            split: preset.split,
+            unison: preset.unison,
+            voice_pan: preset.voice_pan,
+            paraphonic: preset.paraphonic,
+            glide: preset.glide,
+            master_filter: preset.master_filter,
+            fx: preset.fx.clone(),
+            saturation: preset.saturation,
+            velocity_response: VelocityResponse::default(),
+        }
+    }
+}
+
+impl Patch {
+    // Leer el texto de un preset guardado por la aplicación
+    pub fn from_preset_text(text: &str) -> Self {
+        Self::from(&Preset::from_text(text))
+    }
+
+    // Osciladores que suenan; el modo vectorial mezcla siempre los cuatro
+    pub fn active_oscillators(&self) -> usize {
+        if self.vector.enabled {
+            MAX_OSCILLATORS
+        } else {
+            self.oscillator_count.clamp(1, MAX_OSCILLATORS)
+        }
+    }
+}
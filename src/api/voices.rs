@@ -0,0 +1,353 @@
+use std::sync::Arc;
+use crate::audio::engine::{render_block, render_layered_block, LayeredFrame, OutputStage};
+use crate::audio::watchdog::VoiceWatchdog;
+use crate::dsp::wavetable::{build_additive_table, Wavetable, ADDITIVE_HARMONICS};
+use crate::dsp::smoother::{ParamSmoother, GAIN_SMOOTHING_TIME};
+use crate::dsp::{FxSlotSettings, ModSourceValues, Note, ParaphonicBus, VoiceMap};
+use crate::midi::midi_note_to_freq;
+use crate::structs::envelope::Envelope;
+use crate::structs::split::SplitSettings;
+use super::patch::Patch;
+
+// Voces activas con la nota MIDI que las disparó; la cola de una tecla soltada sigue
+// sonando aunque la tecla se vuelva a pulsar
+pub struct VoiceManager {
+    voices: VoiceMap,
+    sample_rate: f32,
+    // Tabla aditiva del patch, reconstruida solo cuando cambian los armónicos
+    harmonic_levels: [f32; ADDITIVE_HARMONICS],
+    additive_table: Arc<Wavetable>,
+    // Filtro maestro, rack de efectos y saturación del patch sobre la mezcla
+    output_stage: OutputStage,
+    // Últimos valores de las fuentes de la matriz de modulación
+    mod_sources: ModSourceValues,
+    // Envolvente y filtro comunes del modo parafónico
+    paraphonic: ParaphonicBus,
+    // Frecuencia de la última nota tocada, de donde parte el glide de la siguiente
+    last_frequency: Option<f32>,
+    // Volumen del patch, suavizado para que la automatización no dé saltos
+    gain: ParamSmoother,
+    // Zonas del teclado dividido y modo legato del último patch aplicado
+    split: SplitSettings,
+    legato: bool,
+    legato_retrigger_amp: bool,
+    legato_retrigger_mod: bool,
+    // Teclas pulsadas en orden, para volver a la anterior en modo legato
+    held_notes: Vec<u8>,
+    // Salida de voces alternas de la próxima pulsación
+    next_bus: usize,
+}
+
+impl VoiceManager {
+    pub fn new(sample_rate: f32) -> Self {
+        let harmonic_levels = Patch::default().harmonic_levels;
+        Self {
+            voices: VoiceMap::new(),
+            sample_rate,
+            harmonic_levels,
+            additive_table: build_additive_table(&harmonic_levels),
+            output_stage: OutputStage::new(sample_rate),
+            mod_sources: ModSourceValues::default(),
+            paraphonic: ParaphonicBus::new(sample_rate),
+            last_frequency: None,
+            gain: ParamSmoother::linear(Patch::default().volume, GAIN_SMOOTHING_TIME, sample_rate),
+            split: SplitSettings::default(),
+            legato: false,
+            legato_retrigger_amp: false,
+            legato_retrigger_mod: true,
+            // Una entrada por tecla como mucho: no reserva al tocar
+            held_notes: Vec::with_capacity(128),
+            next_bus: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    // Las voces activas pasan a la nueva frecuencia sin cortarse
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.output_stage.set_sample_rate(sample_rate);
+        self.gain.set_time(GAIN_SMOOTHING_TIME, sample_rate);
+        for note in self.voices.values_mut() {
+            note.set_sample_rate(sample_rate);
+        }
+    }
+
+    // Empezar una nota; si la tecla seguía pulsada, sus voces se reutilizan y el ataque
+    // arranca desde su nivel actual. Con unísono cada pulsación crea varias voces. En el
+    // teclado dividido la zona de la tecla decide la onda y la envolvente; en modo legato
+    // la voz que ya suena pasa a la nota nueva.
+    pub fn note_on(&mut self, note: u8, velocity: f32, patch: &Patch) {
+        let velocity = velocity.clamp(0.0, 1.0);
+        self.update_paraphonic(patch);
+        self.update_keyboard(patch);
+        let frequency = midi_note_to_freq(note);
+        let glide_from = self.last_frequency.replace(frequency);
+
+        if self.legato {
+            let is_legato = !self.held_notes.is_empty();
+            self.held_notes.retain(|&held| held != note);
+            self.held_notes.push(note);
+            // Con unísono son varias voces, todas de la misma nota
+            if !self.voices.is_empty() {
+                for voice in self.voices.values_mut() {
+                    if !is_legato {
+                        voice.envelope.set_velocity(velocity);
+                    }
+                    voice.set_glide(patch.glide);
+                    voice.legato_to(
+                        frequency,
+                        !is_legato || self.legato_retrigger_amp,
+                        !is_legato || self.legato_retrigger_mod,
+                    );
+                }
+                self.voices.rekey_all(note);
+                return;
+            }
+        }
+
+        let envelope_settings = patch.paraphonic.voice_envelope(&patch.split.envelope(note, &patch.envelope));
+        if self.voices.contains_key(note) {
+            for voice in self.voices.held_mut(note) {
+                voice.envelope.set_settings(&envelope_settings);
+                voice.set_filter_envelope(&patch.filter_envelope);
+                voice.retrigger(velocity);
+            }
+            return;
+        }
+        self.update_additive_table(patch);
+        let oscillators = patch.split.oscillators(note, &patch.oscillators);
+        let pan = patch.voice_pan.next_pan();
+        // Las copias del unísono van juntas a la misma salida de voces alternas
+        let bus = self.next_bus;
+        self.next_bus = 1 - bus;
+        for unison_voice in patch.unison.unison_voices() {
+            let mut envelope = Envelope::new(self.sample_rate);
+            envelope.set_settings(&envelope_settings);
+            envelope.set_key(note);
+            envelope.set_velocity(velocity);
+            envelope.note_on();
+
+            let mut voice = Note::new(
+                frequency,
+                envelope,
+                self.sample_rate,
+                &oscillators[..patch.active_oscillators()],
+            );
+            Self::configure(&mut voice, patch, &self.additive_table);
+            voice.set_mod_sources(&self.mod_sources);
+            voice.set_unison(unison_voice);
+            voice.set_pan(pan);
+            if let Some(from) = glide_from {
+                voice.glide_from(from);
+            }
+            self.voices.insert_on_bus(note, bus, voice);
+        }
+    }
+
+    // Soltar una tecla; en modo legato, si queda otra pulsada, la voz vuelve a ella
+    pub fn note_off(&mut self, note: u8) {
+        self.held_notes.retain(|&held| held != note);
+        if self.legato {
+            if let Some(&previous) = self.held_notes.last() {
+                for voice in self.voices.held_mut(note) {
+                    voice.legato_to(midi_note_to_freq(previous), self.legato_retrigger_amp, self.legato_retrigger_mod);
+                }
+                self.voices.rekey(note, previous);
+                return;
+            }
+        }
+        for voice in self.voices.held_mut(note) {
+            voice.note_off();
+        }
+    }
+
+    // Controlador MIDI (valor de 0 a 127) como fuente de la matriz de modulación, o
+    // parte de un RPN que cambia el rango del pitch bend o la transposición. Devuelve
+    // true si el CC era parte de un RPN.
+    pub fn control_change(&mut self, cc: u8, value: u8) -> bool {
+        let is_rpn = self.mod_sources.control_change(cc, value);
+        for voice in self.voices.values_mut() {
+            voice.set_mod_sources(&self.mod_sources);
+        }
+        is_rpn
+    }
+
+    // Pitch bend de 14 bits (de 0 a 16383, centro en 8192)
+    pub fn pitch_bend(&mut self, value: u16) {
+        self.mod_sources.set_pitch_bend((value & 0x7F) as u8, ((value >> 7) & 0x7F) as u8);
+        for voice in self.voices.values_mut() {
+            voice.set_mod_sources(&self.mod_sources);
+        }
+    }
+
+    // Presión de canal (valor de 0 a 127) como fuente de la matriz de modulación
+    pub fn channel_pressure(&mut self, value: u8) {
+        self.mod_sources.set_aftertouch(value);
+        for voice in self.voices.values_mut() {
+            voice.set_mod_sources(&self.mod_sources);
+        }
+    }
+
+    // Presión de una tecla (valor de 0 a 127), solo para la voz que la toca
+    pub fn poly_pressure(&mut self, note: u8, value: u8) {
+        for voice in self.voices.held_mut(note) {
+            voice.set_pressure(value);
+        }
+    }
+
+    // Fuentes de la matriz de modulación a cero (pedales, CC, aftertouch, bend), para el
+    // pánico; el rango del bend y la transposición de los RPN se conservan
+    pub fn reset_mod_sources(&mut self) {
+        let mut sources = ModSourceValues::default();
+        sources.bend_range = self.mod_sources.bend_range;
+        sources.coarse_tune = self.mod_sources.coarse_tune;
+        self.mod_sources = sources;
+        for voice in self.voices.values_mut() {
+            voice.set_mod_sources(&self.mod_sources);
+        }
+    }
+
+    // Pasar todas las voces a la fase de liberación
+    pub fn all_notes_off(&mut self) {
+        self.held_notes.clear();
+        for voice in self.voices.values_mut() {
+            voice.note_off();
+        }
+    }
+
+    // Cortar todas las voces con un fundido corto; se eliminan al terminar
+    pub fn kill_all(&mut self) {
+        self.held_notes.clear();
+        for voice in self.voices.values_mut() {
+            voice.kill();
+        }
+    }
+
+    // Cortar todas las voces en seco
+    pub fn reset(&mut self) {
+        self.held_notes.clear();
+        self.voices.clear();
+        self.output_stage.reset();
+    }
+
+    pub fn active_voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    // Alguna voz de la tecla sigue sonando, aunque sea en liberación
+    pub fn is_active(&self, note: u8) -> bool {
+        self.voices.iter().any(|(key, _)| key == note)
+    }
+
+    // La tecla sigue pulsada en alguna voz (no ha llegado a la liberación)
+    pub fn is_held(&self, note: u8) -> bool {
+        self.voices.contains_key(note)
+    }
+
+    // Lo que le queda de liberación a la voz que más tarda en apagarse, en segundos
+    pub fn remaining_tail(&self) -> f32 {
+        self.voices.values()
+            .map(|voice| voice.envelope.remaining_tail())
+            .fold(0.0, f32::max)
+    }
+
+    // Últimos valores de las fuentes de modulación que llegan por MIDI
+    pub(crate) fn mod_sources(&self) -> &ModSourceValues {
+        &self.mod_sources
+    }
+
+    // Liberar las voces colgadas por Note Off perdidos
+    pub(crate) fn check_watchdog(&mut self, watchdog: &VoiceWatchdog) {
+        watchdog.check(&mut self.voices);
+    }
+
+    // Acceso a la (primera) voz de una tecla pulsada, por ejemplo para añadirle un `OscillatorSource`
+    pub fn voice_mut(&mut self, note: u8) -> Option<&mut Note> {
+        self.voices.get_mut(note)
+    }
+
+    // Aplicar el patch a las voces que ya suenan, para automatizar mientras se toca
+    pub fn apply_patch(&mut self, patch: &Patch) {
+        self.update_additive_table(patch);
+        self.update_paraphonic(patch);
+        self.update_keyboard(patch);
+        self.output_stage.set_settings(patch.master_filter, &patch.fx, patch.saturation);
+        let count = patch.active_oscillators();
+        for (key, voice) in self.voices.iter_mut() {
+            // Cada voz con la onda de su zona del teclado
+            voice.apply_oscillator_settings(&patch.split.oscillators(key, &patch.oscillators)[..count]);
+            Self::configure(voice, patch, &self.additive_table);
+        }
+    }
+
+    // Bypass y mezcla de una ranura del rack, p. ej. automatizados por el host, sin
+    // volver a aplicar el patch entero
+    pub(crate) fn set_fx_slot(&mut self, slot: usize, settings: FxSlotSettings) {
+        self.output_stage.set_fx_slot(slot, settings);
+    }
+
+    // Renderizar un bloque estéreo intercalado y retirar las voces terminadas; devuelve
+    // cuántas de ellas se habían cortado a la fuerza
+    pub fn render(&mut self, gain: f32, output: &mut [f32]) -> usize {
+        self.gain.set_target(gain);
+        let paraphonic = self.paraphonic.is_enabled().then_some(&mut self.paraphonic);
+        render_block(&mut self.voices, paraphonic, &self.split, &mut self.output_stage, &mut self.gain, output)
+    }
+
+    // Como `render`, con cada etapa de la salida y las voces pares e impares por separado
+    pub(crate) fn render_layers(&mut self, gain: f32, output: &mut [LayeredFrame]) -> usize {
+        self.gain.set_target(gain);
+        let paraphonic = self.paraphonic.is_enabled().then_some(&mut self.paraphonic);
+        render_layered_block(&mut self.voices, paraphonic, &self.split, &mut self.output_stage, &mut self.gain, output)
+    }
+
+    // Tabla aditiva ya calculada para esos armónicos fuera del hilo de audio, p. ej. por
+    // la interfaz; así `apply_patch` no la vuelve a construir
+    pub(crate) fn set_additive_table(&mut self, levels: [f32; ADDITIVE_HARMONICS], table: &Arc<Wavetable>) {
+        self.harmonic_levels = levels;
+        self.additive_table = table.clone();
+    }
+
+    fn update_additive_table(&mut self, patch: &Patch) {
+        if patch.harmonic_levels != self.harmonic_levels {
+            self.harmonic_levels = patch.harmonic_levels;
+            self.additive_table = build_additive_table(&patch.harmonic_levels);
+        }
+    }
+
+    fn update_paraphonic(&mut self, patch: &Patch) {
+        self.paraphonic.set_settings(patch.paraphonic, &patch.envelope, patch.filter, &patch.filter_envelope, self.sample_rate);
+    }
+
+    fn update_keyboard(&mut self, patch: &Patch) {
+        self.split = patch.split;
+        self.legato = patch.legato;
+        self.legato_retrigger_amp = patch.legato_retrigger_amp;
+        self.legato_retrigger_mod = patch.legato_retrigger_mod;
+    }
+
+    fn configure(voice: &mut Note, patch: &Patch, additive_table: &Arc<Wavetable>) {
+        voice.set_wavetable(patch.wavetable_position, patch.wavetable_sweep);
+        voice.set_additive_table(additive_table);
+        voice.set_quality(patch.quality);
+        voice.set_voice_fx(patch.voice_drive, patch.voice_drive_velocity, patch.voice_filter_env_depth);
+        voice.set_vector(patch.vector);
+        voice.set_lfo(patch.lfo);
+        voice.set_mseg(patch.mseg);
+        voice.set_mod_matrix(patch.mod_matrix);
+        voice.set_filter(patch.filter);
+        voice.set_filter_envelope(&patch.filter_envelope);
+        voice.set_paraphonic(patch.paraphonic.enabled);
+        voice.set_glide(patch.glide);
+    }
+}
+
+// La frecuencia de muestreo real se fija en el primer `set_sample_rate`
+impl Default for VoiceManager {
+    fn default() -> Self {
+        Self::new(44100.0)
+    }
+}
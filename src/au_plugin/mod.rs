@@ -0,0 +1,637 @@
+// Audio Unit v2 (feature "au", solo macOS) sobre el mismo motor que la API, para Logic
+// y GarageBand. El host carga el bundle .component, busca en su Info.plist la función
+// de fábrica (`RustSynthAUFactory`) y llama a los métodos de la instancia por selector.
+// Los parámetros son los de `PARAMETERS`, en sus unidades reales. El bundle lo monta
+// el binario `au-bundle`:
+//
+//     cargo build --release --features au
+//     cargo run --release --features au --bin au-bundle -- ~/Library/Audio/Plug-Ins/Components/RustSynth.component
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicU32, Ordering};
+use coreaudio_sys::*;
+use core_foundation_sys::base::{kCFAllocatorDefault, CFGetTypeID, CFRelease, CFTypeRef};
+use core_foundation_sys::data::{CFDataCreate, CFDataGetBytePtr, CFDataGetLength, CFDataGetTypeID, CFDataRef};
+use core_foundation_sys::dictionary::{
+    kCFTypeDictionaryKeyCallBacks, kCFTypeDictionaryValueCallBacks, CFDictionaryCreateMutable, CFDictionaryGetTypeID,
+    CFDictionaryGetValue, CFDictionaryRef, CFDictionarySetValue,
+};
+use core_foundation_sys::number::{kCFNumberSInt32Type, CFNumberCreate};
+use core_foundation_sys::string::{kCFStringEncodingUTF8, CFStringCreateWithBytes, CFStringRef};
+use crate::api::{ParameterKind, SynthEngine, PARAMETERS};
+
+pub mod plist;
+
+// Códigos de cuatro letras con los que el host identifica el componente; el fabricante
+// lleva mayúsculas porque Apple reserva los códigos en minúsculas
+pub const AU_TYPE: &str = "aumu";
+pub const AU_SUBTYPE: &str = "rsyn";
+pub const AU_MANUFACTURER: &str = "RSyn";
+pub const AU_NAME: &str = "Your Name: Rust Synth";
+// 1.0.0, la misma versión que el VST3, como 0xMMMMmmbb
+pub const AU_VERSION: u32 = 0x0001_0000;
+pub const FACTORY_FUNCTION: &str = "RustSynthAUFactory";
+
+const CHANNELS: u32 = 2;
+const DEFAULT_SAMPLE_RATE: f64 = 44100.0;
+const DEFAULT_MAX_FRAMES: u32 = 1156;
+// Eventos MIDI que caben en un bloque sin reservar memoria en el hilo de audio
+const MIDI_QUEUE_CAPACITY: usize = 1024;
+
+fn four_char_code(code: &str) -> u32 {
+    let bytes = code.as_bytes();
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+// Instancia del componente. El host solo conoce el puntero a `interface`, que por eso
+// va primero: ese mismo puntero es el `self` de todos los métodos.
+#[repr(C)]
+struct RustSynthAu {
+    interface: AudioComponentPlugInInterface,
+    engine: SynthEngine,
+    sample_rate: f64,
+    max_frames: u32,
+    initialized: bool,
+    // Valor de cada parámetro (bits de un f32), en el orden de `PARAMETERS`; el host lo
+    // cambia desde cualquier hilo y render() lo lleva al motor al empezar cada bloque
+    values: Vec<AtomicU32>,
+    // Últimos valores que ha recibido el motor
+    applied: Vec<f32>,
+    // Eventos MIDI del próximo bloque como (muestra, mensaje)
+    midi: Vec<(u32, [u8; 3])>,
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+impl RustSynthAu {
+    fn new() -> Self {
+        let engine = SynthEngine::new(DEFAULT_SAMPLE_RATE as f32);
+        let applied: Vec<f32> = PARAMETERS
+            .iter()
+            .map(|info| engine.parameter(info.id).unwrap_or(info.default))
+            .collect();
+        Self {
+            interface: AudioComponentPlugInInterface {
+                Open: Some(open),
+                Close: Some(close),
+                Lookup: Some(lookup),
+                reserved: std::ptr::null_mut(),
+            },
+            engine,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            max_frames: DEFAULT_MAX_FRAMES,
+            initialized: false,
+            values: applied.iter().map(|value| AtomicU32::new(value.to_bits())).collect(),
+            applied,
+            midi: Vec::with_capacity(MIDI_QUEUE_CAPACITY),
+            left: Vec::new(),
+            right: Vec::new(),
+        }
+    }
+
+    fn parameter_index(id: AudioUnitParameterID) -> Option<usize> {
+        PARAMETERS.iter().position(|info| info.id == id)
+    }
+
+    fn value(&self, index: usize) -> f32 {
+        f32::from_bits(self.values[index].load(Ordering::Relaxed))
+    }
+
+    fn set_value(&self, index: usize, value: f32) {
+        let info = &PARAMETERS[index];
+        self.values[index].store(value.clamp(info.min, info.max).to_bits(), Ordering::Relaxed);
+    }
+
+    fn apply_parameters(&mut self) {
+        for (index, info) in PARAMETERS.iter().enumerate() {
+            let value = self.value(index);
+            if value != self.applied[index] {
+                self.applied[index] = value;
+                // Los identificadores salen del propio registro, así que siempre existen
+                let _ = self.engine.set_parameter(info.id, value);
+            }
+        }
+    }
+
+    fn stream_format(&self) -> AudioStreamBasicDescription {
+        AudioStreamBasicDescription {
+            mSampleRate: self.sample_rate,
+            mFormatID: kAudioFormatLinearPCM,
+            mFormatFlags: kAudioFormatFlagsNativeFloatPacked | kAudioFormatFlagIsNonInterleaved,
+            mBytesPerPacket: 4,
+            mFramesPerPacket: 1,
+            mBytesPerFrame: 4,
+            mChannelsPerFrame: CHANNELS,
+            mBitsPerChannel: 32,
+            mReserved: 0,
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.engine.set_sample_rate(sample_rate as f32);
+    }
+
+    // Estado para las sesiones y los presets del host: un `id=valor` por línea, como
+    // en los presets de la aplicación
+    fn state_text(&self) -> String {
+        PARAMETERS
+            .iter()
+            .enumerate()
+            .map(|(index, info)| format!("{}={}\n", info.id, self.value(index)))
+            .collect()
+    }
+
+    fn load_state_text(&self, text: &str) {
+        for line in text.lines() {
+            let Some((id, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (Ok(id), Ok(value)) = (id.trim().parse(), value.trim().parse::<f32>()) else {
+                continue;
+            };
+            if let Some(index) = Self::parameter_index(id) {
+                self.set_value(index, value);
+            }
+        }
+    }
+}
+
+// Punto de entrada que nombra el Info.plist del bundle
+#[no_mangle]
+pub extern "C" fn RustSynthAUFactory(_description: *const AudioComponentDescription) -> *mut AudioComponentPlugInInterface {
+    Box::into_raw(Box::new(RustSynthAu::new())).cast()
+}
+
+unsafe fn instance<'a>(this: *mut c_void) -> &'a mut RustSynthAu {
+    &mut *this.cast::<RustSynthAu>()
+}
+
+unsafe extern "C" fn open(_this: *mut c_void, _instance: AudioComponentInstance) -> OSStatus {
+    noErr as OSStatus
+}
+
+unsafe extern "C" fn close(this: *mut c_void) -> OSStatus {
+    drop(Box::from_raw(this.cast::<RustSynthAu>()));
+    noErr as OSStatus
+}
+
+// Los métodos tienen firmas distintas según el selector; el host los llama con la que
+// corresponde a cada uno
+macro_rules! method {
+    ($function:expr) => {
+        std::mem::transmute::<*const (), AudioComponentMethod>($function as *const ())
+    };
+}
+
+unsafe extern "C" fn lookup(selector: SInt16) -> AudioComponentMethod {
+    match selector as u32 {
+        kAudioUnitInitializeSelect => method!(initialize),
+        kAudioUnitUninitializeSelect => method!(uninitialize),
+        kAudioUnitGetPropertyInfoSelect => method!(get_property_info),
+        kAudioUnitGetPropertySelect => method!(get_property),
+        kAudioUnitSetPropertySelect => method!(set_property),
+        kAudioUnitAddPropertyListenerSelect => method!(add_property_listener),
+        kAudioUnitRemovePropertyListenerSelect => method!(remove_property_listener),
+        kAudioUnitRemovePropertyListenerWithUserDataSelect => method!(remove_property_listener_with_user_data),
+        kAudioUnitAddRenderNotifySelect => method!(render_notify),
+        kAudioUnitRemoveRenderNotifySelect => method!(render_notify),
+        kAudioUnitGetParameterSelect => method!(get_parameter),
+        kAudioUnitSetParameterSelect => method!(set_parameter),
+        kAudioUnitRenderSelect => method!(render),
+        kAudioUnitResetSelect => method!(reset),
+        kMusicDeviceMIDIEventSelect => method!(midi_event),
+        _ => None,
+    }
+}
+
+unsafe extern "C" fn initialize(this: *mut c_void) -> OSStatus {
+    let unit = instance(this);
+    let frames = unit.max_frames as usize;
+    unit.left = vec![0.0; frames];
+    unit.right = vec![0.0; frames];
+    unit.engine.reset();
+    unit.initialized = true;
+    noErr as OSStatus
+}
+
+unsafe extern "C" fn uninitialize(this: *mut c_void) -> OSStatus {
+    instance(this).initialized = false;
+    noErr as OSStatus
+}
+
+// Tamaño de cada propiedad y si el host puede cambiarla
+fn property_info(id: AudioUnitPropertyID, scope: AudioUnitScope) -> Result<(usize, bool), OSStatus> {
+    let info = match id {
+        kAudioUnitProperty_ClassInfo => (std::mem::size_of::<CFTypeRef>(), true),
+        kAudioUnitProperty_StreamFormat => (std::mem::size_of::<AudioStreamBasicDescription>(), true),
+        kAudioUnitProperty_SampleRate => (std::mem::size_of::<f64>(), true),
+        kAudioUnitProperty_ElementCount => (std::mem::size_of::<u32>(), false),
+        kAudioUnitProperty_Latency => (std::mem::size_of::<f64>(), false),
+        kAudioUnitProperty_MaximumFramesPerSlice => (std::mem::size_of::<u32>(), true),
+        kAudioUnitProperty_SupportedNumChannels => (std::mem::size_of::<AUChannelInfo>(), false),
+        kAudioUnitProperty_ParameterList => {
+            // Todos los parámetros son globales
+            let count = if scope == kAudioUnitScope_Global { PARAMETERS.len() } else { 0 };
+            (count * std::mem::size_of::<AudioUnitParameterID>(), false)
+        }
+        kAudioUnitProperty_ParameterInfo => (std::mem::size_of::<AudioUnitParameterInfo>(), false),
+        _ => return Err(kAudioUnitErr_InvalidProperty as OSStatus),
+    };
+    Ok(info)
+}
+
+unsafe extern "C" fn get_property_info(
+    _this: *mut c_void,
+    id: AudioUnitPropertyID,
+    scope: AudioUnitScope,
+    _element: AudioUnitElement,
+    out_size: *mut UInt32,
+    out_writable: *mut Boolean,
+) -> OSStatus {
+    match property_info(id, scope) {
+        Ok((size, writable)) => {
+            if !out_size.is_null() {
+                *out_size = size as UInt32;
+            }
+            if !out_writable.is_null() {
+                *out_writable = writable as Boolean;
+            }
+            noErr as OSStatus
+        }
+        Err(status) => status,
+    }
+}
+
+unsafe fn write_property<T>(data: *mut c_void, size: *mut UInt32, value: T) -> OSStatus {
+    if data.is_null() || size.is_null() || (*size as usize) < std::mem::size_of::<T>() {
+        return kAudio_ParamError as OSStatus;
+    }
+    data.cast::<T>().write_unaligned(value);
+    *size = std::mem::size_of::<T>() as UInt32;
+    noErr as OSStatus
+}
+
+unsafe fn read_property<T>(data: *const c_void, size: UInt32) -> Option<T> {
+    if data.is_null() || (size as usize) < std::mem::size_of::<T>() {
+        return None;
+    }
+    Some(data.cast::<T>().read_unaligned())
+}
+
+unsafe extern "C" fn get_property(
+    this: *mut c_void,
+    id: AudioUnitPropertyID,
+    scope: AudioUnitScope,
+    element: AudioUnitElement,
+    data: *mut c_void,
+    size: *mut UInt32,
+) -> OSStatus {
+    let unit = instance(this);
+    match id {
+        kAudioUnitProperty_ClassInfo => write_property(data, size, class_info(unit)),
+        kAudioUnitProperty_StreamFormat => {
+            if scope != kAudioUnitScope_Output || element != 0 {
+                return kAudioUnitErr_InvalidScope as OSStatus;
+            }
+            write_property(data, size, unit.stream_format())
+        }
+        kAudioUnitProperty_SampleRate => write_property(data, size, unit.sample_rate),
+        kAudioUnitProperty_ElementCount => {
+            // Instrumento: ninguna entrada y una salida estéreo
+            let count: u32 = if scope == kAudioUnitScope_Output || scope == kAudioUnitScope_Global { 1 } else { 0 };
+            write_property(data, size, count)
+        }
+        kAudioUnitProperty_Latency => write_property(data, size, 0.0f64),
+        kAudioUnitProperty_MaximumFramesPerSlice => write_property(data, size, unit.max_frames),
+        kAudioUnitProperty_SupportedNumChannels => {
+            write_property(data, size, AUChannelInfo { inChannels: 0, outChannels: CHANNELS as SInt16 })
+        }
+        kAudioUnitProperty_ParameterList => {
+            if scope != kAudioUnitScope_Global {
+                if !size.is_null() {
+                    *size = 0;
+                }
+                return noErr as OSStatus;
+            }
+            let needed = PARAMETERS.len() * std::mem::size_of::<AudioUnitParameterID>();
+            if data.is_null() || size.is_null() || (*size as usize) < needed {
+                return kAudio_ParamError as OSStatus;
+            }
+            let ids = std::slice::from_raw_parts_mut(data.cast::<AudioUnitParameterID>(), PARAMETERS.len());
+            for (id, info) in ids.iter_mut().zip(PARAMETERS) {
+                *id = info.id;
+            }
+            *size = needed as UInt32;
+            noErr as OSStatus
+        }
+        kAudioUnitProperty_ParameterInfo => match RustSynthAu::parameter_index(element) {
+            Some(index) => write_property(data, size, parameter_info(index)),
+            None => kAudioUnitErr_InvalidParameter as OSStatus,
+        },
+        _ => kAudioUnitErr_InvalidProperty as OSStatus,
+    }
+}
+
+unsafe extern "C" fn set_property(
+    this: *mut c_void,
+    id: AudioUnitPropertyID,
+    scope: AudioUnitScope,
+    element: AudioUnitElement,
+    data: *const c_void,
+    size: UInt32,
+) -> OSStatus {
+    let unit = instance(this);
+    match id {
+        kAudioUnitProperty_ClassInfo => match read_property::<CFTypeRef>(data, size) {
+            Some(state) if load_class_info(unit, state) => noErr as OSStatus,
+            _ => kAudioUnitErr_InvalidPropertyValue as OSStatus,
+        },
+        kAudioUnitProperty_StreamFormat => {
+            if scope != kAudioUnitScope_Output || element != 0 {
+                return kAudioUnitErr_InvalidScope as OSStatus;
+            }
+            // La frecuencia no cambia mientras el motor puede estar en render()
+            if unit.initialized {
+                return kAudioUnitErr_Initialized as OSStatus;
+            }
+            // Solo estéreo en f32 no intercalado; del formato se toma la frecuencia
+            match read_property::<AudioStreamBasicDescription>(data, size) {
+                Some(format) if format.mChannelsPerFrame == CHANNELS && format.mFormatID == kAudioFormatLinearPCM => {
+                    unit.set_sample_rate(format.mSampleRate);
+                    noErr as OSStatus
+                }
+                _ => kAudioUnitErr_FormatNotSupported as OSStatus,
+            }
+        }
+        kAudioUnitProperty_SampleRate => {
+            if unit.initialized {
+                return kAudioUnitErr_Initialized as OSStatus;
+            }
+            match read_property::<f64>(data, size) {
+                Some(sample_rate) if sample_rate > 0.0 => {
+                    unit.set_sample_rate(sample_rate);
+                    noErr as OSStatus
+                }
+                _ => kAudioUnitErr_InvalidPropertyValue as OSStatus,
+            }
+        }
+        kAudioUnitProperty_MaximumFramesPerSlice => {
+            if unit.initialized {
+                return kAudioUnitErr_Initialized as OSStatus;
+            }
+            match read_property::<u32>(data, size) {
+                Some(frames) if frames > 0 => {
+                    unit.max_frames = frames;
+                    noErr as OSStatus
+                }
+                _ => kAudioUnitErr_InvalidPropertyValue as OSStatus,
+            }
+        }
+        _ => match property_info(id, scope) {
+            Ok(_) => kAudioUnitErr_PropertyNotWritable as OSStatus,
+            Err(status) => status,
+        },
+    }
+}
+
+// Las propiedades que cambian solas no existen: no hace falta avisar a nadie
+unsafe extern "C" fn add_property_listener(
+    _this: *mut c_void,
+    _id: AudioUnitPropertyID,
+    _listener: AudioUnitPropertyListenerProc,
+    _user_data: *mut c_void,
+) -> OSStatus {
+    noErr as OSStatus
+}
+
+unsafe extern "C" fn remove_property_listener(
+    _this: *mut c_void,
+    _id: AudioUnitPropertyID,
+    _listener: AudioUnitPropertyListenerProc,
+) -> OSStatus {
+    noErr as OSStatus
+}
+
+unsafe extern "C" fn remove_property_listener_with_user_data(
+    _this: *mut c_void,
+    _id: AudioUnitPropertyID,
+    _listener: AudioUnitPropertyListenerProc,
+    _user_data: *mut c_void,
+) -> OSStatus {
+    noErr as OSStatus
+}
+
+unsafe extern "C" fn render_notify(_this: *mut c_void, _callback: AURenderCallback, _user_data: *mut c_void) -> OSStatus {
+    noErr as OSStatus
+}
+
+unsafe extern "C" fn get_parameter(
+    this: *mut c_void,
+    id: AudioUnitParameterID,
+    _scope: AudioUnitScope,
+    _element: AudioUnitElement,
+    out_value: *mut AudioUnitParameterValue,
+) -> OSStatus {
+    // Llega desde cualquier hilo mientras render() trabaja: solo se leen los atómicos
+    let unit = &*this.cast::<RustSynthAu>();
+    match RustSynthAu::parameter_index(id) {
+        Some(index) if !out_value.is_null() => {
+            *out_value = unit.value(index);
+            noErr as OSStatus
+        }
+        _ => kAudioUnitErr_InvalidParameter as OSStatus,
+    }
+}
+
+// El desplazamiento dentro del bloque se ignora: el valor se aplica al principio del
+// siguiente render
+unsafe extern "C" fn set_parameter(
+    this: *mut c_void,
+    id: AudioUnitParameterID,
+    _scope: AudioUnitScope,
+    _element: AudioUnitElement,
+    value: AudioUnitParameterValue,
+    _offset: UInt32,
+) -> OSStatus {
+    // Igual que get_parameter: referencia compartida, el valor va a un atómico
+    let unit = &*this.cast::<RustSynthAu>();
+    match RustSynthAu::parameter_index(id) {
+        Some(index) => {
+            unit.set_value(index, value);
+            noErr as OSStatus
+        }
+        None => kAudioUnitErr_InvalidParameter as OSStatus,
+    }
+}
+
+// El host envía los eventos del bloque antes de llamar a render(), desde el mismo hilo
+unsafe extern "C" fn midi_event(this: *mut c_void, status: UInt32, data1: UInt32, data2: UInt32, offset: UInt32) -> OSStatus {
+    let unit = instance(this);
+    if unit.midi.len() < unit.midi.capacity() {
+        unit.midi.push((offset, [status as u8, data1 as u8, data2 as u8]));
+    }
+    noErr as OSStatus
+}
+
+unsafe extern "C" fn reset(this: *mut c_void, _scope: AudioUnitScope, _element: AudioUnitElement) -> OSStatus {
+    let unit = instance(this);
+    unit.midi.clear();
+    unit.engine.reset();
+    noErr as OSStatus
+}
+
+// El bloque se parte en cada evento MIDI, para que las notas empiecen en su muestra
+unsafe extern "C" fn render(
+    this: *mut c_void,
+    _flags: *mut AudioUnitRenderActionFlags,
+    _timestamp: *const AudioTimeStamp,
+    bus: UInt32,
+    frames: UInt32,
+    io_data: *mut AudioBufferList,
+) -> OSStatus {
+    let unit = instance(this);
+    if !unit.initialized {
+        return kAudioUnitErr_Uninitialized as OSStatus;
+    }
+    if bus != 0 || io_data.is_null() {
+        return kAudioUnitErr_InvalidElement as OSStatus;
+    }
+    if frames > unit.max_frames {
+        return kAudioUnitErr_TooManyFramesToProcess as OSStatus;
+    }
+    unit.apply_parameters();
+
+    let frames = frames as usize;
+    let mut start = 0;
+    for i in 0..unit.midi.len() {
+        let (offset, message) = unit.midi[i];
+        let frame = (offset as usize).clamp(start, frames);
+        if frame > start {
+            unit.engine.process(&mut unit.left[start..frame], &mut unit.right[start..frame]);
+            start = frame;
+        }
+        unit.engine.midi_message(&message);
+    }
+    unit.midi.clear();
+    if frames > start {
+        unit.engine.process(&mut unit.left[start..frames], &mut unit.right[start..frames]);
+    }
+
+    // Si el host no da buffers se le prestan los propios, válidos hasta el siguiente render
+    let buffer_count = (*io_data).mNumberBuffers as usize;
+    let buffers = std::slice::from_raw_parts_mut((*io_data).mBuffers.as_mut_ptr(), buffer_count);
+    for (channel, buffer) in buffers.iter_mut().enumerate() {
+        let source = if channel == 0 { &unit.left[..frames] } else { &unit.right[..frames] };
+        if buffer.mData.is_null() {
+            buffer.mData = source.as_ptr() as *mut c_void;
+        } else {
+            std::slice::from_raw_parts_mut(buffer.mData.cast::<f32>(), frames).copy_from_slice(source);
+        }
+        buffer.mDataByteSize = (frames * std::mem::size_of::<f32>()) as UInt32;
+    }
+    noErr as OSStatus
+}
+
+fn parameter_unit(unit: &str) -> AudioUnitParameterUnit {
+    match unit {
+        "s" => kAudioUnitParameterUnit_Seconds,
+        "ms" => kAudioUnitParameterUnit_Milliseconds,
+        "Hz" => kAudioUnitParameterUnit_Hertz,
+        "dB" => kAudioUnitParameterUnit_Decibels,
+        "BPM" => kAudioUnitParameterUnit_BPM,
+        "st" => kAudioUnitParameterUnit_RelativeSemiTones,
+        _ => kAudioUnitParameterUnit_Generic,
+    }
+}
+
+// El host libera el nombre (CFNameRelease)
+unsafe fn parameter_info(index: usize) -> AudioUnitParameterInfo {
+    let info = &PARAMETERS[index];
+    let mut parameter: AudioUnitParameterInfo = std::mem::zeroed();
+    // Nombre en C truncado por si el host no lee el CFString; sin cortar un carácter por la mitad
+    let mut length = info.name.len().min(parameter.name.len() - 1);
+    while !info.name.is_char_boundary(length) {
+        length -= 1;
+    }
+    for (target, &byte) in parameter.name.iter_mut().zip(&info.name.as_bytes()[..length]) {
+        *target = byte as _;
+    }
+    parameter.cfNameString = cf_string(info.name) as _;
+    parameter.unit = match info.kind {
+        ParameterKind::Toggle => kAudioUnitParameterUnit_Boolean,
+        ParameterKind::Choice(_) => kAudioUnitParameterUnit_Indexed,
+        _ => parameter_unit(info.unit),
+    };
+    parameter.minValue = info.min;
+    parameter.maxValue = info.max;
+    parameter.defaultValue = info.default;
+    parameter.flags = kAudioUnitParameterFlag_IsReadable
+        | kAudioUnitParameterFlag_IsWritable
+        | kAudioUnitParameterFlag_HasCFNameString
+        | kAudioUnitParameterFlag_CFNameRelease;
+    if info.logarithmic {
+        parameter.flags |= kAudioUnitParameterFlag_DisplayLogarithmic;
+    }
+    parameter
+}
+
+unsafe fn cf_string(text: &str) -> CFStringRef {
+    CFStringCreateWithBytes(kCFAllocatorDefault, text.as_ptr(), text.len() as _, kCFStringEncodingUTF8, 0)
+}
+
+unsafe fn set_number(dictionary: *mut c_void, key: &str, value: i32) {
+    let key = cf_string(key);
+    let number = CFNumberCreate(kCFAllocatorDefault, kCFNumberSInt32Type, (&value as *const i32).cast());
+    CFDictionarySetValue(dictionary.cast(), key.cast(), number.cast());
+    CFRelease(number.cast());
+    CFRelease(key.cast());
+}
+
+// Diccionario de ClassInfo con las claves que espera el host (kAUPreset*Key); el
+// estado propio va en "data". Quien lo pide se queda con la referencia.
+unsafe fn class_info(unit: &RustSynthAu) -> CFTypeRef {
+    let dictionary = CFDictionaryCreateMutable(
+        kCFAllocatorDefault,
+        0,
+        &kCFTypeDictionaryKeyCallBacks,
+        &kCFTypeDictionaryValueCallBacks,
+    );
+    set_number(dictionary.cast(), "version", 0);
+    set_number(dictionary.cast(), "type", four_char_code(AU_TYPE) as i32);
+    set_number(dictionary.cast(), "subtype", four_char_code(AU_SUBTYPE) as i32);
+    set_number(dictionary.cast(), "manufacturer", four_char_code(AU_MANUFACTURER) as i32);
+
+    let key = cf_string("name");
+    let name = cf_string("Sin título");
+    CFDictionarySetValue(dictionary, key.cast(), name.cast());
+    CFRelease(name.cast());
+    CFRelease(key.cast());
+
+    let state = unit.state_text();
+    let key = cf_string("data");
+    let data = CFDataCreate(kCFAllocatorDefault, state.as_ptr(), state.len() as _);
+    CFDictionarySetValue(dictionary, key.cast(), data.cast());
+    CFRelease(data.cast());
+    CFRelease(key.cast());
+    dictionary.cast()
+}
+
+unsafe fn load_class_info(unit: &RustSynthAu, state: CFTypeRef) -> bool {
+    if state.is_null() || CFGetTypeID(state) != CFDictionaryGetTypeID() {
+        return false;
+    }
+    let key = cf_string("data");
+    let data = CFDictionaryGetValue(state as CFDictionaryRef, key.cast()) as CFDataRef;
+    CFRelease(key.cast());
+    if data.is_null() || CFGetTypeID(data.cast()) != CFDataGetTypeID() {
+        return false;
+    }
+    let bytes = std::slice::from_raw_parts(CFDataGetBytePtr(data), CFDataGetLength(data) as usize);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => {
+            unit.load_state_text(text);
+            true
+        }
+        Err(_) => false,
+    }
+}
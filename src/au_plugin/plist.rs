@@ -0,0 +1,74 @@
+// Info.plist del bundle .component: además de los datos del bundle, la entrada
+// AudioComponents registra el instrumento (tipo, subtipo y fabricante) y nombra la
+// función de fábrica que el host busca en la biblioteca.
+use std::fs;
+use std::io;
+use std::path::Path;
+use super::{AU_MANUFACTURER, AU_NAME, AU_SUBTYPE, AU_TYPE, AU_VERSION, FACTORY_FUNCTION};
+
+// Nombre del ejecutable dentro de Contents/MacOS
+pub const EXECUTABLE: &str = "RustSynth";
+pub const BUNDLE_IDENTIFIER: &str = "com.yourname.rust-synth.au";
+
+pub fn info_plist() -> String {
+    let version = format!("{}.{}.{}", AU_VERSION >> 16, (AU_VERSION >> 8) & 0xFF, AU_VERSION & 0xFF);
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict>
+    <key>CFBundleDevelopmentRegion</key>
+    <string>es</string>
+    <key>CFBundleExecutable</key>
+    <string>{EXECUTABLE}</string>
+    <key>CFBundleIdentifier</key>
+    <string>{BUNDLE_IDENTIFIER}</string>
+    <key>CFBundleInfoDictionaryVersion</key>
+    <string>6.0</string>
+    <key>CFBundleName</key>
+    <string>Rust Synth</string>
+    <key>CFBundlePackageType</key>
+    <string>BNDL</string>
+    <key>CFBundleShortVersionString</key>
+    <string>{version}</string>
+    <key>CFBundleVersion</key>
+    <string>{version}</string>
+    <key>AudioComponents</key>
+    <array>
+        <dict>
+            <key>type</key>
+            <string>{AU_TYPE}</string>
+            <key>subtype</key>
+            <string>{AU_SUBTYPE}</string>
+            <key>manufacturer</key>
+            <string>{AU_MANUFACTURER}</string>
+            <key>name</key>
+            <string>{AU_NAME}</string>
+            <key>description</key>
+            <string>Sintetizador polifónico</string>
+            <key>version</key>
+            <integer>{AU_VERSION}</integer>
+            <key>factoryFunction</key>
+            <string>{FACTORY_FUNCTION}</string>
+            <key>sandboxSafe</key>
+            <true/>
+            <key>tags</key>
+            <array>
+                <string>Synthesizer</string>
+            </array>
+        </dict>
+    </array>
+</dict>
+</plist>
+"
+    )
+}
+
+// Montar el bundle: Contents/Info.plist y la biblioteca como Contents/MacOS/RustSynth
+pub fn write_bundle(dir: &Path, library: &Path) -> io::Result<()> {
+    let contents = dir.join("Contents");
+    fs::create_dir_all(contents.join("MacOS"))?;
+    fs::write(contents.join("Info.plist"), info_plist())?;
+    fs::copy(library, contents.join("MacOS").join(EXECUTABLE))?;
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use crate::dsp::smoother::ParamSmoother;
+use crate::dsp::{FxRack, FxSettings, FxSlotSettings, MasterFilter, MasterFilterSettings, Note, ParaphonicBus, Saturation, SaturationSettings, VoiceMap};
+use crate::structs::split::SplitSettings;
+
+// Núcleo de render del `VoiceManager` de la API, que usan todos los frontends: la
+// aplicación, el modo consola, la tubería y los plugins. Las voces se mezclan con
+// `mix_voice` y la mezcla pasa por la `OutputStage`.
+
+// Sumar a la mezcla el frame de una voz, con su envolvente de amplitud y la ganancia dada
+#[inline]
+pub fn mix_voice(mix: &mut (f32, f32), note: &mut Note, gain: f32) {
+    let envelope_amp = note.envelope.next_sample();
+    let (left, right) = note.get_sample();
+    mix.0 += left * envelope_amp * gain;
+    mix.1 += right * envelope_amp * gain;
+}
+
+// Frame de salida por etapas, para quien reparte cada una por su lado (salidas del plugin)
+#[derive(Clone, Copy)]
+pub struct OutputLayers {
+    // Mezcla tras el filtro maestro, antes de los efectos
+    pub dry: (f32, f32),
+    // Salida del rack de efectos
+    pub wet: (f32, f32),
+    // Salida final, tras la saturación
+    pub output: (f32, f32),
+}
+
+// Frame de `render_layered_block`: las etapas de salida y, aparte, las voces alternas
+// por pulsación (con sus copias del unísono). Son tomas directas de las voces, antes del
+// filtro maestro, el bloqueador de continua, los efectos y la saturación; en modo
+// parafónico quedan en silencio, porque sin la envolvente común las voces no se apagan.
+#[derive(Clone, Copy)]
+pub struct LayeredFrame {
+    pub layers: OutputLayers,
+    pub even_voices: (f32, f32),
+    pub odd_voices: (f32, f32),
+}
+
+impl Default for LayeredFrame {
+    fn default() -> Self {
+        let silence = (0.0, 0.0);
+        Self {
+            layers: OutputLayers { dry: silence, wet: silence, output: silence },
+            even_voices: silence,
+            odd_voices: silence,
+        }
+    }
+}
+
+// Cadena de salida sobre la mezcla de voces: filtro maestro (con el bloqueador de
+// continua), rack de efectos y saturación
+pub struct OutputStage {
+    master: MasterFilter,
+    fx_rack: FxRack,
+    saturation: Saturation,
+    sample_rate: f32,
+}
+
+impl OutputStage {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            master: MasterFilter::new(sample_rate),
+            fx_rack: FxRack::new(sample_rate),
+            saturation: Saturation::default(),
+            sample_rate,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.master.set_sample_rate(sample_rate);
+        self.fx_rack.set_sample_rate(sample_rate);
+    }
+
+    // Los efectos que siguen en el rack conservan sus colas; el filtro maestro desliza
+    // los cortes hacia los nuevos
+    pub fn set_settings(&mut self, master: MasterFilterSettings, fx: &FxSettings, saturation: SaturationSettings) {
+        self.master.set_settings(master, self.sample_rate);
+        self.fx_rack.set_settings(fx, self.sample_rate);
+        self.saturation = Saturation::new(saturation);
+    }
+
+    // Bypass y mezcla de una ranura del rack, sin pasar por el resto de los ajustes
+    pub fn set_fx_slot(&mut self, slot: usize, settings: FxSlotSettings) {
+        self.fx_rack.set_slot(slot, settings);
+    }
+
+    // Vaciar las colas de los efectos y el estado del bloqueador de continua (All Sound Off)
+    pub fn reset(&mut self) {
+        self.master.reset();
+        self.fx_rack.reset();
+    }
+
+    #[inline]
+    pub fn process(&mut self, mix: (f32, f32)) -> (f32, f32) {
+        self.process_layers(mix).output
+    }
+
+    #[inline]
+    pub fn process_layers(&mut self, mix: (f32, f32)) -> OutputLayers {
+        let dry = self.master.process(mix);
+        let wet = self.fx_rack.process(dry);
+        OutputLayers { dry, wet, output: self.saturation.process_frame(wet) }
+    }
+
+    // Bloque estéreo intercalado (L, R, L, R...): el rack procesa el bloque entero, que
+    // es más eficiente para la reverb por convolución
+    pub fn process_block(&mut self, block: &mut [f32]) {
+        for frame in block.chunks_exact_mut(2) {
+            let (left, right) = self.master.process((frame[0], frame[1]));
+            frame[0] = left;
+            frame[1] = right;
+        }
+        self.fx_rack.process_block(block);
+        for sample in block.iter_mut() {
+            *sample = self.saturation.process(*sample);
+        }
+    }
+}
+
+// La frecuencia de muestreo real se fija en el primer `set_sample_rate`
+impl Default for OutputStage {
+    fn default() -> Self {
+        Self::new(44100.0)
+    }
+}
+
+// Renderizar un bloque estéreo intercalado (L, R, L, R...) con las notas activas y
+// eliminar las que han terminado. Es la ruta de render offline, independiente de
+// cualquier dispositivo de audio. En modo parafónico la mezcla pasa antes por la
+// envolvente y el filtro comunes. La ganancia se suaviza muestra a muestra hacia su
+// objetivo y cada voz suena con el volumen de su zona del teclado dividido. Devuelve
+// cuántas voces se eliminaron tras cortarse a la fuerza.
+pub fn render_block(
+    notes: &mut VoiceMap,
+    mut paraphonic: Option<&mut ParaphonicBus>,
+    split: &SplitSettings,
+    output_stage: &mut OutputStage,
+    gain: &mut ParamSmoother,
+    output: &mut [f32],
+) -> usize {
+    if let Some(bus) = paraphonic.as_mut() {
+        bus.update(notes);
+    }
+    for frame in output.chunks_exact_mut(2) {
+        let mut mix = (0.0, 0.0);
+        let gain = gain.next_value();
+        for (key, note) in notes.iter_mut() {
+            mix_voice(&mut mix, note, gain * split.volume(key));
+        }
+        if let Some(bus) = paraphonic.as_mut() {
+            mix = bus.process(mix);
+        }
+        frame[0] = mix.0;
+        frame[1] = mix.1;
+    }
+
+    output_stage.process_block(output);
+
+    notes.remove_finished()
+}
+
+// Igual que `render_block`, pero frame a frame y con cada etapa por separado, para
+// quien reparte las capas en salidas distintas (el plugin VST3)
+pub fn render_layered_block(
+    notes: &mut VoiceMap,
+    mut paraphonic: Option<&mut ParaphonicBus>,
+    split: &SplitSettings,
+    output_stage: &mut OutputStage,
+    gain: &mut ParamSmoother,
+    output: &mut [LayeredFrame],
+) -> usize {
+    if let Some(bus) = paraphonic.as_mut() {
+        bus.update(notes);
+    }
+    for frame in output.iter_mut() {
+        let mut voices = [(0.0, 0.0); 2];
+        let gain = gain.next_value();
+        for voice in notes.voices_mut() {
+            mix_voice(&mut voices[voice.bus], &mut voice.note, gain * split.volume(voice.key));
+        }
+        let mut mix = (voices[0].0 + voices[1].0, voices[0].1 + voices[1].1);
+        if let Some(bus) = paraphonic.as_mut() {
+            mix = bus.process(mix);
+            voices = [(0.0, 0.0); 2];
+        }
+        *frame = LayeredFrame {
+            layers: output_stage.process_layers(mix),
+            even_voices: voices[0],
+            odd_voices: voices[1],
+        };
+    }
+
+    notes.remove_finished()
+}
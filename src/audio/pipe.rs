@@ -0,0 +1,120 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use super::watchdog::{StaleVoiceLog, VoiceWatchdog};
+use crate::api::SynthEngine;
+
+// Frames renderizados por cada escritura en la tubería
+const PIPE_BLOCK_FRAMES: usize = 512;
+const PIPE_CHANNELS: u16 = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PipeFormat {
+    // PCM float de 32 bits little-endian, estéreo intercalado
+    Raw,
+    // WAV PCM de 16 bits con tamaño indefinido para streaming
+    Wav,
+}
+
+impl PipeFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "raw" => Some(PipeFormat::Raw),
+            "wav" => Some(PipeFormat::Wav),
+            _ => None,
+        }
+    }
+}
+
+// Cabecera WAV con los tamaños al máximo, ya que la longitud no se conoce de antemano
+fn write_wav_header<W: Write>(writer: &mut W, sample_rate: u32, channels: u16) -> io::Result<()> {
+    let bits_per_sample: u16 = 16;
+    let block_align = channels * bits_per_sample / 8;
+    let byte_rate = sample_rate * block_align as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&u32::MAX.to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&bits_per_sample.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&u32::MAX.to_le_bytes())?;
+    Ok(())
+}
+
+// Enviar el audio a stdout ("-") o a un archivo / tubería con nombre, en tiempo real,
+// hasta que `running` pase a false o el lector cierre la tubería. El motor ya tiene el
+// patch y la frecuencia de muestreo de la salida.
+pub fn run_pipe_output(
+    target: &str,
+    format: PipeFormat,
+    sample_rate: u32,
+    engine: Arc<Mutex<SynthEngine>>,
+    watchdog: Arc<VoiceWatchdog>,
+    running: Arc<AtomicBool>,
+) -> io::Result<()> {
+    let sink: Box<dyn Write> = if target == "-" {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(OpenOptions::new().write(true).create(true).open(target)?)
+    };
+    let mut writer = BufWriter::new(sink);
+
+    if format == PipeFormat::Wav {
+        write_wav_header(&mut writer, sample_rate, PIPE_CHANNELS)?;
+    }
+
+    let mut left = [0.0f32; PIPE_BLOCK_FRAMES];
+    let mut right = [0.0f32; PIPE_BLOCK_FRAMES];
+    let mut bytes = Vec::with_capacity(PIPE_BLOCK_FRAMES * PIPE_CHANNELS as usize * 4);
+    let start = Instant::now();
+    let mut frames_written: u64 = 0;
+    // Las voces liberadas por el vigilante se anotan en stderr, fuera del motor
+    let mut stale_voices = StaleVoiceLog::new();
+
+    while running.load(Ordering::Relaxed) {
+        {
+            let mut engine = engine.lock().unwrap();
+            // Vigilar voces colgadas por Note Off perdidos
+            engine.voices_mut().check_watchdog(&watchdog);
+            engine.process(&mut left, &mut right);
+        }
+
+        bytes.clear();
+        for &sample in left.iter().zip(right.iter()).flat_map(|(left, right)| [left, right]) {
+            match format {
+                PipeFormat::Raw => bytes.extend_from_slice(&sample.to_le_bytes()),
+                PipeFormat::Wav => {
+                    let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+
+        match writer.write_all(&bytes).and_then(|_| writer.flush()) {
+            Ok(()) => (),
+            // El lector cerró la tubería: terminar sin error
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => break,
+            Err(err) => return Err(err),
+        }
+        stale_voices.update(&watchdog);
+
+        // Mantener el ritmo del reloj real para que el MIDI en vivo suene a tiempo
+        frames_written += PIPE_BLOCK_FRAMES as u64;
+        let expected = Duration::from_secs_f64(frames_written as f64 / sample_rate as f64);
+        let elapsed = start.elapsed();
+        if expected > elapsed {
+            std::thread::sleep(expected - elapsed);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+// Peso de cada callback nuevo en la media del tiempo de proceso
+const CALLBACK_TIME_SMOOTHING: f32 = 0.05;
+// Caída del medidor de pico entre callbacks (dB por segundo)
+const PEAK_FALL_DB_PER_SECOND: f32 = 20.0;
+
+// Estadísticas del motor en tiempo real. Solo las escribe el hilo de audio, con
+// atómicos para que la GUI, el host o quien incrusta el motor las lean sin bloquearlo.
+// Los valores en coma flotante se guardan como bits de f32.
+#[derive(Default)]
+pub struct EngineStats {
+    active_voices: AtomicUsize,
+    // Voces cortadas antes de terminar su liberación (pánico, All Sound Off...)
+    stolen_voices: AtomicU64,
+    // Media del tiempo de cada callback, en segundos
+    callback_time: AtomicU32,
+    // Tiempo de proceso respecto a la duración del bloque (1.0 = al límite)
+    load: AtomicU32,
+    peak: AtomicU32,
+}
+
+// Copia de las estadísticas en un instante
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct StatsSnapshot {
+    pub active_voices: usize,
+    pub stolen_voices: u64,
+    pub average_callback_time: Duration,
+    pub load: f32,
+    // Pico de la salida con caída lenta, en amplitud lineal
+    pub peak: f32,
+}
+
+impl StatsSnapshot {
+    pub fn peak_db(&self) -> f32 {
+        20.0 * self.peak.max(1e-6).log10()
+    }
+}
+
+impl EngineStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Anotar un callback de audio: lo que tardó, cuántos frames produjo, las voces que
+    // quedan sonando y el pico del bloque de salida
+    pub fn record_callback(&self, elapsed: Duration, frames: usize, sample_rate: f32, active_voices: usize, block_peak: f32) {
+        self.active_voices.store(active_voices, Ordering::Relaxed);
+
+        let elapsed = elapsed.as_secs_f32();
+        let average = load_f32(&self.callback_time);
+        let average = if average == 0.0 {
+            elapsed
+        } else {
+            average + (elapsed - average) * CALLBACK_TIME_SMOOTHING
+        };
+        store_f32(&self.callback_time, average);
+
+        let block_time = frames as f32 / sample_rate.max(1.0);
+        if block_time > 0.0 {
+            store_f32(&self.load, average / block_time);
+        }
+
+        let fall = 10f32.powf(-PEAK_FALL_DB_PER_SECOND * block_time / 20.0);
+        let peak = (load_f32(&self.peak) * fall).max(block_peak.abs());
+        store_f32(&self.peak, peak);
+    }
+
+    pub fn add_stolen(&self, count: usize) {
+        if count > 0 {
+            self.stolen_voices.fetch_add(count as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            active_voices: self.active_voices.load(Ordering::Relaxed),
+            stolen_voices: self.stolen_voices.load(Ordering::Relaxed),
+            average_callback_time: Duration::from_secs_f32(load_f32(&self.callback_time)),
+            load: load_f32(&self.load),
+            peak: load_f32(&self.peak),
+        }
+    }
+
+    // Volver a contar las voces robadas y el pico desde cero
+    pub fn reset(&self) {
+        self.stolen_voices.store(0, Ordering::Relaxed);
+        store_f32(&self.peak, 0.0);
+    }
+}
+
+// Pico absoluto de un bloque de muestras
+pub fn block_peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+}
+
+fn load_f32(value: &AtomicU32) -> f32 {
+    f32::from_bits(value.load(Ordering::Relaxed))
+}
+
+fn store_f32(value: &AtomicU32, new_value: f32) {
+    value.store(new_value.to_bits(), Ordering::Relaxed);
+}
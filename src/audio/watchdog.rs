@@ -0,0 +1,173 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use crate::structs::envelope::EnvelopeState;
+use crate::dsp::VoiceMap;
+
+// Tiempo en sustain sin tecla pulsada antes de liberar una voz (segundos)
+pub const DEFAULT_STALE_VOICE_TIMEOUT: f32 = 30.0;
+// Entradas que se conservan en el registro de diagnóstico
+const MAX_LOG_ENTRIES: usize = 50;
+
+// Voz liberada por el vigilante: la tecla y los segundos que llevaba en sustain
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StaleVoice {
+    pub key: u8,
+    pub seconds: f32,
+}
+
+impl StaleVoice {
+    // Segundos como bits de f32 en los 32 bits bajos y la tecla en los 8 siguientes; el
+    // bit 40 marca la entrada como escrita
+    fn pack(self) -> u64 {
+        (1 << 40) | ((self.key as u64) << 32) | self.seconds.to_bits() as u64
+    }
+
+    fn unpack(bits: u64) -> Option<Self> {
+        (bits & (1 << 40) != 0).then(|| Self {
+            key: (bits >> 32) as u8,
+            seconds: f32::from_bits(bits as u32),
+        })
+    }
+}
+
+// Protección contra Note Off perdidos: libera las voces que llevan demasiado
+// tiempo en sustain sin que su tecla siga pulsada ni el pedal de sustain pisado.
+// Todo son atómicos para que el hilo de audio nunca espere a la interfaz: las voces
+// liberadas se anotan en un anillo de tamaño fijo y el texto lo compone quien lee
+// el registro (`StaleVoiceLog`).
+pub struct VoiceWatchdog {
+    // Segundos en sustain antes de forzar el release (0 = desactivado), como bits de f32
+    timeout: AtomicU32,
+    held_keys: [AtomicBool; 128],
+    sustain_pedal: AtomicBool,
+    events: [AtomicU64; MAX_LOG_ENTRIES],
+    // Voces liberadas desde el principio; la siguiente va en `written % MAX_LOG_ENTRIES`
+    written: AtomicUsize,
+}
+
+impl Default for VoiceWatchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VoiceWatchdog {
+    pub fn new() -> Self {
+        Self::with_timeout(DEFAULT_STALE_VOICE_TIMEOUT)
+    }
+
+    pub fn with_timeout(timeout: f32) -> Self {
+        Self {
+            timeout: AtomicU32::new(timeout.to_bits()),
+            held_keys: std::array::from_fn(|_| AtomicBool::new(false)),
+            sustain_pedal: AtomicBool::new(false),
+            events: std::array::from_fn(|_| AtomicU64::new(0)),
+            written: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn timeout(&self) -> f32 {
+        f32::from_bits(self.timeout.load(Ordering::Relaxed))
+    }
+
+    pub fn set_timeout(&self, timeout: f32) {
+        self.timeout.store(timeout.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn key_down(&self, key: u8) {
+        self.held_keys[(key & 0x7F) as usize].store(true, Ordering::Relaxed);
+    }
+
+    pub fn key_up(&self, key: u8) {
+        self.held_keys[(key & 0x7F) as usize].store(false, Ordering::Relaxed);
+    }
+
+    // Pedal de sustain (CC64): mientras está pisado no se libera ninguna voz
+    pub fn set_sustain_pedal(&self, down: bool) {
+        self.sustain_pedal.store(down, Ordering::Relaxed);
+    }
+
+    // Todas las teclas sueltas y el pedal levantado (All Notes Off, All Sound Off, pánico)
+    pub fn release_all(&self) {
+        for key in &self.held_keys {
+            key.store(false, Ordering::Relaxed);
+        }
+        self.sustain_pedal.store(false, Ordering::Relaxed);
+    }
+
+    // Revisar las voces activas y forzar el release de las colgadas. No reserva memoria
+    // ni bloquea: se puede llamar en cada bloque desde el hilo de audio.
+    pub fn check(&self, notes: &mut VoiceMap) {
+        let timeout = self.timeout();
+        if timeout <= 0.0 || self.sustain_pedal.load(Ordering::Relaxed) {
+            return;
+        }
+        for (key, note) in notes.iter_mut() {
+            let envelope = &note.envelope;
+            if envelope.state == EnvelopeState::Sustain
+                && !self.held_keys[(key & 0x7F) as usize].load(Ordering::Relaxed)
+                && envelope.sustain_seconds() > timeout
+            {
+                self.record(StaleVoice { key, seconds: envelope.sustain_seconds() });
+                note.note_off();
+            }
+        }
+    }
+
+    // Un único escritor (el hilo de audio); si el lector se queda atrás pierde las más viejas
+    fn record(&self, event: StaleVoice) {
+        let index = self.written.load(Ordering::Relaxed);
+        self.events[index % MAX_LOG_ENTRIES].store(event.pack(), Ordering::Relaxed);
+        self.written.store(index + 1, Ordering::Release);
+    }
+
+    // Voces liberadas a partir de `cursor`, que se deja apuntando a la siguiente
+    fn read_since(&self, cursor: &mut usize, mut f: impl FnMut(StaleVoice)) {
+        let written = self.written.load(Ordering::Acquire);
+        let start = (*cursor).max(written.saturating_sub(MAX_LOG_ENTRIES));
+        for index in start..written {
+            if let Some(event) = StaleVoice::unpack(self.events[index % MAX_LOG_ENTRIES].load(Ordering::Relaxed)) {
+                f(event);
+            }
+        }
+        *cursor = written;
+    }
+}
+
+// Registro de diagnóstico de quien muestra las voces liberadas (la interfaz, la consola).
+// Compone y escribe el texto fuera del hilo de audio.
+#[derive(Default)]
+pub struct StaleVoiceLog {
+    cursor: usize,
+    entries: VecDeque<String>,
+}
+
+impl StaleVoiceLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Recoger las voces liberadas desde la última vez, mostrándolas también por stderr
+    pub fn update(&mut self, watchdog: &VoiceWatchdog) {
+        let entries = &mut self.entries;
+        watchdog.read_since(&mut self.cursor, |event| {
+            let message = format!(
+                "Voz {} liberada tras {:.1} s en sustain sin tecla pulsada",
+                event.key, event.seconds
+            );
+            eprintln!("{}", message);
+            if entries.len() == MAX_LOG_ENTRIES {
+                entries.pop_front();
+            }
+            entries.push_back(message);
+        });
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
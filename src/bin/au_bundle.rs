@@ -0,0 +1,46 @@
+// Monta el bundle del Audio Unit: Info.plist con el registro del componente y la
+// biblioteca compilada como ejecutable del bundle. Solo en macOS.
+//
+//     cargo build --release --features au
+//     cargo run --release --features au --bin au-bundle -- ~/Library/Audio/Plug-Ins/Components/RustSynth.component
+//
+// Sin argumento el bundle queda en target/RustSynth.component. --lib indica otra
+// biblioteca distinta de la de target/release. Logic vuelve a leer los componentes al
+// arrancar; si no aparece, `auval -v aumu rsyn RSyn` muestra por qué.
+use std::process;
+
+#[cfg(target_os = "macos")]
+fn main() {
+    use std::env;
+    use std::path::PathBuf;
+    use rust_synth::au_plugin::plist::write_bundle;
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut bundle = PathBuf::from("target/RustSynth.component");
+    let mut library = PathBuf::from("target/release/librust_synth.dylib");
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--lib" {
+            i += 1;
+            if let Some(path) = args.get(i) {
+                library = PathBuf::from(path);
+            }
+        } else {
+            bundle = PathBuf::from(&args[i]);
+        }
+        i += 1;
+    }
+
+    if let Err(err) = write_bundle(&bundle, &library) {
+        eprintln!("No se pudo montar el bundle en {}: {}", bundle.display(), err);
+        eprintln!("Compila antes el plugin con: cargo build --release --features au");
+        process::exit(1);
+    }
+    println!("Audio Unit listo en {}", bundle.display());
+}
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    eprintln!("Los Audio Units solo existen en macOS");
+    process::exit(1);
+}
@@ -0,0 +1,41 @@
+// Monta el bundle LV2: escribe los .ttl y copia la biblioteca compilada a su lado.
+//
+//     cargo build --release --features lv2
+//     cargo run --release --features lv2 --bin lv2-bundle -- ~/.lv2/rust-synth.lv2
+//
+// Sin argumento el bundle queda en target/rust-synth.lv2. --lib indica otra biblioteca
+// distinta de la de target/release.
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use rust_synth::lv2_plugin::ttl::{library_name, write_bundle};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut bundle = PathBuf::from("target/rust-synth.lv2");
+    let mut library = PathBuf::from("target/release").join(library_name());
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--lib" {
+            i += 1;
+            if let Some(path) = args.get(i) {
+                library = PathBuf::from(path);
+            }
+        } else {
+            bundle = PathBuf::from(&args[i]);
+        }
+        i += 1;
+    }
+
+    // El manifest nombra la biblioteca con el nombre de la plataforma, aunque venga de otra ruta
+    if let Err(err) = write_bundle(&bundle, &library_name()) {
+        eprintln!("No se pudo escribir el bundle en {}: {}", bundle.display(), err);
+        process::exit(1);
+    }
+    if let Err(err) = std::fs::copy(&library, bundle.join(library_name())) {
+        eprintln!("No se pudo copiar {}: {}", library.display(), err);
+        eprintln!("Compila antes el plugin con: cargo build --release --features lv2");
+        process::exit(1);
+    }
+    println!("Bundle LV2 listo en {}", bundle.display());
+}
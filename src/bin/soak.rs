@@ -0,0 +1,215 @@
+// Prueba de resistencia: hace sonar el motor durante horas con MIDI y cambios de
+// parámetros aleatorios a alta polifonía, y falla si aparece un NaN, si la memoria
+// crece sin límite o si un bloque tarda más de lo que dura en tiempo real.
+//
+//     cargo run --release --features soak --bin soak -- --hours 4
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use rust_synth::api::{SynthEngine, WaveType, MAX_OSCILLATORS, PARAMETERS};
+
+const BLOCK_FRAMES: usize = 256;
+// Teclas pulsadas a la vez como objetivo de la prueba
+const TARGET_POLYPHONY: usize = 64;
+// Tiempo de calentamiento antes de fijar la memoria de referencia
+const WARMUP: Duration = Duration::from_secs(30);
+// Crecimiento máximo permitido sobre la referencia
+const MAX_MEMORY_GROWTH: usize = 16 * 1024 * 1024;
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+// Asignador que lleva la cuenta de los bytes vivos
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// Generador xorshift: reproducible con la misma semilla
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn unit(&mut self) -> f32 {
+        (self.next() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f32) -> bool {
+        self.unit() < probability
+    }
+}
+
+struct Options {
+    duration: Duration,
+    seed: u64,
+    sample_rate: f32,
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        duration: Duration::from_secs(3600),
+        seed: 0x5EED,
+        sample_rate: 48000.0,
+    };
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut i = 0;
+    while i < args.len() {
+        let value = args.get(i + 1).map(String::as_str).unwrap_or("");
+        match args[i].as_str() {
+            "--hours" => options.duration = Duration::from_secs_f64(value.parse::<f64>().unwrap_or(1.0) * 3600.0),
+            "--seconds" => options.duration = Duration::from_secs_f64(value.parse().unwrap_or(60.0)),
+            "--seed" => options.seed = value.parse().unwrap_or(options.seed).max(1),
+            "--sample-rate" => options.sample_rate = value.parse().unwrap_or(options.sample_rate),
+            other => {
+                eprintln!("Argumento desconocido: {}", other);
+                std::process::exit(2);
+            }
+        }
+        i += 2;
+    }
+    options
+}
+
+fn main() {
+    let options = parse_options();
+    let mut rng = Rng(options.seed);
+    let mut engine = SynthEngine::new(options.sample_rate);
+    let mut left = vec![0.0; BLOCK_FRAMES];
+    let mut right = vec![0.0; BLOCK_FRAMES];
+    let mut held = Vec::with_capacity(128);
+
+    let budget = Duration::from_secs_f32(BLOCK_FRAMES as f32 / options.sample_rate);
+    let start = Instant::now();
+    let mut next_report = start + REPORT_INTERVAL;
+    let mut memory_baseline = None;
+    let mut blocks: u64 = 0;
+    let mut slowest = Duration::ZERO;
+    let mut peak_voices = 0;
+
+    println!(
+        "Prueba de resistencia: {:.1} h, semilla {}, {} Hz, bloques de {} muestras ({:.2} ms)",
+        options.duration.as_secs_f64() / 3600.0,
+        options.seed,
+        options.sample_rate,
+        BLOCK_FRAMES,
+        budget.as_secs_f64() * 1000.0,
+    );
+
+    while start.elapsed() < options.duration {
+        // Eventos MIDI: la polifonía oscila alrededor del objetivo
+        let press_probability = if held.len() < TARGET_POLYPHONY { 0.6 } else { 0.2 };
+        if rng.chance(press_probability) {
+            let note = rng.below(128) as u8;
+            engine.note_on(note, rng.unit());
+            if !held.contains(&note) {
+                held.push(note);
+            }
+        }
+        if !held.is_empty() && rng.chance(0.4) {
+            let note = held.swap_remove(rng.below(held.len()));
+            engine.note_off(note);
+        }
+        if rng.chance(0.0005) {
+            engine.all_notes_off();
+            held.clear();
+        }
+
+        // Automatización de parámetros en todo su rango
+        if rng.chance(0.3) {
+            let info = &PARAMETERS[rng.below(PARAMETERS.len())];
+            let value = info.from_normalized(rng.unit());
+            engine.set_parameter(info.id, value).expect("parámetro del registro");
+        }
+        if rng.chance(0.01) {
+            let mut patch = engine.patch().clone();
+            let settings = &mut patch.oscillators[rng.below(MAX_OSCILLATORS)];
+            settings.wave_type = WaveType::ALL[rng.below(WaveType::ALL.len())];
+            settings.detune = rng.unit() * 24.0 - 12.0;
+            settings.fold = rng.unit();
+            settings.pan = rng.unit() * 2.0 - 1.0;
+            engine.set_patch(patch);
+        }
+
+        let block_start = Instant::now();
+        engine.process(&mut left, &mut right);
+        let elapsed = block_start.elapsed();
+        blocks += 1;
+
+        for (frame, (l, r)) in left.iter().zip(right.iter()).enumerate() {
+            assert!(
+                l.is_finite() && r.is_finite(),
+                "muestra no finita en el bloque {} (frame {}): ({}, {})",
+                blocks,
+                frame,
+                l,
+                r
+            );
+        }
+        assert!(
+            elapsed <= budget,
+            "el bloque {} tardó {:.3} ms, más que su duración en tiempo real ({:.3} ms)",
+            blocks,
+            elapsed.as_secs_f64() * 1000.0,
+            budget.as_secs_f64() * 1000.0
+        );
+        slowest = slowest.max(elapsed);
+        peak_voices = peak_voices.max(engine.voices().active_voices());
+
+        let live = LIVE_BYTES.load(Ordering::Relaxed);
+        match memory_baseline {
+            None if start.elapsed() >= WARMUP => memory_baseline = Some(live),
+            Some(baseline) => assert!(
+                live <= baseline + MAX_MEMORY_GROWTH,
+                "la memoria creció de {} a {} bytes",
+                baseline,
+                live
+            ),
+            None => (),
+        }
+
+        if Instant::now() >= next_report {
+            next_report += REPORT_INTERVAL;
+            println!(
+                "{:>6.1} min | {} bloques | bloque más lento {:.3} ms | voces máx. {} | memoria {} KiB",
+                start.elapsed().as_secs_f64() / 60.0,
+                blocks,
+                slowest.as_secs_f64() * 1000.0,
+                peak_voices,
+                live / 1024,
+            );
+        }
+    }
+
+    println!(
+        "Prueba superada: {} bloques, bloque más lento {:.3} ms, voces máx. {}",
+        blocks,
+        slowest.as_secs_f64() * 1000.0,
+        peak_voices
+    );
+}
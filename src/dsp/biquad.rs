@@ -0,0 +1,159 @@
+use std::f32::consts::PI;
+
+// Coeficientes de un biquad ya normalizados (a0 = 1), calculados con las fórmulas
+// del "Audio EQ Cookbook" de Robert Bristow-Johnson. Las ganancias van en dB.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BiquadCoefficients {
+    pub b0: f32,
+    pub b1: f32,
+    pub b2: f32,
+    pub a1: f32,
+    pub a2: f32,
+}
+
+impl Default for BiquadCoefficients {
+    // Paso directo: la salida es igual a la entrada
+    fn default() -> Self {
+        Self {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+        }
+    }
+}
+
+// Frecuencia angular y alfa comunes a todos los diseños
+fn omega_alpha(frequency: f32, q: f32, sample_rate: f32) -> (f32, f32) {
+    let frequency = frequency.clamp(1.0, sample_rate * 0.49);
+    let omega = 2.0 * PI * frequency / sample_rate;
+    (omega, omega.sin() / (2.0 * q.max(0.01)))
+}
+
+impl BiquadCoefficients {
+    fn normalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    pub fn low_pass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (omega, alpha) = omega_alpha(frequency, q, sample_rate);
+        let cos = omega.cos();
+        Self::normalized((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    pub fn high_pass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (omega, alpha) = omega_alpha(frequency, q, sample_rate);
+        let cos = omega.cos();
+        Self::normalized((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    // Paso banda con ganancia 0 dB en el pico
+    pub fn band_pass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (omega, alpha) = omega_alpha(frequency, q, sample_rate);
+        Self::normalized(alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * omega.cos(), 1.0 - alpha)
+    }
+
+    pub fn notch(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (omega, alpha) = omega_alpha(frequency, q, sample_rate);
+        let cos = omega.cos();
+        Self::normalized(1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    // Campana de ecualizador
+    pub fn peak(frequency: f32, q: f32, gain_db: f32, sample_rate: f32) -> Self {
+        let (omega, alpha) = omega_alpha(frequency, q, sample_rate);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let cos = omega.cos();
+        Self::normalized(
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        )
+    }
+
+    pub fn low_shelf(frequency: f32, q: f32, gain_db: f32, sample_rate: f32) -> Self {
+        let (omega, alpha) = omega_alpha(frequency, q, sample_rate);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let cos = omega.cos();
+        let root = 2.0 * a.sqrt() * alpha;
+        Self::normalized(
+            a * ((a + 1.0) - (a - 1.0) * cos + root),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - root),
+            (a + 1.0) + (a - 1.0) * cos + root,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - root,
+        )
+    }
+
+    pub fn high_shelf(frequency: f32, q: f32, gain_db: f32, sample_rate: f32) -> Self {
+        let (omega, alpha) = omega_alpha(frequency, q, sample_rate);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let cos = omega.cos();
+        let root = 2.0 * a.sqrt() * alpha;
+        Self::normalized(
+            a * ((a + 1.0) + (a - 1.0) * cos + root),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - root),
+            (a + 1.0) - (a - 1.0) * cos + root,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - root,
+        )
+    }
+}
+
+// Filtro biquad en forma directa I. Los coeficientes se pueden cambiar en cualquier
+// momento sin perder el estado.
+#[derive(Clone, Copy, Default)]
+pub struct Biquad {
+    coefficients: BiquadCoefficients,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    pub fn new(coefficients: BiquadCoefficients) -> Self {
+        Self {
+            coefficients,
+            ..Self::default()
+        }
+    }
+
+    pub fn coefficients(&self) -> BiquadCoefficients {
+        self.coefficients
+    }
+
+    pub fn set_coefficients(&mut self, coefficients: BiquadCoefficients) {
+        self.coefficients = coefficients;
+    }
+
+    pub fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.x2 = 0.0;
+        self.y1 = 0.0;
+        self.y2 = 0.0;
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        let c = &self.coefficients;
+        let output = c.b0 * input + c.b1 * self.x1 + c.b2 * self.x2 - c.a1 * self.y1 - c.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = input;
+        self.y2 = self.y1;
+        self.y1 = output;
+        output
+    }
+}
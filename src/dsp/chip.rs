@@ -0,0 +1,111 @@
+// Canales del modo chiptune, al estilo de los chips de sonido de NES y Game Boy
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChipChannel {
+    // Pulso con ciclo de trabajo fijo
+    Pulse,
+    // Triangular escalonada
+    Triangle,
+    // Ruido de registro de desplazamiento (LFSR)
+    Noise,
+}
+
+impl ChipChannel {
+    pub const ALL: [ChipChannel; 3] = [ChipChannel::Pulse, ChipChannel::Triangle, ChipChannel::Noise];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChipChannel::Pulse => "Pulso",
+            ChipChannel::Triangle => "Triangular",
+            ChipChannel::Noise => "Ruido",
+        }
+    }
+}
+
+// Ciclos de trabajo disponibles en los canales de pulso
+#[derive(Clone, Copy, PartialEq)]
+pub enum DutyCycle {
+    Eighth,
+    Quarter,
+    Half,
+}
+
+impl DutyCycle {
+    pub const ALL: [DutyCycle; 3] = [DutyCycle::Eighth, DutyCycle::Quarter, DutyCycle::Half];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DutyCycle::Eighth => "12.5%",
+            DutyCycle::Quarter => "25%",
+            DutyCycle::Half => "50%",
+        }
+    }
+
+    pub fn ratio(&self) -> f32 {
+        match self {
+            DutyCycle::Eighth => 0.125,
+            DutyCycle::Quarter => 0.25,
+            DutyCycle::Half => 0.5,
+        }
+    }
+}
+
+// Profundidad por defecto, la del DAC de la triangular de la NES
+pub const DEFAULT_CHIP_BIT_DEPTH: u8 = 4;
+pub const MAX_CHIP_BIT_DEPTH: u8 = 8;
+// Veces que se desplaza el registro de ruido por cada ciclo de la nota
+const NOISE_CLOCKS_PER_CYCLE: f32 = 16.0;
+
+// Pulso sin limitar en banda: el aliasing forma parte del sonido original
+#[inline(always)]
+pub fn pulse(phase_norm: f32, duty: DutyCycle) -> f32 {
+    if phase_norm < duty.ratio() { 1.0 } else { -1.0 }
+}
+
+#[inline(always)]
+pub fn triangle(phase_norm: f32) -> f32 {
+    1.0 - 4.0 * (phase_norm - 0.5).abs()
+}
+
+// Reducir una muestra bipolar a 2^bits niveles
+#[inline(always)]
+pub fn quantize(sample: f32, bit_depth: u8) -> f32 {
+    let steps = ((1u32 << bit_depth.clamp(1, MAX_CHIP_BIT_DEPTH)) - 1) as f32;
+    ((sample.clamp(-1.0, 1.0) + 1.0) * 0.5 * steps).round() / steps * 2.0 - 1.0
+}
+
+// Ruido de 15 bits con realimentación de los bits 0 y 1, como el canal de ruido de la NES
+pub struct LfsrNoise {
+    register: u16,
+    clock_phase: f32,
+}
+
+impl Default for LfsrNoise {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LfsrNoise {
+    pub fn new() -> Self {
+        Self {
+            register: 1,
+            clock_phase: 0.0,
+        }
+    }
+
+    // Avanzar el registro al ritmo de la nota para que el ruido tenga altura
+    #[inline(always)]
+    pub fn advance(&mut self, phase_inc: f32) {
+        self.clock_phase += phase_inc * NOISE_CLOCKS_PER_CYCLE;
+        while self.clock_phase >= 1.0 {
+            self.clock_phase -= 1.0;
+            let feedback = (self.register ^ (self.register >> 1)) & 1;
+            self.register = (self.register >> 1) | (feedback << 14);
+        }
+    }
+
+    #[inline(always)]
+    pub fn output(&self) -> f32 {
+        if self.register & 1 == 0 { 1.0 } else { -1.0 }
+    }
+}
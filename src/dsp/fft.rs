@@ -0,0 +1,74 @@
+use std::f32::consts::PI;
+
+// FFT compleja radix-2 in-place con los factores de giro y la tabla de inversión de
+// bits calculados de antemano, para poder usarla en el hilo de audio sin reservar
+// memoria ni llamar a sin/cos. El tamaño tiene que ser potencia de dos.
+pub struct Fft {
+    size: usize,
+    // e^(-2πik/N) para k en 0..N/2
+    twiddle_re: Vec<f32>,
+    twiddle_im: Vec<f32>,
+    bit_reverse: Vec<usize>,
+}
+
+impl Fft {
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "el tamaño de la FFT tiene que ser potencia de dos");
+        let half = size / 2;
+        let (twiddle_im, twiddle_re) = (0..half)
+            .map(|k| (-2.0 * PI * k as f32 / size as f32).sin_cos())
+            .unzip();
+        let bits = size.trailing_zeros();
+        let bit_reverse = (0..size)
+            .map(|i| if bits == 0 { 0 } else { i.reverse_bits() >> (usize::BITS - bits) })
+            .collect();
+        Self { size, twiddle_re, twiddle_im, bit_reverse }
+    }
+
+    pub fn forward(&self, re: &mut [f32], im: &mut [f32]) {
+        self.transform(re, im, false);
+    }
+
+    // Transformada inversa sin escalar por 1/N
+    pub fn inverse(&self, re: &mut [f32], im: &mut [f32]) {
+        self.transform(re, im, true);
+    }
+
+    fn transform(&self, re: &mut [f32], im: &mut [f32], inverse: bool) {
+        let n = self.size;
+        debug_assert!(re.len() == n && im.len() == n);
+
+        for i in 0..n {
+            let j = self.bit_reverse[i];
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+
+        // La inversa usa los giros conjugados
+        let sign = if inverse { -1.0 } else { 1.0 };
+        let mut len = 2;
+        while len <= n {
+            let half = len / 2;
+            let stride = n / len;
+            for start in (0..n).step_by(len) {
+                for k in 0..half {
+                    let w_re = self.twiddle_re[k * stride];
+                    let w_im = sign * self.twiddle_im[k * stride];
+
+                    let a = start + k;
+                    let b = a + half;
+                    let t_re = re[b] * w_re - im[b] * w_im;
+                    let t_im = re[b] * w_im + im[b] * w_re;
+
+                    re[b] = re[a] - t_re;
+                    im[b] = im[a] - t_im;
+                    re[a] += t_re;
+                    im[a] += t_im;
+                }
+            }
+            len <<= 1;
+        }
+    }
+}
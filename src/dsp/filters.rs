@@ -0,0 +1,598 @@
+use std::f32::consts::PI;
+use super::biquad::{Biquad, BiquadCoefficients};
+use super::smoother::ParamSmoother;
+
+// Respuesta del filtro de banda
+#[derive(Clone, Copy, PartialEq)]
+pub enum BandMode {
+    // Rechaza la banda alrededor de la frecuencia central
+    Notch,
+    // Deja pasar solo la banda, con ganancia 0 dB en el pico
+    BandPass,
+}
+
+impl BandMode {
+    pub const ALL: [BandMode; 2] = [BandMode::Notch, BandMode::BandPass];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BandMode::Notch => "Banda eliminada",
+            BandMode::BandPass => "Paso banda",
+        }
+    }
+}
+
+// Rango del factor de calidad del filtro de banda
+pub const MIN_BAND_Q: f32 = 0.1;
+pub const MAX_BAND_Q: f32 = 20.0;
+
+// Factor de calidad a partir de un valor normalizado (0..1) en escala logarítmica
+pub fn band_q_from_normalized(value: f32) -> f32 {
+    MIN_BAND_Q * (MAX_BAND_Q / MIN_BAND_Q).powf(value.clamp(0.0, 1.0))
+}
+
+pub fn band_q_to_normalized(q: f32) -> f32 {
+    ((q / MIN_BAND_Q).ln() / (MAX_BAND_Q / MIN_BAND_Q).ln()).clamp(0.0, 1.0)
+}
+
+// Ajustes del filtro de banda de cada voz, tras el filtro resonante
+#[derive(Clone, Copy, PartialEq)]
+pub struct BandFilterSettings {
+    pub enabled: bool,
+    pub mode: BandMode,
+    pub frequency: f32,
+    pub q: f32,
+}
+
+impl Default for BandFilterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: BandMode::Notch,
+            frequency: 1000.0,
+            q: 1.0,
+        }
+    }
+}
+
+// Filtro biquad de banda: notch (rechaza banda) o paso banda
+pub struct BandFilter {
+    mode: BandMode,
+    sample_rate: f32,
+    biquad: Biquad,
+}
+
+impl BandFilter {
+    pub fn new(mode: BandMode, frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let mut filter = BandFilter {
+            mode,
+            sample_rate,
+            biquad: Biquad::default(),
+        };
+        filter.set_params(frequency, q);
+        filter
+    }
+    
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.biquad.process(input)
+    }
+    
+    pub fn set_settings(&mut self, settings: &BandFilterSettings) {
+        self.mode = settings.mode;
+        self.set_params(settings.frequency, settings.q);
+    }
+
+    pub fn set_sample_rate(&mut self, settings: &BandFilterSettings, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.set_settings(settings);
+    }
+    
+    pub fn set_params(&mut self, frequency: f32, q: f32) {
+        let frequency = frequency.clamp(MIN_CUTOFF, self.sample_rate * 0.45);
+        let q = q.clamp(MIN_BAND_Q, MAX_BAND_Q);
+        self.biquad.set_coefficients(match self.mode {
+            BandMode::Notch => BiquadCoefficients::notch(frequency, q, self.sample_rate),
+            BandMode::BandPass => BiquadCoefficients::band_pass(frequency, q, self.sample_rate),
+        });
+    }
+}
+
+// Rango de la frecuencia de corte del filtro de voz (Hz)
+pub const MIN_CUTOFF: f32 = 20.0;
+pub const MAX_CUTOFF: f32 = 20000.0;
+// Resonancia máxima como factor de calidad, justo antes de la autooscilación
+const MAX_Q: f32 = 20.0;
+// Octavas que mueve la envolvente del filtro el corte con la cantidad al máximo
+const ENV_AMOUNT_OCTAVES: f32 = 7.0;
+// Octavas que mueven el corte la velocidad máxima y el pico del LFO con su cantidad al máximo
+const VELOCITY_AMOUNT_OCTAVES: f32 = 4.0;
+const LFO_AMOUNT_OCTAVES: f32 = 3.0;
+
+// Frecuencia de corte a partir de un valor normalizado (0..1) en escala logarítmica
+pub fn cutoff_from_normalized(value: f32) -> f32 {
+    MIN_CUTOFF * (MAX_CUTOFF / MIN_CUTOFF).powf(value.clamp(0.0, 1.0))
+}
+
+pub fn cutoff_to_normalized(cutoff: f32) -> f32 {
+    ((cutoff / MIN_CUTOFF).ln() / (MAX_CUTOFF / MIN_CUTOFF).ln()).clamp(0.0, 1.0)
+}
+
+// Vocales del filtro de formantes, en el orden en que las recorre el morph
+#[derive(Clone, Copy, PartialEq)]
+pub enum Vowel {
+    A,
+    E,
+    I,
+    O,
+    U,
+}
+
+impl Vowel {
+    pub const ALL: [Vowel; 5] = [Vowel::A, Vowel::E, Vowel::I, Vowel::O, Vowel::U];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Vowel::A => "A",
+            Vowel::E => "E",
+            Vowel::I => "I",
+            Vowel::O => "O",
+            Vowel::U => "U",
+        }
+    }
+
+    // Vocal más cercana a una posición del morph (0.0 = A, 1.0 = U)
+    pub fn nearest(morph: f32) -> Vowel {
+        let index = (morph.clamp(0.0, 1.0) * (Vowel::ALL.len() - 1) as f32).round() as usize;
+        Vowel::ALL[index]
+    }
+
+    // Frecuencias (Hz) y ganancias de los tres primeros formantes de una voz masculina
+    fn formants(&self) -> [(f32, f32); FORMANT_COUNT] {
+        match self {
+            Vowel::A => [(730.0, 1.0), (1090.0, 0.5), (2440.0, 0.25)],
+            Vowel::E => [(530.0, 1.0), (1840.0, 0.4), (2480.0, 0.25)],
+            Vowel::I => [(270.0, 1.0), (2290.0, 0.3), (3010.0, 0.2)],
+            Vowel::O => [(570.0, 1.0), (840.0, 0.6), (2410.0, 0.1)],
+            Vowel::U => [(300.0, 1.0), (870.0, 0.4), (2240.0, 0.05)],
+        }
+    }
+}
+
+const FORMANT_COUNT: usize = 3;
+// Ancho de banda de cada formante (Hz)
+const FORMANT_BANDWIDTHS: [f32; FORMANT_COUNT] = [80.0, 90.0, 120.0];
+
+// Formantes interpolados entre las dos vocales vecinas de la posición del morph.
+// Las frecuencias se interpolan en escala logarítmica para que el barrido suene uniforme.
+fn morphed_formants(morph: f32) -> [(f32, f32); FORMANT_COUNT] {
+    let position = morph.clamp(0.0, 1.0) * (Vowel::ALL.len() - 1) as f32;
+    let index = (position.floor() as usize).min(Vowel::ALL.len() - 2);
+    let t = position - index as f32;
+    let from = Vowel::ALL[index].formants();
+    let to = Vowel::ALL[index + 1].formants();
+    std::array::from_fn(|i| {
+        let frequency = from[i].0 * (to[i].0 / from[i].0).powf(t);
+        let gain = from[i].1 + (to[i].1 - from[i].1) * t;
+        (frequency, gain)
+    })
+}
+
+// Ajustes del filtro de formantes de cada voz
+#[derive(Clone, Copy, PartialEq)]
+pub struct FormantSettings {
+    pub enabled: bool,
+    // Posición continua entre vocales: 0.0 = A, 0.25 = E, 0.5 = I, 0.75 = O, 1.0 = U
+    pub morph: f32,
+}
+
+impl Default for FormantSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            morph: 0.0,
+        }
+    }
+}
+
+// Filtro de formantes: paso bandas biquad en paralelo, uno por formante, para
+// sonidos vocales tipo "sintetizador que habla"
+pub struct FormantFilter {
+    bands: [BandFilter; FORMANT_COUNT],
+    gains: [f32; FORMANT_COUNT],
+}
+
+impl FormantFilter {
+    pub fn new(morph: f32, sample_rate: f32) -> Self {
+        let mut filter = Self {
+            bands: std::array::from_fn(|_| BandFilter::new(BandMode::BandPass, 1000.0, 1.0, sample_rate)),
+            gains: [0.0; FORMANT_COUNT],
+        };
+        filter.set_morph(morph);
+        filter
+    }
+
+    pub fn set_sample_rate(&mut self, morph: f32, sample_rate: f32) {
+        for band in self.bands.iter_mut() {
+            band.sample_rate = sample_rate;
+        }
+        self.set_morph(morph);
+    }
+
+    pub fn set_morph(&mut self, morph: f32) {
+        for (i, (frequency, gain)) in morphed_formants(morph).into_iter().enumerate() {
+            self.bands[i].set_params(frequency, frequency / FORMANT_BANDWIDTHS[i]);
+            self.gains[i] = gain;
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.bands
+            .iter_mut()
+            .zip(self.gains)
+            .map(|(band, gain)| band.process(input) * gain)
+            .sum()
+    }
+}
+
+// Salida del filtro de variables de estado
+#[derive(Clone, Copy, PartialEq)]
+pub enum FilterType {
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+}
+
+impl FilterType {
+    pub const ALL: [FilterType; 4] = [
+        FilterType::LowPass,
+        FilterType::HighPass,
+        FilterType::BandPass,
+        FilterType::Notch,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterType::LowPass => "Paso bajo",
+            FilterType::HighPass => "Paso alto",
+            FilterType::BandPass => "Paso banda",
+            FilterType::Notch => "Banda eliminada",
+        }
+    }
+}
+
+// Ajustes del filtro resonante de cada voz
+#[derive(Clone, Copy, PartialEq)]
+pub struct FilterSettings {
+    pub filter_type: FilterType,
+    pub cutoff: f32,
+    pub resonance: f32,
+    // Cantidad bipolar de la envolvente del filtro: positiva abre el corte, negativa lo cierra
+    pub env_amount: f32,
+    // Cantidad bipolar de la velocidad de la nota sobre el corte
+    pub velocity_amount: f32,
+    // Profundidad del LFO de la voz sobre el corte
+    pub lfo_amount: f32,
+    pub slope: FilterSlope,
+    pub band: BandFilterSettings,
+    pub formant: FormantSettings,
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        Self {
+            filter_type: FilterType::LowPass,
+            cutoff: MAX_CUTOFF,
+            resonance: 0.0,
+            env_amount: 0.0,
+            velocity_amount: 0.0,
+            lfo_amount: 0.0,
+            slope: FilterSlope::TwoPole,
+            band: BandFilterSettings::default(),
+            formant: FormantSettings::default(),
+        }
+    }
+}
+
+impl FilterSettings {
+    // Sin resonancia, un paso bajo totalmente abierto o un paso alto totalmente
+    // cerrado no colorean la señal
+    pub fn is_bypassed(&self) -> bool {
+        if self.resonance > 0.0 || self.is_modulated() {
+            return false;
+        }
+        match self.filter_type {
+            FilterType::LowPass => self.cutoff >= MAX_CUTOFF,
+            FilterType::HighPass => self.cutoff <= MIN_CUTOFF,
+            FilterType::BandPass | FilterType::Notch => false,
+        }
+    }
+
+    // El corte cambia durante la nota y hay que recalcularlo en cada muestra
+    pub fn is_modulated(&self) -> bool {
+        self.env_amount != 0.0 || self.velocity_amount != 0.0 || self.lfo_amount != 0.0
+    }
+
+    // Corte base desplazado por la envolvente del filtro y la velocidad (de 0.0 a 1.0)
+    // y por el LFO (de -1.0 a 1.0)
+    #[inline]
+    pub fn modulated_cutoff(&self, cutoff: f32, envelope_level: f32, velocity: f32, lfo_value: f32) -> f32 {
+        let octaves = self.env_amount * envelope_level * ENV_AMOUNT_OCTAVES
+            + self.velocity_amount * velocity * VELOCITY_AMOUNT_OCTAVES
+            + self.lfo_amount * lfo_value * LFO_AMOUNT_OCTAVES;
+        (cutoff * 2.0f32.powf(octaves)).clamp(MIN_CUTOFF, MAX_CUTOFF)
+    }
+}
+
+// Pendiente del filtro de voz: número de polos en cascada
+#[derive(Clone, Copy, PartialEq)]
+pub enum FilterSlope {
+    // 6 dB/oct; el paso banda y la banda eliminada no existen con un polo y usan 2 polos
+    OnePole,
+    TwoPole,
+    FourPole,
+}
+
+impl FilterSlope {
+    pub const ALL: [FilterSlope; 3] = [FilterSlope::OnePole, FilterSlope::TwoPole, FilterSlope::FourPole];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterSlope::OnePole => "6 dB/oct",
+            FilterSlope::TwoPole => "12 dB/oct",
+            FilterSlope::FourPole => "24 dB/oct",
+        }
+    }
+}
+
+// Una etapa de 2 polos del filtro de variables de estado
+#[derive(Clone, Copy, Default)]
+struct SvfStage {
+    ic1eq: f32,
+    ic2eq: f32,
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    k: f32,
+}
+
+impl SvfStage {
+    fn set_coefficients(&mut self, g: f32, k: f32) {
+        self.k = k;
+        self.a1 = 1.0 / (1.0 + g * (g + k));
+        self.a2 = g * self.a1;
+        self.a3 = g * self.a2;
+    }
+
+    #[inline(always)]
+    fn process(&mut self, filter_type: FilterType, input: f32) -> f32 {
+        let v3 = input - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+        // v1 es la salida paso banda y v2 la paso bajo; el resto se obtiene restando
+        match filter_type {
+            FilterType::LowPass => v2,
+            FilterType::HighPass => input - self.k * v1 - v2,
+            FilterType::BandPass => v1,
+            FilterType::Notch => input - self.k * v1,
+        }
+    }
+}
+
+// Filtro de variables de estado (topología TPT) con salidas paso bajo, paso alto,
+// paso banda y banda eliminada, y pendiente de 1, 2 o 4 polos. Es estable aunque la
+// frecuencia de corte cambie en cada muestra.
+pub struct StateVariableFilter {
+    pub filter_type: FilterType,
+    pub slope: FilterSlope,
+    // Con 4 polos la resonancia solo se aplica en la primera etapa; la segunda es Butterworth
+    stages: [SvfStage; 2],
+    // Integrador y coeficiente de la versión de 1 polo
+    one_pole_state: f32,
+    one_pole_gain: f32,
+}
+
+impl StateVariableFilter {
+    pub fn new(filter_type: FilterType, cutoff: f32, resonance: f32, sample_rate: f32) -> Self {
+        let mut filter = Self {
+            filter_type,
+            slope: FilterSlope::TwoPole,
+            stages: [SvfStage::default(); 2],
+            one_pole_state: 0.0,
+            one_pole_gain: 0.0,
+        };
+        filter.set_params(cutoff, resonance, sample_rate);
+        filter
+    }
+
+    // Resonancia de 0.0 (Q = 0.5, sin pico) a 1.0 (Q alto)
+    pub fn set_params(&mut self, cutoff: f32, resonance: f32, sample_rate: f32) {
+        let cutoff = cutoff.clamp(MIN_CUTOFF, sample_rate * 0.45);
+        let q = 0.5 * (2.0 * MAX_Q).powf(resonance.clamp(0.0, 1.0));
+        let g = (PI * cutoff / sample_rate).tan();
+        self.stages[0].set_coefficients(g, 1.0 / q);
+        self.stages[1].set_coefficients(g, std::f32::consts::SQRT_2);
+        self.one_pole_gain = g / (1.0 + g);
+    }
+
+    #[inline(always)]
+    pub fn process(&mut self, input: f32) -> f32 {
+        match (self.slope, self.filter_type) {
+            (FilterSlope::OnePole, FilterType::LowPass | FilterType::HighPass) => {
+                let v = (input - self.one_pole_state) * self.one_pole_gain;
+                let low = v + self.one_pole_state;
+                self.one_pole_state = low + v;
+                if self.filter_type == FilterType::LowPass { low } else { input - low }
+            }
+            (FilterSlope::FourPole, filter_type) => {
+                let first = self.stages[0].process(filter_type, input);
+                self.stages[1].process(filter_type, first)
+            }
+            (_, filter_type) => self.stages[0].process(filter_type, input),
+        }
+    }
+}
+
+// Corte del bloqueador de continua de la salida (Hz)
+const DC_BLOCKER_CUTOFF: f32 = 7.0;
+
+// Tiempo de suavizado de los cortes del filtro maestro (segundos)
+const MASTER_CUTOFF_SMOOTHING_TIME: f32 = 0.005;
+
+// Paso alto de un polo muy grave que elimina la componente continua de la mezcla.
+// Las ondas asimétricas, el wavefolder o la FM pueden desplazar la señal, y ese
+// desplazamiento llegaría al soft clip y a los altavoces.
+pub struct DcBlocker {
+    coefficient: f32,
+    previous_input: (f32, f32),
+    previous_output: (f32, f32),
+}
+
+impl DcBlocker {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut blocker = Self {
+            coefficient: 0.0,
+            previous_input: (0.0, 0.0),
+            previous_output: (0.0, 0.0),
+        };
+        blocker.set_sample_rate(sample_rate);
+        blocker
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.coefficient = (-2.0 * PI * DC_BLOCKER_CUTOFF / sample_rate).exp();
+    }
+
+    pub fn reset(&mut self) {
+        self.previous_input = (0.0, 0.0);
+        self.previous_output = (0.0, 0.0);
+    }
+
+    #[inline]
+    pub fn process(&mut self, frame: (f32, f32)) -> (f32, f32) {
+        let output = (
+            frame.0 - self.previous_input.0 + self.coefficient * self.previous_output.0,
+            frame.1 - self.previous_input.1 + self.coefficient * self.previous_output.1,
+        );
+        self.previous_input = frame;
+        self.previous_output = output;
+        output
+    }
+}
+
+// Ajustes del filtro maestro: un paso bajo y un paso alto sobre la mezcla de todas
+// las voces. Con los cortes en los extremos del rango no se aplican.
+#[derive(Clone, Copy, PartialEq)]
+pub struct MasterFilterSettings {
+    pub lowpass_cutoff: f32,
+    pub highpass_cutoff: f32,
+}
+
+impl Default for MasterFilterSettings {
+    fn default() -> Self {
+        Self {
+            lowpass_cutoff: MAX_CUTOFF,
+            highpass_cutoff: MIN_CUTOFF,
+        }
+    }
+}
+
+// Filtro estéreo del bus de mezcla, aplicado antes de la saturación de salida. Incluye siempre
+// el bloqueador de continua.
+pub struct MasterFilter {
+    settings: MasterFilterSettings,
+    sample_rate: f32,
+    lowpass: [StateVariableFilter; 2],
+    highpass: [StateVariableFilter; 2],
+    // Cortes suavizados hacia los de `settings`
+    lowpass_cutoff: ParamSmoother,
+    highpass_cutoff: ParamSmoother,
+    dc_blocker: DcBlocker,
+}
+
+impl MasterFilter {
+    pub fn new(sample_rate: f32) -> Self {
+        let settings = MasterFilterSettings::default();
+        Self {
+            settings,
+            sample_rate,
+            lowpass: std::array::from_fn(|_| {
+                StateVariableFilter::new(FilterType::LowPass, settings.lowpass_cutoff, 0.0, sample_rate)
+            }),
+            highpass: std::array::from_fn(|_| {
+                StateVariableFilter::new(FilterType::HighPass, settings.highpass_cutoff, 0.0, sample_rate)
+            }),
+            lowpass_cutoff: ParamSmoother::new(settings.lowpass_cutoff, MASTER_CUTOFF_SMOOTHING_TIME, sample_rate),
+            highpass_cutoff: ParamSmoother::new(settings.highpass_cutoff, MASTER_CUTOFF_SMOOTHING_TIME, sample_rate),
+            dc_blocker: DcBlocker::new(sample_rate),
+        }
+    }
+
+    // Recalcular los coeficientes solo cuando cambia la frecuencia de muestreo; un
+    // cambio de corte se desliza hacia el nuevo valor en process()
+    pub fn set_settings(&mut self, settings: MasterFilterSettings, sample_rate: f32) {
+        if settings == self.settings && sample_rate == self.sample_rate {
+            return;
+        }
+        self.settings = settings;
+        self.lowpass_cutoff.set_target(settings.lowpass_cutoff);
+        self.highpass_cutoff.set_target(settings.highpass_cutoff);
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.lowpass_cutoff.set_time(MASTER_CUTOFF_SMOOTHING_TIME, sample_rate);
+            self.highpass_cutoff.set_time(MASTER_CUTOFF_SMOOTHING_TIME, sample_rate);
+            for filter in self.lowpass.iter_mut() {
+                filter.set_params(self.lowpass_cutoff.value(), 0.0, sample_rate);
+            }
+            for filter in self.highpass.iter_mut() {
+                filter.set_params(self.highpass_cutoff.value(), 0.0, sample_rate);
+            }
+            self.dc_blocker.set_sample_rate(sample_rate);
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, mut frame: (f32, f32)) -> (f32, f32) {
+        // Mientras un corte se desliza hacia el extremo el filtro sigue activo
+        let lowpass_settling = self.lowpass_cutoff.is_settling();
+        if lowpass_settling {
+            let cutoff = self.lowpass_cutoff.next_value();
+            for filter in self.lowpass.iter_mut() {
+                filter.set_params(cutoff, 0.0, self.sample_rate);
+            }
+        }
+        if lowpass_settling || self.settings.lowpass_cutoff < MAX_CUTOFF {
+            frame = (self.lowpass[0].process(frame.0), self.lowpass[1].process(frame.1));
+        }
+        let highpass_settling = self.highpass_cutoff.is_settling();
+        if highpass_settling {
+            let cutoff = self.highpass_cutoff.next_value();
+            for filter in self.highpass.iter_mut() {
+                filter.set_params(cutoff, 0.0, self.sample_rate);
+            }
+        }
+        if highpass_settling || self.settings.highpass_cutoff > MIN_CUTOFF {
+            frame = (self.highpass[0].process(frame.0), self.highpass[1].process(frame.1));
+        }
+        self.dc_blocker.process(frame)
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.set_settings(self.settings, sample_rate);
+    }
+
+    pub fn reset(&mut self) {
+        self.dc_blocker.reset();
+    }
+}
+
+// La frecuencia de muestreo real se fija en el primer `set_settings`
+impl Default for MasterFilter {
+    fn default() -> Self {
+        Self::new(44100.0)
+    }
+}
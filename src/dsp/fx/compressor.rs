@@ -0,0 +1,105 @@
+use super::{Effect, FxSettings};
+
+pub const MIN_COMPRESSOR_THRESHOLD: f32 = -60.0;
+pub const MAX_COMPRESSOR_RATIO: f32 = 20.0;
+pub const MAX_COMPRESSOR_MAKEUP: f32 = 24.0;
+
+// Rango de los tiempos de ataque y relajación (milisegundos)
+pub const MIN_COMPRESSOR_ATTACK: f32 = 0.1;
+pub const MAX_COMPRESSOR_ATTACK: f32 = 100.0;
+pub const MIN_COMPRESSOR_RELEASE: f32 = 10.0;
+pub const MAX_COMPRESSOR_RELEASE: f32 = 2000.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CompressorSettings {
+    // Nivel a partir del cual se reduce la ganancia (dBFS)
+    pub threshold: f32,
+    // Con la relación máxima se comporta como un limitador
+    pub ratio: f32,
+    pub attack: f32,
+    pub release: f32,
+    // Ganancia tras la compresión (dB)
+    pub makeup: f32,
+}
+
+impl Default for CompressorSettings {
+    fn default() -> Self {
+        Self {
+            threshold: -18.0,
+            ratio: 4.0,
+            attack: 10.0,
+            release: 150.0,
+            makeup: 0.0,
+        }
+    }
+}
+
+// Coeficiente de un seguidor de un polo para un tiempo en milisegundos
+fn time_coefficient(ms: f32, sample_rate: f32) -> f32 {
+    (-1.0 / (ms * 0.001 * sample_rate).max(1.0)).exp()
+}
+
+// Compresor de picos con los dos canales enlazados: la reducción se calcula sobre el
+// mayor de los dos para no mover la imagen estéreo
+pub struct Compressor {
+    settings: CompressorSettings,
+    sample_rate: f32,
+    attack: f32,
+    release: f32,
+    makeup: f32,
+    // Reducción de ganancia actual (dB, positiva)
+    reduction: f32,
+}
+
+impl Compressor {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut compressor = Self {
+            settings: CompressorSettings::default(),
+            sample_rate,
+            attack: 0.0,
+            release: 0.0,
+            makeup: 1.0,
+            reduction: 0.0,
+        };
+        compressor.update_coefficients();
+        compressor
+    }
+
+    fn update_coefficients(&mut self) {
+        let settings = &self.settings;
+        self.attack = time_coefficient(settings.attack.clamp(MIN_COMPRESSOR_ATTACK, MAX_COMPRESSOR_ATTACK), self.sample_rate);
+        self.release = time_coefficient(settings.release.clamp(MIN_COMPRESSOR_RELEASE, MAX_COMPRESSOR_RELEASE), self.sample_rate);
+        self.makeup = 10f32.powf(settings.makeup.clamp(0.0, MAX_COMPRESSOR_MAKEUP) / 20.0);
+    }
+}
+
+impl Effect for Compressor {
+    #[inline]
+    fn process(&mut self, frame: (f32, f32)) -> (f32, f32) {
+        let peak = frame.0.abs().max(frame.1.abs());
+        let level = 20.0 * peak.max(1e-6).log10();
+        let threshold = self.settings.threshold.clamp(MIN_COMPRESSOR_THRESHOLD, 0.0);
+        let ratio = self.settings.ratio.clamp(1.0, MAX_COMPRESSOR_RATIO);
+        let target = (level - threshold).max(0.0) * (1.0 - 1.0 / ratio);
+
+        let coefficient = if target > self.reduction { self.attack } else { self.release };
+        self.reduction = target + coefficient * (self.reduction - target);
+
+        let gain = 10f32.powf(-self.reduction / 20.0) * self.makeup;
+        (frame.0 * gain, frame.1 * gain)
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+    }
+
+    fn apply_settings(&mut self, settings: &FxSettings) {
+        self.settings = settings.compressor;
+        self.update_coefficients();
+    }
+
+    fn reset(&mut self) {
+        self.reduction = 0.0;
+    }
+}
@@ -0,0 +1,227 @@
+use crate::dsp::smoother::{ParamSmoother, GAIN_SMOOTHING_TIME};
+use super::{Effect, FxSettings};
+
+// Tiempo máximo del retardo (milisegundos)
+pub const MAX_DELAY_TIME: f32 = 2000.0;
+pub const MIN_DELAY_TIME: f32 = 1.0;
+
+// Realimentación máxima, por debajo de 1.0 para que las repeticiones siempre se apaguen
+pub const MAX_DELAY_FEEDBACK: f32 = 0.95;
+
+// Tiempo de deslizamiento al cambiar el tiempo de retardo (segundos); evita saltos en
+// la lectura y da el efecto de cinta habitual al mover el control
+const DELAY_TIME_SMOOTHING: f32 = 0.05;
+
+// Nivel al que se considera que las repeticiones ya no se oyen (-60 dB)
+const TAIL_THRESHOLD: f32 = 0.001;
+
+pub fn delay_time_from_normalized(value: f32) -> f32 {
+    MIN_DELAY_TIME * (MAX_DELAY_TIME / MIN_DELAY_TIME).powf(value.clamp(0.0, 1.0))
+}
+
+pub fn delay_time_to_normalized(time: f32) -> f32 {
+    ((time / MIN_DELAY_TIME).ln() / (MAX_DELAY_TIME / MIN_DELAY_TIME).ln()).clamp(0.0, 1.0)
+}
+
+// Divisiones de nota para el retardo sincronizado al tempo
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DelayDivision {
+    Whole,
+    Half,
+    Quarter,
+    DottedEighth,
+    Eighth,
+    TripletEighth,
+    Sixteenth,
+}
+
+impl DelayDivision {
+    pub const ALL: [DelayDivision; 7] = [
+        DelayDivision::Whole,
+        DelayDivision::Half,
+        DelayDivision::Quarter,
+        DelayDivision::DottedEighth,
+        DelayDivision::Eighth,
+        DelayDivision::TripletEighth,
+        DelayDivision::Sixteenth,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DelayDivision::Whole => "1/1",
+            DelayDivision::Half => "1/2",
+            DelayDivision::Quarter => "1/4",
+            DelayDivision::DottedEighth => "1/8 con puntillo",
+            DelayDivision::Eighth => "1/8",
+            DelayDivision::TripletEighth => "1/8 de tresillo",
+            DelayDivision::Sixteenth => "1/16",
+        }
+    }
+
+    // Duración en pulsos (negras)
+    pub fn beats(&self) -> f32 {
+        match self {
+            DelayDivision::Whole => 4.0,
+            DelayDivision::Half => 2.0,
+            DelayDivision::Quarter => 1.0,
+            DelayDivision::DottedEighth => 0.75,
+            DelayDivision::Eighth => 0.5,
+            DelayDivision::TripletEighth => 1.0 / 3.0,
+            DelayDivision::Sixteenth => 0.25,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DelaySettings {
+    // Milisegundos sin sincronización
+    pub time: f32,
+    // Tiempo como división de nota a `bpm` en lugar de en milisegundos
+    pub tempo_sync: bool,
+    pub division: DelayDivision,
+    pub bpm: f32,
+    pub feedback: f32,
+    // Pérdida de agudos en cada repetición, de 0.0 a 1.0
+    pub damping: f32,
+    // Las repeticiones alternan entre el canal izquierdo y el derecho
+    pub ping_pong: bool,
+    // Proporción de señal con retardo, de 0.0 (seca) a 1.0 (solo repeticiones)
+    pub mix: f32,
+}
+
+impl Default for DelaySettings {
+    fn default() -> Self {
+        Self {
+            time: 375.0,
+            tempo_sync: false,
+            division: DelayDivision::DottedEighth,
+            bpm: 120.0,
+            feedback: 0.4,
+            damping: 0.3,
+            ping_pong: false,
+            mix: 0.3,
+        }
+    }
+}
+
+impl DelaySettings {
+    // Tiempo de retardo efectivo en segundos
+    pub fn delay_time(&self) -> f32 {
+        let ms = if self.tempo_sync {
+            self.division.beats() * 60_000.0 / self.bpm.max(1.0)
+        } else {
+            self.time
+        };
+        ms.clamp(MIN_DELAY_TIME, MAX_DELAY_TIME) / 1000.0
+    }
+
+    // Segundos hasta que las repeticiones caen por debajo de -60 dB
+    pub fn tail_time(&self) -> f32 {
+        let feedback = self.feedback.clamp(0.0, MAX_DELAY_FEEDBACK);
+        let repeats = if feedback > 0.0 {
+            (TAIL_THRESHOLD.ln() / feedback.ln()).ceil()
+        } else {
+            0.0
+        };
+        self.delay_time() * (repeats + 1.0)
+    }
+}
+
+// Retardo estéreo con realimentación amortiguada por un paso bajo de un polo
+pub struct Delay {
+    settings: DelaySettings,
+    sample_rate: f32,
+    buffers: [Vec<f32>; 2],
+    write_index: usize,
+    // Tiempo de retardo en muestras, suavizado
+    delay_samples: ParamSmoother,
+    mix: ParamSmoother,
+    // Estado del paso bajo de la realimentación por canal
+    damping_state: [f32; 2],
+}
+
+impl Delay {
+    pub fn new(sample_rate: f32) -> Self {
+        let settings = DelaySettings::default();
+        let mut delay = Self {
+            settings,
+            sample_rate,
+            buffers: [Vec::new(), Vec::new()],
+            write_index: 0,
+            delay_samples: ParamSmoother::new(0.0, DELAY_TIME_SMOOTHING, sample_rate),
+            mix: ParamSmoother::linear(settings.mix, GAIN_SMOOTHING_TIME, sample_rate),
+            damping_state: [0.0; 2],
+        };
+        delay.set_sample_rate(sample_rate);
+        delay
+    }
+
+    // Muestra de un canal `delay` muestras atrás, con interpolación lineal
+    #[inline]
+    fn read(&self, channel: usize, delay: f32) -> f32 {
+        let buffer = &self.buffers[channel];
+        let len = buffer.len();
+        let position = self.write_index as f32 + len as f32 - delay;
+        let index = position as usize;
+        let fraction = position - index as f32;
+        let a = buffer[index % len];
+        let b = buffer[(index + 1) % len];
+        a + (b - a) * fraction
+    }
+}
+
+impl Effect for Delay {
+    #[inline]
+    fn process(&mut self, frame: (f32, f32)) -> (f32, f32) {
+        let delay = self.delay_samples.next_value();
+        let wet = (self.read(0, delay), self.read(1, delay));
+
+        // Paso bajo de un polo en el camino de realimentación
+        let coefficient = 1.0 - self.settings.damping.clamp(0.0, 1.0) * 0.95;
+        self.damping_state[0] += coefficient * (wet.0 - self.damping_state[0]);
+        self.damping_state[1] += coefficient * (wet.1 - self.damping_state[1]);
+        let feedback = self.settings.feedback.clamp(0.0, MAX_DELAY_FEEDBACK);
+        let damped = (self.damping_state[0] * feedback, self.damping_state[1] * feedback);
+
+        // En ping-pong la entrada mono entra por la izquierda y cada repetición cambia de lado
+        let input = if self.settings.ping_pong {
+            ((frame.0 + frame.1) * 0.5 + damped.1, damped.0)
+        } else {
+            (frame.0 + damped.0, frame.1 + damped.1)
+        };
+        self.buffers[0][self.write_index] = input.0;
+        self.buffers[1][self.write_index] = input.1;
+        self.write_index = (self.write_index + 1) % self.buffers[0].len();
+
+        let mix = self.mix.next_value();
+        (
+            frame.0 * (1.0 - mix) + wet.0 * mix,
+            frame.1 * (1.0 - mix) + wet.1 * mix,
+        )
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        // Una muestra de margen para la interpolación
+        let len = (MAX_DELAY_TIME / 1000.0 * sample_rate).ceil() as usize + 2;
+        self.buffers = [vec![0.0; len], vec![0.0; len]];
+        self.write_index = 0;
+        self.damping_state = [0.0; 2];
+        self.delay_samples.set_time(DELAY_TIME_SMOOTHING, sample_rate);
+        self.delay_samples.reset(self.settings.delay_time() * sample_rate);
+        self.mix.set_time(GAIN_SMOOTHING_TIME, sample_rate);
+    }
+
+    fn apply_settings(&mut self, settings: &FxSettings) {
+        self.settings = settings.delay;
+        self.delay_samples.set_target(self.settings.delay_time() * self.sample_rate);
+        self.mix.set_target(self.settings.mix.clamp(0.0, 1.0));
+    }
+
+    fn reset(&mut self) {
+        for buffer in self.buffers.iter_mut() {
+            buffer.fill(0.0);
+        }
+        self.damping_state = [0.0; 2];
+    }
+}
@@ -0,0 +1,147 @@
+use crate::dsp::smoother::{ParamSmoother, GAIN_SMOOTHING_TIME};
+use super::{Effect, FxSettings};
+
+// Ganancia de entrada máxima (dB)
+pub const MAX_DISTORTION_DRIVE: f32 = 36.0;
+// Rango del ajuste de salida (dB)
+pub const MAX_DISTORTION_TRIM: f32 = 24.0;
+
+// Curva de transferencia del waveshaper
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DistortionCurve {
+    SoftClip,
+    HardClip,
+    Foldback,
+    Tube,
+}
+
+impl DistortionCurve {
+    pub const ALL: [DistortionCurve; 4] = [
+        DistortionCurve::SoftClip,
+        DistortionCurve::HardClip,
+        DistortionCurve::Foldback,
+        DistortionCurve::Tube,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DistortionCurve::SoftClip => "Saturación suave",
+            DistortionCurve::HardClip => "Recorte duro",
+            DistortionCurve::Foldback => "Plegado",
+            DistortionCurve::Tube => "Válvula asimétrica",
+        }
+    }
+
+    #[inline]
+    pub fn shape(&self, x: f32) -> f32 {
+        match self {
+            DistortionCurve::SoftClip => x.tanh(),
+            DistortionCurve::HardClip => x.clamp(-1.0, 1.0),
+            // Lo que pasa de ±1 se refleja hacia dentro, una y otra vez
+            DistortionCurve::Foldback => {
+                let folded = (x - 1.0).rem_euclid(4.0);
+                (folded - 2.0).abs() - 1.0
+            }
+            // Los positivos se comprimen antes que los negativos, lo que añade armónicos
+            // pares; se resta la salida en reposo para no introducir continua
+            DistortionCurve::Tube => {
+                const BIAS: f32 = 0.2;
+                (x + BIAS).tanh() - BIAS.tanh()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DistortionSettings {
+    pub curve: DistortionCurve,
+    // Ganancia antes de la curva (dB)
+    pub drive: f32,
+    // Ganancia después de la curva (dB)
+    pub trim: f32,
+    pub mix: f32,
+}
+
+impl Default for DistortionSettings {
+    fn default() -> Self {
+        Self {
+            curve: DistortionCurve::SoftClip,
+            drive: 12.0,
+            trim: -6.0,
+            mix: 1.0,
+        }
+    }
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+// Waveshaper sin memoria; las ganancias se calculan al cambiar los ajustes y se
+// suavizan para que mover los mandos no dé saltos
+pub struct Distortion {
+    curve: DistortionCurve,
+    drive: ParamSmoother,
+    trim: ParamSmoother,
+    mix: ParamSmoother,
+}
+
+impl Default for Distortion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Distortion {
+    pub fn new() -> Self {
+        let settings = DistortionSettings::default();
+        let (drive, trim, mix) = Self::gains(&settings);
+        Self {
+            curve: settings.curve,
+            drive: ParamSmoother::linear(drive, GAIN_SMOOTHING_TIME, 44100.0),
+            trim: ParamSmoother::linear(trim, GAIN_SMOOTHING_TIME, 44100.0),
+            mix: ParamSmoother::linear(mix, GAIN_SMOOTHING_TIME, 44100.0),
+        }
+    }
+
+    // Ganancias lineales de entrada y salida y mezcla de unos ajustes
+    fn gains(settings: &DistortionSettings) -> (f32, f32, f32) {
+        (
+            db_to_gain(settings.drive.clamp(0.0, MAX_DISTORTION_DRIVE)),
+            db_to_gain(settings.trim.clamp(-MAX_DISTORTION_TRIM, MAX_DISTORTION_TRIM)),
+            settings.mix.clamp(0.0, 1.0),
+        )
+    }
+
+    fn set(&mut self, settings: &DistortionSettings) {
+        self.curve = settings.curve;
+        let (drive, trim, mix) = Self::gains(settings);
+        self.drive.set_target(drive);
+        self.trim.set_target(trim);
+        self.mix.set_target(mix);
+    }
+
+    #[inline]
+    fn channel(&self, x: f32, drive: f32, trim: f32, mix: f32) -> f32 {
+        let wet = self.curve.shape(x * drive) * trim;
+        x * (1.0 - mix) + wet * mix
+    }
+}
+
+impl Effect for Distortion {
+    #[inline]
+    fn process(&mut self, frame: (f32, f32)) -> (f32, f32) {
+        let (drive, trim, mix) = (self.drive.next_value(), self.trim.next_value(), self.mix.next_value());
+        (self.channel(frame.0, drive, trim, mix), self.channel(frame.1, drive, trim, mix))
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        for smoother in [&mut self.drive, &mut self.trim, &mut self.mix] {
+            smoother.set_time(GAIN_SMOOTHING_TIME, sample_rate);
+        }
+    }
+
+    fn apply_settings(&mut self, settings: &FxSettings) {
+        self.set(&settings.distortion);
+    }
+}
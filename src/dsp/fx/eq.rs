@@ -0,0 +1,104 @@
+use crate::dsp::biquad::{Biquad, BiquadCoefficients};
+use super::{Effect, FxSettings};
+
+// Ganancia máxima de cada banda, en realce o en corte (dB)
+pub const MAX_EQ_GAIN: f32 = 18.0;
+
+// Pendiente de los shelves (Q de Butterworth, sin sobreoscilación)
+const SHELF_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EqSettings {
+    pub low_frequency: f32,
+    pub low_gain: f32,
+    pub mid_frequency: f32,
+    pub mid_gain: f32,
+    pub mid_q: f32,
+    pub high_frequency: f32,
+    pub high_gain: f32,
+}
+
+impl Default for EqSettings {
+    fn default() -> Self {
+        Self {
+            low_frequency: 120.0,
+            low_gain: 0.0,
+            mid_frequency: 1000.0,
+            mid_gain: 0.0,
+            mid_q: 1.0,
+            high_frequency: 8000.0,
+            high_gain: 0.0,
+        }
+    }
+}
+
+impl EqSettings {
+    fn coefficients(&self, sample_rate: f32) -> [BiquadCoefficients; 3] {
+        let gain = |db: f32| db.clamp(-MAX_EQ_GAIN, MAX_EQ_GAIN);
+        [
+            BiquadCoefficients::low_shelf(self.low_frequency, SHELF_Q, gain(self.low_gain), sample_rate),
+            BiquadCoefficients::peak(self.mid_frequency, self.mid_q, gain(self.mid_gain), sample_rate),
+            BiquadCoefficients::high_shelf(self.high_frequency, SHELF_Q, gain(self.high_gain), sample_rate),
+        ]
+    }
+}
+
+// Ecualizador de tres bandas: shelf de graves, campana de medios y shelf de agudos,
+// en serie y con un biquad por banda y canal
+pub struct Eq {
+    settings: EqSettings,
+    sample_rate: f32,
+    bands: [[Biquad; 3]; 2],
+}
+
+impl Eq {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut eq = Self {
+            settings: EqSettings::default(),
+            sample_rate,
+            bands: [[Biquad::default(); 3]; 2],
+        };
+        eq.update_coefficients();
+        eq
+    }
+
+    fn update_coefficients(&mut self) {
+        let coefficients = self.settings.coefficients(self.sample_rate);
+        for channel in self.bands.iter_mut() {
+            for (band, coefficients) in channel.iter_mut().zip(coefficients) {
+                band.set_coefficients(coefficients);
+            }
+        }
+    }
+
+    #[inline]
+    fn channel(&mut self, channel: usize, input: f32) -> f32 {
+        self.bands[channel].iter_mut().fold(input, |sample, band| band.process(sample))
+    }
+}
+
+impl Effect for Eq {
+    #[inline]
+    fn process(&mut self, frame: (f32, f32)) -> (f32, f32) {
+        (self.channel(0, frame.0), self.channel(1, frame.1))
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+        self.reset();
+    }
+
+    fn apply_settings(&mut self, settings: &FxSettings) {
+        if settings.eq != self.settings {
+            self.settings = settings.eq;
+            self.update_coefficients();
+        }
+    }
+
+    fn reset(&mut self) {
+        for band in self.bands.iter_mut().flatten() {
+            band.reset();
+        }
+    }
+}
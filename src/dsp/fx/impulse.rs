@@ -0,0 +1,166 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::dsp::noise::NoiseSource;
+
+// Duración máxima de una respuesta al impulso; lo que pase de aquí se descarta
+pub const MAX_IMPULSE_LENGTH: f32 = 10.0;
+
+// Duración de la respuesta sintética que se usa mientras no se cargue ningún archivo
+pub const SYNTHETIC_LENGTH: f32 = 2.0;
+
+// Formatos de muestra de la cabecera "fmt " de un WAV
+const WAV_FORMAT_PCM: u16 = 1;
+const WAV_FORMAT_FLOAT: u16 = 3;
+const WAV_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Respuesta al impulso mono o estéreo, tal como se leyó, con su frecuencia de muestreo.
+// El reverb la remuestrea a la del motor al preparar las particiones.
+pub struct ImpulseResponse {
+    // Ruta del archivo, vacía en la respuesta sintética
+    pub path: String,
+    pub sample_rate: f32,
+    // Uno o dos canales; con uno se usa el mismo para la izquierda y la derecha
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl fmt::Debug for ImpulseResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImpulseResponse")
+            .field("path", &self.path)
+            .field("sample_rate", &self.sample_rate)
+            .field("channels", &self.channels.len())
+            .field("length", &self.length())
+            .finish()
+    }
+}
+
+impl ImpulseResponse {
+    // Leer un WAV PCM de 8, 16, 24 o 32 bits o de coma flotante de 32 bits. Solo se
+    // usan los dos primeros canales.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let (sample_rate, channels) = parse_wav(&bytes)?;
+        Ok(Self {
+            path: path.to_string_lossy().into_owned(),
+            sample_rate,
+            channels,
+        })
+    }
+
+    // Ruido estéreo con caída exponencial de -60 dB en toda su duración: una sala
+    // genérica para que el efecto suene sin haber cargado nada
+    pub fn synthetic(sample_rate: f32) -> Self {
+        let length = (SYNTHETIC_LENGTH * sample_rate) as usize;
+        let decay = (0.001f32).ln() / length as f32;
+        let mut noise = NoiseSource::new();
+        let channels = (0..2)
+            .map(|_| (0..length).map(|i| noise.next_bipolar() * (decay * i as f32).exp()).collect())
+            .collect();
+        Self { path: String::new(), sample_rate, channels }
+    }
+
+    // Duración en segundos
+    pub fn length(&self) -> f32 {
+        self.channels.first().map_or(0, |channel| channel.len()) as f32 / self.sample_rate
+    }
+
+    // Canal izquierdo y derecho a otra frecuencia de muestreo (interpolación lineal),
+    // normalizados a energía unitaria para que el nivel no dependa del archivo
+    pub fn resampled(&self, sample_rate: f32) -> [Vec<f32>; 2] {
+        let ratio = self.sample_rate / sample_rate;
+        let resample = |channel: &Vec<f32>| -> Vec<f32> {
+            if channel.is_empty() {
+                return Vec::new();
+            }
+            let length = ((channel.len() as f32 / ratio) as usize).max(1);
+            (0..length)
+                .map(|i| {
+                    let position = i as f32 * ratio;
+                    let index = position as usize;
+                    let fraction = position - index as f32;
+                    let a = channel[index.min(channel.len() - 1)];
+                    let b = channel[(index + 1).min(channel.len() - 1)];
+                    a + (b - a) * fraction
+                })
+                .collect()
+        };
+        let left = self.channels.first().map(resample).unwrap_or_default();
+        let right = self.channels.get(1).map(resample).unwrap_or_else(|| left.clone());
+
+        let energy = |channel: &[f32]| channel.iter().map(|v| v * v).sum::<f32>();
+        let peak_energy = energy(&left).max(energy(&right));
+        let scale = if peak_energy > 0.0 { 1.0 / peak_energy.sqrt() } else { 0.0 };
+        [
+            left.into_iter().map(|v| v * scale).collect(),
+            right.into_iter().map(|v| v * scale).collect(),
+        ]
+    }
+}
+
+// Frecuencia de muestreo y canales (hasta dos) de un archivo WAV completo en memoria
+fn parse_wav(bytes: &[u8]) -> io::Result<(f32, Vec<Vec<f32>>)> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid("no es un archivo WAV"));
+    }
+    let u16_at = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+    let u32_at = |offset: usize| u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+
+    let mut format = None;
+    let mut data = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32_at(offset + 4) as usize;
+        let start = offset + 8;
+        let end = (start + size).min(bytes.len());
+        match id {
+            b"fmt " if end - start >= 16 => {
+                let mut tag = u16_at(start);
+                // En el formato extensible el tipo real son los dos primeros bytes del GUID
+                if tag == WAV_FORMAT_EXTENSIBLE && end - start >= 26 {
+                    tag = u16_at(start + 24);
+                }
+                format = Some((tag, u16_at(start + 2) as usize, u32_at(start + 4) as f32, u16_at(start + 14)));
+            }
+            b"data" => data = Some(&bytes[start..end]),
+            _ => (),
+        }
+        // Los bloques de tamaño impar llevan un byte de relleno
+        offset = start + size + (size & 1);
+    }
+
+    let (tag, channel_count, sample_rate, bits) = format.ok_or_else(|| invalid("falta el bloque fmt"))?;
+    let data = data.ok_or_else(|| invalid("falta el bloque data"))?;
+    if channel_count == 0 || sample_rate <= 0.0 {
+        return Err(invalid("cabecera fmt no válida"));
+    }
+
+    let decode: fn(&[u8]) -> f32 = match (tag, bits) {
+        (WAV_FORMAT_PCM, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+        (WAV_FORMAT_PCM, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+        (WAV_FORMAT_PCM, 24) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0,
+        (WAV_FORMAT_PCM, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+        (WAV_FORMAT_FLOAT, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        _ => return Err(invalid("formato de muestra no soportado")),
+    };
+
+    let sample_size = bits as usize / 8;
+    let frame_size = sample_size * channel_count;
+    let max_frames = (MAX_IMPULSE_LENGTH * sample_rate) as usize;
+    let mut channels = vec![Vec::new(); channel_count.min(2)];
+    for frame in data.chunks_exact(frame_size).take(max_frames) {
+        for (channel, samples) in channels.iter_mut().enumerate() {
+            samples.push(decode(&frame[channel * sample_size..]));
+        }
+    }
+    if channels[0].is_empty() {
+        return Err(invalid("el archivo no tiene muestras"));
+    }
+    Ok((sample_rate, channels))
+}
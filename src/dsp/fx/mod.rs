@@ -0,0 +1,370 @@
+// Rack de efectos de la mezcla final: una lista ordenada de ranuras, cada una con un
+// efecto que se puede puentear. Se aplica después del filtro maestro y antes del soft
+// clip en todas las rutas de render (aplicación, plugin y render offline).
+pub mod compressor;
+pub mod delay;
+pub mod distortion;
+pub mod eq;
+pub mod impulse;
+pub mod reverb;
+
+use std::sync::Arc;
+use compressor::{Compressor, CompressorSettings};
+use delay::{Delay, DelaySettings};
+use distortion::{Distortion, DistortionSettings};
+use eq::{Eq, EqSettings};
+use reverb::{ConvolutionReverb, ReverbSettings};
+use super::smoother::{ParamSmoother, GAIN_SMOOTHING_TIME};
+
+// Número máximo de ranuras del rack
+pub const MAX_FX_SLOTS: usize = 8;
+
+// Efecto de una ranura del rack; procesa un frame estéreo cada vez
+pub trait Effect: Send {
+    fn process(&mut self, frame: (f32, f32)) -> (f32, f32);
+
+    // Procesar en su sitio un bloque estéreo intercalado (L, R, L, R...). Por defecto
+    // va frame a frame; los efectos que trabajan por bloques lo sustituyen.
+    fn process_block(&mut self, block: &mut [f32]) {
+        for frame in block.chunks_exact_mut(2) {
+            let (left, right) = self.process((frame[0], frame[1]));
+            frame[0] = left;
+            frame[1] = right;
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32);
+
+    // Tomar de los ajustes del rack los que corresponden a este efecto
+    fn apply_settings(&mut self, _settings: &FxSettings) {}
+
+    // Vaciar el estado interno (colas, líneas de retardo) sin cambiar los ajustes
+    fn reset(&mut self) {}
+}
+
+// Efectos disponibles para las ranuras del rack
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EffectType {
+    Delay,
+    Distortion,
+    Eq,
+    Compressor,
+    Reverb,
+}
+
+impl EffectType {
+    pub const ALL: [EffectType; 5] = [
+        EffectType::Delay,
+        EffectType::Distortion,
+        EffectType::Eq,
+        EffectType::Compressor,
+        EffectType::Reverb,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EffectType::Delay => "Delay",
+            EffectType::Distortion => "Distorsión",
+            EffectType::Eq => "Ecualizador",
+            EffectType::Compressor => "Compresor",
+            EffectType::Reverb => "Reverb",
+        }
+    }
+
+    // Posición en `ALL`
+    fn index(&self) -> usize {
+        *self as usize
+    }
+
+    fn create(&self, sample_rate: f32) -> Box<dyn Effect> {
+        match self {
+            EffectType::Delay => Box::new(Delay::new(sample_rate)),
+            EffectType::Distortion => {
+                let mut distortion = Distortion::new();
+                distortion.set_sample_rate(sample_rate);
+                Box::new(distortion)
+            }
+            EffectType::Eq => Box::new(Eq::new(sample_rate)),
+            EffectType::Compressor => Box::new(Compressor::new(sample_rate)),
+            EffectType::Reverb => Box::new(ConvolutionReverb::new(sample_rate)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FxSlotSettings {
+    pub effect: EffectType,
+    // La señal pasa por la ranura sin procesar
+    pub bypass: bool,
+    // Proporción de la salida del efecto frente a su entrada, de 0.0 a 1.0
+    pub mix: f32,
+}
+
+impl FxSlotSettings {
+    pub fn new(effect: EffectType) -> Self {
+        Self { effect, bypass: false, mix: 1.0 }
+    }
+}
+
+// Orden de las ranuras y ajustes de cada efecto, tal como se guardan en el preset. Cada
+// efecto aparece como mucho en una ranura.
+#[derive(PartialEq, Debug, Default)]
+pub struct FxSettings {
+    pub slots: Vec<FxSlotSettings>,
+    pub delay: DelaySettings,
+    pub distortion: DistortionSettings,
+    pub eq: EqSettings,
+    pub compressor: CompressorSettings,
+    pub reverb: ReverbSettings,
+}
+
+// `clone_from` reutiliza la memoria de las ranuras y de la ruta de la respuesta al
+// impulso, para copiar los ajustes en el hilo de audio
+impl Clone for FxSettings {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            delay: self.delay,
+            distortion: self.distortion,
+            eq: self.eq,
+            compressor: self.compressor,
+            reverb: self.reverb.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.slots.clone_from(&source.slots);
+        self.copy_effects_from(source);
+    }
+}
+
+impl FxSettings {
+    pub fn contains(&self, effect: EffectType) -> bool {
+        self.slots.iter().any(|slot| slot.effect == effect)
+    }
+
+    // Añadir el efecto si no está en el rack o quitarlo si está
+    pub fn set_enabled(&mut self, effect: EffectType, enabled: bool) {
+        match self.slots.iter().position(|slot| slot.effect == effect) {
+            Some(index) if !enabled => self.remove_slot(index),
+            None if enabled => {
+                self.add_slot(effect);
+            }
+            _ => (),
+        }
+    }
+
+    // Segundos que el rack sigue sonando después de que la entrada quede en silencio
+    pub fn tail_time(&self) -> f32 {
+        self.slots.iter()
+            .filter(|slot| !slot.bypass)
+            .map(|slot| match slot.effect {
+                EffectType::Delay => self.delay.tail_time(),
+                EffectType::Reverb => self.reverb.tail_time(),
+                EffectType::Distortion | EffectType::Eq | EffectType::Compressor => 0.0,
+            })
+            .sum()
+    }
+
+    // No añade el efecto si el rack está lleno o ya lo tiene
+    pub fn add_slot(&mut self, effect: EffectType) -> bool {
+        if self.slots.len() >= MAX_FX_SLOTS || self.contains(effect) {
+            return false;
+        }
+        self.slots.push(FxSlotSettings::new(effect));
+        true
+    }
+
+    pub fn remove_slot(&mut self, index: usize) {
+        if index < self.slots.len() {
+            self.slots.remove(index);
+        }
+    }
+
+    // Mismos ajustes de cada efecto, sin mirar las ranuras
+    fn same_effects(&self, other: &Self) -> bool {
+        self.delay == other.delay
+            && self.distortion == other.distortion
+            && self.eq == other.eq
+            && self.compressor == other.compressor
+            && self.reverb == other.reverb
+    }
+
+    fn copy_effects_from(&mut self, source: &Self) {
+        self.delay = source.delay;
+        self.distortion = source.distortion;
+        self.eq = source.eq;
+        self.compressor = source.compressor;
+        self.reverb.clone_from(&source.reverb);
+    }
+
+    // Mover una ranura a otra posición; las de en medio se desplazan
+    pub fn move_slot(&mut self, from: usize, to: usize) {
+        if from < self.slots.len() && to < self.slots.len() {
+            let slot = self.slots.remove(from);
+            self.slots.insert(to, slot);
+        }
+    }
+}
+
+// Efecto de un tipo con el estado de su ranura en el rack
+struct FxSlot {
+    effect: Box<dyn Effect>,
+    bypass: bool,
+    // Mezcla de la ranura, suavizada hacia la de sus ajustes
+    mix: ParamSmoother,
+}
+
+// Efectos del rack en marcha: una instancia de cada tipo, creada con el rack, y el orden
+// en que suenan. Cambiar el orden, el bypass o la mezcla no reserva memoria, así que los
+// ajustes se pueden aplicar en cada bloque desde el hilo de audio; los de cada efecto se
+// comparan y solo se vuelven a aplicar cuando cambian.
+pub struct FxRack {
+    // Indexados por `EffectType`
+    effects: Vec<FxSlot>,
+    // Efectos de las ranuras, por su índice en `effects`
+    order: [usize; MAX_FX_SLOTS],
+    slot_count: usize,
+    // Ajustes de los efectos aplicados por última vez; sus ranuras no se usan
+    settings: FxSettings,
+    sample_rate: f32,
+    // Copia de la entrada de una ranura para mezclarla con su salida
+    dry: Vec<f32>,
+}
+
+impl Default for FxRack {
+    fn default() -> Self {
+        Self::new(44100.0)
+    }
+}
+
+impl FxRack {
+    pub fn new(sample_rate: f32) -> Self {
+        let settings = FxSettings::default();
+        let effects = EffectType::ALL.iter()
+            .map(|effect| {
+                let mut effect = effect.create(sample_rate);
+                effect.apply_settings(&settings);
+                FxSlot {
+                    effect,
+                    bypass: false,
+                    mix: ParamSmoother::linear(1.0, GAIN_SMOOTHING_TIME, sample_rate),
+                }
+            })
+            .collect();
+        Self {
+            effects,
+            order: [0; MAX_FX_SLOTS],
+            slot_count: 0,
+            settings,
+            sample_rate,
+            dry: Vec::new(),
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            for slot in self.effects.iter_mut() {
+                slot.effect.set_sample_rate(sample_rate);
+                slot.mix.set_time(GAIN_SMOOTHING_TIME, sample_rate);
+            }
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &FxSettings, sample_rate: f32) {
+        self.set_sample_rate(sample_rate);
+
+        // Orden de las ranuras; un efecto que entra en el rack empieza sin cola y con su
+        // mezcla, como recién creado. Los que siguen conservan sus colas.
+        let previous = self.order;
+        let previous_count = self.slot_count;
+        self.slot_count = 0;
+        for slot_settings in settings.slots.iter() {
+            let index = slot_settings.effect.index();
+            if self.slot_count == MAX_FX_SLOTS || self.order[..self.slot_count].contains(&index) {
+                continue;
+            }
+            let mix = slot_settings.mix.clamp(0.0, 1.0);
+            let slot = &mut self.effects[index];
+            if !previous[..previous_count].contains(&index) {
+                slot.effect.reset();
+                slot.mix.reset(mix);
+            }
+            slot.bypass = slot_settings.bypass;
+            slot.mix.set_target(mix);
+            self.order[self.slot_count] = index;
+            self.slot_count += 1;
+        }
+
+        // Las particiones nuevas de la reverb no cambian la comparación de los ajustes
+        let same_kernel = match (&settings.reverb.kernel, &self.settings.reverb.kernel) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        if settings.same_effects(&self.settings) && same_kernel {
+            return;
+        }
+        self.settings.copy_effects_from(settings);
+        for slot in self.effects.iter_mut() {
+            slot.effect.apply_settings(&self.settings);
+        }
+    }
+
+    // Bypass y mezcla de una ranura, directamente y sin comparar el resto de los ajustes
+    // (automatización del host); con la ranura vacía no hace nada
+    pub fn set_slot(&mut self, slot: usize, settings: FxSlotSettings) {
+        if slot < self.slot_count {
+            let slot = &mut self.effects[self.order[slot]];
+            slot.bypass = settings.bypass;
+            slot.mix.set_target(settings.mix.clamp(0.0, 1.0));
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, mut frame: (f32, f32)) -> (f32, f32) {
+        for &index in &self.order[..self.slot_count] {
+            let slot = &mut self.effects[index];
+            if slot.bypass {
+                continue;
+            }
+            let wet = slot.effect.process(frame);
+            let mix = slot.mix.next_value();
+            frame = (
+                frame.0 * (1.0 - mix) + wet.0 * mix,
+                frame.1 * (1.0 - mix) + wet.1 * mix,
+            );
+        }
+        frame
+    }
+
+    // Procesar un bloque estéreo intercalado, efecto a efecto
+    pub fn process_block(&mut self, block: &mut [f32]) {
+        for &index in &self.order[..self.slot_count] {
+            let slot = &mut self.effects[index];
+            if slot.bypass {
+                continue;
+            }
+            if !slot.mix.is_settling() && slot.mix.value() >= 1.0 {
+                slot.effect.process_block(block);
+                continue;
+            }
+            // Solo reserva memoria si el bloque es más largo que los anteriores
+            self.dry.clear();
+            self.dry.extend_from_slice(block);
+            slot.effect.process_block(block);
+            for (frame, dry) in block.chunks_exact_mut(2).zip(self.dry.chunks_exact(2)) {
+                let mix = slot.mix.next_value();
+                frame[0] = dry[0] * (1.0 - mix) + frame[0] * mix;
+                frame[1] = dry[1] * (1.0 - mix) + frame[1] * mix;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for slot in self.effects.iter_mut() {
+            slot.effect.reset();
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use crate::dsp::fft::Fft;
+use crate::dsp::smoother::{ParamSmoother, GAIN_SMOOTHING_TIME};
+use super::impulse::{ImpulseResponse, SYNTHETIC_LENGTH};
+use super::{Effect, FxSettings};
+
+// Pre-delay máximo (milisegundos)
+pub const MAX_PRE_DELAY: f32 = 500.0;
+
+// Tamaño de cada partición de la respuesta al impulso. Es también la latencia de la
+// señal húmeda, que se descuenta del pre-delay.
+const PARTITION_SIZE: usize = 256;
+const FFT_SIZE: usize = PARTITION_SIZE * 2;
+
+#[derive(Debug)]
+pub struct ReverbSettings {
+    // Ruta del WAV con la respuesta al impulso; vacía para usar la sintética
+    pub impulse_path: String,
+    // Respuesta cargada desde `impulse_path`, o None si no hay archivo o no se pudo leer
+    pub impulse: Option<Arc<ImpulseResponse>>,
+    // Particiones de la respuesta ya transformadas para la frecuencia de muestreo del
+    // motor; las calcula `prepare` fuera del hilo de audio
+    pub kernel: Option<Arc<ReverbKernel>>,
+    // Milisegundos antes de las primeras reflexiones
+    pub pre_delay: f32,
+    pub mix: f32,
+}
+
+impl Default for ReverbSettings {
+    fn default() -> Self {
+        Self {
+            impulse_path: String::new(),
+            impulse: None,
+            kernel: None,
+            pre_delay: 10.0,
+            mix: 0.25,
+        }
+    }
+}
+
+// `clone_from` reutiliza la memoria de la ruta: el rack copia los ajustes en el hilo
+// de audio y la ruta solo crece al cargar otra respuesta
+impl Clone for ReverbSettings {
+    fn clone(&self) -> Self {
+        Self {
+            impulse_path: self.impulse_path.clone(),
+            impulse: self.impulse.clone(),
+            kernel: self.kernel.clone(),
+            pre_delay: self.pre_delay,
+            mix: self.mix,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.impulse_path.clone_from(&source.impulse_path);
+        self.impulse.clone_from(&source.impulse);
+        self.kernel.clone_from(&source.kernel);
+        self.pre_delay = source.pre_delay;
+        self.mix = source.mix;
+    }
+}
+
+// La respuesta se compara por puntero: el rack compara los ajustes en cada bloque y
+// comparar las muestras sería demasiado caro. Las particiones no cuentan: salen de la
+// respuesta, y calcularlas no es un cambio del sonido (ni un paso de deshacer).
+impl PartialEq for ReverbSettings {
+    fn eq(&self, other: &Self) -> bool {
+        self.impulse_path == other.impulse_path
+            && self.pre_delay == other.pre_delay
+            && self.mix == other.mix
+            && match (&self.impulse, &other.impulse) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+impl ReverbSettings {
+    // Leer el WAV de `path`. Si falla se conserva la ruta y se usa la respuesta sintética.
+    // Si ya había particiones calculadas se rehacen a la misma frecuencia de muestreo.
+    pub fn load_impulse(&mut self, path: &str) -> io::Result<()> {
+        let sample_rate = self.kernel.take().map(|kernel| kernel.sample_rate);
+        self.impulse_path = path.to_string();
+        self.impulse = None;
+        let result = if path.is_empty() {
+            Ok(())
+        } else {
+            ImpulseResponse::load(Path::new(path)).map(|impulse| self.impulse = Some(Arc::new(impulse)))
+        };
+        if let Some(sample_rate) = sample_rate {
+            self.prepare(sample_rate);
+        }
+        result
+    }
+
+    // Si faltan las particiones de la respuesta actual para `sample_rate`
+    pub fn needs_kernel(&self, sample_rate: f32) -> bool {
+        !self.kernel.as_ref().is_some_and(|kernel| kernel.matches(self.impulse.as_ref(), sample_rate))
+    }
+
+    // Calcular las particiones si faltan. Remuestrear la respuesta y pasarla a frecuencia
+    // es caro, así que se llama desde fuera del hilo de audio (al cargar el patch o
+    // cambiar la frecuencia de muestreo) y la reverb recibe el resultado en el Arc
+    pub fn prepare(&mut self, sample_rate: f32) {
+        if self.needs_kernel(sample_rate) {
+            self.kernel = Some(ReverbKernel::new(self.impulse.clone(), sample_rate));
+        }
+    }
+
+    // Particiones calculadas aparte, p. ej. sin tener bloqueados los ajustes; se
+    // descartan si mientras tanto ha cambiado la respuesta
+    pub fn set_kernel(&mut self, kernel: Arc<ReverbKernel>) {
+        if kernel.matches(self.impulse.as_ref(), kernel.sample_rate) {
+            self.kernel = Some(kernel);
+        }
+    }
+
+    // Segundos hasta que se apaga la cola de la respuesta
+    pub fn tail_time(&self) -> f32 {
+        let length = self.impulse.as_ref().map_or(SYNTHETIC_LENGTH, |impulse| impulse.length());
+        self.pre_delay.clamp(0.0, MAX_PRE_DELAY) / 1000.0 + length
+    }
+}
+
+// Espectros de las particiones de un canal de la respuesta, FFT_SIZE valores cada una
+struct Partitions {
+    re: Vec<f32>,
+    im: Vec<f32>,
+}
+
+impl Partitions {
+    fn new(impulse: &[f32], fft: &Fft) -> Self {
+        let partition_count = impulse.len().div_ceil(PARTITION_SIZE).max(1);
+        let mut re = vec![0.0; partition_count * FFT_SIZE];
+        let mut im = vec![0.0; partition_count * FFT_SIZE];
+        for (index, chunk) in impulse.chunks(PARTITION_SIZE).enumerate() {
+            let re = &mut re[index * FFT_SIZE..(index + 1) * FFT_SIZE];
+            let im = &mut im[index * FFT_SIZE..(index + 1) * FFT_SIZE];
+            re[..chunk.len()].copy_from_slice(chunk);
+            fft.forward(re, im);
+        }
+        Self { re, im }
+    }
+
+    // Una sola partición a cero: la reverb no suena hasta recibir las de verdad
+    fn silent() -> Self {
+        Self { re: vec![0.0; FFT_SIZE], im: vec![0.0; FFT_SIZE] }
+    }
+
+    fn count(&self) -> usize {
+        self.re.len() / FFT_SIZE
+    }
+}
+
+// Respuesta al impulso (la del archivo o la sintética) remuestreada a una frecuencia y
+// partida en espectros para los dos canales del convolucionador
+pub struct ReverbKernel {
+    // Respuesta de la que sale, None para la sintética
+    impulse: Option<Arc<ImpulseResponse>>,
+    sample_rate: f32,
+    channels: [Partitions; 2],
+}
+
+impl ReverbKernel {
+    pub fn new(impulse: Option<Arc<ImpulseResponse>>, sample_rate: f32) -> Arc<Self> {
+        let fft = Fft::new(FFT_SIZE);
+        let [left, right] = match &impulse {
+            Some(impulse) => impulse.resampled(sample_rate),
+            None => ImpulseResponse::synthetic(sample_rate).resampled(sample_rate),
+        };
+        Arc::new(Self {
+            impulse,
+            sample_rate,
+            channels: [Partitions::new(&left, &fft), Partitions::new(&right, &fft)],
+        })
+    }
+
+    fn silent() -> Arc<Self> {
+        Arc::new(Self { impulse: None, sample_rate: 0.0, channels: [Partitions::silent(), Partitions::silent()] })
+    }
+
+    fn matches(&self, impulse: Option<&Arc<ImpulseResponse>>, sample_rate: f32) -> bool {
+        self.sample_rate == sample_rate
+            && match (&self.impulse, impulse) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+impl fmt::Debug for ReverbKernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReverbKernel")
+            .field("sample_rate", &self.sample_rate)
+            .field("partitions", &self.channels[0].count())
+            .finish()
+    }
+}
+
+// Convolución de un canal por particiones uniformes con solapamiento y suma: cada
+// bloque de entrada se transforma una vez y se multiplica en frecuencia por todas las
+// particiones de la respuesta, usando una línea de retardo de espectros
+struct Convolver {
+    // Espectros de los últimos bloques de entrada, en anillo, uno por partición
+    history_re: Vec<f32>,
+    history_im: Vec<f32>,
+    history_index: usize,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    overlap: Vec<f32>,
+}
+
+impl Convolver {
+    fn new(partition_count: usize) -> Self {
+        Self {
+            history_re: vec![0.0; partition_count * FFT_SIZE],
+            history_im: vec![0.0; partition_count * FFT_SIZE],
+            history_index: 0,
+            input: vec![0.0; PARTITION_SIZE],
+            output: vec![0.0; PARTITION_SIZE],
+            overlap: vec![0.0; PARTITION_SIZE],
+        }
+    }
+
+    // Ajustar el anillo de espectros a otro número de particiones. Solo reserva memoria
+    // si la respuesta nueva es más larga que todas las anteriores
+    fn resize(&mut self, partition_count: usize) {
+        for history in [&mut self.history_re, &mut self.history_im] {
+            history.clear();
+            history.resize(partition_count * FFT_SIZE, 0.0);
+        }
+        self.history_index = 0;
+        self.output.fill(0.0);
+        self.overlap.fill(0.0);
+    }
+
+    // Convolucionar el bloque de entrada completo; el resultado queda en `output`
+    fn process_partition(&mut self, partitions: &Partitions, fft: &Fft, scratch_re: &mut [f32], scratch_im: &mut [f32]) {
+        let partition_count = partitions.count();
+
+        // Espectro del bloque nuevo, con ceros en la segunda mitad
+        let slot = self.history_index * FFT_SIZE;
+        let (re, im) = (&mut self.history_re[slot..slot + FFT_SIZE], &mut self.history_im[slot..slot + FFT_SIZE]);
+        re[..PARTITION_SIZE].copy_from_slice(&self.input);
+        re[PARTITION_SIZE..].fill(0.0);
+        im.fill(0.0);
+        fft.forward(re, im);
+
+        // Bloque de hace k particiones por la partición k
+        scratch_re.fill(0.0);
+        scratch_im.fill(0.0);
+        for k in 0..partition_count {
+            let input = ((self.history_index + partition_count - k) % partition_count) * FFT_SIZE;
+            let partition = k * FFT_SIZE;
+            for bin in 0..FFT_SIZE {
+                let (a_re, a_im) = (self.history_re[input + bin], self.history_im[input + bin]);
+                let (b_re, b_im) = (partitions.re[partition + bin], partitions.im[partition + bin]);
+                scratch_re[bin] += a_re * b_re - a_im * b_im;
+                scratch_im[bin] += a_re * b_im + a_im * b_re;
+            }
+        }
+        fft.inverse(scratch_re, scratch_im);
+
+        let scale = 1.0 / FFT_SIZE as f32;
+        for i in 0..PARTITION_SIZE {
+            self.output[i] = scratch_re[i] * scale + self.overlap[i];
+            self.overlap[i] = scratch_re[PARTITION_SIZE + i] * scale;
+        }
+        self.history_index = (self.history_index + 1) % partition_count;
+    }
+
+    fn reset(&mut self) {
+        self.history_re.fill(0.0);
+        self.history_im.fill(0.0);
+        self.output.fill(0.0);
+        self.overlap.fill(0.0);
+    }
+}
+
+// Reverb por convolución con una respuesta al impulso de archivo o sintética
+pub struct ConvolutionReverb {
+    settings: ReverbSettings,
+    sample_rate: f32,
+    fft: Fft,
+    kernel: Arc<ReverbKernel>,
+    channels: [Convolver; 2],
+    // Posición dentro del bloque en curso, común a los dos canales
+    position: usize,
+    pre_delay_buffer: Vec<(f32, f32)>,
+    pre_delay_index: usize,
+    pre_delay_samples: usize,
+    scratch_re: Vec<f32>,
+    scratch_im: Vec<f32>,
+    mix: ParamSmoother,
+}
+
+impl ConvolutionReverb {
+    // Arranca en silencio: las particiones llegan ya calculadas en los ajustes
+    pub fn new(sample_rate: f32) -> Self {
+        let fft = Fft::new(FFT_SIZE);
+        let channels = [Convolver::new(1), Convolver::new(1)];
+        let settings = ReverbSettings::default();
+        let mix = ParamSmoother::linear(settings.mix, GAIN_SMOOTHING_TIME, sample_rate);
+        let mut reverb = Self {
+            settings,
+            sample_rate,
+            fft,
+            kernel: ReverbKernel::silent(),
+            channels,
+            position: 0,
+            pre_delay_buffer: Vec::new(),
+            pre_delay_index: 0,
+            pre_delay_samples: 0,
+            scratch_re: vec![0.0; FFT_SIZE],
+            scratch_im: vec![0.0; FFT_SIZE],
+            mix,
+        };
+        reverb.set_sample_rate(sample_rate);
+        reverb
+    }
+
+    // Cambiar a las particiones de los ajustes si son de esta frecuencia de muestreo; si
+    // no, se sigue con las que hay hasta que lleguen las buenas
+    fn swap_kernel(&mut self) {
+        let Some(kernel) = &self.settings.kernel else {
+            return;
+        };
+        if Arc::ptr_eq(kernel, &self.kernel) || kernel.sample_rate != self.sample_rate {
+            return;
+        }
+        self.kernel = kernel.clone();
+        for (channel, partitions) in self.channels.iter_mut().zip(&self.kernel.channels) {
+            channel.resize(partitions.count());
+        }
+        self.position = 0;
+    }
+
+    fn update_pre_delay(&mut self) {
+        let samples = (self.settings.pre_delay.clamp(0.0, MAX_PRE_DELAY) / 1000.0 * self.sample_rate) as usize;
+        self.pre_delay_samples = samples.saturating_sub(PARTITION_SIZE);
+    }
+}
+
+impl Effect for ConvolutionReverb {
+    fn process(&mut self, frame: (f32, f32)) -> (f32, f32) {
+        let mut block = [frame.0, frame.1];
+        self.process_block(&mut block);
+        (block[0], block[1])
+    }
+
+    fn process_block(&mut self, block: &mut [f32]) {
+        let buffer_len = self.pre_delay_buffer.len();
+        for frame in block.chunks_exact_mut(2) {
+            let mix = self.mix.next_value();
+            let read_index = (self.pre_delay_index + buffer_len - self.pre_delay_samples) % buffer_len;
+            self.pre_delay_buffer[self.pre_delay_index] = (frame[0], frame[1]);
+            let delayed = self.pre_delay_buffer[read_index];
+            self.pre_delay_index = (self.pre_delay_index + 1) % buffer_len;
+
+            let position = self.position;
+            self.channels[0].input[position] = delayed.0;
+            self.channels[1].input[position] = delayed.1;
+            let wet = (self.channels[0].output[position], self.channels[1].output[position]);
+            frame[0] = frame[0] * (1.0 - mix) + wet.0 * mix;
+            frame[1] = frame[1] * (1.0 - mix) + wet.1 * mix;
+
+            self.position += 1;
+            if self.position == PARTITION_SIZE {
+                for (channel, partitions) in self.channels.iter_mut().zip(&self.kernel.channels) {
+                    channel.process_partition(partitions, &self.fft, &mut self.scratch_re, &mut self.scratch_im);
+                }
+                self.position = 0;
+            }
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        let len = (MAX_PRE_DELAY / 1000.0 * sample_rate) as usize + 1;
+        self.pre_delay_buffer = vec![(0.0, 0.0); len];
+        self.pre_delay_index = 0;
+        self.mix.set_time(GAIN_SMOOTHING_TIME, sample_rate);
+        self.update_pre_delay();
+        self.swap_kernel();
+    }
+
+    fn apply_settings(&mut self, settings: &FxSettings) {
+        self.settings.clone_from(&settings.reverb);
+        self.mix.set_target(self.settings.mix.clamp(0.0, 1.0));
+        self.update_pre_delay();
+        self.swap_kernel();
+    }
+
+    fn reset(&mut self) {
+        self.pre_delay_buffer.fill((0.0, 0.0));
+        for channel in self.channels.iter_mut() {
+            channel.reset();
+        }
+    }
+}
@@ -0,0 +1,94 @@
+// Tiempo máximo de deslizamiento entre dos notas (segundos)
+pub const MAX_GLIDE_TIME: f32 = 2.0;
+
+// Cómo pasa el tono de una nota a la siguiente
+#[derive(Clone, Copy, PartialEq)]
+pub enum GlideMode {
+    // Salto directo a la nota nueva
+    Off,
+    // Deslizamiento continuo (portamento)
+    Portamento,
+    // Deslizamiento por semitonos enteros, como un glissando de arpa o de piano
+    Glissando,
+}
+
+impl GlideMode {
+    pub const ALL: [GlideMode; 3] = [GlideMode::Off, GlideMode::Portamento, GlideMode::Glissando];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GlideMode::Off => "Sin glide",
+            GlideMode::Portamento => "Portamento",
+            GlideMode::Glissando => "Glissando",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct GlideSettings {
+    pub mode: GlideMode,
+    // Duración del deslizamiento, la misma sea cual sea el intervalo (segundos)
+    pub time: f32,
+}
+
+impl Default for GlideSettings {
+    fn default() -> Self {
+        Self {
+            mode: GlideMode::Off,
+            time: 0.1,
+        }
+    }
+}
+
+// Deslizamiento de una voz: desplazamiento en semitonos respecto a su nota, que
+// llega a cero en el tiempo ajustado
+#[derive(Clone, Copy, Default)]
+pub struct Glide {
+    pub settings: GlideSettings,
+    offset: f32,
+    // Semitonos que se recorren en cada muestra
+    step: f32,
+}
+
+impl Glide {
+    pub fn new(settings: GlideSettings) -> Self {
+        Self { settings, offset: 0.0, step: 0.0 }
+    }
+
+    // Empezar a deslizar desde `semitones` por encima (o por debajo) de la nota
+    pub fn start(&mut self, semitones: f32, sample_rate: f32) {
+        if self.settings.mode == GlideMode::Off || self.settings.time <= 0.0 {
+            self.offset = 0.0;
+            return;
+        }
+        self.offset = semitones;
+        self.step = semitones.abs() / (self.settings.time * sample_rate);
+    }
+
+    // El deslizamiento en curso dura lo mismo a la nueva frecuencia de muestreo
+    pub fn set_sample_rate(&mut self, old_sample_rate: f32, sample_rate: f32) {
+        self.step *= old_sample_rate / sample_rate;
+    }
+
+    // Desplazamiento que suena ahora; en glissando se queda en el semitono más cercano
+    pub fn offset(&self) -> f32 {
+        match self.settings.mode {
+            GlideMode::Glissando => self.offset.round(),
+            _ => self.offset,
+        }
+    }
+
+    #[inline]
+    pub fn next_offset(&mut self) -> f32 {
+        if self.offset == 0.0 {
+            return 0.0;
+        }
+        let offset = self.offset();
+        self.offset = if self.offset > 0.0 {
+            (self.offset - self.step).max(0.0)
+        } else {
+            (self.offset + self.step).min(0.0)
+        };
+        offset
+    }
+}
@@ -0,0 +1,216 @@
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
+use super::noise::NoiseSource;
+
+// Rango de la velocidad del LFO (Hz)
+pub const MIN_LFO_RATE: f32 = 0.05;
+pub const MAX_LFO_RATE: f32 = 20.0;
+
+// Profundidad máxima del vibrato (semitonos hacia cada lado)
+pub const MAX_LFO_PITCH_DEPTH: f32 = 12.0;
+
+// Velocidad a partir de un valor normalizado (0..1) en escala logarítmica
+pub fn lfo_rate_from_normalized(value: f32) -> f32 {
+    MIN_LFO_RATE * (MAX_LFO_RATE / MIN_LFO_RATE).powf(value.clamp(0.0, 1.0))
+}
+
+pub fn lfo_rate_to_normalized(rate: f32) -> f32 {
+    ((rate / MIN_LFO_RATE).ln() / (MAX_LFO_RATE / MIN_LFO_RATE).ln()).clamp(0.0, 1.0)
+}
+
+// Forma de onda del LFO; todas son bipolares, de -1.0 a 1.0
+#[derive(Clone, Copy, PartialEq)]
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    Sawtooth,
+    Square,
+    // Un valor aleatorio nuevo en cada ciclo, mantenido hasta el siguiente
+    SampleHold,
+    // Paseo aleatorio que se desliza de un valor al siguiente en cada ciclo
+    SmoothRandom,
+}
+
+impl LfoShape {
+    pub const ALL: [LfoShape; 6] = [
+        LfoShape::Sine,
+        LfoShape::Triangle,
+        LfoShape::Sawtooth,
+        LfoShape::Square,
+        LfoShape::SampleHold,
+        LfoShape::SmoothRandom,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LfoShape::Sine => "Seno",
+            LfoShape::Triangle => "Triangular",
+            LfoShape::Sawtooth => "Sierra",
+            LfoShape::Square => "Cuadrada",
+            LfoShape::SampleHold => "S&H",
+            LfoShape::SmoothRandom => "Aleatoria suave",
+        }
+    }
+}
+
+// Fase con la que arranca el LFO de cada voz
+#[derive(Clone, Copy, PartialEq)]
+pub enum LfoTrigger {
+    // Vuelve a la fase inicial con cada nota
+    Key,
+    // Fase inicial aleatoria en cada voz
+    Random,
+    // Continúa una fase común a todas las voces, como un LFO global
+    FreeRun,
+}
+
+impl LfoTrigger {
+    pub const ALL: [LfoTrigger; 3] = [LfoTrigger::Key, LfoTrigger::Random, LfoTrigger::FreeRun];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LfoTrigger::Key => "Con la nota",
+            LfoTrigger::Random => "Aleatoria",
+            LfoTrigger::FreeRun => "Libre",
+        }
+    }
+}
+
+// Última fase (bits de f32) del LFO en modo libre, compartida por todas las voces
+static FREE_RUN_PHASE: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct LfoSettings {
+    pub shape: LfoShape,
+    pub rate: f32,
+    // Vibrato: desviación del tono en semitonos con el LFO al máximo
+    pub pitch_depth: f32,
+    pub trigger: LfoTrigger,
+    // Fase de 0.0 a 1.0 con la que arranca en modo Key
+    pub start_phase: f32,
+    // Recorrer un solo ciclo por pulsación y quedarse en el valor final, como una
+    // envolvente más
+    pub one_shot: bool,
+}
+
+impl Default for LfoSettings {
+    fn default() -> Self {
+        Self {
+            shape: LfoShape::Sine,
+            rate: 5.0,
+            pitch_depth: 0.0,
+            trigger: LfoTrigger::Key,
+            start_phase: 0.0,
+            one_shot: false,
+        }
+    }
+}
+
+// Oscilador de baja frecuencia para modulación. Cada voz tiene el suyo; la fase
+// inicial se elige en la primera muestra según `settings.trigger`. Las formas
+// aleatorias usan un generador con semilla propia en cada voz, así que dos notas
+// no siguen la misma secuencia.
+pub struct Lfo {
+    pub settings: LfoSettings,
+    phase: f32,
+    started: bool,
+    noise: NoiseSource,
+    // Valores aleatorios al principio y al final del ciclo actual
+    random_from: f32,
+    random_to: f32,
+    // Parte del ciclo recorrida desde la pulsación y valor en el que se detiene el
+    // modo de un solo ciclo
+    elapsed: f32,
+    held: Option<f32>,
+}
+
+impl Lfo {
+    pub fn new(settings: LfoSettings) -> Self {
+        Self {
+            settings,
+            phase: 0.0,
+            started: false,
+            noise: NoiseSource::new(),
+            random_from: 0.0,
+            random_to: 0.0,
+            elapsed: 0.0,
+            held: None,
+        }
+    }
+
+    // Nueva pulsación de la nota: la fase se vuelve a elegir en la siguiente muestra
+    pub fn retrigger(&mut self) {
+        self.started = false;
+    }
+
+    fn initial_phase(&mut self) -> f32 {
+        match self.settings.trigger {
+            LfoTrigger::Key => self.settings.start_phase.clamp(0.0, 1.0).fract(),
+            LfoTrigger::Random => (self.noise.next_bipolar() + 1.0) * 0.5,
+            LfoTrigger::FreeRun => f32::from_bits(FREE_RUN_PHASE.load(Ordering::Relaxed)),
+        }
+    }
+
+    // Multiplicador de frecuencia del vibrato para un valor del LFO, con `extra_depth`
+    // semitonos sumados a la profundidad de los ajustes (p. ej. desde la rueda de modulación)
+    #[inline]
+    pub fn pitch_ratio(&self, value: f32, extra_depth: f32) -> f32 {
+        let depth = (self.settings.pitch_depth + extra_depth).clamp(0.0, MAX_LFO_PITCH_DEPTH);
+        if depth == 0.0 {
+            1.0
+        } else {
+            2.0f32.powf(value * depth / 12.0)
+        }
+    }
+
+    // Valor actual y avance de una muestra
+    #[inline]
+    pub fn next_sample(&mut self, sample_rate: f32) -> f32 {
+        if !self.started {
+            self.phase = self.initial_phase();
+            self.random_from = self.noise.next_bipolar();
+            self.random_to = self.noise.next_bipolar();
+            self.elapsed = 0.0;
+            self.held = None;
+            self.started = true;
+        }
+        if let Some(value) = self.held.filter(|_| self.settings.one_shot) {
+            return value;
+        }
+        let phase = self.phase;
+        let value = match self.settings.shape {
+            LfoShape::Sine => (2.0 * PI * phase).sin(),
+            LfoShape::Triangle => {
+                if phase < 0.25 {
+                    4.0 * phase
+                } else if phase < 0.75 {
+                    2.0 - 4.0 * phase
+                } else {
+                    4.0 * phase - 4.0
+                }
+            }
+            LfoShape::Sawtooth => 2.0 * phase - 1.0,
+            LfoShape::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            LfoShape::SampleHold => self.random_from,
+            LfoShape::SmoothRandom => {
+                // Interpolación coseno para que no haya esquinas entre ciclos
+                let t = (1.0 - (PI * phase).cos()) * 0.5;
+                self.random_from + (self.random_to - self.random_from) * t
+            }
+        };
+        let step = self.settings.rate / sample_rate;
+        self.phase = (phase + step).fract();
+        self.elapsed += step;
+        if self.settings.one_shot && self.elapsed >= 1.0 {
+            self.held = Some(value);
+        }
+        if self.phase < phase {
+            self.random_from = self.random_to;
+            self.random_to = self.noise.next_bipolar();
+        }
+        if self.settings.trigger == LfoTrigger::FreeRun {
+            FREE_RUN_PHASE.store(self.phase.to_bits(), Ordering::Relaxed);
+        }
+        value
+    }
+}
@@ -0,0 +1,47 @@
+// Procesado de señal: osciladores, filtros y voces. Cada componente tiene una única
+// definición aquí, compartida por la aplicación, el plugin y la API pública.
+pub mod biquad;
+pub mod chip;
+pub mod fft;
+pub mod filters;
+pub mod fx;
+pub mod glide;
+pub mod lfo;
+pub mod modulation;
+pub mod noise;
+pub mod note;
+pub mod oscillator;
+pub mod paraphonic;
+pub mod saturation;
+pub mod smoother;
+pub mod source;
+pub mod unison;
+pub mod vector;
+pub mod voice_fx;
+pub mod voice_map;
+pub mod voice_pan;
+pub mod wavetable;
+
+// Re-export principales componentes
+pub use biquad::{Biquad, BiquadCoefficients};
+pub use chip::{ChipChannel, DutyCycle};
+pub use filters::{
+    BandFilter, BandFilterSettings, BandMode, DcBlocker, FilterSettings, FilterSlope, FilterType, FormantFilter,
+    FormantSettings, MasterFilter, MasterFilterSettings, Vowel,
+};
+pub use fx::{EffectType, FxRack, FxSettings, FxSlotSettings};
+pub use glide::{GlideMode, GlideSettings, MAX_GLIDE_TIME};
+pub use lfo::{Lfo, LfoSettings, LfoShape, LfoTrigger};
+pub use modulation::{ModCurve, ModDestination, ModMatrix, ModRoute, ModSource, ModSourceValues};
+pub use oscillator::{
+    FreeRunPhases, Oscillator, OscillatorSettings, PhaseMode, Quality, WaveType, MAX_OSCILLATORS, MAX_OSCILLATOR_DETUNE,
+};
+pub use note::Note;
+pub use paraphonic::{ParaphonicBus, ParaphonicSettings, ParaphonicTrigger};
+pub use saturation::{Saturation, SaturationMode, SaturationSettings};
+pub use source::OscillatorSource;
+pub use unison::{UnisonSettings, UnisonVoice, MAX_UNISON_DETUNE, MAX_UNISON_VOICES};
+pub use vector::VectorSettings;
+pub use voice_map::{Voice, VoiceId, VoiceMap};
+pub use voice_pan::{VoicePanMode, VoicePanSettings};
+pub use wavetable::WavetableOscillator;
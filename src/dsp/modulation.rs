@@ -0,0 +1,428 @@
+use super::noise::NoiseSource;
+use super::smoother::ParamSmoother;
+use crate::midi::rpn::{RpnChange, RpnReceiver};
+
+// Matriz de modulación: cada ruta lleva una fuente (un CC MIDI, el aftertouch de canal
+// o de la tecla, la velocidad o un valor aleatorio por nota) a un destino de la voz con una cantidad bipolar y una
+// curva de respuesta. El MSEG usa los mismos destinos.
+
+// Número máximo de rutas de la matriz
+pub const MAX_MOD_ROUTES: usize = 8;
+
+// Alcance de cada destino con cantidad 1.0 y fuente al máximo
+pub const MOD_PITCH_SEMITONES: f32 = 24.0;
+pub const MOD_CUTOFF_OCTAVES: f32 = 4.0;
+// Profundidad de vibrato (semitonos) que se suma a la del LFO
+pub const MOD_VIBRATO_SEMITONES: f32 = 2.0;
+
+// Rueda de modulación, que por defecto controla el vibrato
+pub const MOD_WHEEL_CC: u8 = 1;
+
+// Rango del pitch bend (semitonos en cada sentido) hasta que un RPN 0,0 lo cambie
+pub const DEFAULT_BEND_RANGE: f32 = 2.0;
+
+// Tiempo de suavizado de las fuentes que llegan a saltos, como los CC de 7 bits (segundos)
+const MOD_SMOOTHING_TIME: f32 = 0.01;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ModSource {
+    // Valor del controlador MIDI de la ruta, de 0.0 a 1.0
+    Cc,
+    // Valor fijo de -1.0 a 1.0 elegido al azar en cada pulsación, distinto en cada voz
+    NoteRandom,
+    // Velocidad de la pulsación, de 0.0 a 1.0
+    Velocity,
+    // Presión de canal (aftertouch), de 0.0 a 1.0
+    Aftertouch,
+    // Presión de la tecla de cada voz (aftertouch polifónico), de 0.0 a 1.0
+    PolyAftertouch,
+}
+
+impl ModSource {
+    pub const ALL: [ModSource; 5] = [
+        ModSource::Cc,
+        ModSource::NoteRandom,
+        ModSource::Velocity,
+        ModSource::Aftertouch,
+        ModSource::PolyAftertouch,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModSource::Cc => "CC",
+            ModSource::NoteRandom => "Aleatorio por nota",
+            ModSource::Velocity => "Velocidad",
+            ModSource::Aftertouch => "Aftertouch",
+            ModSource::PolyAftertouch => "Aftertouch polifónico",
+        }
+    }
+}
+
+// Respuesta de la ruta al valor de la fuente. En las fuentes bipolares la curva se
+// aplica a la magnitud y se conserva el signo.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ModCurve {
+    Linear,
+    // Cambia poco al principio del recorrido y mucho al final
+    Exponential,
+    // Suave en los dos extremos y rápida en el centro
+    SCurve,
+}
+
+impl ModCurve {
+    pub const ALL: [ModCurve; 3] = [
+        ModCurve::Linear,
+        ModCurve::Exponential,
+        ModCurve::SCurve,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModCurve::Linear => "Lineal",
+            ModCurve::Exponential => "Exponencial",
+            ModCurve::SCurve => "Curva S",
+        }
+    }
+
+    pub fn apply(&self, value: f32) -> f32 {
+        let magnitude = value.abs().min(1.0);
+        let shaped = match self {
+            ModCurve::Linear => magnitude,
+            ModCurve::Exponential => magnitude * magnitude,
+            ModCurve::SCurve => magnitude * magnitude * (3.0 - 2.0 * magnitude),
+        };
+        shaped.copysign(value)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ModDestination {
+    Pitch,
+    Cutoff,
+    WavetablePosition,
+    Pan,
+    // Profundidad del LFO sobre el tono
+    Vibrato,
+}
+
+impl ModDestination {
+    pub const ALL: [ModDestination; 5] = [
+        ModDestination::Pitch,
+        ModDestination::Cutoff,
+        ModDestination::WavetablePosition,
+        ModDestination::Pan,
+        ModDestination::Vibrato,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModDestination::Pitch => "Tono",
+            ModDestination::Cutoff => "Corte",
+            ModDestination::WavetablePosition => "Posición de tabla",
+            ModDestination::Pan => "Panorama",
+            ModDestination::Vibrato => "Vibrato",
+        }
+    }
+
+    // Nombre usado en la línea de comandos
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pitch" => Some(ModDestination::Pitch),
+            "cutoff" => Some(ModDestination::Cutoff),
+            "wavetable" => Some(ModDestination::WavetablePosition),
+            "pan" => Some(ModDestination::Pan),
+            "vibrato" => Some(ModDestination::Vibrato),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct ModRoute {
+    pub source: ModSource,
+    // Controlador que se lee con la fuente `Cc`
+    pub cc: u8,
+    pub destination: ModDestination,
+    // De -1.0 a 1.0; con una cantidad negativa la fuente resta, p. ej. la velocidad
+    // cierra el filtro
+    pub amount: f32,
+    pub curve: ModCurve,
+}
+
+impl Default for ModRoute {
+    // CC 74 (brillo) al corte, la asignación habitual de los teclados
+    fn default() -> Self {
+        Self {
+            source: ModSource::Cc,
+            cc: 74,
+            destination: ModDestination::Cutoff,
+            amount: 0.5,
+            curve: ModCurve::Linear,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct ModMatrix {
+    pub routes: [ModRoute; MAX_MOD_ROUTES],
+    pub route_count: usize,
+    // Profundidad global de la fuente aleatoria por nota, de 0.0 a 1.0
+    pub random_depth: f32,
+}
+
+impl Default for ModMatrix {
+    // La rueda de modulación ya llega al vibrato; la ruta se puede reasignar o quitar
+    fn default() -> Self {
+        let mut routes = [ModRoute::default(); MAX_MOD_ROUTES];
+        routes[0] = ModRoute {
+            cc: MOD_WHEEL_CC,
+            destination: ModDestination::Vibrato,
+            ..ModRoute::default()
+        };
+        Self {
+            routes,
+            route_count: 1,
+            random_depth: 1.0,
+        }
+    }
+}
+
+impl ModMatrix {
+    pub fn active_routes(&self) -> &[ModRoute] {
+        &self.routes[..self.route_count.min(MAX_MOD_ROUTES)]
+    }
+
+    pub fn add_route(&mut self, route: ModRoute) -> bool {
+        if self.route_count >= MAX_MOD_ROUTES {
+            return false;
+        }
+        self.routes[self.route_count] = route;
+        self.route_count += 1;
+        true
+    }
+
+    pub fn remove_route(&mut self, index: usize) {
+        if index < self.route_count {
+            self.routes.copy_within(index + 1..self.route_count, index);
+            self.route_count -= 1;
+        }
+    }
+
+    // Ruta en una posición fija, activando las anteriores si hace falta
+    pub fn route_mut(&mut self, index: usize) -> &mut ModRoute {
+        let index = index.min(MAX_MOD_ROUTES - 1);
+        self.route_count = self.route_count.max(index + 1);
+        &mut self.routes[index]
+    }
+}
+
+// Valores actuales de las fuentes comunes a todas las voces, junto con el pitch bend y
+// la afinación que fijan los RPN, que también valen para todas
+#[derive(Clone, Copy, PartialEq)]
+pub struct ModSourceValues {
+    pub cc: [f32; 128],
+    pub aftertouch: f32,
+    // De -1.0 a 1.0
+    pub pitch_bend: f32,
+    // Semitonos en cada sentido (RPN 0,0) y transposición en semitonos (RPN 0,2)
+    pub bend_range: f32,
+    pub coarse_tune: f32,
+    rpn: RpnReceiver,
+}
+
+impl Default for ModSourceValues {
+    fn default() -> Self {
+        Self {
+            cc: [0.0; 128],
+            aftertouch: 0.0,
+            pitch_bend: 0.0,
+            bend_range: DEFAULT_BEND_RANGE,
+            coarse_tune: 0.0,
+            rpn: RpnReceiver::new(),
+        }
+    }
+}
+
+impl ModSourceValues {
+    pub fn set_cc(&mut self, cc: u8, value: u8) {
+        self.cc[(cc & 0x7F) as usize] = (value & 0x7F) as f32 / 127.0;
+    }
+
+    // Control Change entrante: los CC de los RPN cambian el rango del bend o la
+    // afinación y el resto son fuentes de la matriz. Devuelve true si era de un RPN.
+    pub fn control_change(&mut self, cc: u8, value: u8) -> bool {
+        if !self.rpn.handles(cc) {
+            self.set_cc(cc, value);
+            return false;
+        }
+        match self.rpn.control_change(cc, value) {
+            Some(RpnChange::BendRange(range)) => self.bend_range = range,
+            Some(RpnChange::CoarseTune(semitones)) => self.coarse_tune = semitones,
+            None => (),
+        }
+        true
+    }
+
+    // Mensaje de pitch bend (0xE0) con sus dos bytes de datos; 8192 es el centro
+    pub fn set_pitch_bend(&mut self, lsb: u8, msb: u8) {
+        let value = ((msb & 0x7F) as i32) << 7 | (lsb & 0x7F) as i32;
+        self.pitch_bend = ((value - 8192) as f32 / 8192.0).clamp(-1.0, 1.0);
+    }
+
+    // Desplazamiento de todas las voces en semitonos
+    pub fn pitch_semitones(&self) -> f32 {
+        self.pitch_bend * self.bend_range + self.coarse_tune
+    }
+
+    // Mensaje de presión de canal (0xD0), con el valor de 0 a 127
+    pub fn set_aftertouch(&mut self, value: u8) {
+        self.aftertouch = (value & 0x7F) as f32 / 127.0;
+    }
+
+    // Valor de la fuente común a todas las voces; las fuentes propias de cada voz
+    // valen 0.0 aquí
+    pub fn value(&self, route: &ModRoute) -> f32 {
+        match route.source {
+            ModSource::Cc => self.cc[(route.cc & 0x7F) as usize],
+            ModSource::Aftertouch => self.aftertouch,
+            ModSource::NoteRandom | ModSource::Velocity | ModSource::PolyAftertouch => 0.0,
+        }
+    }
+}
+
+// Desplazamiento de cada destino en una muestra, ya en sus unidades
+#[derive(Clone, Copy, Default)]
+pub struct ModOffsets {
+    // Semitonos
+    pub pitch: f32,
+    // Octavas
+    pub cutoff: f32,
+    // Fracción de la tabla
+    pub wavetable: f32,
+    // De -1.0 (izquierda) a 1.0 (derecha)
+    pub pan: f32,
+    // Semitonos de vibrato añadidos a la profundidad del LFO
+    pub vibrato: f32,
+}
+
+impl ModOffsets {
+    // `value` de -1.0 a 1.0: fuente por cantidad
+    #[inline]
+    pub fn add(&mut self, destination: ModDestination, value: f32) {
+        match destination {
+            ModDestination::Pitch => self.pitch += value * MOD_PITCH_SEMITONES,
+            ModDestination::Cutoff => self.cutoff += value * MOD_CUTOFF_OCTAVES,
+            ModDestination::WavetablePosition => self.wavetable += value,
+            ModDestination::Pan => self.pan += value,
+            ModDestination::Vibrato => self.vibrato += value * MOD_VIBRATO_SEMITONES,
+        }
+    }
+
+    pub fn pitch_ratio(&self) -> f32 {
+        if self.pitch == 0.0 {
+            1.0
+        } else {
+            2.0f32.powf(self.pitch / 12.0)
+        }
+    }
+}
+
+// Estado de la matriz en una voz: cada ruta se suaviza hacia el valor de su fuente
+pub struct VoiceModulation {
+    matrix: ModMatrix,
+    smoothers: [ParamSmoother; MAX_MOD_ROUTES],
+    // Hasta la primera muestra los valores se fijan sin suavizado
+    started: bool,
+    sources: ModSourceValues,
+    // Valor aleatorio, velocidad y presión de la pulsación actual
+    noise: NoiseSource,
+    random: f32,
+    velocity: f32,
+    pressure: f32,
+}
+
+impl VoiceModulation {
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for smoother in self.smoothers.iter_mut() {
+            smoother.set_time(MOD_SMOOTHING_TIME, sample_rate);
+        }
+    }
+
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            matrix: ModMatrix::default(),
+            smoothers: std::array::from_fn(|_| ParamSmoother::new(0.0, MOD_SMOOTHING_TIME, sample_rate)),
+            started: false,
+            sources: ModSourceValues::default(),
+            noise: NoiseSource::new(),
+            random: 0.0,
+            velocity: 0.0,
+            pressure: 0.0,
+        }
+    }
+
+    // Nueva pulsación de la tecla: se elige otro valor aleatorio
+    pub fn note_on(&mut self, velocity: f32) {
+        self.random = self.noise.next_bipolar();
+        self.velocity = velocity;
+        self.pressure = 0.0;
+        self.update_targets();
+    }
+
+    // Presión de la tecla de 0.0 a 1.0, desde los mensajes de aftertouch polifónico
+    pub fn set_pressure(&mut self, pressure: f32) {
+        if pressure != self.pressure {
+            self.pressure = pressure;
+            self.update_targets();
+        }
+    }
+
+    pub fn set_matrix(&mut self, matrix: ModMatrix) {
+        if matrix != self.matrix {
+            self.matrix = matrix;
+            self.update_targets();
+        }
+    }
+
+    pub fn set_sources(&mut self, sources: &ModSourceValues) {
+        if *sources != self.sources {
+            self.sources = *sources;
+            self.update_targets();
+        }
+    }
+
+    fn update_targets(&mut self) {
+        for (smoother, route) in self.smoothers.iter_mut().zip(self.matrix.active_routes()) {
+            let value = match route.source {
+                ModSource::NoteRandom => self.random * self.matrix.random_depth,
+                ModSource::Velocity => self.velocity,
+                ModSource::PolyAftertouch => self.pressure,
+                ModSource::Cc | ModSource::Aftertouch => self.sources.value(route),
+            };
+            let value = route.curve.apply(value);
+            if self.started {
+                smoother.set_target(value);
+            } else {
+                smoother.reset(value);
+            }
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.matrix.route_count > 0 || self.sources.pitch_semitones() != 0.0
+    }
+
+    #[inline]
+    pub fn next_offsets(&mut self) -> ModOffsets {
+        self.started = true;
+        // El pitch bend llega con 14 bits y no necesita suavizado
+        let mut offsets = ModOffsets {
+            pitch: self.sources.pitch_semitones(),
+            ..ModOffsets::default()
+        };
+        for (smoother, route) in self.smoothers.iter_mut().zip(self.matrix.active_routes()) {
+            offsets.add(route.destination, smoother.next_value() * route.amount);
+        }
+        offsets
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// Semilla global para que cada fuente de ruido arranque en un punto distinto
+static NEXT_SEED: AtomicU32 = AtomicU32::new(0x9E37_79B9);
+
+// Generador de ruido blanco xorshift, barato y sin asignaciones
+pub struct NoiseSource {
+    state: u32,
+}
+
+impl NoiseSource {
+    pub fn new() -> Self {
+        let seed = NEXT_SEED.fetch_add(0x6D2B_79F5, Ordering::Relaxed);
+        Self { state: seed | 1 }
+    }
+
+    // Valor aleatorio uniforme entre -1.0 y 1.0
+    #[inline]
+    pub fn next_bipolar(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+impl Default for NoiseSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Frecuencia con la que el paseo aleatorio elige un nuevo destino (Hz)
+const DRIFT_RATE: f32 = 4.0;
+// Frecuencia de corte del suavizado del paseo aleatorio (Hz)
+const DRIFT_SMOOTHING: f32 = 1.5;
+
+// Paseo aleatorio de banda limitada entre -1.0 y 1.0 para emular la inestabilidad analógica
+pub struct DriftGenerator {
+    noise: NoiseSource,
+    target: f32,
+    value: f32,
+    samples_until_step: u32,
+}
+
+impl DriftGenerator {
+    pub fn new() -> Self {
+        let mut noise = NoiseSource::new();
+        let target = noise.next_bipolar();
+        Self {
+            noise,
+            target,
+            value: target,
+            samples_until_step: 0,
+        }
+    }
+
+    // Valor aleatorio sin suavizar, útil para variaciones puntuales como la fase inicial
+    pub fn random(&mut self) -> f32 {
+        self.noise.next_bipolar()
+    }
+
+    #[inline]
+    pub fn next(&mut self, sample_rate: f32) -> f32 {
+        if self.samples_until_step == 0 {
+            // Paso del paseo aleatorio, limitado para no salir del rango
+            self.target = (self.target + self.noise.next_bipolar() * 0.5).clamp(-1.0, 1.0);
+            self.samples_until_step = (sample_rate / DRIFT_RATE) as u32;
+        }
+        self.samples_until_step -= 1;
+
+        let alpha = 2.0 * std::f32::consts::PI * DRIFT_SMOOTHING / sample_rate;
+        self.value += alpha * (self.target - self.value);
+        self.value
+    }
+}
+
+impl Default for DriftGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,443 @@
+use crate::structs::envelope::{AdsrSettings, Envelope, EnvelopeState};
+use crate::structs::mseg::{Mseg, MsegSettings};
+use std::sync::Arc;
+use super::filters::{BandFilter, FilterSettings, FormantFilter, StateVariableFilter, MAX_CUTOFF, MIN_CUTOFF};
+use super::glide::{Glide, GlideSettings};
+use super::lfo::{Lfo, LfoSettings};
+use super::modulation::{ModMatrix, ModOffsets, ModSourceValues, VoiceModulation};
+use super::smoother::ParamSmoother;
+use super::oscillator::{FreeRunPhases, Oscillator, OscillatorSettings, Quality, MAX_OSCILLATORS};
+use super::source::OscillatorSource;
+use super::unison::UnisonVoice;
+use super::vector::VectorSettings;
+use super::voice_fx::VoiceFx;
+use super::wavetable::Wavetable;
+
+// Tiempo de suavizado del corte y la resonancia del filtro (segundos)
+const FILTER_SMOOTHING_TIME: f32 = 0.005;
+// Muestras que renderiza cada fuente externa de una vez
+const SOURCE_BLOCK_SIZE: usize = 64;
+
+pub struct Note {
+    pub frequency: f32,
+    pub sample_rate: f32,
+    pub envelope: Envelope,
+    // Envolvente propia del corte del filtro, independiente de la de amplitud
+    pub filter_envelope: Envelope,
+    pub lfo: Lfo,
+    pub mseg: Mseg,
+    // Rutas de la matriz de modulación con sus fuentes suavizadas
+    modulation: VoiceModulation,
+    pub oscillators: [Oscillator; MAX_OSCILLATORS],
+    // Cuántos osciladores del array suenan en esta voz
+    pub oscillator_count: usize,
+    pub fx: VoiceFx,
+    pub vector: VectorSettings,
+    filter_settings: FilterSettings,
+    // Corte y resonancia suavizados hacia los valores de `filter_settings`
+    cutoff_smoother: ParamSmoother,
+    resonance_smoother: ParamSmoother,
+    // Hasta la primera muestra los cambios de filtro se aplican sin suavizado
+    has_rendered: bool,
+    // Un filtro resonante por canal, aplicado a la mezcla de osciladores
+    filters: [StateVariableFilter; 2],
+    // Filtro de banda (notch o paso banda) por canal, tras el filtro resonante
+    band_filters: [BandFilter; 2],
+    // Filtro de formantes por canal, al final de la cadena de filtros
+    formant_filters: [FormantFilter; 2],
+    // Osciladores externos añadidos por quien usa la librería
+    sources: Vec<Box<dyn OscillatorSource>>,
+    // Suma de las fuentes externas para un bloque, la salida de cada una antes de
+    // sumarla y la siguiente muestra del bloque que sale en get_sample
+    source_block: Vec<f32>,
+    source_scratch: Vec<f32>,
+    source_position: usize,
+    // Desafinación, panorama y ganancia de esta voz dentro del unísono de su tecla
+    unison: UnisonVoice,
+    // Posición de la voz en el campo estéreo, de -1.0 a 1.0, elegida al crearla
+    pan: f32,
+    // En modo parafónico el filtro es común a todas las voces y la voz no filtra
+    paraphonic: bool,
+    // Portamento o glissando desde la nota anterior
+    glide: Glide,
+}
+
+impl Note {
+    pub fn new(frequency: f32, envelope: Envelope, sample_rate: f32, settings: &[OscillatorSettings]) -> Self {
+        let oscillators = std::array::from_fn(|slot| {
+            let mut osc = Oscillator::new(super::WaveType::Sine, sample_rate);
+            osc.slot = slot;
+            osc
+        });
+        let mut filter_envelope = Envelope::new(sample_rate);
+        filter_envelope.note_on();
+        let mut note = Self {
+            frequency,
+            sample_rate,
+            envelope,
+            filter_envelope,
+            lfo: Lfo::new(LfoSettings::default()),
+            mseg: Mseg::new(MsegSettings::default()),
+            modulation: VoiceModulation::new(sample_rate),
+            oscillators,
+            oscillator_count: 0,
+            fx: VoiceFx::new(sample_rate),
+            vector: VectorSettings::default(),
+            filter_settings: FilterSettings::default(),
+            cutoff_smoother: ParamSmoother::new(FilterSettings::default().cutoff, FILTER_SMOOTHING_TIME, sample_rate),
+            resonance_smoother: ParamSmoother::new(0.0, FILTER_SMOOTHING_TIME, sample_rate),
+            has_rendered: false,
+            filters: std::array::from_fn(|_| {
+                let settings = FilterSettings::default();
+                StateVariableFilter::new(settings.filter_type, settings.cutoff, settings.resonance, sample_rate)
+            }),
+            band_filters: std::array::from_fn(|_| {
+                let band = FilterSettings::default().band;
+                BandFilter::new(band.mode, band.frequency, band.q, sample_rate)
+            }),
+            formant_filters: std::array::from_fn(|_| {
+                FormantFilter::new(FilterSettings::default().formant.morph, sample_rate)
+            }),
+            sources: Vec::new(),
+            source_block: Vec::new(),
+            source_scratch: Vec::new(),
+            source_position: 0,
+            unison: UnisonVoice::default(),
+            pan: 0.0,
+            paraphonic: false,
+            glide: Glide::default(),
+        };
+        note.apply_oscillator_settings(settings);
+        note.modulation.note_on(note.envelope.velocity);
+        note
+    }
+
+    // Los ajustes que sobran se ignoran; la voz usa tantos osciladores como ajustes reciba
+    pub fn apply_oscillator_settings(&mut self, settings: &[OscillatorSettings]) {
+        self.oscillator_count = settings.len().clamp(1, MAX_OSCILLATORS);
+        for (osc, settings) in self.oscillators.iter_mut().zip(settings) {
+            osc.apply_settings(settings);
+        }
+    }
+
+    fn active_oscillators(&mut self) -> &mut [Oscillator] {
+        &mut self.oscillators[..self.oscillator_count]
+    }
+
+    // Frame estéreo (izquierdo, derecho) con cada oscilador en su posición de panorama
+    pub fn get_sample(&mut self) -> (f32, f32) {
+        let (mut frequency, sample_rate) = (self.frequency, self.sample_rate);
+        // Matriz de modulación y curva MSEG (escalada por su cantidad) se suman por destino
+        let mut offsets = if self.modulation.is_active() {
+            self.modulation.next_offsets()
+        } else {
+            ModOffsets::default()
+        };
+        if self.mseg.settings.enabled {
+            let mseg = self.mseg.settings.amount * self.mseg.next_sample(sample_rate);
+            offsets.add(self.mseg.settings.destination, mseg);
+        }
+        // El LFO avanza una vez por muestra y se reparte entre el vibrato y el filtro
+        let lfo_value = self.lfo.next_sample(sample_rate);
+        offsets.pitch += self.unison.detune + self.glide.next_offset();
+        offsets.pan += self.pan + self.unison.pan;
+        frequency *= self.lfo.pitch_ratio(lfo_value, offsets.vibrato) * offsets.pitch_ratio();
+        for osc in self.active_oscillators() {
+            osc.wavetable_mod = offsets.wavetable;
+        }
+        // En modo vectorial cada oscilador pesa según la posición X/Y; si no, todos por igual.
+        // Las esquinas sin oscilador activo quedan en silencio.
+        let mut weights = if self.vector.enabled {
+            self.vector.weights(self.envelope.current_level)
+        } else {
+            [1.0 / self.oscillator_count as f32; MAX_OSCILLATORS]
+        };
+        // Cada fuente externa cuenta como un oscilador más: los osciladores ceden su
+        // parte de la ganancia y todos suenan con el mismo peso
+        let source_gain = if self.sources.is_empty() {
+            0.0
+        } else {
+            let total = (self.oscillator_count + self.sources.len()) as f32;
+            for weight in weights.iter_mut() {
+                *weight *= self.oscillator_count as f32 / total;
+            }
+            1.0 / total
+        };
+        let mut mixed = (0.0, 0.0);
+        for (osc, weight) in self.active_oscillators().iter_mut().zip(weights) {
+            let sample = osc.get_sample(frequency, sample_rate) * weight;
+            let (left_gain, right_gain) = osc.pan_gains(offsets.pan);
+            mixed.0 += sample * left_gain;
+            mixed.1 += sample * right_gain;
+        }
+        // Las fuentes externas suenan en el centro
+        if !self.sources.is_empty() {
+            if self.source_position == self.source_block.len() {
+                self.render_sources(frequency);
+            }
+            let sample = self.source_block[self.source_position] * source_gain;
+            self.source_position += 1;
+            mixed.0 += sample;
+            mixed.1 += sample;
+        }
+        self.has_rendered = true;
+        if self.paraphonic {
+            let (left, right) = self.fx.process(mixed, self.envelope.current_level, self.envelope.velocity, self.sample_rate);
+            return (left * self.unison.gain, right * self.unison.gain);
+        }
+        let filter_level = self.filter_envelope.next_sample();
+        let smoothing = self.cutoff_smoother.is_settling() || self.resonance_smoother.is_settling();
+        let mod_cutoff = offsets.cutoff != 0.0;
+        if smoothing || mod_cutoff || self.filter_settings.is_modulated() {
+            let mut cutoff = self.filter_settings.modulated_cutoff(
+                self.cutoff_smoother.next_value(),
+                filter_level,
+                self.envelope.velocity,
+                lfo_value,
+            );
+            if mod_cutoff {
+                cutoff = (cutoff * 2.0f32.powf(offsets.cutoff)).clamp(MIN_CUTOFF, MAX_CUTOFF);
+            }
+            let resonance = self.resonance_smoother.next_value();
+            for filter in self.filters.iter_mut() {
+                filter.set_params(cutoff, resonance, sample_rate);
+            }
+        }
+        // Mientras el corte se desliza hacia un valor neutro el filtro sigue activo
+        if smoothing || mod_cutoff || !self.filter_settings.is_bypassed() {
+            mixed = (self.filters[0].process(mixed.0), self.filters[1].process(mixed.1));
+        }
+        if self.filter_settings.band.enabled {
+            mixed = (self.band_filters[0].process(mixed.0), self.band_filters[1].process(mixed.1));
+        }
+        if self.filter_settings.formant.enabled {
+            mixed = (self.formant_filters[0].process(mixed.0), self.formant_filters[1].process(mixed.1));
+        }
+        let (left, right) = self.fx.process(mixed, self.envelope.current_level, self.envelope.velocity, self.sample_rate);
+        (left * self.unison.gain, right * self.unison.gain)
+    }
+
+    pub fn set_voice_fx(&mut self, drive: f32, drive_velocity: f32, filter_env_depth: f32) {
+        self.fx.drive = drive;
+        self.fx.drive_velocity = drive_velocity;
+        self.fx.filter_env_depth = filter_env_depth;
+    }
+
+    pub fn add_source(&mut self, mut source: Box<dyn OscillatorSource>) {
+        source.set_sample_rate(self.sample_rate);
+        self.sources.push(source);
+        // Los buffers se reservan aquí y no en el audio; el bloque empieza agotado
+        // para que la primera muestra renderice uno nuevo con todas las fuentes
+        self.source_block.resize(SOURCE_BLOCK_SIZE, 0.0);
+        self.source_scratch.resize(SOURCE_BLOCK_SIZE, 0.0);
+        self.source_position = SOURCE_BLOCK_SIZE;
+    }
+
+    // Renderizar el siguiente bloque de todas las fuentes externas, a la frecuencia
+    // de la primera muestra del bloque
+    fn render_sources(&mut self, frequency: f32) {
+        self.source_block.fill(0.0);
+        for source in self.sources.iter_mut() {
+            source.render(frequency, &mut self.source_scratch);
+            for (sum, sample) in self.source_block.iter_mut().zip(&self.source_scratch) {
+                *sum += sample;
+            }
+        }
+        self.source_position = 0;
+    }
+
+    // Recalcular los coeficientes solo cuando cambian los ajustes. Con la voz ya
+    // sonando, el corte y la resonancia se deslizan hacia el nuevo valor en get_sample.
+    pub fn set_filter(&mut self, settings: FilterSettings) {
+        if settings != self.filter_settings {
+            self.filter_settings = settings;
+            if self.has_rendered {
+                self.cutoff_smoother.set_target(settings.cutoff);
+                self.resonance_smoother.set_target(settings.resonance);
+            } else {
+                self.cutoff_smoother.reset(settings.cutoff);
+                self.resonance_smoother.reset(settings.resonance);
+            }
+            let (cutoff, resonance) = (self.cutoff_smoother.value(), self.resonance_smoother.value());
+            for filter in self.filters.iter_mut() {
+                filter.filter_type = settings.filter_type;
+                filter.slope = settings.slope;
+                filter.set_params(cutoff, resonance, self.sample_rate);
+            }
+            for filter in self.band_filters.iter_mut() {
+                filter.set_settings(&settings.band);
+            }
+            for filter in self.formant_filters.iter_mut() {
+                filter.set_morph(settings.formant.morph);
+            }
+        }
+    }
+
+    pub fn set_vector(&mut self, vector: VectorSettings) {
+        self.vector = vector;
+    }
+
+    pub fn set_lfo(&mut self, settings: LfoSettings) {
+        self.lfo.settings = settings;
+    }
+
+    pub fn set_mseg(&mut self, settings: MsegSettings) {
+        self.mseg.settings = settings;
+    }
+
+    pub fn set_mod_matrix(&mut self, matrix: ModMatrix) {
+        self.modulation.set_matrix(matrix);
+    }
+
+    // Valores actuales de los CC y demás fuentes comunes; se aplican suavizados
+    pub fn set_mod_sources(&mut self, sources: &ModSourceValues) {
+        self.modulation.set_sources(sources);
+    }
+
+    // Posición de la voz dentro del unísono de su tecla
+    pub fn set_unison(&mut self, unison: UnisonVoice) {
+        self.unison = unison;
+    }
+
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = pan.clamp(-1.0, 1.0);
+    }
+
+    pub fn set_paraphonic(&mut self, paraphonic: bool) {
+        self.paraphonic = paraphonic;
+    }
+
+    pub fn set_glide(&mut self, settings: GlideSettings) {
+        self.glide.settings = settings;
+    }
+
+    // Deslizar hasta la nota de la voz desde otra frecuencia (la nota tocada antes)
+    pub fn glide_from(&mut self, frequency: f32) {
+        if frequency > 0.0 {
+            self.glide.start(12.0 * (frequency / self.frequency).log2(), self.sample_rate);
+        }
+    }
+
+    // Presión de la tecla (aftertouch polifónico, valor MIDI de 0 a 127)
+    pub fn set_pressure(&mut self, value: u8) {
+        self.modulation.set_pressure((value & 0x7F) as f32 / 127.0);
+    }
+
+    pub fn set_quality(&mut self, quality: Quality) {
+        for osc in self.oscillators.iter_mut() {
+            osc.set_quality(quality);
+        }
+    }
+
+    // Fases libres del motor que toca la voz; las asigna el mapa de voces al insertarla
+    pub fn set_free_run_phases(&mut self, phases: &FreeRunPhases) {
+        for osc in self.oscillators.iter_mut() {
+            osc.set_free_run_phases(phases);
+        }
+    }
+
+    pub fn set_wavetable(&mut self, position: f32, sweep: f32) {
+        for osc in self.oscillators.iter_mut() {
+            osc.wavetable_position = position;
+            osc.wavetable_sweep = sweep;
+        }
+    }
+
+    pub fn set_additive_table(&mut self, table: &Arc<Wavetable>) {
+        for osc in self.active_oscillators() {
+            osc.set_additive_table(table);
+        }
+    }
+
+    // La envolvente del filtro sigue la misma tecla que la de amplitud
+    pub fn set_filter_envelope(&mut self, settings: &AdsrSettings) {
+        self.filter_envelope.set_settings(settings);
+        self.filter_envelope.set_key(self.envelope.key());
+    }
+
+    // Soltar la tecla: las dos envolventes pasan a su fase de liberación y el MSEG
+    // sale de su bucle
+    pub fn note_off(&mut self) {
+        self.envelope.note_off();
+        self.filter_envelope.note_off();
+        self.mseg.note_off();
+    }
+
+    // La tecla sigue pulsada: la envolvente no ha llegado a la liberación
+    pub fn is_held(&self) -> bool {
+        !matches!(self.envelope.state, EnvelopeState::Release | EnvelopeState::Idle)
+    }
+
+    // Quitar la voz a la fuerza: se apaga en unos milisegundos y después se elimina
+    // como cualquier voz terminada
+    pub fn kill(&mut self) {
+        self.envelope.kill();
+        self.filter_envelope.note_off();
+        self.mseg.note_off();
+    }
+
+    // Nueva pulsación de la misma tecla mientras la voz sigue sonando (por ejemplo en
+    // release): se reutiliza la voz sin cortar la fase de los osciladores
+    pub fn retrigger(&mut self, velocity: f32) {
+        self.envelope.retrigger(velocity);
+        self.filter_envelope.note_on();
+        self.mseg.note_on();
+        self.lfo.retrigger();
+        self.modulation.note_on(velocity);
+    }
+
+    // Cambiar de nota sin crear una voz nueva (modo mono/legato)
+    pub fn legato_to(&mut self, frequency: f32, retrigger_amp: bool, retrigger_mod: bool) {
+        // El deslizamiento parte del tono que suena, aunque aún no haya llegado a su nota
+        let current = self.frequency * 2.0f32.powf(self.glide.offset() / 12.0);
+        self.update_frequency(frequency);
+        self.glide_from(current);
+        if retrigger_amp {
+            self.envelope.note_on();
+        }
+        if retrigger_mod {
+            self.filter_envelope.note_on();
+            self.mseg.note_on();
+            self.lfo.retrigger();
+            self.modulation.note_on(self.envelope.velocity);
+            for osc in self.oscillators.iter_mut() {
+                osc.retrigger_modulation();
+            }
+        }
+    }
+
+    // Cambio de frecuencia de muestreo con la voz sonando: envolventes, filtros,
+    // suavizados y osciladores siguen donde estaban con los coeficientes nuevos
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate == self.sample_rate {
+            return;
+        }
+        self.glide.set_sample_rate(self.sample_rate, sample_rate);
+        self.sample_rate = sample_rate;
+        self.envelope.set_sample_rate(sample_rate);
+        self.filter_envelope.set_sample_rate(sample_rate);
+        self.modulation.set_sample_rate(sample_rate);
+        self.cutoff_smoother.set_time(FILTER_SMOOTHING_TIME, sample_rate);
+        self.resonance_smoother.set_time(FILTER_SMOOTHING_TIME, sample_rate);
+        let (cutoff, resonance) = (self.cutoff_smoother.value(), self.resonance_smoother.value());
+        for filter in self.filters.iter_mut() {
+            filter.set_params(cutoff, resonance, sample_rate);
+        }
+        for filter in self.band_filters.iter_mut() {
+            filter.set_sample_rate(&self.filter_settings.band, sample_rate);
+        }
+        for filter in self.formant_filters.iter_mut() {
+            filter.set_sample_rate(self.filter_settings.formant.morph, sample_rate);
+        }
+        for osc in self.oscillators.iter_mut() {
+            osc.set_sample_rate(sample_rate);
+        }
+        for source in self.sources.iter_mut() {
+            source.set_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn update_frequency(&mut self, new_frequency: f32) {
+        self.frequency = new_frequency;
+    }
+}
@@ -0,0 +1,479 @@
+use std::f32::consts::{FRAC_1_SQRT_2, PI, SQRT_2};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use super::chip::{self, ChipChannel, DutyCycle, LfsrNoise, DEFAULT_CHIP_BIT_DEPTH};
+use super::biquad::{Biquad, BiquadCoefficients};
+use super::noise::DriftGenerator;
+use super::smoother::{ParamSmoother, GAIN_SMOOTHING_TIME};
+use super::wavetable::{
+    get_default_additive_table, get_morph_table, saw_mipmap, sine_from_table, square_mipmap, triangle_mipmap,
+    Wavetable,
+};
+
+const OVERSAMPLING: usize = 4;
+// Desviación máxima de afinación con el drift al máximo (en cents)
+const MAX_DRIFT_CENTS: f32 = 20.0;
+// Duración del fundido al cambiar de calidad en vivo (segundos)
+const QUALITY_CROSSFADE_TIME: f32 = 0.02;
+// Tiempo de la rampa de la desafinación al moverla con la nota sonando (segundos)
+const DETUNE_SMOOTHING_TIME: f32 = 0.01;
+// Ganancia de entrada del wavefolder con el plegado al máximo
+const MAX_FOLD_GAIN: f32 = 8.0;
+
+// Forma de onda de un oscilador
+#[derive(Clone, Copy, PartialEq)]
+pub enum WaveType {
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+    Wavetable,
+    Additive,
+    Chip,
+}
+
+impl WaveType {
+    // Todas las formas de onda, en el orden de su valor numérico
+    pub const ALL: [WaveType; 7] = [
+        WaveType::Sine,
+        WaveType::Square,
+        WaveType::Triangle,
+        WaveType::Sawtooth,
+        WaveType::Wavetable,
+        WaveType::Additive,
+        WaveType::Chip,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WaveType::Sine => "Senoidal",
+            WaveType::Square => "Cuadrada",
+            WaveType::Triangle => "Triangular",
+            WaveType::Sawtooth => "Sierra",
+            WaveType::Wavetable => "Tabla de ondas",
+            WaveType::Additive => "Aditiva",
+            WaveType::Chip => "Chiptune",
+        }
+    }
+}
+
+// Calidad del antialiasing para las formas de onda clásicas
+#[derive(Clone, Copy, PartialEq)]
+pub enum Quality {
+    // Solo PolyBLEP
+    Draft,
+    // PolyBLEP con sobremuestreo 2x
+    Normal,
+    // Tablas de ondas con mipmaps de banda limitada
+    High,
+}
+
+impl Quality {
+    pub const ALL: [Quality; 3] = [Quality::Draft, Quality::Normal, Quality::High];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Quality::Draft => "Borrador",
+            Quality::Normal => "Normal",
+            Quality::High => "Alta",
+        }
+    }
+}
+
+// Cómo arranca la fase de un oscilador al comenzar una nota
+#[derive(Clone, Copy, PartialEq)]
+pub enum PhaseMode {
+    // Siempre desde cero
+    Reset,
+    // Fase inicial aleatoria
+    Random,
+    // Continúa la fase del oscilador compartida entre notas
+    FreeRun,
+}
+
+impl PhaseMode {
+    pub const ALL: [PhaseMode; 3] = [PhaseMode::Reset, PhaseMode::Random, PhaseMode::FreeRun];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhaseMode::Reset => "Reinicio",
+            PhaseMode::Random => "Aleatoria",
+            PhaseMode::FreeRun => "Libre",
+        }
+    }
+}
+
+// Número máximo de osciladores por voz
+pub const MAX_OSCILLATORS: usize = 4;
+// Desafinación máxima de un oscilador, en semitonos hacia cada lado
+pub const MAX_OSCILLATOR_DETUNE: f32 = 12.0;
+
+// Última fase (bits de f32) de cada posición de oscilador en modo libre. Cada motor
+// tiene la suya y la comparte con sus voces, así dos instancias no se pisan la fase.
+#[derive(Clone, Default)]
+pub struct FreeRunPhases(Arc<[AtomicU32; MAX_OSCILLATORS]>);
+
+impl FreeRunPhases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn load(&self, slot: usize) -> f32 {
+        f32::from_bits(self.0[slot % MAX_OSCILLATORS].load(Ordering::Relaxed))
+    }
+
+    fn store(&self, slot: usize, phase: f32) {
+        self.0[slot % MAX_OSCILLATORS].store(phase.to_bits(), Ordering::Relaxed);
+    }
+}
+
+// Ajustes de un oscilador de la voz
+#[derive(Clone, Copy, PartialEq)]
+pub struct OscillatorSettings {
+    pub wave_type: WaveType,
+    pub volume: f32,
+    pub detune: f32,
+    pub octave: i32,
+    pub drift: f32,
+    pub phase_mode: PhaseMode,
+    // Panorama de -1.0 (izquierda) a 1.0 (derecha)
+    pub pan: f32,
+    // Cantidad de plegado del wavefolder (0 = señal limpia)
+    pub fold: f32,
+    // Ajustes de la onda chiptune
+    pub chip_channel: ChipChannel,
+    pub duty_cycle: DutyCycle,
+    pub bit_depth: u8,
+}
+
+impl Default for OscillatorSettings {
+    fn default() -> Self {
+        Self {
+            wave_type: WaveType::Sine,
+            volume: 1.0,
+            detune: 0.0,
+            octave: 0,
+            drift: 0.0,
+            phase_mode: PhaseMode::Reset,
+            pan: 0.0,
+            fold: 0.0,
+            chip_channel: ChipChannel::Pulse,
+            duty_cycle: DutyCycle::Half,
+            bit_depth: DEFAULT_CHIP_BIT_DEPTH,
+        }
+    }
+}
+
+pub struct Oscillator {
+    pub wave_type: WaveType,
+    pub phase: f32,
+    pub detune: f32,
+    pub octave: i32,
+    pub volume: f32,
+    pub drift: f32,
+    pub phase_mode: PhaseMode,
+    pub pan: f32,
+    pub fold: f32,
+    pub chip_channel: ChipChannel,
+    pub duty_cycle: DutyCycle,
+    pub bit_depth: u8,
+    chip_noise: LfsrNoise,
+    // Posición del oscilador dentro de la voz, para compartir la fase libre
+    pub slot: usize,
+    // Fases libres del motor al que pertenece la voz; sin ellas el oscilador arranca en 0
+    free_run: Option<FreeRunPhases>,
+    drift_generator: DriftGenerator,
+    started: bool,
+    pub wavetable_position: f32,
+    pub wavetable_sweep: f32,
+    wavetable_offset: f32,
+    // Desplazamiento de la posición por modulación externa (MSEG), muestra a muestra
+    pub wavetable_mod: f32,
+    morph_table: Arc<Wavetable>,
+    additive_table: Arc<Wavetable>,
+    quality: Quality,
+    previous_quality: Quality,
+    quality_crossfade: f32,
+    // Paso bajo Butterworth contra el aliasing de las frecuencias altas
+    filter: Biquad,
+    oversample_buffer: [f32; OVERSAMPLING],
+    prev_frequency: f32,
+    prev_cutoff: f32,
+    // Volumen y desafinación suavizados hacia `volume` y `detune`
+    volume_smoother: ParamSmoother,
+    detune_smoother: ParamSmoother,
+}
+
+impl Oscillator {
+    pub fn new(wave_type: WaveType, sample_rate: f32) -> Self {
+        Self {
+            wave_type,
+            phase: 0.0,
+            detune: 0.0,
+            octave: 0,
+            volume: 1.0,
+            drift: 0.0,
+            phase_mode: PhaseMode::Reset,
+            pan: 0.0,
+            fold: 0.0,
+            chip_channel: ChipChannel::Pulse,
+            duty_cycle: DutyCycle::Half,
+            bit_depth: DEFAULT_CHIP_BIT_DEPTH,
+            chip_noise: LfsrNoise::new(),
+            slot: 0,
+            free_run: None,
+            drift_generator: DriftGenerator::new(),
+            started: false,
+            wavetable_position: 0.0,
+            wavetable_sweep: 0.0,
+            wavetable_offset: 0.0,
+            wavetable_mod: 0.0,
+            morph_table: get_morph_table(),
+            additive_table: get_default_additive_table(),
+            quality: Quality::Normal,
+            previous_quality: Quality::Normal,
+            quality_crossfade: 0.0,
+            filter: Biquad::new(BiquadCoefficients::low_pass(20000.0, FRAC_1_SQRT_2, sample_rate * OVERSAMPLING as f32)),
+            oversample_buffer: [0.0; OVERSAMPLING],
+            prev_frequency: 0.0,
+            prev_cutoff: 20000.0,
+            volume_smoother: ParamSmoother::linear(1.0, GAIN_SMOOTHING_TIME, sample_rate),
+            detune_smoother: ParamSmoother::linear(0.0, DETUNE_SMOOTHING_TIME, sample_rate),
+        }
+    }
+
+    pub fn set_free_run_phases(&mut self, phases: &FreeRunPhases) {
+        self.free_run = Some(phases.clone());
+    }
+
+    pub fn apply_settings(&mut self, settings: &OscillatorSettings) {
+        self.wave_type = settings.wave_type;
+        self.volume = settings.volume;
+        self.detune = settings.detune;
+        self.octave = settings.octave;
+        self.drift = settings.drift;
+        self.phase_mode = settings.phase_mode;
+        self.pan = settings.pan;
+        self.fold = settings.fold;
+        self.chip_channel = settings.chip_channel;
+        self.duty_cycle = settings.duty_cycle;
+        self.bit_depth = settings.bit_depth;
+    }
+
+    // Ganancias (izquierda, derecha) de potencia constante, normalizadas para
+    // que en el centro cada canal reciba la señal a volumen completo. `offset` es la
+    // modulación de panorama de la voz, sumada al del oscilador.
+    #[inline(always)]
+    pub fn pan_gains(&self, offset: f32) -> (f32, f32) {
+        let angle = ((self.pan + offset).clamp(-1.0, 1.0) + 1.0) * PI / 4.0;
+        (angle.cos() * SQRT_2, angle.sin() * SQRT_2)
+    }
+
+    pub fn set_additive_table(&mut self, table: &Arc<Wavetable>) {
+        if !Arc::ptr_eq(&self.additive_table, table) {
+            self.additive_table = table.clone();
+        }
+    }
+
+    // Los suavizados pasan a la nueva frecuencia y el filtro antialiasing se recalcula
+    // en la siguiente muestra
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.volume_smoother.set_time(GAIN_SMOOTHING_TIME, sample_rate);
+        self.detune_smoother.set_time(DETUNE_SMOOTHING_TIME, sample_rate);
+        self.prev_cutoff = 0.0;
+    }
+
+    // Cambiar la calidad con un fundido corto entre el modo anterior y el nuevo
+    pub fn set_quality(&mut self, quality: Quality) {
+        if quality != self.quality {
+            self.previous_quality = self.quality;
+            self.quality = quality;
+            // Una voz que aún no ha sonado no necesita fundido
+            self.quality_crossfade = if self.started { 1.0 } else { 0.0 };
+        }
+    }
+
+    // Reiniciar las modulaciones que evolucionan en el tiempo (barrido de la tabla)
+    pub fn retrigger_modulation(&mut self) {
+        self.wavetable_offset = 0.0;
+    }
+
+    #[inline(always)]
+    fn poly_blep(&self, t: f32, dt: f32) -> f32 {
+        if t < dt {
+            let t = t / dt;
+            return 2.0 * t - t * t - 1.0;
+        } else if t > 1.0 - dt {
+            let t = (t - 1.0) / dt;
+            return t * t + 2.0 * t + 1.0;
+        }
+        0.0
+    }
+
+    #[inline(always)]
+    fn get_bandlimited_square(&self, phase_norm: f32, phase_inc: f32) -> f32 {
+        let mut square = if phase_norm < 0.5 { 1.0 } else { -1.0 };
+        square += self.poly_blep(phase_norm, phase_inc);
+        square -= self.poly_blep((phase_norm + 0.5) % 1.0, phase_inc);
+        square
+    }
+
+    #[inline(always)]
+    fn get_bandlimited_saw(&self, phase_norm: f32, phase_inc: f32) -> f32 {
+        let mut saw = 2.0 * phase_norm - 1.0;
+        saw -= self.poly_blep(phase_norm, phase_inc);
+        saw
+    }
+
+    #[inline(always)]
+    fn get_bandlimited_triangle(&self, phase_norm: f32) -> f32 {
+        let phase_quad = phase_norm * 4.0;
+        if phase_quad < 1.0 {
+            phase_quad
+        } else if phase_quad < 2.0 {
+            2.0 - phase_quad
+        } else if phase_quad < 3.0 {
+            phase_quad - 4.0
+        } else {
+            -4.0 + phase_quad
+        }
+    }
+
+    #[inline(always)]
+    fn render_polyblep(&self, phase_norm: f32, phase_inc: f32) -> f32 {
+        match self.wave_type {
+            WaveType::Square => self.get_bandlimited_square(phase_norm, phase_inc),
+            WaveType::Triangle => self.get_bandlimited_triangle(phase_norm),
+            _ => self.get_bandlimited_saw(phase_norm, phase_inc),
+        }
+    }
+
+    // Generar la forma de onda para una fase normalizada con la calidad indicada
+    #[inline(always)]
+    fn render(&self, quality: Quality, phase_norm: f32, phase_inc: f32) -> f32 {
+        match self.wave_type {
+            WaveType::Sine => sine_from_table(phase_norm),
+            WaveType::Square | WaveType::Triangle | WaveType::Sawtooth => match quality {
+                Quality::Draft => self.render_polyblep(phase_norm, phase_inc),
+                Quality::Normal => {
+                    // Dos submuestras a media fase, promediadas
+                    let half_inc = phase_inc * 0.5;
+                    let second_phase = (phase_norm + half_inc).fract();
+                    0.5 * (self.render_polyblep(phase_norm, half_inc) + self.render_polyblep(second_phase, half_inc))
+                }
+                Quality::High => {
+                    let mipmap = match self.wave_type {
+                        WaveType::Square => square_mipmap(),
+                        WaveType::Triangle => triangle_mipmap(),
+                        _ => saw_mipmap(),
+                    };
+                    mipmap.sample(phase_norm, phase_inc)
+                }
+            },
+            WaveType::Wavetable => {
+                let position = (self.wavetable_position + self.wavetable_offset + self.wavetable_mod).clamp(0.0, 1.0);
+                self.morph_table.sample(phase_norm, position)
+            }
+            WaveType::Additive => self.additive_table.sample(phase_norm, 0.0),
+            WaveType::Chip => {
+                let sample = match self.chip_channel {
+                    ChipChannel::Pulse => chip::pulse(phase_norm, self.duty_cycle),
+                    ChipChannel::Triangle => chip::triangle(phase_norm),
+                    ChipChannel::Noise => self.chip_noise.output(),
+                };
+                chip::quantize(sample, self.bit_depth)
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_sample(&mut self, base_frequency: f32, sample_rate: f32) -> f32 {
+        if !self.started {
+            self.started = true;
+            // La primera muestra ya suena con los ajustes de la nota, sin rampa
+            self.volume_smoother.set_time(GAIN_SMOOTHING_TIME, sample_rate);
+            self.volume_smoother.reset(self.volume);
+            self.detune_smoother.set_time(DETUNE_SMOOTHING_TIME, sample_rate);
+            self.detune_smoother.reset(self.detune);
+            self.phase = match self.phase_mode {
+                // Variación aleatoria proporcional al drift, como en un oscilador analógico
+                PhaseMode::Reset if self.drift > 0.0 => {
+                    (self.drift_generator.random().abs() * self.drift * 2.0 * PI) % (2.0 * PI)
+                }
+                PhaseMode::Reset => 0.0,
+                PhaseMode::Random => self.drift_generator.random().abs() * 2.0 * PI,
+                PhaseMode::FreeRun => self.free_run.as_ref().map_or(0.0, |phases| phases.load(self.slot)),
+            };
+        }
+
+        let drift_cents = if self.drift > 0.0 {
+            self.drift_generator.next(sample_rate) * self.drift * MAX_DRIFT_CENTS
+        } else {
+            0.0
+        };
+        self.volume_smoother.set_target(self.volume);
+        self.detune_smoother.set_target(self.detune);
+        let volume = self.volume_smoother.next_value();
+        let detune = self.detune_smoother.next_value();
+        let frequency = base_frequency * (2.0f32.powf(self.octave as f32 + detune / 12.0 + drift_cents / 1200.0));
+        let phase_inc = frequency / sample_rate;
+        
+        let cutoff = if frequency > sample_rate * 0.125 {
+            frequency * 1.5
+        } else {
+            frequency * 2.5
+        };
+
+        if (cutoff - self.prev_cutoff).abs() > 1.0 {
+            self.filter.set_coefficients(BiquadCoefficients::low_pass(cutoff.min(sample_rate * 0.45), FRAC_1_SQRT_2, sample_rate));
+            self.prev_cutoff = cutoff;
+        }
+        
+        let phase_norm = self.phase / (2.0 * PI);
+
+        if self.wave_type == WaveType::Chip && self.chip_channel == ChipChannel::Noise {
+            self.chip_noise.advance(phase_inc);
+        }
+        
+        let mut raw_sample = self.render(self.quality, phase_norm, phase_inc);
+        if self.quality_crossfade > 0.0 {
+            let previous = self.render(self.previous_quality, phase_norm, phase_inc);
+            raw_sample += (previous - raw_sample) * self.quality_crossfade;
+            self.quality_crossfade = (self.quality_crossfade - 1.0 / (QUALITY_CROSSFADE_TIME * sample_rate)).max(0.0);
+        }
+
+        if self.fold > 0.0 {
+            raw_sample = wavefold(raw_sample, self.fold);
+        }
+
+        // Barrido de la posición en la tabla (unidades de posición por segundo)
+        if self.wavetable_sweep != 0.0 {
+            self.wavetable_offset = (self.wavetable_offset + self.wavetable_sweep / sample_rate).clamp(-1.0, 1.0);
+        }
+
+        self.phase += 2.0 * PI * phase_inc;
+        if self.phase >= 2.0 * PI {
+            self.phase -= 2.0 * PI;
+        }
+        if self.phase_mode == PhaseMode::FreeRun {
+            if let Some(phases) = &self.free_run {
+                phases.store(self.slot, self.phase);
+            }
+        }
+
+        if frequency > sample_rate * 0.25 {
+            let smoothing = 1.0 - ((frequency - sample_rate * 0.25) / (sample_rate * 0.25)).min(1.0);
+            self.filter.process(raw_sample) * smoothing * volume
+        } else {
+            raw_sample * volume
+        }
+    }
+}
+
+// Wavefolder triangular al estilo West Coast: amplifica la señal y refleja lo que
+// sobrepasa ±1 de vuelta hacia dentro, añadiendo armónicos a medida que sube el plegado
+#[inline(always)]
+fn wavefold(input: f32, amount: f32) -> f32 {
+    let driven = input * (1.0 + amount.clamp(0.0, 1.0) * (MAX_FOLD_GAIN - 1.0));
+    1.0 - ((driven + 1.0).rem_euclid(4.0) - 2.0).abs()
+}
@@ -0,0 +1,190 @@
+use crate::structs::envelope::{AdsrSettings, Envelope, MIN_STAGE_TIME};
+use super::filters::{FilterSettings, StateVariableFilter};
+use super::voice_map::{VoiceId, VoiceMap};
+
+// Ataque de la puerta de cada voz (segundos), lo justo para que no haya chasquido
+const GATE_ATTACK_TIME: f32 = 0.002;
+
+// Cuándo vuelve a empezar la envolvente común
+#[derive(Clone, Copy, PartialEq)]
+pub enum ParaphonicTrigger {
+    // Solo con la primera nota tras soltar todas; las demás entran al nivel actual
+    FirstNote,
+    // Con cada nota nueva, también las que se añaden a un acorde
+    EveryNote,
+}
+
+impl ParaphonicTrigger {
+    pub const ALL: [ParaphonicTrigger; 2] = [ParaphonicTrigger::FirstNote, ParaphonicTrigger::EveryNote];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParaphonicTrigger::FirstNote => "Primera nota",
+            ParaphonicTrigger::EveryNote => "Cada nota",
+        }
+    }
+}
+
+// Modo parafónico, como en las máquinas de cuerdas: todas las notas comparten una
+// envolvente de amplitud y un filtro aplicados a la mezcla, en lugar de uno por voz
+#[derive(Clone, Copy, PartialEq)]
+pub struct ParaphonicSettings {
+    pub enabled: bool,
+    pub trigger: ParaphonicTrigger,
+}
+
+impl Default for ParaphonicSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            trigger: ParaphonicTrigger::FirstNote,
+        }
+    }
+}
+
+impl ParaphonicSettings {
+    // Envolvente de una voz nueva. En modo parafónico cada voz es una puerta que solo
+    // conserva la liberación, con el mismo tiempo que la envolvente común.
+    pub fn voice_envelope(&self, envelope: &AdsrSettings) -> AdsrSettings {
+        if !self.enabled {
+            return *envelope;
+        }
+        AdsrSettings {
+            attack: GATE_ATTACK_TIME,
+            decay: MIN_STAGE_TIME,
+            sustain: 1.0,
+            release: envelope.release,
+            key_tracking: envelope.key_tracking,
+        }
+    }
+}
+
+// Envolvente y filtro comunes del modo parafónico, aplicados a la mezcla de las voces.
+// El ataque, el decaimiento y el sostenido los marca la envolvente común; la liberación
+// la hace cada voz con su puerta, para que una tecla soltada en mitad de un acorde se
+// apague sin cortar a las demás.
+pub struct ParaphonicBus {
+    settings: ParaphonicSettings,
+    sample_rate: f32,
+    envelope_settings: AdsrSettings,
+    filter_envelope_settings: AdsrSettings,
+    envelope: Envelope,
+    filter_envelope: Envelope,
+    filter_settings: FilterSettings,
+    filters: [StateVariableFilter; 2],
+    // Voz más reciente ya vista, para reconocer las notas nuevas
+    last_voice: Option<VoiceId>,
+    // Había alguna tecla pulsada en la última actualización
+    held: bool,
+    // Velocidad de la última nota que disparó las envolventes, para el corte del filtro
+    velocity: f32,
+}
+
+impl ParaphonicBus {
+    pub fn new(sample_rate: f32) -> Self {
+        let filter_settings = FilterSettings::default();
+        Self {
+            settings: ParaphonicSettings::default(),
+            sample_rate,
+            envelope_settings: AdsrSettings::default(),
+            filter_envelope_settings: AdsrSettings::default(),
+            envelope: Envelope::new(sample_rate),
+            filter_envelope: Envelope::new(sample_rate),
+            filter_settings,
+            filters: std::array::from_fn(|_| {
+                StateVariableFilter::new(filter_settings.filter_type, filter_settings.cutoff, filter_settings.resonance, sample_rate)
+            }),
+            last_voice: None,
+            held: false,
+            velocity: 1.0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    pub fn set_settings(
+        &mut self,
+        settings: ParaphonicSettings,
+        envelope: &AdsrSettings,
+        filter: FilterSettings,
+        filter_envelope: &AdsrSettings,
+        sample_rate: f32,
+    ) {
+        self.settings = settings;
+        let rate_changed = sample_rate != self.sample_rate;
+        if rate_changed {
+            self.sample_rate = sample_rate;
+            self.envelope.set_sample_rate(sample_rate);
+            self.filter_envelope.set_sample_rate(sample_rate);
+        }
+        if rate_changed || *envelope != self.envelope_settings {
+            self.envelope_settings = *envelope;
+            self.envelope.set_settings(envelope);
+        }
+        if rate_changed || *filter_envelope != self.filter_envelope_settings {
+            self.filter_envelope_settings = *filter_envelope;
+            self.filter_envelope.set_settings(filter_envelope);
+        }
+        if rate_changed || filter != self.filter_settings {
+            self.filter_settings = filter;
+            for stage in self.filters.iter_mut() {
+                stage.filter_type = filter.filter_type;
+                stage.slope = filter.slope;
+                stage.set_params(filter.cutoff, filter.resonance, sample_rate);
+            }
+        }
+    }
+
+    // Seguir las voces una vez por bloque: las notas nuevas disparan la envolvente común
+    // según la regla elegida, y sin voces sonando todo vuelve a reposo
+    pub fn update(&mut self, voices: &VoiceMap) {
+        if voices.is_empty() {
+            self.reset();
+            return;
+        }
+        let newest = voices.voices().iter()
+            .filter(|voice| voice.note.is_held())
+            .max_by_key(|voice| voice.id);
+        if let Some(voice) = newest {
+            let is_new = self.last_voice.map_or(true, |last| voice.id > last);
+            if is_new {
+                // La velocidad ya la lleva la puerta de cada voz
+                if !self.held || self.settings.trigger == ParaphonicTrigger::EveryNote {
+                    self.envelope.retrigger(1.0);
+                    self.filter_envelope.note_on();
+                    self.velocity = voice.note.envelope.velocity;
+                }
+                self.last_voice = Some(voice.id);
+            }
+        }
+        self.held = newest.is_some();
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope = Envelope::new(self.sample_rate);
+        self.envelope.set_settings(&self.envelope_settings);
+        self.filter_envelope = Envelope::new(self.sample_rate);
+        self.filter_envelope.set_settings(&self.filter_envelope_settings);
+        self.held = false;
+    }
+
+    #[inline]
+    pub fn process(&mut self, frame: (f32, f32)) -> (f32, f32) {
+        let amp = self.envelope.next_sample();
+        let filter_level = self.filter_envelope.next_sample();
+        let settings = self.filter_settings;
+        if settings.is_modulated() {
+            let cutoff = settings.modulated_cutoff(settings.cutoff, filter_level, self.velocity, 0.0);
+            for stage in self.filters.iter_mut() {
+                stage.set_params(cutoff, settings.resonance, self.sample_rate);
+            }
+        }
+        let mut frame = (frame.0 * amp, frame.1 * amp);
+        if !settings.is_bypassed() {
+            frame = (self.filters[0].process(frame.0), self.filters[1].process(frame.1));
+        }
+        frame
+    }
+}
@@ -0,0 +1,108 @@
+use std::f32::consts::FRAC_PI_2;
+
+// Ganancia de entrada máxima de la saturación de salida (dB)
+pub const MAX_SATURATION_DRIVE: f32 = 24.0;
+
+// Algoritmo de la saturación final de la mezcla
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SaturationMode {
+    Tanh,
+    HardClip,
+    CubicSoft,
+    SineFold,
+    // Sin saturación ni ganancia: la salida puede pasar de ±1.0
+    None,
+}
+
+impl SaturationMode {
+    pub const ALL: [SaturationMode; 5] = [
+        SaturationMode::Tanh,
+        SaturationMode::HardClip,
+        SaturationMode::CubicSoft,
+        SaturationMode::SineFold,
+        SaturationMode::None,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SaturationMode::Tanh => "Tanh",
+            SaturationMode::HardClip => "Recorte duro",
+            SaturationMode::CubicSoft => "Cúbica suave",
+            SaturationMode::SineFold => "Plegado senoidal",
+            SaturationMode::None => "Ninguna",
+        }
+    }
+
+    // Nombre corto para la línea de comandos
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tanh" => Some(SaturationMode::Tanh),
+            "hard" => Some(SaturationMode::HardClip),
+            "cubic" => Some(SaturationMode::CubicSoft),
+            "sine" => Some(SaturationMode::SineFold),
+            "none" => Some(SaturationMode::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SaturationSettings {
+    pub mode: SaturationMode,
+    // Ganancia antes de la curva (dB)
+    pub drive: f32,
+}
+
+impl Default for SaturationSettings {
+    fn default() -> Self {
+        Self {
+            mode: SaturationMode::Tanh,
+            drive: 0.0,
+        }
+    }
+}
+
+// Etapa de saturación lista para el hilo de audio, con la ganancia ya en lineal
+#[derive(Clone, Copy)]
+pub struct Saturation {
+    mode: SaturationMode,
+    gain: f32,
+}
+
+impl Default for Saturation {
+    fn default() -> Self {
+        Self::new(SaturationSettings::default())
+    }
+}
+
+impl Saturation {
+    pub fn new(settings: SaturationSettings) -> Self {
+        let gain = match settings.mode {
+            SaturationMode::None => 1.0,
+            _ => 10f32.powf(settings.drive.clamp(0.0, MAX_SATURATION_DRIVE) / 20.0),
+        };
+        Self { mode: settings.mode, gain }
+    }
+
+    #[inline]
+    pub fn process(&self, x: f32) -> f32 {
+        let x = x * self.gain;
+        match self.mode {
+            SaturationMode::Tanh => x.tanh(),
+            SaturationMode::HardClip => x.clamp(-1.0, 1.0),
+            // Polinomio de tercer grado que llega a ±1 con pendiente nula en |x| = 1.5
+            SaturationMode::CubicSoft => {
+                let x = (x / 1.5).clamp(-1.0, 1.0);
+                1.5 * (x - x * x * x / 3.0)
+            }
+            // Llega a ±1 en |x| = 1 y a partir de ahí se pliega hacia dentro
+            SaturationMode::SineFold => (x * FRAC_PI_2).sin(),
+            SaturationMode::None => x,
+        }
+    }
+
+    #[inline]
+    pub fn process_frame(&self, frame: (f32, f32)) -> (f32, f32) {
+        (self.process(frame.0), self.process(frame.1))
+    }
+}
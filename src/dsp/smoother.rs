@@ -0,0 +1,112 @@
+// Tiempo de suavizado de volúmenes, mezclas y demás ganancias (segundos)
+pub const GAIN_SMOOTHING_TIME: f32 = 0.02;
+
+// Cómo llega el valor suavizado a su objetivo
+#[derive(Clone, Copy, PartialEq)]
+pub enum SmoothingMode {
+    // Rampa recta que llega justo en el tiempo ajustado; para ganancias y mezclas
+    Linear,
+    // Un polo: deprisa al principio y cada vez más despacio; para frecuencias y tiempos
+    Exponential,
+}
+
+// Suavizado de parámetros: el valor va hacia el objetivo en rampa en lugar de
+// saltar, para que los cambios desde la GUI o la automatización no produzcan
+// chasquidos ("zipper noise")
+pub struct ParamSmoother {
+    mode: SmoothingMode,
+    current: f32,
+    target: f32,
+    coefficient: f32,
+    // Duración de la rampa lineal en muestras, con su paso y las muestras que le quedan
+    ramp_samples: u32,
+    step: f32,
+    remaining: u32,
+}
+
+// Por debajo de esta distancia al objetivo el valor se fija y deja de suavizarse
+const SETTLE_THRESHOLD: f32 = 1e-4;
+
+impl ParamSmoother {
+    // Suavizado exponencial; `time` en segundos: tiempo para recorrer ~63% del salto
+    pub fn new(value: f32, time: f32, sample_rate: f32) -> Self {
+        Self::with_mode(SmoothingMode::Exponential, value, time, sample_rate)
+    }
+
+    // Rampa lineal que recorre todo el salto en `time` segundos
+    pub fn linear(value: f32, time: f32, sample_rate: f32) -> Self {
+        Self::with_mode(SmoothingMode::Linear, value, time, sample_rate)
+    }
+
+    pub fn with_mode(mode: SmoothingMode, value: f32, time: f32, sample_rate: f32) -> Self {
+        let mut smoother = Self {
+            mode,
+            current: value,
+            target: value,
+            coefficient: 0.0,
+            ramp_samples: 1,
+            step: 0.0,
+            remaining: 0,
+        };
+        smoother.set_time(time, sample_rate);
+        smoother
+    }
+
+    pub fn set_time(&mut self, time: f32, sample_rate: f32) {
+        let samples = (time * sample_rate).max(1.0);
+        self.coefficient = (-1.0 / samples).exp();
+        self.ramp_samples = samples as u32;
+    }
+
+    // Un objetivo nuevo empieza una rampa desde el valor actual; repetir el mismo
+    // objetivo en cada bloque no la alarga
+    pub fn set_target(&mut self, target: f32) {
+        if target == self.target {
+            return;
+        }
+        self.target = target;
+        if self.mode == SmoothingMode::Linear {
+            self.remaining = self.ramp_samples;
+            self.step = (target - self.current) / self.ramp_samples as f32;
+        }
+    }
+
+    // Saltar al valor sin suavizado, p. ej. al crear una voz
+    pub fn reset(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.remaining = 0;
+    }
+
+    pub fn value(&self) -> f32 {
+        self.current
+    }
+
+    pub fn is_settling(&self) -> bool {
+        self.current != self.target
+    }
+
+    #[inline]
+    pub fn next_value(&mut self) -> f32 {
+        if self.current != self.target {
+            match self.mode {
+                SmoothingMode::Linear => {
+                    if self.remaining > 1 {
+                        self.current += self.step;
+                        self.remaining -= 1;
+                    } else {
+                        self.current = self.target;
+                        self.remaining = 0;
+                    }
+                }
+                SmoothingMode::Exponential => {
+                    self.current = self.target + (self.current - self.target) * self.coefficient;
+                    if (self.current - self.target).abs() <= SETTLE_THRESHOLD * self.target.abs().max(1.0) {
+                        self.current = self.target;
+                    }
+                }
+            }
+        }
+        self.current
+    }
+}
@@ -0,0 +1,10 @@
+// Punto de extensión para algoritmos de oscilador externos al motor. Quien use la
+// librería puede implementar este trait y añadir la fuente a una voz con
+// `Note::add_source`, sin tocar el código del sintetizador.
+pub trait OscillatorSource: Send {
+    // Rellenar `out` con las siguientes muestras a la frecuencia indicada (Hz). La voz
+    // lo llama una vez por bloque, con la frecuencia del principio del bloque.
+    fn render(&mut self, frequency: f32, out: &mut [f32]);
+
+    fn set_sample_rate(&mut self, sample_rate: f32);
+}
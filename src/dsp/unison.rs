@@ -0,0 +1,69 @@
+// Unísono al repartir las notas: cada pulsación crea varias voces completas, cada una
+// algo desafinada y abierta en el panorama, con la ganancia compensada para que el
+// volumen no suba con el número de voces.
+
+// Voces por tecla como máximo
+pub const MAX_UNISON_VOICES: usize = 8;
+// Desafinación máxima entre las dos voces de los extremos (semitonos)
+pub const MAX_UNISON_DETUNE: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct UnisonSettings {
+    // De 1 (sin unísono) a MAX_UNISON_VOICES
+    pub voices: usize,
+    // Distancia en semitonos entre la voz más grave y la más aguda
+    pub detune: f32,
+    // Apertura estéreo de 0.0 (todas al centro) a 1.0 (extremos a los lados)
+    pub spread: f32,
+}
+
+impl Default for UnisonSettings {
+    fn default() -> Self {
+        Self {
+            voices: 1,
+            detune: 0.2,
+            spread: 0.5,
+        }
+    }
+}
+
+// Desplazamientos de una de las voces del unísono
+#[derive(Clone, Copy, PartialEq)]
+pub struct UnisonVoice {
+    // Semitonos respecto a la nota
+    pub detune: f32,
+    // De -1.0 (izquierda) a 1.0 (derecha), sumado al panorama de los osciladores
+    pub pan: f32,
+    pub gain: f32,
+}
+
+impl Default for UnisonVoice {
+    fn default() -> Self {
+        Self { detune: 0.0, pan: 0.0, gain: 1.0 }
+    }
+}
+
+impl UnisonSettings {
+    pub fn voice_count(&self) -> usize {
+        self.voices.clamp(1, MAX_UNISON_VOICES)
+    }
+
+    // Voces repartidas por igual entre los extremos; la ganancia 1/√N mantiene la
+    // potencia de la mezcla, ya que las voces desafinadas no suman en fase
+    pub fn voice(&self, index: usize) -> UnisonVoice {
+        let count = self.voice_count();
+        if count == 1 {
+            return UnisonVoice::default();
+        }
+        let position = index.min(count - 1) as f32 / (count - 1) as f32 * 2.0 - 1.0;
+        UnisonVoice {
+            detune: position * self.detune.clamp(0.0, MAX_UNISON_DETUNE) / 2.0,
+            pan: position * self.spread.clamp(0.0, 1.0),
+            gain: 1.0 / (count as f32).sqrt(),
+        }
+    }
+
+    pub fn unison_voices(&self) -> impl Iterator<Item = UnisonVoice> + '_ {
+        (0..self.voice_count()).map(move |index| self.voice(index))
+    }
+}
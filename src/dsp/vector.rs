@@ -0,0 +1,40 @@
+// Síntesis vectorial al estilo Prophet-VS: los cuatro osciladores de la voz se
+// colocan en las esquinas de un plano y la posición X/Y decide cuánto suena cada uno
+#[derive(Clone, Copy, PartialEq)]
+pub struct VectorSettings {
+    pub enabled: bool,
+    // Posición en el plano, de 0.0 a 1.0 en cada eje
+    pub x: f32,
+    pub y: f32,
+    // Desplazamiento bipolar de la posición según la envolvente de la voz
+    pub env_x: f32,
+    pub env_y: f32,
+}
+
+impl Default for VectorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            x: 0.5,
+            y: 0.5,
+            env_x: 0.0,
+            env_y: 0.0,
+        }
+    }
+}
+
+impl VectorSettings {
+    // Ganancias de los osciladores A (abajo izquierda), B (abajo derecha),
+    // C (arriba izquierda) y D (arriba derecha); siempre suman 1.0
+    #[inline]
+    pub fn weights(&self, envelope_level: f32) -> [f32; 4] {
+        let x = (self.x + self.env_x * envelope_level).clamp(0.0, 1.0);
+        let y = (self.y + self.env_y * envelope_level).clamp(0.0, 1.0);
+        [
+            (1.0 - x) * (1.0 - y),
+            x * (1.0 - y),
+            (1.0 - x) * y,
+            x * y,
+        ]
+    }
+}
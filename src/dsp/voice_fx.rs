@@ -0,0 +1,55 @@
+use super::filters::{FilterType, StateVariableFilter};
+
+// Frecuencia de corte del filtro por voz con la envolvente en cero (Hz)
+const FILTER_BASE_CUTOFF: f32 = 150.0;
+// Octavas que abre el filtro con profundidad y envolvente al máximo
+const FILTER_ENV_OCTAVES: f32 = 7.0;
+// Ganancia de entrada del drive al máximo
+const MAX_DRIVE_GAIN: f32 = 10.0;
+
+// Inserto de efectos de cada voz, procesado antes de la mezcla
+pub struct VoiceFx {
+    pub drive: f32,
+    // Cuánto aumenta el drive con la velocidad de la nota
+    pub drive_velocity: f32,
+    // Cuánto abre la envolvente de la voz el filtro paso bajo
+    pub filter_env_depth: f32,
+    // Un filtro por canal del frame estéreo
+    filters: [StateVariableFilter; 2],
+}
+
+impl VoiceFx {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            drive: 0.0,
+            drive_velocity: 0.0,
+            filter_env_depth: 0.0,
+            filters: std::array::from_fn(|_| StateVariableFilter::new(FilterType::LowPass, 20000.0, 0.0, sample_rate)),
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: (f32, f32), envelope_level: f32, velocity: f32, sample_rate: f32) -> (f32, f32) {
+        let mut output = [input.0, input.1];
+
+        let drive = (self.drive + self.drive_velocity * velocity).min(1.0);
+        if drive > 0.0 {
+            // Saturación tanh normalizada para mantener el pico en 1.0
+            let gain = 1.0 + drive * (MAX_DRIVE_GAIN - 1.0);
+            for sample in output.iter_mut() {
+                *sample = (*sample * gain).tanh() / gain.tanh();
+            }
+        }
+
+        if self.filter_env_depth > 0.0 {
+            let octaves = self.filter_env_depth * envelope_level * FILTER_ENV_OCTAVES;
+            let cutoff = (FILTER_BASE_CUTOFF * 2.0f32.powf(octaves)).min(sample_rate * 0.45);
+            for (filter, sample) in self.filters.iter_mut().zip(output.iter_mut()) {
+                filter.set_params(cutoff, 0.0, sample_rate);
+                *sample = filter.process(*sample);
+            }
+        }
+
+        (output[0], output[1])
+    }
+}
@@ -0,0 +1,157 @@
+use super::oscillator::FreeRunPhases;
+use super::Note;
+
+// Identificador único de una voz mientras suena
+pub type VoiceId = u64;
+
+// Voces que caben a la vez (teclas, colas en liberación y copias del unísono); el mapa
+// las reserva al crearse para no reservar memoria en el hilo de audio
+pub const MAX_VOICES: usize = 256;
+
+// Voz activa con la tecla que la disparó
+pub struct Voice {
+    pub id: VoiceId,
+    pub key: u8,
+    // Salida de voces alternas (0 = pares, 1 = impares); las copias del unísono de una
+    // pulsación comparten la misma
+    pub bus: usize,
+    pub note: Note,
+}
+
+// Voces activas. Una misma tecla puede tener varias: al volver a pulsarla mientras la
+// anterior está en liberación se añade una voz nueva y la cola de la otra sigue sonando.
+// Las búsquedas por tecla solo encuentran la voz que aún la mantiene pulsada.
+pub struct VoiceMap {
+    // Ordenadas de la más antigua a la más nueva
+    voices: Vec<Voice>,
+    next_id: VoiceId,
+    // Fase de los osciladores en modo libre, común a todas las voces de este mapa
+    free_run: FreeRunPhases,
+    // Voces robadas al llenarse el mapa, pendientes de contar en `remove_finished`
+    stolen: usize,
+}
+
+impl Default for VoiceMap {
+    fn default() -> Self {
+        Self {
+            voices: Vec::with_capacity(MAX_VOICES),
+            next_id: 0,
+            free_run: FreeRunPhases::default(),
+            stolen: 0,
+        }
+    }
+}
+
+impl VoiceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Añadir una voz sin tocar las que ya suenan en la misma tecla. Con el mapa lleno
+    // se roba la voz en liberación más antigua, o la más antigua si todas están pulsadas.
+    pub fn insert(&mut self, key: u8, note: Note) -> VoiceId {
+        self.insert_on_bus(key, 0, note)
+    }
+
+    // Como `insert`, en la salida de voces alternas `bus` (0 o 1)
+    pub fn insert_on_bus(&mut self, key: u8, bus: usize, mut note: Note) -> VoiceId {
+        if self.voices.len() >= MAX_VOICES {
+            let oldest = self.voices.iter().position(|voice| !voice.note.is_held()).unwrap_or(0);
+            self.voices.remove(oldest);
+            self.stolen += 1;
+        }
+        note.set_free_run_phases(&self.free_run);
+        let id = self.next_id;
+        self.next_id += 1;
+        self.voices.push(Voice { id, key, bus: bus % 2, note });
+        id
+    }
+
+    // Voz de la tecla que todavía no se ha soltado
+    pub fn get_mut(&mut self, key: u8) -> Option<&mut Note> {
+        self.held_mut(key).next()
+    }
+
+    // Todas las voces pulsadas de la tecla, varias si suena en unísono
+    pub fn held_mut(&mut self, key: u8) -> impl Iterator<Item = &mut Note> {
+        self.voices.iter_mut()
+            .filter(move |voice| voice.key == key && voice.note.is_held())
+            .map(|voice| &mut voice.note)
+    }
+
+    pub fn contains_key(&self, key: u8) -> bool {
+        self.voices.iter().any(|voice| voice.key == key && voice.note.is_held())
+    }
+
+    // Pasar las voces pulsadas de una tecla a otra, p. ej. en modo legato
+    pub fn rekey(&mut self, from: u8, to: u8) {
+        for voice in self.voices.iter_mut().filter(|voice| voice.key == from && voice.note.is_held()) {
+            voice.key = to;
+        }
+    }
+
+    // Pasar todas las voces a una tecla; en modo mono todas son de la misma nota
+    pub fn rekey_all(&mut self, to: u8) {
+        for voice in self.voices.iter_mut() {
+            voice.key = to;
+        }
+    }
+
+    pub fn get_by_id_mut(&mut self, id: VoiceId) -> Option<&mut Note> {
+        self.voices.iter_mut().find(|voice| voice.id == id).map(|voice| &mut voice.note)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &Note)> {
+        self.voices.iter().map(|voice| (voice.key, &voice.note))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u8, &mut Note)> {
+        self.voices.iter_mut().map(|voice| (voice.key, &mut voice.note))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Note> {
+        self.voices.iter().map(|voice| &voice.note)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Note> {
+        self.voices.iter_mut().map(|voice| &mut voice.note)
+    }
+
+    pub fn voices(&self) -> &[Voice] {
+        &self.voices
+    }
+
+    pub fn voices_mut(&mut self) -> &mut [Voice] {
+        &mut self.voices
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&Note) -> bool) {
+        self.voices.retain(|voice| keep(&voice.note));
+    }
+
+    // Eliminar las voces terminadas; devuelve cuántas se cortaron a la fuerza (robadas)
+    // en lugar de acabar su liberación
+    pub fn remove_finished(&mut self) -> usize {
+        let mut stolen = std::mem::take(&mut self.stolen);
+        self.voices.retain(|voice| {
+            let finished = voice.note.envelope.is_finished();
+            if finished && voice.note.envelope.is_killed() {
+                stolen += 1;
+            }
+            !finished
+        });
+        stolen
+    }
+
+    pub fn clear(&mut self) {
+        self.voices.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voices.is_empty()
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Posiciones por las que pasa el modo Repartir, en orden: centro, extremos y
+// puntos intermedios, para que pocas notas ya ocupen todo el campo estéreo
+const SPREAD_POSITIONS: [f32; 5] = [0.0, -1.0, 1.0, -0.5, 0.5];
+
+// Cómo se reparte el panorama entre las voces que se van creando
+#[derive(Clone, Copy, PartialEq)]
+pub enum VoicePanMode {
+    // Todas las voces en la misma posición
+    Fixed,
+    // Una voz a cada lado, alternando con cada nota
+    Alternate,
+    // Cada nota en la siguiente de SPREAD_POSITIONS
+    Spread,
+}
+
+impl VoicePanMode {
+    pub const ALL: [VoicePanMode; 3] = [VoicePanMode::Fixed, VoicePanMode::Alternate, VoicePanMode::Spread];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VoicePanMode::Fixed => "Fijo",
+            VoicePanMode::Alternate => "Alternar",
+            VoicePanMode::Spread => "Repartir",
+        }
+    }
+}
+
+// Turno de la próxima voz, compartido por todas como la fase del LFO libre
+static NEXT_VOICE_SLOT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct VoicePanSettings {
+    pub mode: VoicePanMode,
+    // Posición central de -1.0 (izquierda) a 1.0 (derecha)
+    pub pan: f32,
+    // Anchura de 0.0 a 1.0 alrededor de la posición central en Alternar y Repartir
+    pub spread: f32,
+}
+
+impl Default for VoicePanSettings {
+    fn default() -> Self {
+        Self {
+            mode: VoicePanMode::Fixed,
+            pan: 0.0,
+            spread: 0.5,
+        }
+    }
+}
+
+impl VoicePanSettings {
+    // Panorama de una voz nueva; en Alternar y Repartir cada llamada avanza un turno
+    pub fn next_pan(&self) -> f32 {
+        let offset = match self.mode {
+            VoicePanMode::Fixed => 0.0,
+            VoicePanMode::Alternate => {
+                let slot = NEXT_VOICE_SLOT.fetch_add(1, Ordering::Relaxed);
+                if slot % 2 == 0 { -1.0 } else { 1.0 }
+            }
+            VoicePanMode::Spread => {
+                let slot = NEXT_VOICE_SLOT.fetch_add(1, Ordering::Relaxed);
+                SPREAD_POSITIONS[slot % SPREAD_POSITIONS.len()]
+            }
+        };
+        (self.pan + offset * self.spread.clamp(0.0, 1.0)).clamp(-1.0, 1.0)
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::Arc;
+use std::sync::OnceLock;
+use super::fft::Fft;
+
+// Tamaño de la tabla de ondas (potencia de 2 para optimizar)
+pub const WAVETABLE_SIZE: usize = 4096;
+// Máscara para operaciones de módulo eficientes
+const WAVETABLE_MASK: usize = WAVETABLE_SIZE - 1;
+
+// Tabla de ondas compartida global
+static SINE_TABLE: OnceLock<Arc<[f32; WAVETABLE_SIZE]>> = OnceLock::new();
+
+fn build_sine_table() -> Arc<[f32; WAVETABLE_SIZE]> {
+    let mut table = [0.0; WAVETABLE_SIZE];
+    for i in 0..WAVETABLE_SIZE {
+        let phase = 2.0 * std::f32::consts::PI * (i as f32 / WAVETABLE_SIZE as f32);
+        table[i] = phase.sin();
+    }
+    Arc::new(table)
+}
+
+fn get_sine_table() -> Arc<[f32; WAVETABLE_SIZE]> {
+    SINE_TABLE.get_or_init(build_sine_table).clone()
+}
+
+// Seno de una fase normalizada (0..1) leído de la tabla compartida con interpolación
+// lineal. Sin clonar el Arc, para la ruta por muestra de las voces.
+#[inline(always)]
+pub fn sine_from_table(phase_norm: f32) -> f32 {
+    let table = SINE_TABLE.get_or_init(build_sine_table);
+    let position = phase_norm * WAVETABLE_SIZE as f32;
+    let index = position as usize;
+    let frac = position - index as f32;
+    let y1 = table[index & WAVETABLE_MASK];
+    let y2 = table[(index + 1) & WAVETABLE_MASK];
+    y1 + frac * (y2 - y1)
+}
+
+// Oscilador senoidal de tabla de ondas
+pub struct WavetableOscillator {
+    wavetable: Arc<[f32; WAVETABLE_SIZE]>,
+    phase: f32,
+    phase_increment: f32,
+}
+
+impl WavetableOscillator {
+    pub fn new(sample_rate: f32, frequency: f32) -> Self {
+        let wavetable = get_sine_table();
+        let phase_increment = frequency * WAVETABLE_SIZE as f32 / sample_rate;
+        
+        WavetableOscillator {
+            wavetable,
+            phase: 0.0,
+            phase_increment,
+        }
+    }
+    
+    #[inline]
+    pub fn set_frequency(&mut self, frequency: f32, sample_rate: f32) {
+        self.phase_increment = frequency * WAVETABLE_SIZE as f32 / sample_rate;
+    }
+    
+    #[inline]
+    pub fn get_sample(&mut self) -> f32 {
+        let phase_floor = self.phase as usize;
+        let phase_frac = self.phase - phase_floor as f32;
+        
+        let idx1 = phase_floor & WAVETABLE_MASK;
+        let idx2 = (phase_floor + 1) & WAVETABLE_MASK;
+        
+        let y1 = self.wavetable[idx1];
+        let y2 = self.wavetable[idx2];
+        
+        let output = y1 + phase_frac * (y2 - y1);
+        
+        self.phase += self.phase_increment;
+        if self.phase >= WAVETABLE_SIZE as f32 {
+            self.phase -= WAVETABLE_SIZE as f32;
+        }
+        
+        output
+    }
+}
+
+// Número de armónicos usados para generar los cuadros de la tabla morfable
+const MORPH_HARMONICS: usize = 64;
+
+// Tabla de ondas con varios cuadros (frames) entre los que se puede interpolar
+pub struct Wavetable {
+    frames: Vec<[f32; WAVETABLE_SIZE]>,
+}
+
+impl Wavetable {
+    pub fn from_frames(frames: Vec<[f32; WAVETABLE_SIZE]>) -> Self {
+        assert!(!frames.is_empty(), "La tabla de ondas necesita al menos un cuadro");
+        Self { frames }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[inline]
+    fn read_frame(&self, frame: usize, phase: f32) -> f32 {
+        let phase_floor = phase as usize;
+        let phase_frac = phase - phase_floor as f32;
+
+        let idx1 = phase_floor & WAVETABLE_MASK;
+        let idx2 = (phase_floor + 1) & WAVETABLE_MASK;
+
+        let table = &self.frames[frame];
+        table[idx1] + phase_frac * (table[idx2] - table[idx1])
+    }
+
+    // Leer una muestra con la fase normalizada (0..1) y la posición en la tabla (0..1),
+    // interpolando entre los dos cuadros más cercanos
+    #[inline]
+    pub fn sample(&self, phase_norm: f32, position: f32) -> f32 {
+        let phase = phase_norm * WAVETABLE_SIZE as f32;
+        let last_frame = self.frames.len() - 1;
+        let frame_pos = position.clamp(0.0, 1.0) * last_frame as f32;
+
+        let frame1 = frame_pos as usize;
+        let frame2 = (frame1 + 1).min(last_frame);
+        let frame_frac = frame_pos - frame1 as f32;
+
+        let y1 = self.read_frame(frame1, phase);
+        if frame1 == frame2 {
+            return y1;
+        }
+        let y2 = self.read_frame(frame2, phase);
+
+        y1 + frame_frac * (y2 - y1)
+    }
+}
+
+// Tabla morfable compartida global: senoidal -> triangular -> sierra -> cuadrada
+static MORPH_TABLE: OnceLock<Arc<Wavetable>> = OnceLock::new();
+
+pub fn get_morph_table() -> Arc<Wavetable> {
+    MORPH_TABLE.get_or_init(|| {
+        let pi = std::f32::consts::PI;
+        let frames = vec![
+            additive_frame(MORPH_HARMONICS, |k| if k == 1 { 1.0 } else { 0.0 }),
+            additive_frame(MORPH_HARMONICS, |k| {
+                if k % 2 == 0 {
+                    0.0
+                } else {
+                    let sign = if (k / 2) % 2 == 0 { 1.0 } else { -1.0 };
+                    sign * 8.0 / (pi * pi * (k * k) as f32)
+                }
+            }),
+            additive_frame(MORPH_HARMONICS, |k| 2.0 / (pi * k as f32)),
+            additive_frame(MORPH_HARMONICS, |k| if k % 2 == 0 { 0.0 } else { 4.0 / (pi * k as f32) }),
+        ];
+        Arc::new(Wavetable::from_frames(frames))
+    }).clone()
+}
+
+// Niveles del mipmap: el nivel i contiene como máximo 2^i armónicos
+const MIPMAP_LEVELS: usize = 11;
+
+// Conjunto de tablas de banda limitada, una por octava, para evitar aliasing
+pub struct MipmapWavetable {
+    levels: Vec<Wavetable>,
+}
+
+impl MipmapWavetable {
+    fn build(amplitude: impl Fn(usize) -> f32) -> Self {
+        let levels = (0..MIPMAP_LEVELS)
+            .map(|level| Wavetable::from_frames(vec![additive_frame(1 << level, &amplitude)]))
+            .collect();
+        Self { levels }
+    }
+
+    // Elegir el nivel con más armónicos que no superen Nyquist para este incremento de fase
+    #[inline]
+    pub fn sample(&self, phase_norm: f32, phase_inc: f32) -> f32 {
+        let max_harmonics = ((0.5 / phase_inc.max(1e-6)) as usize).max(1);
+        let level = (usize::BITS - 1 - max_harmonics.leading_zeros()) as usize;
+        self.levels[level.min(self.levels.len() - 1)].sample(phase_norm, 0.0)
+    }
+}
+
+static SQUARE_MIPMAP: OnceLock<MipmapWavetable> = OnceLock::new();
+static SAW_MIPMAP: OnceLock<MipmapWavetable> = OnceLock::new();
+static TRIANGLE_MIPMAP: OnceLock<MipmapWavetable> = OnceLock::new();
+
+pub fn square_mipmap() -> &'static MipmapWavetable {
+    SQUARE_MIPMAP.get_or_init(|| {
+        MipmapWavetable::build(|k| if k % 2 == 0 { 0.0 } else { 4.0 / (std::f32::consts::PI * k as f32) })
+    })
+}
+
+// Sierra ascendente, igual que la versión PolyBLEP
+pub fn saw_mipmap() -> &'static MipmapWavetable {
+    SAW_MIPMAP.get_or_init(|| MipmapWavetable::build(|k| -2.0 / (std::f32::consts::PI * k as f32)))
+}
+
+pub fn triangle_mipmap() -> &'static MipmapWavetable {
+    TRIANGLE_MIPMAP.get_or_init(|| {
+        MipmapWavetable::build(|k| {
+            if k % 2 == 0 {
+                0.0
+            } else {
+                let sign = if (k / 2) % 2 == 0 { 1.0 } else { -1.0 };
+                sign * 8.0 / (std::f32::consts::PI * std::f32::consts::PI * (k * k) as f32)
+            }
+        })
+    })
+}
+
+// Número de armónicos controlables en el modo aditivo (estilo drawbar)
+pub const ADDITIVE_HARMONICS: usize = 32;
+
+// Tabla aditiva por defecto (solo la fundamental) compartida global
+static DEFAULT_ADDITIVE_TABLE: OnceLock<Arc<Wavetable>> = OnceLock::new();
+
+pub fn get_default_additive_table() -> Arc<Wavetable> {
+    DEFAULT_ADDITIVE_TABLE.get_or_init(|| build_additive_table(&[1.0])).clone()
+}
+
+// Construir la tabla del modo aditivo a partir del nivel de cada armónico.
+// Se regenera solo cuando cambian los niveles, nunca en el hilo de audio.
+pub fn build_additive_table(levels: &[f32]) -> Arc<Wavetable> {
+    let harmonics = levels.len().min(ADDITIVE_HARMONICS);
+    let frame = additive_frame(harmonics, |k| levels[k - 1]);
+    Arc::new(Wavetable::from_frames(vec![frame]))
+}
+
+// Generar un cuadro por síntesis aditiva a partir de la amplitud de cada armónico,
+// usando una FFT inversa y normalizando a un pico de 1.0
+fn additive_frame(harmonics: usize, amplitude: impl Fn(usize) -> f32) -> [f32; WAVETABLE_SIZE] {
+    let mut re = vec![0.0f32; WAVETABLE_SIZE];
+    let mut im = vec![0.0f32; WAVETABLE_SIZE];
+
+    // Un bin con valor -i*a produce a*sin(k*x) en la parte real
+    for k in 1..=harmonics.min(WAVETABLE_SIZE / 2 - 1) {
+        im[k] = -amplitude(k);
+    }
+    Fft::new(WAVETABLE_SIZE).inverse(&mut re, &mut im);
+
+    let mut table = [0.0; WAVETABLE_SIZE];
+    table.copy_from_slice(&re);
+
+    let peak = table.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+    if peak > 0.0 {
+        for value in table.iter_mut() {
+            *value /= peak;
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    #[test]
+    fn sine_from_table_matches_sin() {
+        // Más pasos que muestras tiene la tabla, para caer también entre dos puntos
+        const STEPS: usize = WAVETABLE_SIZE * 4;
+        for i in 0..STEPS {
+            let phase = i as f32 / STEPS as f32;
+            let error = (sine_from_table(phase) - (phase * TAU).sin()).abs();
+            assert!(error < 1e-5, "fase {}: error {}", phase, error);
+        }
+    }
+}
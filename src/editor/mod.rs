@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::api::Patch;
+use crate::dsp::wavetable::{get_default_additive_table, Wavetable};
+
+use baseview::{Size, WindowHandle, WindowOpenOptions, WindowScalePolicy};
+use raw_window_handle::{
+    AppKitWindowHandle, HasRawWindowHandle, RawWindowHandle, Win32WindowHandle, XlibWindowHandle,
+};
+use vst3_plugin::prelude::*;
+
+use crate::gui::{SynthApp, SynthConfig};
+use crate::{parameter_ids, RustSynthController};
+
+mod window;
+
+// Tamaño de la ventana del editor en píxeles lógicos
+const EDITOR_WIDTH: u32 = 960;
+const EDITOR_HEIGHT: u32 = 720;
+
+// Estado que comparten el plugin y su editor
+pub struct EditorShared {
+    // Ajustes que muestran y cambian los controles de la interfaz
+    config: Arc<Mutex<SynthConfig>>,
+    // Último sonido de la interfaz con su tabla aditiva, calculada fuera del hilo de
+    // audio; el proceso toma de aquí lo que no tiene parámetro en el host
+    patch: Mutex<(Patch, Arc<Wavetable>)>,
+    patch_changed: AtomicBool,
+    // Cambios de parámetros del host que la interfaz aún no ha recogido
+    host_changes: Mutex<Vec<(u32, f64)>>,
+    // Por dónde se avisa al host de los parámetros que mueve la interfaz
+    handler: Mutex<Option<ComponentHandler>>,
+    // Con el editor cerrado no se acumulan los cambios del host
+    open: AtomicBool,
+}
+
+impl Default for EditorShared {
+    fn default() -> Self {
+        Self {
+            config: Default::default(),
+            patch: Mutex::new((Patch::default(), get_default_additive_table())),
+            patch_changed: AtomicBool::new(false),
+            host_changes: Default::default(),
+            handler: Default::default(),
+            open: AtomicBool::new(false),
+        }
+    }
+}
+
+impl EditorShared {
+    pub fn config(&self) -> std::sync::MutexGuard<'_, SynthConfig> {
+        self.config.lock().unwrap()
+    }
+
+    pub fn set_handler(&self, handler: Option<ComponentHandler>) {
+        *self.handler.lock().unwrap() = handler;
+    }
+
+    // Dejar el sonido de la interfaz para el siguiente bloque del proceso
+    fn publish_patch(&self, patch: &Patch, additive_table: Arc<Wavetable>) {
+        let mut shared = self.patch.lock().unwrap();
+        shared.0.clone_from(patch);
+        shared.1 = additive_table;
+        self.patch_changed.store(true, Ordering::Release);
+    }
+
+    // Desde el hilo de audio: si la interfaz ha publicado un sonido nuevo y no lo está
+    // escribiendo en este momento, pasarlo a `apply`. Nunca espera al editor.
+    pub fn take_patch(&self, apply: impl FnOnce(&Patch, &Arc<Wavetable>)) {
+        if !self.patch_changed.load(Ordering::Acquire) {
+            return;
+        }
+        if let Ok(shared) = self.patch.try_lock() {
+            self.patch_changed.store(false, Ordering::Relaxed);
+            apply(&shared.0, &shared.1);
+        }
+    }
+
+    pub fn push_host_change(&self, id: u32, value: f64) {
+        if self.open.load(Ordering::Relaxed) {
+            self.host_changes.lock().unwrap().push((id, value));
+        }
+    }
+}
+
+// Vista que el host incrusta en su ventana con la misma interfaz que la aplicación
+pub struct PluginEditor {
+    shared: Arc<EditorShared>,
+    window: Option<WindowHandle>,
+}
+
+impl PluginEditor {
+    pub fn new(shared: Arc<EditorShared>) -> Self {
+        Self { shared, window: None }
+    }
+}
+
+impl IPlugView for PluginEditor {
+    fn is_platform_type_supported(&self, platform_type: &str) -> bool {
+        ParentWindow::new(std::ptr::null_mut(), platform_type).is_some()
+    }
+
+    fn attached(&mut self, parent: *mut c_void, platform_type: &str) -> tresult {
+        let Some(parent) = ParentWindow::new(parent, platform_type) else {
+            return kResultFalse;
+        };
+        // create_view ya dejó en los ajustes los valores del plugin; desde aquí los
+        // cambios del host se recogen en cada fotograma
+        let mut params = RustSynthController::default();
+        params.load_config(&self.shared.config());
+        self.shared.host_changes.lock().unwrap().clear();
+        self.shared.open.store(true, Ordering::Relaxed);
+
+        let options = WindowOpenOptions {
+            title: String::from("Rust Synth"),
+            size: Size::new(EDITOR_WIDTH as f64, EDITOR_HEIGHT as f64),
+            scale: WindowScalePolicy::SystemScaleFactor,
+            gl_config: Some(Default::default()),
+        };
+        let state = EditorState::new(self.shared.clone(), params);
+        self.window = Some(window::open_parented(
+            &parent,
+            options,
+            state,
+            |ctx: &egui::Context, state: &mut EditorState| state.update(ctx),
+        ));
+        kResultOk
+    }
+
+    fn removed(&mut self) -> tresult {
+        self.shared.open.store(false, Ordering::Relaxed);
+        if let Some(mut window) = self.window.take() {
+            window.close();
+        }
+        kResultOk
+    }
+
+    fn get_size(&self) -> ViewRect {
+        ViewRect { left: 0, top: 0, right: EDITOR_WIDTH as i32, bottom: EDITOR_HEIGHT as i32 }
+    }
+
+    fn can_resize(&self) -> bool {
+        false
+    }
+}
+
+// Ventana del host en la que se abre el editor, según la plataforma
+struct ParentWindow(RawWindowHandle);
+
+impl ParentWindow {
+    fn new(parent: *mut c_void, platform_type: &str) -> Option<Self> {
+        let handle = match platform_type {
+            "HWND" => {
+                let mut handle = Win32WindowHandle::empty();
+                handle.hwnd = parent;
+                RawWindowHandle::Win32(handle)
+            }
+            "NSView" => {
+                let mut handle = AppKitWindowHandle::empty();
+                handle.ns_view = parent;
+                RawWindowHandle::AppKit(handle)
+            }
+            "X11EmbedWindowID" => {
+                let mut handle = XlibWindowHandle::empty();
+                handle.window = parent as std::os::raw::c_ulong;
+                RawWindowHandle::Xlib(handle)
+            }
+            _ => return None,
+        };
+        Some(Self(handle))
+    }
+}
+
+unsafe impl HasRawWindowHandle for ParentWindow {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.0
+    }
+}
+
+// Interfaz de la aplicación y copia de los parámetros para saber qué ha cambiado
+struct EditorState {
+    app: SynthApp,
+    shared: Arc<EditorShared>,
+    params: RustSynthController,
+    // Último valor normalizado de cada parámetro que conocen el host y la interfaz
+    values: Vec<(u32, f64)>,
+    // Parámetros con un gesto abierto (begin_edit sin end_edit) en el host
+    editing: HashSet<u32>,
+    // Revisión del sonido de la interfaz que ya se ha llevado al plugin
+    revision: u64,
+}
+
+impl EditorState {
+    fn new(shared: Arc<EditorShared>, params: RustSynthController) -> Self {
+        let values = parameter_ids().map(|id| (id, params.normalized(id))).collect();
+        Self {
+            app: SynthApp::new_plugin(shared.config.clone()),
+            shared,
+            params,
+            values,
+            editing: HashSet::new(),
+            revision: 0,
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context) {
+        self.pull_host_changes();
+        self.app.show(ctx);
+        let editing = ctx.input(|input| input.pointer.any_down());
+        self.push_gui_changes(editing);
+    }
+
+    // Llevar a los controles la automatización y lo que se mueva desde el host
+    fn pull_host_changes(&mut self) {
+        let changes = std::mem::take(&mut *self.shared.host_changes.lock().unwrap());
+        if changes.is_empty() {
+            return;
+        }
+        let config = self.shared.config();
+        self.params.load_config(&config);
+        for (id, value) in changes {
+            self.params.set_normalized(id, value);
+            if let Some(entry) = self.values.iter_mut().find(|entry| entry.0 == id) {
+                entry.1 = self.params.normalized(id);
+            }
+        }
+        self.params.store_config(&config);
+    }
+
+    // Avisar al host de los parámetros que ha movido la interfaz y pasar al proceso el
+    // resto del sonido; mientras se arrastra un control el gesto queda abierto, para que
+    // la automatización grabe un solo tramo. Los ajustes solo se vuelven a leer cuando
+    // la interfaz registra un cambio del sonido (o de la respuesta a la velocidad, que
+    // es de la sesión y no entra en el historial).
+    fn push_gui_changes(&mut self, editing: bool) {
+        let revision = self.app.sound_revision();
+        {
+            let config = self.shared.config();
+            let velocity_response = *config.velocity_response.lock().unwrap();
+            if revision != self.revision || velocity_response != self.params.patch.velocity_response {
+                self.revision = revision;
+                self.params.load_config(&config);
+                let additive_table = config.additive_table.lock().unwrap().1.clone();
+                self.shared.publish_patch(&self.params.patch, additive_table);
+            }
+        }
+        let handler = self.shared.handler.lock().unwrap();
+        let Some(handler) = handler.as_ref() else {
+            return;
+        };
+        for (id, last) in self.values.iter_mut() {
+            let value = self.params.normalized(*id);
+            if value == *last {
+                continue;
+            }
+            *last = value;
+            if self.editing.insert(*id) {
+                handler.begin_edit(*id);
+            }
+            handler.perform_edit(*id, value);
+        }
+        if !editing {
+            for id in self.editing.drain() {
+                handler.end_edit(id);
+            }
+        }
+    }
+}
@@ -0,0 +1,275 @@
+// Ventana del editor: baseview da la ventana hija del host con su contexto OpenGL y
+// sus eventos; aquí se traducen a la entrada de egui y se pinta con egui_glow
+use std::sync::Arc;
+use std::time::Instant;
+
+use baseview::{
+    Event, EventStatus, MouseButton, MouseEvent, ScrollDelta, Window, WindowEvent, WindowHandle,
+    WindowHandler, WindowOpenOptions,
+};
+use glow::HasContext;
+use keyboard_types::{Key, KeyState, KeyboardEvent, Modifiers};
+use raw_window_handle::HasRawWindowHandle;
+
+// Píxeles que avanza cada línea de la rueda del ratón
+const SCROLL_LINE_HEIGHT: f32 = 24.0;
+
+// Abre la ventana dentro de la del host; `update` dibuja la interfaz en cada fotograma
+pub fn open_parented<P, S, U>(
+    parent: &P,
+    options: WindowOpenOptions,
+    state: S,
+    update: U,
+) -> WindowHandle
+where
+    P: HasRawWindowHandle,
+    S: Send + 'static,
+    U: FnMut(&egui::Context, &mut S) + Send + 'static,
+{
+    let size = options.size;
+    Window::open_parented(parent, options, move |window: &mut Window| {
+        EguiWindow::new(window, size, state, update)
+    })
+}
+
+struct EguiWindow<S, U> {
+    context: egui::Context,
+    gl: Arc<glow::Context>,
+    painter: egui_glow::Painter,
+    raw_input: egui::RawInput,
+    start: Instant,
+    // Tamaño físico del framebuffer y píxeles físicos por punto lógico
+    physical_size: [u32; 2],
+    scale: f32,
+    pointer: Option<egui::Pos2>,
+    modifiers: egui::Modifiers,
+    state: S,
+    update: U,
+}
+
+impl<S, U> EguiWindow<S, U>
+where
+    U: FnMut(&egui::Context, &mut S),
+{
+    fn new(window: &mut Window, size: baseview::Size, state: S, update: U) -> Self {
+        let gl_context = window
+            .gl_context()
+            .expect("la ventana del editor se abre con contexto OpenGL");
+        let (gl, painter) = unsafe {
+            gl_context.make_current();
+            let gl = Arc::new(glow::Context::from_loader_function(|name| {
+                gl_context.get_proc_address(name)
+            }));
+            let painter = egui_glow::Painter::new(gl.clone(), "", None)
+                .expect("no se pudo crear el painter de egui_glow");
+            gl_context.make_not_current();
+            (gl, painter)
+        };
+
+        // Hasta que llegue el primer Resized se supone escala 1
+        Self {
+            context: egui::Context::default(),
+            gl,
+            painter,
+            raw_input: egui::RawInput::default(),
+            start: Instant::now(),
+            physical_size: [size.width.round() as u32, size.height.round() as u32],
+            scale: 1.0,
+            pointer: None,
+            modifiers: egui::Modifiers::default(),
+            state,
+            update,
+        }
+    }
+
+    fn mouse_event(&mut self, event: MouseEvent) -> EventStatus {
+        match event {
+            MouseEvent::CursorMoved { position, modifiers } => {
+                self.modifiers = egui_modifiers(modifiers);
+                let pos = egui::pos2(position.x as f32, position.y as f32);
+                self.pointer = Some(pos);
+                self.raw_input.events.push(egui::Event::PointerMoved(pos));
+            }
+            MouseEvent::ButtonPressed { button, modifiers } => {
+                self.modifiers = egui_modifiers(modifiers);
+                self.pointer_button(button, true);
+            }
+            MouseEvent::ButtonReleased { button, modifiers } => {
+                self.modifiers = egui_modifiers(modifiers);
+                self.pointer_button(button, false);
+            }
+            MouseEvent::WheelScrolled { delta, modifiers } => {
+                self.modifiers = egui_modifiers(modifiers);
+                let delta = match delta {
+                    ScrollDelta::Lines { x, y } => egui::vec2(x, y) * SCROLL_LINE_HEIGHT,
+                    ScrollDelta::Pixels { x, y } => egui::vec2(x, y) / self.scale,
+                };
+                self.raw_input.events.push(egui::Event::Scroll(delta));
+            }
+            MouseEvent::CursorLeft => {
+                self.pointer = None;
+                self.raw_input.events.push(egui::Event::PointerGone);
+            }
+            _ => return EventStatus::Ignored,
+        }
+        EventStatus::Captured
+    }
+
+    fn pointer_button(&mut self, button: MouseButton, pressed: bool) {
+        let (Some(pos), Some(button)) = (self.pointer, egui_button(button)) else {
+            return;
+        };
+        self.raw_input.events.push(egui::Event::PointerButton {
+            pos,
+            button,
+            pressed,
+            modifiers: self.modifiers,
+        });
+    }
+
+    fn keyboard_event(&mut self, event: KeyboardEvent) -> EventStatus {
+        self.modifiers = egui_modifiers(event.modifiers);
+        let pressed = event.state == KeyState::Down;
+        if let Some(key) = egui_key(&event.key) {
+            self.raw_input.events.push(egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed,
+                repeat: event.repeat,
+                modifiers: self.modifiers,
+            });
+        }
+        // El texto escrito, salvo los atajos con Ctrl o Cmd
+        if let Key::Character(text) = &event.key {
+            if pressed && !self.modifiers.command && !text.chars().any(char::is_control) {
+                self.raw_input.events.push(egui::Event::Text(text.clone()));
+            }
+        }
+        // Si ningún campo de texto tiene el foco, las teclas siguen llegando al host
+        if self.context.wants_keyboard_input() {
+            EventStatus::Captured
+        } else {
+            EventStatus::Ignored
+        }
+    }
+}
+
+impl<S, U> WindowHandler for EguiWindow<S, U>
+where
+    U: FnMut(&egui::Context, &mut S),
+{
+    fn on_frame(&mut self, window: &mut Window) {
+        let Some(gl_context) = window.gl_context() else {
+            return;
+        };
+        let logical_size = egui::vec2(
+            self.physical_size[0] as f32 / self.scale,
+            self.physical_size[1] as f32 / self.scale,
+        );
+        self.raw_input.screen_rect = Some(egui::Rect::from_min_size(egui::Pos2::ZERO, logical_size));
+        self.raw_input.time = Some(self.start.elapsed().as_secs_f64());
+        self.raw_input.modifiers = self.modifiers;
+        self.raw_input
+            .viewports
+            .entry(egui::ViewportId::ROOT)
+            .or_default()
+            .native_pixels_per_point = Some(self.scale);
+
+        let input = self.raw_input.take();
+        let update = &mut self.update;
+        let state = &mut self.state;
+        let output = self.context.run(input, |ctx| update(ctx, state));
+        let primitives = self.context.tessellate(output.shapes, output.pixels_per_point);
+
+        unsafe {
+            gl_context.make_current();
+            self.gl.clear_color(0.0, 0.0, 0.0, 1.0);
+            self.gl.clear(glow::COLOR_BUFFER_BIT);
+        }
+        self.painter.paint_and_update_textures(
+            self.physical_size,
+            output.pixels_per_point,
+            &primitives,
+            &output.textures_delta,
+        );
+        unsafe {
+            gl_context.swap_buffers();
+            gl_context.make_not_current();
+        }
+    }
+
+    fn on_event(&mut self, window: &mut Window, event: Event) -> EventStatus {
+        match event {
+            Event::Mouse(event) => self.mouse_event(event),
+            Event::Keyboard(event) => self.keyboard_event(event),
+            Event::Window(WindowEvent::Resized(info)) => {
+                let size = info.physical_size();
+                self.physical_size = [size.width, size.height];
+                self.scale = info.scale() as f32;
+                EventStatus::Captured
+            }
+            Event::Window(WindowEvent::Focused) => {
+                self.raw_input.focused = true;
+                EventStatus::Captured
+            }
+            Event::Window(WindowEvent::Unfocused) => {
+                self.raw_input.focused = false;
+                EventStatus::Captured
+            }
+            Event::Window(WindowEvent::WillClose) => {
+                // Las texturas de egui se liberan con el contexto todavía vivo
+                if let Some(gl_context) = window.gl_context() {
+                    unsafe { gl_context.make_current() };
+                    self.painter.destroy();
+                    unsafe { gl_context.make_not_current() };
+                }
+                EventStatus::Captured
+            }
+        }
+    }
+}
+
+fn egui_button(button: MouseButton) -> Option<egui::PointerButton> {
+    match button {
+        MouseButton::Left => Some(egui::PointerButton::Primary),
+        MouseButton::Right => Some(egui::PointerButton::Secondary),
+        MouseButton::Middle => Some(egui::PointerButton::Middle),
+        MouseButton::Back => Some(egui::PointerButton::Extra1),
+        MouseButton::Forward => Some(egui::PointerButton::Extra2),
+        _ => None,
+    }
+}
+
+fn egui_modifiers(modifiers: Modifiers) -> egui::Modifiers {
+    let ctrl = modifiers.contains(Modifiers::CONTROL);
+    let mac_cmd = cfg!(target_os = "macos") && modifiers.contains(Modifiers::META);
+    egui::Modifiers {
+        alt: modifiers.contains(Modifiers::ALT),
+        ctrl,
+        shift: modifiers.contains(Modifiers::SHIFT),
+        mac_cmd,
+        command: if cfg!(target_os = "macos") { mac_cmd } else { ctrl },
+    }
+}
+
+fn egui_key(key: &Key) -> Option<egui::Key> {
+    Some(match key {
+        Key::ArrowDown => egui::Key::ArrowDown,
+        Key::ArrowLeft => egui::Key::ArrowLeft,
+        Key::ArrowRight => egui::Key::ArrowRight,
+        Key::ArrowUp => egui::Key::ArrowUp,
+        Key::Escape => egui::Key::Escape,
+        Key::Tab => egui::Key::Tab,
+        Key::Backspace => egui::Key::Backspace,
+        Key::Enter => egui::Key::Enter,
+        Key::Insert => egui::Key::Insert,
+        Key::Delete => egui::Key::Delete,
+        Key::Home => egui::Key::Home,
+        Key::End => egui::Key::End,
+        Key::PageUp => egui::Key::PageUp,
+        Key::PageDown => egui::Key::PageDown,
+        Key::Character(text) if text == " " => egui::Key::Space,
+        Key::Character(text) => return egui::Key::from_name(&text.to_uppercase()),
+        _ => return None,
+    })
+}
@@ -1,91 +1,955 @@
 use eframe::egui;
 use egui_extras::RetainedImage;
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
 use cpal::Device;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use midir::{MidiInput, MidiInputConnection};
-use crate::midi::midi_note_to_freq;
-use crate::structs::envelope::Envelope;
-use crate::structs::note::Note;
+use midir::{MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use crate::midi::{midi_note_to_freq, ALL_NOTES_OFF_CC, ALL_SOUND_OFF_CC, SUSTAIN_PEDAL_CC};
+use crate::midi::cc::{CcMapper, CcMapping, CcTarget};
+use crate::midi::clock::{MidiClock, CLOCK_TICK};
+use crate::midi::output::MidiOut;
+use crate::midi::parser::MidiParser;
+use crate::midi::smf::{MidiFile, MidiFilePlayer};
+use crate::midi::recorder::{MidiRecorder, RECORDINGS_DIR};
+use crate::midi::velocity::{VelocityCurve, VelocityResponse, VelocityTrim, MIDI_CHANNELS};
+use crate::structs::history::UndoHistory;
+use crate::structs::envelope::{
+    stage_time_from_normalized, stage_time_to_normalized, AdsrSettings, MAX_ATTACK_TIME, MAX_DECAY_TIME,
+    MAX_RELEASE_TIME,
+};
+use crate::structs::preset::{is_valid_file_name, list_preset_metadata, list_presets, Preset, PresetMetadata, PresetMorph, PRESETS_DIR};
+use crate::structs::mseg::{MsegDestination, MsegPoint, MsegSettings, MAX_MSEG_POINTS, MSEG_GRIDS};
+use crate::structs::session::{Session, SESSION_FILE};
+use crate::structs::split::SplitSettings;
+pub use crate::dsp::WaveType;
+use crate::dsp::{
+    ChipChannel, DutyCycle, EffectType, FxSettings, GlideMode, GlideSettings, LfoSettings, LfoShape, LfoTrigger, MasterFilterSettings, ModCurve, ModDestination, ModMatrix,
+    ModRoute, ModSource, OscillatorSettings, ParaphonicSettings, ParaphonicTrigger, PhaseMode, Quality, SaturationMode, SaturationSettings,
+    UnisonSettings, VectorSettings, VoicePanMode, VoicePanSettings, MAX_GLIDE_TIME, MAX_OSCILLATORS, MAX_OSCILLATOR_DETUNE, MAX_UNISON_DETUNE,
+    MAX_UNISON_VOICES,
+};
+use crate::dsp::saturation::MAX_SATURATION_DRIVE;
+use crate::dsp::modulation::MAX_MOD_ROUTES;
+use crate::dsp::fx::MAX_FX_SLOTS;
+use crate::dsp::fx::compressor::{
+    MAX_COMPRESSOR_ATTACK, MAX_COMPRESSOR_MAKEUP, MAX_COMPRESSOR_RATIO, MAX_COMPRESSOR_RELEASE, MIN_COMPRESSOR_ATTACK,
+    MIN_COMPRESSOR_RELEASE, MIN_COMPRESSOR_THRESHOLD,
+};
+use crate::dsp::fx::delay::{DelayDivision, MAX_DELAY_FEEDBACK, MAX_DELAY_TIME, MIN_DELAY_TIME};
+use crate::dsp::fx::distortion::{DistortionCurve, MAX_DISTORTION_DRIVE, MAX_DISTORTION_TRIM};
+use crate::dsp::fx::eq::MAX_EQ_GAIN;
+use crate::dsp::fx::reverb::{ReverbKernel, MAX_PRE_DELAY};
+use crate::dsp::filters::{BandMode, FilterSettings, FilterSlope, FilterType, Vowel, MAX_BAND_Q, MAX_CUTOFF, MIN_BAND_Q, MIN_CUTOFF};
+use crate::dsp::chip::MAX_CHIP_BIT_DEPTH;
+use crate::dsp::lfo::{MAX_LFO_PITCH_DEPTH, MAX_LFO_RATE, MIN_LFO_RATE};
+use crate::api::{Patch, VoiceManager};
+use crate::audio::{BUFFER_SIZES, DEFAULT_BUFFER_SIZE};
+use crate::audio::stats::{block_peak, EngineStats};
+use crate::audio::watchdog::{StaleVoiceLog, VoiceWatchdog, DEFAULT_STALE_VOICE_TIMEOUT};
+use crate::dsp::wavetable::{build_additive_table, get_default_additive_table, Wavetable, ADDITIVE_HARMONICS};
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum WaveType {
-    Sine,
-    Square,
-    Triangle,
-    Sawtooth,
-}
-
-impl WaveType {
-    fn as_str(&self) -> &'static str {
-        match self {
-            WaveType::Sine => "Senoidal",
-            WaveType::Square => "Cuadrada",
-            WaveType::Triangle => "Triangular",
-            WaveType::Sawtooth => "Sierra",
-        }
-    }
-}
+// Intervalo de refresco del estado mientras el sintetizador suena (30 Hz)
+const METER_REFRESH_INTERVAL: Duration = Duration::from_millis(33);
+// Tamaño de los controles de la página de actuación
+const PERFORMANCE_SLIDER_WIDTH: f32 = 420.0;
+const PERFORMANCE_CONTROL_HEIGHT: f32 = 48.0;
+const XY_PAD_SIZE: f32 = 360.0;
+const VECTOR_PAD_SIZE: f32 = 160.0;
+// Tiempo que se mantiene pulsada la nota de audición de presets
+const AUDITION_LENGTH: Duration = Duration::from_millis(800);
+// Cada cuánto mira el reproductor de archivos MIDI si le toca enviar eventos
+const MIDI_FILE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+// Teclado en pantalla: octavas visibles, alto y nota más alta a la que puede empezar
+const KEYBOARD_OCTAVES: usize = 3;
+const KEYBOARD_HEIGHT: f32 = 90.0;
+const KEYBOARD_MAX_FIRST_NOTE: u8 = 84;
+// Semitonos negros de la octava y, para cada semitono, la tecla blanca a su izquierda
+const BLACK_KEYS: [bool; 12] = [false, true, false, true, false, false, true, false, true, false, true, false];
+const WHITE_KEY_INDEX: [usize; 12] = [0, 0, 1, 1, 2, 3, 3, 4, 4, 5, 5, 6];
 
 // Estructura para almacenar la configuración del sintetizador
 pub struct SynthConfig {
     pub host_index: usize,
     pub device_index: usize,
     pub sample_rate_index: usize,
+    // Frames por buffer del stream; ASIO usa el de su panel de control
+    pub buffer_size: u32,
     pub available_hosts: Vec<String>,
     pub available_devices: Vec<String>,
     pub available_sample_rates: Vec<u32>,
     pub selected_config: Option<cpal::SupportedStreamConfig>,
     pub running: bool,
     pub volume: Arc<Mutex<f32>>,
-    pub wave_type1: Arc<Mutex<WaveType>>,
-    pub wave_type2: Arc<Mutex<WaveType>>,
-    pub osc2_volume: Arc<Mutex<f32>>,
-    pub osc2_detune: Arc<Mutex<f32>>,
+    pub oscillators: Arc<Mutex<[OscillatorSettings; MAX_OSCILLATORS]>>,
+    pub oscillator_count: Arc<Mutex<usize>>,
+    pub wavetable_position: Arc<Mutex<f32>>,
+    pub wavetable_sweep: Arc<Mutex<f32>>,
+    pub harmonic_levels: [f32; ADDITIVE_HARMONICS],
+    // Autor, categoría, etiquetas y descripción del sonido actual; no los usa el audio
+    pub metadata: PresetMetadata,
+    // Niveles de los armónicos con su tabla ya calculada, que reciben las voces
+    pub additive_table: Arc<Mutex<([f32; ADDITIVE_HARMONICS], Arc<Wavetable>)>>,
+    pub legato: Arc<Mutex<bool>>,
+    pub legato_retrigger_amp: Arc<Mutex<bool>>,
+    pub legato_retrigger_mod: Arc<Mutex<bool>>,
+    pub cc_mapper: Arc<Mutex<CcMapper>>,
+    pub midi_recorder: Arc<Mutex<MidiRecorder>>,
+    pub voice_watchdog: Arc<VoiceWatchdog>,
+    // Carga del hilo de audio; atómicos, sin Mutex, para no frenar el callback
+    pub stats: Arc<EngineStats>,
+    // Salida a la que se reenvía sin cambios todo el MIDI entrante
+    pub midi_thru: Arc<Mutex<Option<MidiOutputConnection>>>,
+    // Salida de las notas que genera el propio sintetizador hacia un equipo externo
+    pub midi_out: Arc<Mutex<MidiOut>>,
+    // Ajuste de la velocidad de entrada de cada canal MIDI
+    pub velocity_trims: Arc<Mutex<[VelocityTrim; MIDI_CHANNELS]>>,
+    // Curva y sensibilidad con la que responde el teclado, antes del ajuste por canal
+    pub velocity_response: Arc<Mutex<VelocityResponse>>,
+    pub quality: Arc<Mutex<Quality>>,
+    pub voice_drive: Arc<Mutex<f32>>,
+    pub voice_drive_velocity: Arc<Mutex<f32>>,
+    pub voice_filter_env_depth: Arc<Mutex<f32>>,
+    pub vector: Arc<Mutex<VectorSettings>>,
+    pub lfo: Arc<Mutex<LfoSettings>>,
+    pub mseg: Arc<Mutex<MsegSettings>>,
+    pub mod_matrix: Arc<Mutex<ModMatrix>>,
+    pub filter: Arc<Mutex<FilterSettings>>,
+    pub envelope: Arc<Mutex<AdsrSettings>>,
+    pub filter_envelope: Arc<Mutex<AdsrSettings>>,
+    // Teclado dividido: onda, envolvente y volumen propios por debajo del punto de división
+    pub split: Arc<Mutex<SplitSettings>>,
+    // Voces por pulsación, desafinación y apertura estéreo del unísono
+    pub unison: Arc<Mutex<UnisonSettings>>,
+    // Panorama de cada voz nueva, fijo o repartido entre las notas sucesivas
+    pub voice_pan: Arc<Mutex<VoicePanSettings>>,
+    // Envolvente y filtro comunes a todas las notas, como en las máquinas de cuerdas
+    pub paraphonic: Arc<Mutex<ParaphonicSettings>>,
+    // Portamento o glissando entre notas sucesivas
+    pub glide: Arc<Mutex<GlideSettings>>,
+    // Paso bajo y paso alto sobre la mezcla de todas las voces
+    pub master_filter: Arc<Mutex<MasterFilterSettings>>,
+    pub fx: Arc<Mutex<FxSettings>>,
+    // Muestras generadas desde que se abrió el stream: el reloj del reproductor de archivos MIDI
+    pub audio_clock: Arc<Mutex<u64>>,
+    // Tempo del reloj MIDI externo y si el delay y el MSEG sincronizados lo siguen
+    pub midi_clock: Arc<Mutex<MidiClock>>,
+    pub follow_midi_clock: Arc<Mutex<bool>>,
+    pub saturation: Arc<Mutex<SaturationSettings>>,
+    // Presets A y B del morph y posición del control (no se guarda en el preset)
+    pub morph: Arc<Mutex<PresetMorph>>,
 }
 
 impl Default for SynthConfig {
     fn default() -> Self {
+        let session = Session::load(Path::new(SESSION_FILE));
         Self {
             host_index: 0,
             device_index: 0,
             sample_rate_index: 0,
+            buffer_size: session.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE),
             available_hosts: Vec::new(),
             available_devices: Vec::new(),
             available_sample_rates: Vec::new(),
             selected_config: None,
             running: false,
             volume: Arc::new(Mutex::new(0.5)),
-            wave_type1: Arc::new(Mutex::new(WaveType::Sine)),
-            wave_type2: Arc::new(Mutex::new(WaveType::Sine)),
-            osc2_volume: Arc::new(Mutex::new(0.5)),
-            osc2_detune: Arc::new(Mutex::new(0.0)),
+            oscillators: Arc::new(Mutex::new(Preset::default().oscillators)),
+            oscillator_count: Arc::new(Mutex::new(2)),
+            wavetable_position: Arc::new(Mutex::new(0.0)),
+            wavetable_sweep: Arc::new(Mutex::new(0.0)),
+            harmonic_levels: {
+                let mut levels = [0.0; ADDITIVE_HARMONICS];
+                levels[0] = 1.0;
+                levels
+            },
+            metadata: PresetMetadata::default(),
+            additive_table: Arc::new(Mutex::new((Preset::default().harmonic_levels, get_default_additive_table()))),
+            legato: Arc::new(Mutex::new(false)),
+            legato_retrigger_amp: Arc::new(Mutex::new(false)),
+            legato_retrigger_mod: Arc::new(Mutex::new(true)),
+            cc_mapper: Arc::new(Mutex::new(CcMapper::default())),
+            midi_recorder: Arc::new(Mutex::new(MidiRecorder::new())),
+            voice_watchdog: Arc::new(VoiceWatchdog::with_timeout(
+                session.stale_voice_timeout.unwrap_or(DEFAULT_STALE_VOICE_TIMEOUT),
+            )),
+            stats: Arc::new(EngineStats::new()),
+            midi_thru: Arc::new(Mutex::new(None)),
+            midi_out: Arc::new(Mutex::new(MidiOut::new())),
+            velocity_trims: Arc::new(Mutex::new(session.velocity_trims)),
+            velocity_response: Arc::new(Mutex::new(session.velocity_response)),
+            quality: Arc::new(Mutex::new(Quality::Normal)),
+            voice_drive: Arc::new(Mutex::new(0.0)),
+            voice_drive_velocity: Arc::new(Mutex::new(0.0)),
+            voice_filter_env_depth: Arc::new(Mutex::new(0.0)),
+            vector: Arc::new(Mutex::new(VectorSettings::default())),
+            lfo: Arc::new(Mutex::new(LfoSettings::default())),
+            mseg: Arc::new(Mutex::new(MsegSettings::default())),
+            mod_matrix: Arc::new(Mutex::new(ModMatrix::default())),
+            filter: Arc::new(Mutex::new(FilterSettings::default())),
+            envelope: Arc::new(Mutex::new(AdsrSettings::default())),
+            filter_envelope: Arc::new(Mutex::new(AdsrSettings::default())),
+            split: Arc::new(Mutex::new(SplitSettings::default())),
+            unison: Arc::new(Mutex::new(UnisonSettings::default())),
+            voice_pan: Arc::new(Mutex::new(VoicePanSettings::default())),
+            paraphonic: Arc::new(Mutex::new(ParaphonicSettings::default())),
+            glide: Arc::new(Mutex::new(GlideSettings::default())),
+            master_filter: Arc::new(Mutex::new(MasterFilterSettings::default())),
+            fx: Arc::new(Mutex::new(FxSettings::default())),
+            audio_clock: Arc::new(Mutex::new(0)),
+            midi_clock: Arc::new(Mutex::new(MidiClock::new())),
+            follow_midi_clock: Arc::new(Mutex::new(false)),
+            saturation: Arc::new(Mutex::new(SaturationSettings::default())),
+            morph: Arc::new(Mutex::new(PresetMorph::default())),
+        }
+    }
+}
+
+impl SynthConfig {
+    // Capturar el estado actual del sonido como preset
+    pub fn snapshot(&self, name: &str) -> Preset {
+        Preset {
+            name: name.to_string(),
+            metadata: self.metadata.clone(),
+            volume: *self.volume.lock().unwrap(),
+            oscillators: *self.oscillators.lock().unwrap(),
+            oscillator_count: *self.oscillator_count.lock().unwrap(),
+            wavetable_position: *self.wavetable_position.lock().unwrap(),
+            wavetable_sweep: *self.wavetable_sweep.lock().unwrap(),
+            harmonic_levels: self.harmonic_levels,
+            legato: *self.legato.lock().unwrap(),
+            legato_retrigger_amp: *self.legato_retrigger_amp.lock().unwrap(),
+            legato_retrigger_mod: *self.legato_retrigger_mod.lock().unwrap(),
+            quality: *self.quality.lock().unwrap(),
+            voice_drive: *self.voice_drive.lock().unwrap(),
+            voice_drive_velocity: *self.voice_drive_velocity.lock().unwrap(),
+            voice_filter_env_depth: *self.voice_filter_env_depth.lock().unwrap(),
+            vector: *self.vector.lock().unwrap(),
+            lfo: *self.lfo.lock().unwrap(),
+            mseg: *self.mseg.lock().unwrap(),
+            mod_matrix: *self.mod_matrix.lock().unwrap(),
+            filter: *self.filter.lock().unwrap(),
+            envelope: *self.envelope.lock().unwrap(),
+            filter_envelope: *self.filter_envelope.lock().unwrap(),
+            split: *self.split.lock().unwrap(),
+            unison: *self.unison.lock().unwrap(),
+            voice_pan: *self.voice_pan.lock().unwrap(),
+            paraphonic: *self.paraphonic.lock().unwrap(),
+            glide: *self.glide.lock().unwrap(),
+            master_filter: *self.master_filter.lock().unwrap(),
+            fx: self.fx.lock().unwrap().clone(),
+            saturation: *self.saturation.lock().unwrap(),
+        }
+    }
+
+    pub fn apply_preset(&mut self, preset: &Preset) {
+        self.metadata = preset.metadata.clone();
+        *self.volume.lock().unwrap() = preset.volume;
+        *self.oscillators.lock().unwrap() = preset.oscillators;
+        *self.oscillator_count.lock().unwrap() = preset.oscillator_count;
+        *self.wavetable_position.lock().unwrap() = preset.wavetable_position;
+        *self.wavetable_sweep.lock().unwrap() = preset.wavetable_sweep;
+        self.harmonic_levels = preset.harmonic_levels;
+        *self.additive_table.lock().unwrap() = (preset.harmonic_levels, build_additive_table(&preset.harmonic_levels));
+        *self.legato.lock().unwrap() = preset.legato;
+        *self.legato_retrigger_amp.lock().unwrap() = preset.legato_retrigger_amp;
+        *self.legato_retrigger_mod.lock().unwrap() = preset.legato_retrigger_mod;
+        *self.quality.lock().unwrap() = preset.quality;
+        *self.voice_drive.lock().unwrap() = preset.voice_drive;
+        *self.voice_drive_velocity.lock().unwrap() = preset.voice_drive_velocity;
+        *self.voice_filter_env_depth.lock().unwrap() = preset.voice_filter_env_depth;
+        *self.vector.lock().unwrap() = preset.vector;
+        *self.lfo.lock().unwrap() = preset.lfo;
+        *self.mseg.lock().unwrap() = preset.mseg;
+        *self.mod_matrix.lock().unwrap() = preset.mod_matrix;
+        *self.filter.lock().unwrap() = preset.filter;
+        *self.envelope.lock().unwrap() = preset.envelope;
+        *self.filter_envelope.lock().unwrap() = preset.filter_envelope;
+        *self.split.lock().unwrap() = preset.split;
+        *self.unison.lock().unwrap() = preset.unison;
+        *self.voice_pan.lock().unwrap() = preset.voice_pan;
+        *self.paraphonic.lock().unwrap() = preset.paraphonic;
+        *self.glide.lock().unwrap() = preset.glide;
+        *self.master_filter.lock().unwrap() = preset.master_filter;
+        *self.fx.lock().unwrap() = preset.fx.clone();
+        *self.saturation.lock().unwrap() = preset.saturation;
+    }
+
+    // Valor actual de un parámetro asignable, en su propio rango
+    pub fn target_value(&self, target: CcTarget) -> f32 {
+        match target {
+            CcTarget::Volume => *self.volume.lock().unwrap(),
+            CcTarget::Osc2Volume => self.oscillators.lock().unwrap()[1].volume,
+            CcTarget::Osc2Detune => self.oscillators.lock().unwrap()[1].detune,
+            CcTarget::Osc1Fold => self.oscillators.lock().unwrap()[0].fold,
+            CcTarget::WavetablePosition => *self.wavetable_position.lock().unwrap(),
+            CcTarget::WavetableSweep => *self.wavetable_sweep.lock().unwrap(),
+            CcTarget::VoiceFilterEnvDepth => *self.voice_filter_env_depth.lock().unwrap(),
+            CcTarget::VoiceDrive => *self.voice_drive.lock().unwrap(),
+            CcTarget::FilterCutoff => self.filter.lock().unwrap().cutoff,
+            CcTarget::FilterResonance => self.filter.lock().unwrap().resonance,
+            CcTarget::MasterCutoff => self.master_filter.lock().unwrap().lowpass_cutoff,
+            CcTarget::FormantMorph => self.filter.lock().unwrap().formant.morph,
+            CcTarget::Morph => self.morph.lock().unwrap().amount,
+        }
+    }
+
+    pub fn set_target(&self, target: CcTarget, value: f32) {
+        match target {
+            CcTarget::Volume => *self.volume.lock().unwrap() = value,
+            CcTarget::Osc2Volume => self.oscillators.lock().unwrap()[1].volume = value,
+            CcTarget::Osc2Detune => self.oscillators.lock().unwrap()[1].detune = value,
+            CcTarget::Osc1Fold => self.oscillators.lock().unwrap()[0].fold = value,
+            CcTarget::WavetablePosition => *self.wavetable_position.lock().unwrap() = value,
+            CcTarget::WavetableSweep => *self.wavetable_sweep.lock().unwrap() = value,
+            CcTarget::VoiceFilterEnvDepth => *self.voice_filter_env_depth.lock().unwrap() = value,
+            CcTarget::VoiceDrive => *self.voice_drive.lock().unwrap() = value,
+            CcTarget::FilterCutoff => self.filter.lock().unwrap().cutoff = value,
+            CcTarget::FilterResonance => self.filter.lock().unwrap().resonance = value,
+            CcTarget::MasterCutoff => self.master_filter.lock().unwrap().lowpass_cutoff = value,
+            CcTarget::FormantMorph => self.filter.lock().unwrap().formant.morph = value,
+            CcTarget::Morph => self.morph.lock().unwrap().amount = value,
+        }
+    }
+
+    // Ajustes del sonido que se comparten con los hilos de audio y de MIDI
+    fn shared_patch(&self) -> SharedPatch {
+        SharedPatch {
+            volume: self.volume.clone(),
+            oscillators: self.oscillators.clone(),
+            oscillator_count: self.oscillator_count.clone(),
+            wavetable_position: self.wavetable_position.clone(),
+            wavetable_sweep: self.wavetable_sweep.clone(),
+            additive_table: self.additive_table.clone(),
+            legato: self.legato.clone(),
+            legato_retrigger_amp: self.legato_retrigger_amp.clone(),
+            legato_retrigger_mod: self.legato_retrigger_mod.clone(),
+            quality: self.quality.clone(),
+            voice_drive: self.voice_drive.clone(),
+            voice_drive_velocity: self.voice_drive_velocity.clone(),
+            voice_filter_env_depth: self.voice_filter_env_depth.clone(),
+            vector: self.vector.clone(),
+            lfo: self.lfo.clone(),
+            mseg: self.mseg.clone(),
+            mod_matrix: self.mod_matrix.clone(),
+            filter: self.filter.clone(),
+            envelope: self.envelope.clone(),
+            filter_envelope: self.filter_envelope.clone(),
+            split: self.split.clone(),
+            unison: self.unison.clone(),
+            voice_pan: self.voice_pan.clone(),
+            paraphonic: self.paraphonic.clone(),
+            glide: self.glide.clone(),
+            master_filter: self.master_filter.clone(),
+            fx: self.fx.clone(),
+            saturation: self.saturation.clone(),
+        }
+    }
+}
+
+// Referencias a los ajustes del sonido con las que el hilo de audio y el de MIDI montan
+// su copia del patch del motor, sin bloquear la configuración entera
+struct SharedPatch {
+    volume: Arc<Mutex<f32>>,
+    oscillators: Arc<Mutex<[OscillatorSettings; MAX_OSCILLATORS]>>,
+    oscillator_count: Arc<Mutex<usize>>,
+    wavetable_position: Arc<Mutex<f32>>,
+    wavetable_sweep: Arc<Mutex<f32>>,
+    additive_table: Arc<Mutex<([f32; ADDITIVE_HARMONICS], Arc<Wavetable>)>>,
+    legato: Arc<Mutex<bool>>,
+    legato_retrigger_amp: Arc<Mutex<bool>>,
+    legato_retrigger_mod: Arc<Mutex<bool>>,
+    quality: Arc<Mutex<Quality>>,
+    voice_drive: Arc<Mutex<f32>>,
+    voice_drive_velocity: Arc<Mutex<f32>>,
+    voice_filter_env_depth: Arc<Mutex<f32>>,
+    vector: Arc<Mutex<VectorSettings>>,
+    lfo: Arc<Mutex<LfoSettings>>,
+    mseg: Arc<Mutex<MsegSettings>>,
+    mod_matrix: Arc<Mutex<ModMatrix>>,
+    filter: Arc<Mutex<FilterSettings>>,
+    envelope: Arc<Mutex<AdsrSettings>>,
+    filter_envelope: Arc<Mutex<AdsrSettings>>,
+    split: Arc<Mutex<SplitSettings>>,
+    unison: Arc<Mutex<UnisonSettings>>,
+    voice_pan: Arc<Mutex<VoicePanSettings>>,
+    paraphonic: Arc<Mutex<ParaphonicSettings>>,
+    glide: Arc<Mutex<GlideSettings>>,
+    master_filter: Arc<Mutex<MasterFilterSettings>>,
+    fx: Arc<Mutex<FxSettings>>,
+    saturation: Arc<Mutex<SaturationSettings>>,
+}
+
+impl SharedPatch {
+    // Copiar los ajustes actuales en `patch`. El rack solo se clona cuando cambia, para no
+    // reservar memoria en cada bloque, y la tabla aditiva llega a las voces ya calculada.
+    fn update(&self, patch: &mut Patch, voices: &mut VoiceManager) {
+        patch.volume = *self.volume.lock().unwrap();
+        patch.oscillators = *self.oscillators.lock().unwrap();
+        patch.oscillator_count = *self.oscillator_count.lock().unwrap();
+        patch.wavetable_position = *self.wavetable_position.lock().unwrap();
+        patch.wavetable_sweep = *self.wavetable_sweep.lock().unwrap();
+        {
+            let (levels, table) = &*self.additive_table.lock().unwrap();
+            patch.harmonic_levels = *levels;
+            voices.set_additive_table(*levels, table);
+        }
+        patch.legato = *self.legato.lock().unwrap();
+        patch.legato_retrigger_amp = *self.legato_retrigger_amp.lock().unwrap();
+        patch.legato_retrigger_mod = *self.legato_retrigger_mod.lock().unwrap();
+        patch.quality = *self.quality.lock().unwrap();
+        patch.voice_drive = *self.voice_drive.lock().unwrap();
+        patch.voice_drive_velocity = *self.voice_drive_velocity.lock().unwrap();
+        patch.voice_filter_env_depth = *self.voice_filter_env_depth.lock().unwrap();
+        patch.vector = *self.vector.lock().unwrap();
+        patch.lfo = *self.lfo.lock().unwrap();
+        patch.mseg = *self.mseg.lock().unwrap();
+        patch.mod_matrix = *self.mod_matrix.lock().unwrap();
+        patch.filter = *self.filter.lock().unwrap();
+        patch.envelope = *self.envelope.lock().unwrap();
+        patch.filter_envelope = *self.filter_envelope.lock().unwrap();
+        patch.split = *self.split.lock().unwrap();
+        patch.unison = *self.unison.lock().unwrap();
+        patch.voice_pan = *self.voice_pan.lock().unwrap();
+        patch.paraphonic = *self.paraphonic.lock().unwrap();
+        patch.glide = *self.glide.lock().unwrap();
+        patch.master_filter = *self.master_filter.lock().unwrap();
+        patch.saturation = *self.saturation.lock().unwrap();
+        // Las particiones de la reverb no cuentan al comparar los ajustes, pero también
+        // tienen que llegar al rack cuando la interfaz las recalcula
+        let fx = self.fx.lock().unwrap();
+        let kernel_changed = match (&patch.fx.reverb.kernel, &fx.reverb.kernel) {
+            (Some(current), Some(new)) => !Arc::ptr_eq(current, new),
+            (None, None) => false,
+            _ => true,
+        };
+        if kernel_changed || patch.fx != *fx {
+            patch.fx.clone_from(&fx);
+        }
+    }
+}
+
+// Distribución de la interfaz
+#[derive(Clone, Copy, PartialEq)]
+enum GuiLayout {
+    // Todos los controles de edición
+    Standard,
+    // Página de actuación con controles grandes para pantallas táctiles
+    Performance,
+}
+
+// Nota o acorde que suena al seleccionar un preset
+pub struct Audition {
+    pub enabled: bool,
+    pub note: u8,
+    pub velocity: f32,
+    // Tocar una tríada mayor sobre la nota en lugar de la nota sola
+    pub chord: bool,
+}
+
+impl Default for Audition {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            note: 60,
+            velocity: 0.8,
+            chord: false,
         }
     }
 }
 
+// Acción que espera confirmación porque hay cambios sin guardar
+enum PendingAction {
+    LoadPreset(String),
+    Quit,
+}
+
 // Estructura principal de la aplicación
 pub struct SynthApp {
     config: Arc<Mutex<SynthConfig>>,
-    active_notes: Arc<Mutex<HashMap<u8, Note>>>,
+    // Voces del motor, las mismas para el MIDI, el teclado en pantalla y la audición
+    voices: Arc<Mutex<VoiceManager>>,
     sample_rate: Arc<Mutex<f32>>,
     stream_handle: Option<cpal::Stream>,
-    midi_connection: Option<MidiInputConnection<()>>,
+    // Una conexión por cada entrada MIDI abierta
+    midi_connections: Vec<MidiInputConnection<()>>,
+    // Manejador compartido por las entradas MIDI y el reproductor de archivos
+    midi_handler: Option<Arc<Mutex<dyn FnMut(&[u8]) + Send>>>,
+    midi_file_path: String,
+    // Sigue en true mientras suena el archivo; ponerlo a false lo detiene
+    midi_file_playing: Option<Arc<Mutex<bool>>>,
+    midi_input_ports: Vec<String>,
+    // Entradas que se abren al conectar, en el mismo orden que `midi_input_ports`
+    midi_input_selected: Vec<bool>,
+    preset_name: String,
+    preset_snapshot: Preset,
+    available_presets: Vec<String>,
+    // Metadatos de los presets de la carpeta para buscar y filtrar en el navegador
+    preset_library: Vec<(String, PresetMetadata)>,
+    preset_search: String,
+    // Categoría por la que se filtra; sin valor se muestran todas
+    preset_category: Option<String>,
+    pending_action: Option<PendingAction>,
+    allowed_to_close: bool,
+    window_title: String,
+    recording_name: String,
+    midi_output_ports: Vec<String>,
+    midi_thru_port: usize,
+    midi_out_port: usize,
+    // Canal cuyo ajuste de velocidad se está editando (0-15)
+    trim_channel: usize,
+    // Punto del MSEG seleccionado en el editor
+    mseg_point: usize,
+    // Ruta del WAV de respuesta al impulso que se está escribiendo, antes de cargarlo
+    impulse_path: String,
+    audition: Audition,
+    layout: GuiLayout,
+    // Parámetros asignados a cada eje del pad XY
+    xy_targets: (CcTarget, CcTarget),
+    // Momento en que se sueltan las voces de la audición en curso
+    audition_release_at: Option<Instant>,
+    // Teclas de la audición en curso, para soltarlas también en la salida MIDI
+    audition_keys: Vec<u8>,
+    // Registro de las voces que ha liberado el vigilante, para el panel de diagnóstico
+    stale_voices: StaleVoiceLog,
+    // Primera nota (siempre un do) del teclado en pantalla y tecla pulsada con el ratón
+    keyboard_first_note: u8,
+    keyboard_key: Option<u8>,
+    // Contexto de la interfaz para pedir un repintado desde otros hilos (MIDI)
+    repaint_ctx: Option<egui::Context>,
+    // Posición del morph aplicada por última vez al sonido
+    morph_applied: Option<f32>,
+    // Deshacer/rehacer de los cambios del sonido (Ctrl+Z / Ctrl+Y)
+    history: UndoHistory,
+    // Cuenta de los cambios del sonido (controles, deshacer, particiones de la reverb),
+    // para que el editor del plugin solo relea los ajustes cuando la cuenta avanza
+    sound_revision: u64,
+    // Dentro del editor del plugin: el host pone el audio, el MIDI y la ventana
+    plugin: bool,
 }
 
 impl SynthApp {
     pub fn new(
         config: Arc<Mutex<SynthConfig>>,
-        active_notes: Arc<Mutex<HashMap<u8, Note>>>,
+        voices: Arc<Mutex<VoiceManager>>,
+        sample_rate: Arc<Mutex<f32>>,
+    ) -> Self {
+        let mut app = Self::with_config(config, voices, sample_rate, false);
+        app.refresh_midi_inputs();
+        app.refresh_midi_outputs();
+        app.restore_session();
+        // El historial empieza con el sonido recuperado, sin poder deshacer la restauración
+        app.history = UndoHistory::new(app.config.lock().unwrap().snapshot(&Preset::default().name));
+        app
+    }
+
+    // Interfaz del editor del plugin: los mismos controles sobre los ajustes que le pasa
+    // el plugin, sin dispositivos de audio ni MIDI propios y sin sesión
+    pub fn new_plugin(config: Arc<Mutex<SynthConfig>>) -> Self {
+        Self::with_config(config, Arc::new(Mutex::new(VoiceManager::default())), Arc::new(Mutex::new(44100.0)), true)
+    }
+
+    fn with_config(
+        config: Arc<Mutex<SynthConfig>>,
+        voices: Arc<Mutex<VoiceManager>>,
         sample_rate: Arc<Mutex<f32>>,
+        plugin: bool,
     ) -> Self {
+        let preset_snapshot = config.lock().unwrap().snapshot(&Preset::default().name);
         Self {
             config,
-            active_notes,
+            voices,
             sample_rate,
             stream_handle: None,
-            midi_connection: None,
+            midi_connections: Vec::new(),
+            midi_handler: None,
+            midi_file_path: String::new(),
+            midi_file_playing: None,
+            midi_input_ports: Vec::new(),
+            midi_input_selected: Vec::new(),
+            preset_name: preset_snapshot.name.clone(),
+            preset_snapshot,
+            available_presets: list_presets(Path::new(PRESETS_DIR)),
+            preset_library: list_preset_metadata(Path::new(PRESETS_DIR)),
+            preset_search: String::new(),
+            preset_category: None,
+            pending_action: None,
+            allowed_to_close: false,
+            window_title: String::new(),
+            recording_name: String::from("toma"),
+            midi_output_ports: Vec::new(),
+            midi_thru_port: 0,
+            midi_out_port: 0,
+            trim_channel: 9,
+            mseg_point: 1,
+            impulse_path: preset_snapshot.fx.reverb.impulse_path.clone(),
+            audition: Audition::default(),
+            layout: GuiLayout::Standard,
+            xy_targets: (CcTarget::FilterCutoff, CcTarget::FilterResonance),
+            audition_release_at: None,
+            audition_keys: Vec::new(),
+            stale_voices: StaleVoiceLog::new(),
+            keyboard_first_note: 48,
+            keyboard_key: None,
+            repaint_ctx: None,
+            morph_applied: None,
+            history: UndoHistory::new(preset_snapshot.clone()),
+            sound_revision: 0,
+            plugin,
+        }
+    }
+
+    // Volver al audio, las entradas MIDI, el preset y el volumen de la última sesión,
+    // buscando cada equipo por su nombre
+    fn restore_session(&mut self) {
+        let session = Session::load(Path::new(SESSION_FILE));
+        
+        self.init_audio_hosts();
+        {
+            let mut config = self.config.lock().unwrap();
+            if let Some(i) = config.available_hosts.iter().position(|name| Some(name) == session.audio_host.as_ref()) {
+                config.host_index = i;
+            }
+        }
+        self.update_devices();
+        {
+            let mut config = self.config.lock().unwrap();
+            if let Some(i) = config.available_devices.iter().position(|name| Some(name) == session.audio_device.as_ref()) {
+                config.device_index = i;
+            }
+        }
+        self.update_sample_rates();
+        {
+            let mut config = self.config.lock().unwrap();
+            let saved_rate = session.sample_rate
+                .and_then(|rate| config.available_sample_rates.iter().position(|&r| r == rate));
+            if let Some(i) = saved_rate {
+                config.sample_rate_index = i;
+                *self.sample_rate.lock().unwrap() = config.available_sample_rates[i] as f32;
+            }
+        }
+        
+        let selected: Vec<bool> = self.midi_input_ports.iter()
+            .map(|name| session.midi_inputs.contains(name))
+            .collect();
+        if selected.contains(&true) {
+            self.midi_input_selected = selected;
+        }
+        
+        if let Some(name) = session.last_preset.as_ref().filter(|name| self.available_presets.contains(name)) {
+            self.load_preset(name);
+        }
+        // El volumen es de la sesión: no cuenta como cambio del preset cargado
+        if let Some(volume) = session.volume {
+            *self.config.lock().unwrap().volume.lock().unwrap() = volume;
+            self.preset_snapshot.volume = volume;
+        }
+        // Dejar el archivo con lo que se ha podido recuperar, sin los equipos que ya no están
+        self.save_session();
+    }
+
+    // Hay cambios respecto al preset cargado o guardado por última vez
+    fn is_dirty(&self) -> bool {
+        self.config.lock().unwrap().snapshot(&self.preset_snapshot.name) != self.preset_snapshot
+    }
+
+    fn save_preset(&mut self) {
+        let preset = self.config.lock().unwrap().snapshot(&self.preset_name);
+        match preset.save(Path::new(PRESETS_DIR)) {
+            Ok(()) => {
+                println!("Preset guardado: {}", preset.name);
+                self.preset_snapshot = preset;
+                self.available_presets = list_presets(Path::new(PRESETS_DIR));
+                self.preset_library = list_preset_metadata(Path::new(PRESETS_DIR));
+                self.save_session();
+            }
+            Err(err) => eprintln!("Error al guardar el preset {}: {}", preset.name, err),
+        }
+    }
+
+    fn load_preset(&mut self, name: &str) {
+        match Preset::load(Path::new(PRESETS_DIR), name) {
+            Ok(preset) => {
+                self.config.lock().unwrap().apply_preset(&preset);
+                self.preset_name = preset.name.clone();
+                self.impulse_path = preset.fx.reverb.impulse_path.clone();
+                self.preset_snapshot = preset;
+                self.save_session();
+                if self.audition.enabled {
+                    self.start_audition();
+                }
+            }
+            Err(err) => eprintln!("Error al cargar el preset {}: {}", name, err),
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(state) = self.history.undo() {
+            self.apply_history_state(&state);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(state) = self.history.redo() {
+            self.apply_history_state(&state);
+        }
+    }
+
+    fn apply_history_state(&mut self, state: &Preset) {
+        self.config.lock().unwrap().apply_preset(state);
+        self.impulse_path = state.fx.reverb.impulse_path.clone();
+        self.sound_revision += 1;
+    }
+
+    pub fn sound_revision(&self) -> u64 {
+        self.sound_revision
+    }
+
+    // Cargar el preset A o B del morph; el sonido se recalcula en el siguiente repintado
+    fn load_morph_preset(&mut self, name: &str, is_b: bool) {
+        match Preset::load(Path::new(PRESETS_DIR), name) {
+            Ok(preset) => {
+                let morph = self.config.lock().unwrap().morph.clone();
+                let mut morph = morph.lock().unwrap();
+                if is_b {
+                    morph.b = Some(preset);
+                } else {
+                    morph.a = Some(preset);
+                }
+                self.morph_applied = None;
+            }
+            Err(err) => eprintln!("Error al cargar el preset {}: {}", name, err),
+        }
+    }
+
+    // Aplicar el sonido intermedio cuando el control de morph se ha movido, desde la
+    // interfaz, un CC o el pad XY
+    fn apply_morph(&mut self) {
+        let morph = self.config.lock().unwrap().morph.clone();
+        let preset = {
+            let morph = morph.lock().unwrap();
+            if self.morph_applied == Some(morph.amount) {
+                return;
+            }
+            self.morph_applied = Some(morph.amount);
+            morph.current()
+        };
+        if let Some(preset) = preset {
+            self.config.lock().unwrap().apply_preset(&preset);
+            self.impulse_path = preset.fx.reverb.impulse_path.clone();
+        }
+    }
+
+    // Tocar la nota de audición con el sonido recién cargado
+    fn start_audition(&mut self) {
+        let config = self.config.lock().unwrap();
+        if !config.running {
+            return;
+        }
+        
+        let intervals: &[u8] = if self.audition.chord { &[0, 4, 7] } else { &[0] };
+        // Las teclas de la audición suenan por las mismas voces que el MIDI
+        let patch = Patch::from(&config.snapshot(&self.preset_name));
+        let mut voices = self.voices.lock().unwrap();
+        // Soltar la audición anterior para que no se acumulen voces al navegar rápido
+        for key in self.audition_keys.drain(..) {
+            voices.note_off(key);
+            send_audition_key(&config, key, None);
+        }
+        for interval in intervals {
+            let key = self.audition.note.saturating_add(*interval).min(127);
+            voices.note_on(key, self.audition.velocity, &patch);
+            send_audition_key(&config, key, Some(self.audition.velocity));
+            self.audition_keys.push(key);
+        }
+        self.audition_release_at = Some(Instant::now() + AUDITION_LENGTH);
+    }
+    
+    fn release_audition(&mut self, ctx: &egui::Context) {
+        let Some(release_at) = self.audition_release_at else {
+            return;
+        };
+        let now = Instant::now();
+        if now >= release_at {
+            let config = self.config.lock().unwrap();
+            let mut voices = self.voices.lock().unwrap();
+            for key in self.audition_keys.drain(..) {
+                voices.note_off(key);
+                send_audition_key(&config, key, None);
+            }
+            self.audition_release_at = None;
+        } else {
+            ctx.request_repaint_after(release_at - now);
+        }
+    }
+    
+    // Página de actuación: pad XY, botones de onda grandes y deslizadores amplios
+    fn show_performance_page(&mut self, ui: &mut egui::Ui) {
+        let config = self.config.clone();
+        ui.spacing_mut().slider_width = PERFORMANCE_SLIDER_WIDTH;
+        ui.spacing_mut().interact_size.y = PERFORMANCE_CONTROL_HEIGHT;
+        
+        ui.horizontal(|ui| {
+            let running = config.lock().unwrap().running;
+            let label = if running { "Detener" } else { "Iniciar" };
+            if ui.add(egui::Button::new(egui::RichText::new(label).size(24.0))
+                .min_size(egui::vec2(160.0, PERFORMANCE_CONTROL_HEIGHT)))
+                .clicked()
+            {
+                if running {
+                    self.stop_synth();
+                } else {
+                    self.start_synth();
+                }
+            }
+        });
+        
+        ui.add_space(10.0);
+        
+        ui.horizontal(|ui| {
+            // Pad XY con un parámetro asignable por eje
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    target_selector(ui, "xy_target_x", "X", &mut self.xy_targets.0);
+                    target_selector(ui, "xy_target_y", "Y", &mut self.xy_targets.1);
+                });
+                
+                let (x_target, y_target) = self.xy_targets;
+                let mut position = {
+                    let config = config.lock().unwrap();
+                    egui::vec2(
+                        x_target.normalize(config.target_value(x_target)),
+                        y_target.normalize(config.target_value(y_target)),
+                    )
+                };
+                if xy_pad(ui, XY_PAD_SIZE, &mut position).changed() {
+                    let config = config.lock().unwrap();
+                    config.set_target(x_target, x_target.denormalize(position.x));
+                    config.set_target(y_target, y_target.denormalize(position.y));
+                }
+            });
+            
+            ui.add_space(20.0);
+            
+            ui.vertical(|ui| {
+                // Onda de todos los osciladores activos
+                ui.label(egui::RichText::new("Onda").size(20.0));
+                let oscillators = config.lock().unwrap().oscillators.clone();
+                let count = *config.lock().unwrap().oscillator_count.lock().unwrap();
+                let current_wave = oscillators.lock().unwrap()[0].wave_type;
+                ui.horizontal_wrapped(|ui| {
+                    for wave_type in WaveType::ALL {
+                        let selected = current_wave == wave_type;
+                        let response = ui.add(egui::Button::new("")
+                            .min_size(egui::vec2(PERFORMANCE_CONTROL_HEIGHT * 2.0, PERFORMANCE_CONTROL_HEIGHT * 2.0))
+                            .selected(selected))
+                            .on_hover_text(wave_type.as_str());
+                        if response.clicked() {
+                            for settings in oscillators.lock().unwrap()[..count].iter_mut() {
+                                settings.wave_type = wave_type;
+                            }
+                        }
+                        draw_wave_icon(ui.painter(), response.rect.shrink(8.0), wave_type, selected);
+                    }
+                });
+                
+                ui.add_space(20.0);
+                
+                for target in [CcTarget::Volume, CcTarget::WavetablePosition, CcTarget::Osc1Fold, CcTarget::Morph] {
+                    ui.label(egui::RichText::new(target.as_str()).size(20.0));
+                    let config = config.lock().unwrap();
+                    let mut value = target.normalize(config.target_value(target));
+                    if ui.add(egui::widgets::Slider::new(&mut value, 0.0..=1.0).show_value(false)).changed() {
+                        config.set_target(target, target.denormalize(value));
+                    }
+                    ui.add_space(10.0);
+                }
+            });
+        });
+    }
+    
+    // Teclado en pantalla: toca por el mismo camino que la entrada MIDI (y sale por la
+    // salida MIDI) y marca las teclas que están sonando, vengan de donde vengan
+    fn show_virtual_keyboard(&mut self, ui: &mut egui::Ui) {
+        let mut held = [false; 128];
+        {
+            let voices = self.voices.lock().unwrap();
+            for (key, held) in held.iter_mut().enumerate() {
+                *held = voices.is_held(key as u8);
+            }
+        }
+        
+        ui.horizontal(|ui| {
+            if ui.button("Pánico").on_hover_text("Silenciar todo (Esc)").clicked() {
+                self.panic();
+            }
+            ui.separator();
+            ui.label("Teclado");
+            if ui.button("<").clicked() {
+                self.keyboard_first_note = self.keyboard_first_note.saturating_sub(12);
+            }
+            ui.label(note_name(self.keyboard_first_note));
+            if ui.button(">").clicked() {
+                self.keyboard_first_note = (self.keyboard_first_note + 12).min(KEYBOARD_MAX_FIRST_NOTE);
+            }
+        });
+        let pressed = piano_keyboard(ui, self.keyboard_first_note, KEYBOARD_OCTAVES, &held);
+        
+        let key = pressed.map(|(key, _)| key);
+        if key != self.keyboard_key {
+            let handler = self.midi_handler();
+            let midi_out = self.config.lock().unwrap().midi_out.clone();
+            if let Some(previous) = self.keyboard_key {
+                (*handler.lock().unwrap())(&[0x80, previous, 0]);
+                midi_out.lock().unwrap().note_off(previous);
+            }
+            if let Some((key, velocity)) = pressed {
+                let value = (velocity * 127.0).round().max(1.0) as u8;
+                (*handler.lock().unwrap())(&[0x90, key, value]);
+                midi_out.lock().unwrap().note_on(key, velocity);
+            }
+            self.keyboard_key = key;
+        }
+    }
+    
+    // Para notas colgadas: silencio inmediato de todas las voces (también las de la
+    // audición), fuentes de modulación a cero (pedales, CC, aftertouch, bend), colas de los
+    // efectos vaciadas y lo mismo en el equipo conectado a la salida MIDI
+    fn panic(&mut self) {
+        println!("Pánico: silenciando todas las voces");
+        // All Sound Off por el mismo camino que el MIDI: corta las voces (también las de
+        // la audición), olvida las teclas del legato y del vigilante y vacía los efectos
+        let handler = self.midi_handler();
+        (*handler.lock().unwrap())(&[0xB0, ALL_SOUND_OFF_CC, 0]);
+        self.keyboard_key = None;
+        
+        self.audition_keys.clear();
+        self.audition_release_at = None;
+        
+        let config = self.config.lock().unwrap();
+        // El rango del bend y la transposición de los RPN se conservan
+        self.voices.lock().unwrap().reset_mod_sources();
+        config.midi_out.lock().unwrap().panic();
+    }
+    
+    fn export_recording(&self) {
+        let recorder = self.config.lock().unwrap().midi_recorder.clone();
+        let name = self.recording_name.trim();
+        match recorder.lock().unwrap().export(Path::new(RECORDINGS_DIR), name) {
+            Ok(path) => println!("Grabación MIDI exportada: {}", path.display()),
+            Err(err) => eprintln!("Error al exportar la grabación {}: {}", name, err),
+        }
+    }
+
+    fn run_pending_action(&mut self, ctx: &egui::Context, action: PendingAction) {
+        match action {
+            PendingAction::LoadPreset(name) => self.load_preset(&name),
+            PendingAction::Quit => {
+                self.allowed_to_close = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
         }
     }
 
@@ -258,11 +1122,11 @@ impl SynthApp {
         let config_clone;
         let host_index;
         let device_index;
-        let volume;
-        let wave_type1;
-        let wave_type2;
-        let osc2_volume;
-        let osc2_detune;
+        let buffer_size;
+        let shared_patch;
+        let stats;
+        let audio_clock;
+        let voice_watchdog;
         
         {
             let config = self.config.lock().unwrap();
@@ -273,11 +1137,11 @@ impl SynthApp {
             config_clone = config.selected_config.clone();
             host_index = config.host_index;
             device_index = config.device_index;
-            volume = config.volume.clone();
-            wave_type1 = config.wave_type1.clone();
-            wave_type2 = config.wave_type2.clone();
-            osc2_volume = config.osc2_volume.clone();
-            osc2_detune = config.osc2_detune.clone();
+            buffer_size = config.buffer_size;
+            shared_patch = config.shared_patch();
+            stats = config.stats.clone();
+            audio_clock = config.audio_clock.clone();
+            voice_watchdog = config.voice_watchdog.clone();
         }
         
         // Obtener el host seleccionado
@@ -316,7 +1180,7 @@ impl SynthApp {
             buffer_size: if host.id().name() == "ASIO" {
                 cpal::BufferSize::Default // ASIO maneja su propio tamaño de buffer
             } else {
-                cpal::BufferSize::Fixed(512)
+                cpal::BufferSize::Fixed(buffer_size)
             },
         };
         println!("Configuración del stream:");
@@ -325,127 +1189,68 @@ impl SynthApp {
         println!("  Tamaño del buffer: {:?}", stream_config.buffer_size);
         
         // Clonar referencias para el callback
-        let active_notes = self.active_notes.clone();
+        let voices = self.voices.clone();
         let sample_rate_shared = self.sample_rate.clone();
+        // Copia del patch con la que renderiza el hilo de audio
+        let mut patch = Patch::default();
+        // Bloque estéreo intercalado del motor, que solo crece si el dispositivo pide más frames
+        let mut block = Vec::new();
         
-        // Tamaño del buffer de audio para reducir las operaciones de bloqueo
-        const BUFFER_SIZE: usize = 64;
+        // Render en f32, común a todos los formatos de muestra del dispositivo
+        let mut render = move |data: &mut [f32]| {
+            let started = Instant::now();
+            let channels = stream_config.channels as usize;
+            let frames = data.len() / channels;
+            block.resize(frames * 2, 0.0);
+            let current_sample_rate = *sample_rate_shared.lock().unwrap();
+            *audio_clock.lock().unwrap() += frames as u64;
+            
+            // Adquirir el bloqueo una vez por buffer
+            let mut voices = voices.lock().unwrap();
+            if voices.sample_rate() != current_sample_rate {
+                voices.set_sample_rate(current_sample_rate);
+            }
+            // Los cambios de la interfaz llegan también a las notas que ya suenan
+            shared_patch.update(&mut patch, &mut voices);
+            voices.apply_patch(&patch);
+            let stolen = voices.render(patch.volume, &mut block);
+            // Vigilar voces colgadas por Note Off perdidos
+            voices.check_watchdog(&voice_watchdog);
+            let active_voices = voices.active_voices();
+            drop(voices);
+            
+            // Repartir cada frame estéreo entre los canales de salida
+            for (frame, mix) in data.chunks_mut(channels).zip(block.chunks_exact(2)) {
+                for (c, channel) in frame.iter_mut().enumerate() {
+                    *channel = crate::audio::stereo_channel_sample((mix[0], mix[1]), c, channels);
+                }
+            }
+            
+            stats.add_stolen(stolen);
+            stats.record_callback(started.elapsed(), frames, current_sample_rate, active_voices, block_peak(&block));
+        };
         
         // Crear stream de audio
         let stream = match sample_format {
-            cpal::SampleFormat::I32 => device.build_output_stream(
-                &stream_config,
-                move |data: &mut [i32], _: &cpal::OutputCallbackInfo| {
-                    // Adquirir el bloqueo una vez por buffer
-                    let mut notes_guard = active_notes.lock().unwrap();
-                    let current_sample_rate = *sample_rate_shared.lock().unwrap();
-                    let current_volume = *volume.lock().unwrap();
-                    let current_osc2_volume = *osc2_volume.lock().unwrap();
-                    let current_osc2_detune = *osc2_detune.lock().unwrap();
-                    
-                    // Actualizar las frecuencias de muestreo si es necesario
-                    for note in notes_guard.values_mut() {
-                        if note.sample_rate != current_sample_rate {
-                            note.sample_rate = current_sample_rate;
-                            note.update_frequency(note.frequency);
-                        }
-                        note.osc2.volume = current_osc2_volume;
-                        note.osc2.detune = current_osc2_detune;
-                    }
-                    
-                    let channels = stream_config.channels as usize;
-                    
-                    // Procesar el audio en bloques
-                    for chunk in data.chunks_mut(channels * BUFFER_SIZE).filter(|c| !c.is_empty()) {
-                        // Generar un buffer temporal de muestras
-                        let mut temp_buffer = [0.0f32; BUFFER_SIZE];
-                        
-                        // Generar todas las muestras para este bloque
-                        for (i, frame) in chunk.chunks_mut(channels).enumerate() {
-                            if i < BUFFER_SIZE {
-                                let sample = {
-                                    let mut mix = 0.0;
-                                    
-                                    for note in notes_guard.values_mut() {
-                                        let envelope_amp = note.envelope.next_sample();
-                                        let sample = note.get_sample();
-                                        mix += sample * envelope_amp * current_volume;
-                                    }
-                                    
-                                    // Aplicar soft clip
-                                    temp_buffer[i] = crate::audio::soft_clip(mix);
-                                    (temp_buffer[i] * i32::MAX as f32) as i32
-                                };
-                                
-                                // Copiar la muestra a todos los canales
-                                for channel in frame.iter_mut() {
-                                    *channel = sample;
-                                }
-                            }
+            cpal::SampleFormat::I32 => {
+                // Buffer intermedio en f32, que solo crece si el dispositivo pide más frames
+                let mut buffer = Vec::new();
+                device.build_output_stream(
+                    &stream_config,
+                    move |data: &mut [i32], _: &cpal::OutputCallbackInfo| {
+                        buffer.resize(data.len(), 0.0);
+                        render(&mut buffer);
+                        for (sample, value) in data.iter_mut().zip(buffer.iter()) {
+                            *sample = (value * i32::MAX as f32) as i32;
                         }
-                    }
-                    
-                    // Eliminar las notas terminadas
-                    notes_guard.retain(|_, note| !note.envelope.is_finished());
-                },
-                |err| eprintln!("Error en el stream: {}", err),
-                None,
-            ),
+                    },
+                    |err| eprintln!("Error en el stream: {}", err),
+                    None,
+                )
+            }
             _ => device.build_output_stream(
                 &stream_config,
-                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                    // Adquirir el bloqueo una vez por buffer
-                    let mut notes_guard = active_notes.lock().unwrap();
-                    let current_sample_rate = *sample_rate_shared.lock().unwrap();
-                    let current_volume = *volume.lock().unwrap();
-                    let current_osc2_volume = *osc2_volume.lock().unwrap();
-                    let current_osc2_detune = *osc2_detune.lock().unwrap();
-                    
-                    // Actualizar las frecuencias de muestreo si es necesario
-                    for note in notes_guard.values_mut() {
-                        if note.sample_rate != current_sample_rate {
-                            note.sample_rate = current_sample_rate;
-                            note.update_frequency(note.frequency);
-                        }
-                        note.osc2.volume = current_osc2_volume;
-                        note.osc2.detune = current_osc2_detune;
-                    }
-                    
-                    let channels = stream_config.channels as usize;
-                    
-                    // Procesar el audio en bloques
-                    for chunk in data.chunks_mut(channels * BUFFER_SIZE).filter(|c| !c.is_empty()) {
-                        // Generar un buffer temporal de muestras
-                        let mut temp_buffer = [0.0f32; BUFFER_SIZE];
-                        
-                        // Generar todas las muestras para este bloque
-                        for (i, frame) in chunk.chunks_mut(channels).enumerate() {
-                            if i < BUFFER_SIZE {
-                                let sample = {
-                                    let mut mix = 0.0;
-                                    
-                                    for note in notes_guard.values_mut() {
-                                        let envelope_amp = note.envelope.next_sample();
-                                        let sample = note.get_sample();
-                                        mix += sample * envelope_amp * current_volume;
-                                    }
-                                    
-                                    // Aplicar soft clip
-                                    temp_buffer[i] = crate::audio::soft_clip(mix);
-                                    temp_buffer[i]
-                                };
-                                
-                                // Copiar la muestra a todos los canales
-                                for channel in frame.iter_mut() {
-                                    *channel = sample;
-                                }
-                            }
-                        }
-                    }
-                    
-                    // Eliminar las notas terminadas
-                    notes_guard.retain(|_, note| !note.envelope.is_finished());
-                },
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| render(data),
                 |err| eprintln!("Error en el stream: {}", err),
                 None,
             ),
@@ -469,369 +1274,2042 @@ impl SynthApp {
         self.config.lock().unwrap().running = false;
     }
     
-    fn connect_midi(&mut self) {
-        // Verificar si ya hay una conexión MIDI
-        if self.midi_connection.is_some() {
-            return;
+    // Manejador de los mensajes MIDI entrantes, común a todas las entradas y al
+    // reproductor de archivos. Se crea una sola vez para que compartan el estado (la
+    // copia del patch con la que suenan las notas nuevas).
+    fn midi_handler(&mut self) -> Arc<Mutex<dyn FnMut(&[u8]) + Send>> {
+        if let Some(handler) = &self.midi_handler {
+            return handler.clone();
         }
         
-        // Crear entrada MIDI
-        let midi_in = MidiInput::new("rust-synth").unwrap();
-        let ports = midi_in.ports();
-        
-        if ports.is_empty() {
-            println!("No se encontraron puertos MIDI disponibles");
-            return;
-        }
+        // Clonar referencias para el callback
+        let config = self.config.clone();
+        let voices = self.voices.clone();
+        let shared_patch = self.config.lock().unwrap().shared_patch();
+        let cc_mapper = self.config.lock().unwrap().cc_mapper.clone();
+        let midi_recorder = self.config.lock().unwrap().midi_recorder.clone();
+        let voice_watchdog = self.config.lock().unwrap().voice_watchdog.clone();
+        let midi_thru = self.config.lock().unwrap().midi_thru.clone();
+        let mseg = self.config.lock().unwrap().mseg.clone();
+        let velocity_trims = self.config.lock().unwrap().velocity_trims.clone();
+        let velocity_response = self.config.lock().unwrap().velocity_response.clone();
+        let fx = self.config.lock().unwrap().fx.clone();
+        let midi_clock = self.config.lock().unwrap().midi_clock.clone();
+        let follow_midi_clock = self.config.lock().unwrap().follow_midi_clock.clone();
+        let repaint_ctx = self.repaint_ctx.clone();
         
-        println!("Puertos MIDI disponibles:");
-        for (i, port) in ports.iter().enumerate() {
-            println!("  {}: {}", i, midi_in.port_name(port).unwrap_or_else(|_| "Puerto desconocido".into()));
-        }
+        // Copia del patch con la que se crean las voces de las notas nuevas
+        let mut patch = Patch::default();
         
-        // Clonar referencias para el callback
-        let active_notes = self.active_notes.clone();
-        let sample_rate_for_midi = self.sample_rate.clone();
-        let wave_type1 = self.config.lock().unwrap().wave_type1.clone();
-        let wave_type2 = self.config.lock().unwrap().wave_type2.clone();
-        let osc2_volume = self.config.lock().unwrap().osc2_volume.clone();
-        let osc2_detune = self.config.lock().unwrap().osc2_detune.clone();
-        
-        // Conectar al primer puerto MIDI disponible
-        let midi_connection = midi_in.connect(&ports[0], "midi-read", move |_timestamp, message, _| {
+        let handler = move |message: &[u8]| {
+            // Capturar todo lo que llega mientras la grabación está activa: las entradas MIDI,
+            // el teclado en pantalla y el archivo MIDI pasan todos por aquí
+            midi_recorder.lock().unwrap().record(message);
+            
+            // MIDI thru: reenviar el mensaje tal cual a la salida elegida
+            if let Some(output) = midi_thru.lock().unwrap().as_mut() {
+                if let Err(err) = output.send(message) {
+                    eprintln!("Error al reenviar MIDI: {}", err);
+                }
+            }
+            
+            // Reloj MIDI: con el seguimiento activo, el tempo medido pasa al delay y al MSEG
+            if message.len() == 1 {
+                let mut clock = midi_clock.lock().unwrap();
+                if clock.process(message) && message[0] == CLOCK_TICK && *follow_midi_clock.lock().unwrap() {
+                    if let Some(bpm) = clock.bpm() {
+                        // Redondeado a décimas para que la fluctuación no mueva el delay
+                        let bpm = ((bpm * 10.0).round() / 10.0).clamp(30.0, 300.0);
+                        fx.lock().unwrap().delay.bpm = bpm;
+                        mseg.lock().unwrap().bpm = bpm;
+                    }
+                }
+                return;
+            }
+            
+            // Channel Pressure: aftertouch de canal, fuente de la matriz. Es el único
+            // mensaje de dos bytes que se atiende.
+            if message.len() == 2 && message[0] & 0xF0 == 0xD0 {
+                voices.lock().unwrap().channel_pressure(message[1]);
+            }
+            
             if message.len() == 3 {
-                let mut notes = active_notes.lock().unwrap();
-                let current_sample_rate = *sample_rate_for_midi.lock().unwrap();
-                let current_wave_type1 = *wave_type1.lock().unwrap();
-                let current_wave_type2 = *wave_type2.lock().unwrap();
-                let current_osc2_volume = *osc2_volume.lock().unwrap();
-                let current_osc2_detune = *osc2_detune.lock().unwrap();
-                
-                match message[0] {
+                let mut voices = voices.lock().unwrap();
+                let channel = (message[0] & 0x0F) as usize;
+                
+                // Se aceptan todos los canales; cada uno con su ajuste de velocidad
+                match message[0] & 0xF0 {
                     0x90 => { // Note On
                         let note = message[1];
-                        let velocity = message[2] as f32 / 127.0;
+                        let velocity = velocity_response.lock().unwrap().apply(message[2] as f32 / 127.0);
+                        let velocity = if velocity > 0.0 { velocity_trims.lock().unwrap()[channel].apply(velocity) } else { 0.0 };
                         if velocity > 0.0 {
-                            let freq = midi_note_to_freq(note);
-                            println!("Nota ON - Número: {}, Frecuencia: {:.2} Hz, Velocidad: {:.2}", note, freq, velocity);
-                            let mut envelope = Envelope::new(current_sample_rate);
-                            envelope.set_adsr(0.01, 0.1, 0.7, 0.3);
-                            envelope.set_velocity(velocity);
-                            envelope.note_on();
-                            
-                            let mut new_note = Note::new(freq, envelope, current_sample_rate, current_wave_type1, current_wave_type2);
-                            new_note.osc2.volume = current_osc2_volume;
-                            new_note.osc2.detune = current_osc2_detune;
-                            notes.insert(note, new_note);
+                            voice_watchdog.key_down(note);
+                            println!("Nota ON - Número: {}, Frecuencia: {:.2} Hz, Velocidad: {:.2}", note, midi_note_to_freq(note), velocity);
+                            // El motor reparte la nota con el sonido actual: legato, glide,
+                            // zona del teclado, unísono y panorama de la voz
+                            shared_patch.update(&mut patch, &mut voices);
+                            voices.note_on(note, velocity, &patch);
                         } else {
                             println!("Nota OFF (velocity 0) - Número: {}", note);
-                            if let Some(note_data) = notes.get_mut(&note) {
-                                note_data.envelope.note_off();
-                            }
+                            voice_watchdog.key_up(note);
+                            voices.note_off(note);
                         }
                     },
                     0x80 => { // Note Off
                         let note = message[1];
                         println!("Nota OFF - Número: {}", note);
-                        if let Some(note_data) = notes.get_mut(&note) {
-                            note_data.envelope.note_off();
+                        voice_watchdog.key_up(note);
+                        voices.note_off(note);
+                    },
+                    0xB0 if message[1] == ALL_NOTES_OFF_CC => {
+                        voice_watchdog.release_all();
+                        voices.all_notes_off();
+                    },
+                    0xB0 if message[1] == ALL_SOUND_OFF_CC => {
+                        // También vacía las colas del rack de efectos
+                        voice_watchdog.release_all();
+                        voices.reset();
+                    },
+                    0xB0 => { // Control Change
+                        // Con el pedal de sustain pisado el vigilante no libera voces
+                        if message[1] == SUSTAIN_PEDAL_CC {
+                            voice_watchdog.set_sustain_pedal(message[2] >= 64);
+                        }
+                        // Los CC de los RPN (rango del pitch bend, transposición) no se
+                        // asignan; cualquier otro puede ser fuente de la matriz de modulación
+                        let is_rpn = voices.control_change(message[1], message[2]);
+                        let mapped = if is_rpn { None } else { cc_mapper.lock().unwrap().process(message[1], message[2]) };
+                        if let Some((target, value)) = mapped {
+                            // Soltar las voces antes de bloquear la configuración, que la interfaz
+                            // bloquea antes que las voces. El morph lo aplica la interfaz al repintarse.
+                            drop(voices);
+                            config.lock().unwrap().set_target(target, target.denormalize(value));
                         }
                     },
+                    0xA0 => { // Polyphonic Key Pressure: presión propia de la voz de esa tecla
+                        voices.poly_pressure(message[1], message[2]);
+                    },
+                    0xE0 => { // Pitch Bend, con el rango que fije el RPN 0,0
+                        voices.pitch_bend(((message[2] as u16) << 7) | message[1] as u16);
+                    },
                     _ => (),
                 }
+                
+                // La actividad MIDI cambia las notas activas o los parámetros mostrados
+                if let Some(ctx) = &repaint_ctx {
+                    ctx.request_repaint();
+                }
             }
-        }, ()).unwrap();
-        
-        println!("Conexión MIDI establecida");
-        
-        // Guardar la conexión MIDI
-        self.midi_connection = Some(midi_connection);
+        };
+        let handler: Arc<Mutex<dyn FnMut(&[u8]) + Send>> = Arc::new(Mutex::new(handler));
+        self.midi_handler = Some(handler.clone());
+        handler
     }
     
-    fn disconnect_midi(&mut self) {
-        // Cerrar la conexión MIDI
-        self.midi_connection = None;
-    }
-}
-
-impl eframe::App for SynthApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Inicializar hosts de audio si es necesario
-        if self.config.lock().unwrap().available_hosts.is_empty() {
-            self.init_audio_hosts();
+    // Abrir todas las entradas marcadas. Los mensajes de todas pasan por el mismo
+    // manejador, así que un teclado y un controlador de mandos tocan las mismas voces.
+    fn connect_midi(&mut self) {
+        // Verificar si ya hay una conexión MIDI
+        if !self.midi_connections.is_empty() {
+            return;
+        }
+        
+        let selected: Vec<usize> = self.midi_input_selected.iter()
+            .enumerate()
+            .filter(|(_, selected)| **selected)
+            .map(|(i, _)| i)
+            .collect();
+        if selected.is_empty() {
+            println!("No hay ninguna entrada MIDI seleccionada");
+            return;
+        }
+        
+        let handler = self.midi_handler();
+        for index in selected {
+            // Cada conexión consume su propia entrada
+            let midi_in = match MidiInput::new("rust-synth") {
+                Ok(midi_in) => midi_in,
+                Err(err) => {
+                    eprintln!("Error al crear la entrada MIDI: {}", err);
+                    continue;
+                }
+            };
+            let ports = midi_in.ports();
+            let Some(port) = ports.get(index) else {
+                println!("La entrada MIDI {} ya no está disponible", index);
+                continue;
+            };
+            let name = midi_in.port_name(port).unwrap_or_else(|_| "Puerto desconocido".into());
+            let handler = handler.clone();
+            // Cada puerto es un flujo aparte, con su propio estado implícito
+            let mut parser = MidiParser::new();
+            match midi_in.connect(port, "midi-read", move |_timestamp, bytes, _| {
+                let mut handler = handler.lock().unwrap();
+                parser.feed(bytes, |message| (*handler)(message));
+            }, ()) {
+                Ok(connection) => {
+                    println!("Conexión MIDI establecida: {}", name);
+                    self.midi_connections.push(connection);
+                }
+                Err(err) => eprintln!("Error al conectar la entrada MIDI {}: {}", name, err),
+            }
+        }
+        // Recordar las entradas abiertas para marcarlas en el próximo arranque
+        if !self.midi_connections.is_empty() {
+            self.save_session();
+        }
+    }
+    
+    // Reproducir un archivo MIDI estándar por el mismo camino que las entradas MIDI. Un
+    // hilo aparte reparte los eventos según las muestras generadas, así que el archivo
+    // sigue al audio y se para si se detiene el sintetizador.
+    fn play_midi_file(&mut self) {
+        self.stop_midi_file();
+        let file = match MidiFile::load(Path::new(&self.midi_file_path)) {
+            Ok(file) => file,
+            Err(err) => {
+                eprintln!("No se pudo leer el archivo MIDI {}: {}", self.midi_file_path, err);
+                return;
+            }
+        };
+        println!("Reproduciendo {} ({} eventos, {:.1} s)", self.midi_file_path, file.event_count(), file.duration());
+        
+        let handler = self.midi_handler();
+        let audio_clock = self.config.lock().unwrap().audio_clock.clone();
+        let sample_rate = self.sample_rate.clone();
+        let playing = Arc::new(Mutex::new(true));
+        self.midi_file_playing = Some(playing.clone());
+        thread::spawn(move || {
+            let mut player = MidiFilePlayer::new(file);
+            let start = *audio_clock.lock().unwrap();
+            while *playing.lock().unwrap() && !player.is_finished() {
+                let elapsed = audio_clock.lock().unwrap().saturating_sub(start);
+                let time = elapsed as f64 / *sample_rate.lock().unwrap() as f64;
+                player.advance_to(time, |message| (*handler.lock().unwrap())(message));
+                thread::sleep(MIDI_FILE_POLL_INTERVAL);
+            }
+            player.stop(|message| (*handler.lock().unwrap())(message));
+            *playing.lock().unwrap() = false;
+        });
+    }
+    
+    fn stop_midi_file(&mut self) {
+        if let Some(playing) = self.midi_file_playing.take() {
+            *playing.lock().unwrap() = false;
+        }
+    }
+    
+    fn is_midi_file_playing(&self) -> bool {
+        self.midi_file_playing.as_ref().is_some_and(|playing| *playing.lock().unwrap())
+    }
+    
+    // Guarda en el archivo de sesión el audio, las entradas MIDI, el volumen, el último
+    // preset y los ajustes de velocidad
+    fn save_session(&self) {
+        // El plugin no tiene equipos propios que recordar y no pisa la sesión de la aplicación
+        if self.plugin {
+            return;
+        }
+        let session = {
+            let config = self.config.lock().unwrap();
+            Session {
+                velocity_trims: *config.velocity_trims.lock().unwrap(),
+                velocity_response: *config.velocity_response.lock().unwrap(),
+                audio_host: config.available_hosts.get(config.host_index).cloned(),
+                audio_device: config.available_devices.get(config.device_index).cloned(),
+                sample_rate: config.available_sample_rates.get(config.sample_rate_index).copied(),
+                buffer_size: Some(config.buffer_size),
+                midi_inputs: self.midi_input_ports.iter()
+                    .zip(&self.midi_input_selected)
+                    .filter(|(_, selected)| **selected)
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+                volume: Some(*config.volume.lock().unwrap()),
+                stale_voice_timeout: Some(config.voice_watchdog.timeout()),
+                last_preset: self.available_presets.contains(&self.preset_snapshot.name)
+                    .then(|| self.preset_snapshot.name.clone()),
+            }
+        };
+        if let Err(err) = session.save(Path::new(SESSION_FILE)) {
+            eprintln!("Error al guardar la sesión: {}", err);
+        }
+    }
+    
+    fn disconnect_midi(&mut self) {
+        // Cerrar todas las conexiones MIDI
+        self.midi_connections.clear();
+    }
+    
+    // Volver a listar las entradas, conservando marcadas las que lo estaban por nombre.
+    // Sin nada marcado se marca la primera, como antes de poder elegir varias.
+    fn refresh_midi_inputs(&mut self) {
+        let ports = match MidiInput::new("rust-synth") {
+            Ok(midi_in) => midi_in.ports()
+                .iter()
+                .map(|port| midi_in.port_name(port).unwrap_or_else(|_| "Puerto desconocido".into()))
+                .collect(),
+            Err(err) => {
+                eprintln!("Error al listar las entradas MIDI: {}", err);
+                Vec::new()
+            }
+        };
+        let previous: Vec<&String> = self.midi_input_ports.iter()
+            .zip(&self.midi_input_selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(name, _)| name)
+            .collect();
+        let mut selected: Vec<bool> = ports.iter().map(|name| previous.contains(&name)).collect();
+        if !selected.contains(&true) {
+            if let Some(first) = selected.first_mut() {
+                *first = true;
+            }
+        }
+        self.midi_input_ports = ports;
+        self.midi_input_selected = selected;
+    }
+    
+    fn refresh_midi_outputs(&mut self) {
+        self.midi_output_ports = match MidiOutput::new("rust-synth-thru") {
+            Ok(midi_out) => midi_out.ports()
+                .iter()
+                .map(|port| midi_out.port_name(port).unwrap_or_else(|_| "Puerto desconocido".into()))
+                .collect(),
+            Err(err) => {
+                eprintln!("Error al listar las salidas MIDI: {}", err);
+                Vec::new()
+            }
+        };
+        if self.midi_thru_port >= self.midi_output_ports.len() {
+            self.midi_thru_port = 0;
+        }
+        if self.midi_out_port >= self.midi_output_ports.len() {
+            self.midi_out_port = 0;
+        }
+    }
+    
+    fn connect_midi_thru(&mut self) {
+        let midi_out = match MidiOutput::new("rust-synth-thru") {
+            Ok(midi_out) => midi_out,
+            Err(err) => {
+                eprintln!("Error al crear la salida MIDI: {}", err);
+                return;
+            }
+        };
+        let ports = midi_out.ports();
+        let Some(port) = ports.get(self.midi_thru_port) else {
+            println!("No se encontró el puerto de salida MIDI seleccionado");
+            return;
+        };
+        match midi_out.connect(port, "midi-thru") {
+            Ok(connection) => {
+                println!("MIDI thru conectado");
+                *self.config.lock().unwrap().midi_thru.lock().unwrap() = Some(connection);
+            }
+            Err(err) => eprintln!("Error al conectar el MIDI thru: {}", err),
+        }
+    }
+    
+    fn disconnect_midi_thru(&mut self) {
+        if let Some(connection) = self.config.lock().unwrap().midi_thru.lock().unwrap().take() {
+            connection.close();
+        }
+    }
+    
+    fn connect_midi_out(&mut self) {
+        let midi_out = self.config.lock().unwrap().midi_out.clone();
+        match midi_out.lock().unwrap().connect(self.midi_out_port) {
+            Ok(()) => println!("Salida MIDI conectada"),
+            Err(err) => eprintln!("Error al conectar la salida MIDI: {}", err),
+        }
+    }
+    
+    fn disconnect_midi_out(&mut self) {
+        self.config.lock().unwrap().midi_out.lock().unwrap().disconnect();
+    }
+}
+
+// Las voces de la audición no pasan por el manejador MIDI, así que sus teclas salen por
+// la salida MIDI y entran en la grabación desde aquí, igual que las del teclado en
+// pantalla o las del archivo MIDI. Sin velocidad es un Note Off.
+fn send_audition_key(config: &SynthConfig, key: u8, velocity: Option<f32>) {
+    let mut midi_out = config.midi_out.lock().unwrap();
+    let mut recorder = config.midi_recorder.lock().unwrap();
+    match velocity {
+        Some(velocity) => {
+            midi_out.note_on(key, velocity);
+            recorder.record(&[0x90, key, (velocity * 127.0).round().clamp(1.0, 127.0) as u8]);
+        }
+        None => {
+            midi_out.note_off(key);
+            recorder.record(&[0x80, key, 0]);
+        }
+    }
+}
+
+impl eframe::App for SynthApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.show(ctx);
+    }
+}
+
+impl SynthApp {
+    // Particiones de la reverb para la frecuencia de muestreo actual, tras cargar una
+    // respuesta, un preset o cambiar de frecuencia. Se calculan sin tener bloqueados los
+    // efectos, así que el hilo de audio solo recibe el Arc con el resultado
+    fn prepare_reverb(&mut self) {
+        let sample_rate = *self.sample_rate.lock().unwrap();
+        let fx = self.config.lock().unwrap().fx.clone();
+        let impulse = {
+            let fx = fx.lock().unwrap();
+            if !fx.reverb.needs_kernel(sample_rate) {
+                return;
+            }
+            fx.reverb.impulse.clone()
+        };
+        let kernel = ReverbKernel::new(impulse, sample_rate);
+        fx.lock().unwrap().reverb.set_kernel(kernel);
+        self.sound_revision += 1;
+    }
+
+    // Dibuja la interfaz en el contexto de la ventana propia o del editor del plugin
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if self.repaint_ctx.is_none() {
+            self.repaint_ctx = Some(ctx.clone());
+        }
+        
+        self.release_audition(ctx);
+        self.apply_morph();
+        self.prepare_reverb();
+        
+        // Atajo del botón de pánico, salvo mientras se escribe en un campo de texto
+        if ctx.memory(|memory| memory.focused().is_none())
+            && ctx.input_mut(|input| input.consume_key(egui::Modifiers::NONE, egui::Key::Escape)) {
+            self.panic();
+        }
+        
+        // Deshacer y rehacer, también con Ctrl+Mayús+Z, salvo mientras se escribe en un campo de texto
+        if ctx.memory(|memory| memory.focused().is_none()) {
+            let (undo, redo) = ctx.input_mut(|input| {
+                let redo = input.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z)
+                    || input.consume_key(egui::Modifiers::COMMAND, egui::Key::Y);
+                (input.consume_key(egui::Modifiers::COMMAND, egui::Key::Z), redo)
+            });
+            if undo {
+                self.undo();
+            } else if redo {
+                self.redo();
+            }
+        }
+        
+        // Registrar los cambios del sonido; mientras se arrastra un control todo el
+        // gesto cuenta como un solo paso
+        let editing = ctx.input(|input| input.pointer.any_down());
+        let state = self.config.lock().unwrap().snapshot(&Preset::default().name);
+        if self.history.record(state, editing) {
+            self.sound_revision += 1;
+        }
+        
+        // Inicializar hosts de audio si es necesario
+        if !self.plugin && self.config.lock().unwrap().available_hosts.is_empty() {
+            self.init_audio_hosts();
+        }
+        
+        // Título con asterisco si hay cambios sin guardar; la ventana del plugin es del host
+        let dirty = self.is_dirty();
+        let title = format!("Rust Synth - {}{}", self.preset_snapshot.name, if dirty { "*" } else { "" });
+        if !self.plugin && title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+        
+        // Al cerrar se guarda la sesión (el volumen cambia sin más aviso) y se pregunta
+        // antes si hay cambios sin guardar
+        let close_requested = !self.plugin && ctx.input(|i| i.viewport().close_requested());
+        if close_requested {
+            self.save_session();
+        }
+        if close_requested && dirty && !self.allowed_to_close {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.pending_action = Some(PendingAction::Quit);
+        }
+        
+        // Diálogo de cambios sin guardar
+        if self.pending_action.is_some() {
+            let mut choice = None;
+            egui::Window::new("Cambios sin guardar")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .show(ctx, |ui| {
+                    ui.label(format!("El preset \"{}\" tiene cambios sin guardar. ¿Guardarlos?", self.preset_snapshot.name));
+                    ui.horizontal(|ui| {
+                        if ui.button("Guardar").clicked() {
+                            choice = Some(true);
+                        }
+                        if ui.button("Descartar").clicked() {
+                            choice = Some(false);
+                        }
+                        if ui.button("Cancelar").clicked() {
+                            self.pending_action = None;
+                        }
+                    });
+                });
+            
+            if let Some(save) = choice {
+                if save {
+                    self.preset_name = self.preset_snapshot.name.clone();
+                    self.save_preset();
+                }
+                if let Some(action) = self.pending_action.take() {
+                    self.run_pending_action(ctx, action);
+                }
+            }
+        }
+        
+        // En el plugin las notas llegan por el MIDI del host
+        if !self.plugin {
+            egui::TopBottomPanel::bottom("virtual_keyboard").show(ctx, |ui| {
+                self.show_virtual_keyboard(ui);
+            });
         }
         
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Rust Synth");
+            ui.horizontal(|ui| {
+                ui.heading("Rust Synth");
+                ui.add_space(20.0);
+                ui.selectable_value(&mut self.layout, GuiLayout::Standard, "Edición");
+                ui.selectable_value(&mut self.layout, GuiLayout::Performance, "Actuación");
+            });
             ui.add_space(10.0);
             
-            // Configuración de audio
+            if self.layout == GuiLayout::Performance {
+                self.show_performance_page(ui);
+                return;
+            }
+            
+            // Presets
             ui.group(|ui| {
-                ui.heading("Configuración de Audio");
-                
-                // Obtener datos para mostrar en la UI
-                let host_text;
-                let device_text;
-                let rate_text;
-                let volume;
-                let wave_type1;
-                let wave_type2;
-                let available_hosts;
-                let available_devices;
-                let available_sample_rates;
-                let host_index;
-                let device_index;
-                let sample_rate_index;
+                ui.horizontal(|ui| {
+                    ui.heading("Presets");
+                    ui.add_space(20.0);
+                    if ui.add_enabled(self.history.can_undo(), egui::Button::new("Deshacer"))
+                        .on_hover_text("Ctrl+Z")
+                        .clicked() {
+                        self.undo();
+                    }
+                    if ui.add_enabled(self.history.can_redo(), egui::Button::new("Rehacer"))
+                        .on_hover_text("Ctrl+Y")
+                        .clicked() {
+                        self.redo();
+                    }
+                });
+                // Búsqueda por nombre y metadatos, y filtro por categoría
+                ui.horizontal(|ui| {
+                    ui.label("Buscar");
+                    ui.text_edit_singleline(&mut self.preset_search);
+                    let mut categories: Vec<String> = self.preset_library.iter()
+                        .map(|(_, metadata)| metadata.category.clone())
+                        .filter(|category| !category.is_empty())
+                        .collect();
+                    categories.sort();
+                    categories.dedup();
+                    egui::ComboBox::from_label("Categoría")
+                        .selected_text(self.preset_category.clone().unwrap_or_else(|| "Todas".to_string()))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.preset_category, None, "Todas");
+                            for category in categories {
+                                ui.selectable_value(&mut self.preset_category, Some(category.clone()), category);
+                            }
+                        });
+                });
                 
-                {
-                    let config = self.config.lock().unwrap();
-                    host_text = config.available_hosts.get(config.host_index)
-                        .cloned().unwrap_or_else(|| "Ninguno".to_string());
-                    device_text = config.available_devices.get(config.device_index)
-                        .cloned().unwrap_or_else(|| "Ninguno".to_string());
-                    rate_text = config.available_sample_rates.get(config.sample_rate_index)
-                        .map(|rate| format!("{} Hz", rate))
-                        .unwrap_or_else(|| "Ninguna".to_string());
-                    volume = config.volume.clone();
-                    wave_type1 = config.wave_type1.clone();
-                    wave_type2 = config.wave_type2.clone();
+                ui.horizontal(|ui| {
+                    let mut selected_preset = None;
+                    let found: Vec<&(String, PresetMetadata)> = self.preset_library.iter()
+                        .filter(|(name, metadata)| {
+                            self.preset_category.as_ref().map_or(true, |category| metadata.category == *category)
+                                && metadata.matches(name, &self.preset_search)
+                        })
+                        .collect();
+                    egui::ComboBox::from_label(format!("Cargar ({} de {})", found.len(), self.preset_library.len()))
+                        .selected_text(self.preset_snapshot.name.clone())
+                        .show_ui(ui, |ui| {
+                            for (name, metadata) in found {
+                                let response = ui.selectable_label(*name == self.preset_snapshot.name, name);
+                                let response = if metadata == &PresetMetadata::default() {
+                                    response
+                                } else {
+                                    response.on_hover_text(preset_info(metadata))
+                                };
+                                if response.clicked() {
+                                    selected_preset = Some(name.clone());
+                                }
+                            }
+                        });
                     
-                    // Clonar las colecciones para evitar problemas de préstamo
-                    available_hosts = config.available_hosts.clone();
-                    available_devices = config.available_devices.clone();
-                    available_sample_rates = config.available_sample_rates.clone();
-                    host_index = config.host_index;
-                    device_index = config.device_index;
-                    sample_rate_index = config.sample_rate_index;
-                }
+                    if let Some(name) = selected_preset {
+                        if dirty {
+                            self.pending_action = Some(PendingAction::LoadPreset(name));
+                        } else {
+                            self.load_preset(&name);
+                        }
+                    }
+                    
+                    ui.add_space(20.0);
+                    
+                    ui.label("Nombre");
+                    ui.text_edit_singleline(&mut self.preset_name);
+                    let can_save = is_valid_file_name(&self.preset_name);
+                    if ui.add_enabled(can_save, egui::Button::new("Guardar")).clicked() {
+                        self.save_preset();
+                    }
+                });
                 
-                // Selección de host
-                let mut host_changed = false;
-                let mut new_host_index = host_index;
+                // Metadatos del sonido actual, que se guardan con el preset
+                ui.collapsing("Información del preset", |ui| {
+                    let mut config = self.config.lock().unwrap();
+                    let metadata = &mut config.metadata;
+                    egui::Grid::new("preset_metadata").num_columns(2).show(ui, |ui| {
+                        ui.label("Autor");
+                        ui.text_edit_singleline(&mut metadata.author);
+                        ui.end_row();
+                        ui.label("Categoría");
+                        ui.text_edit_singleline(&mut metadata.category);
+                        ui.end_row();
+                        ui.label("Etiquetas");
+                        ui.add(egui::TextEdit::singleline(&mut metadata.tags).hint_text("separadas por comas"));
+                        ui.end_row();
+                        ui.label("Descripción");
+                        ui.text_edit_singleline(&mut metadata.description);
+                        ui.end_row();
+                    });
+                });
                 
-                egui::ComboBox::from_label("Host de Audio")
-                    .selected_text(host_text)
-                    .show_ui(ui, |ui| {
-                        for (i, host) in available_hosts.iter().enumerate() {
-                            let is_selected = i == host_index;
-                            if ui.selectable_label(is_selected, host).clicked() {
-                                new_host_index = i;
-                                host_changed = true;
-                            }
+                // Audición automática al seleccionar un preset
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.audition.enabled, "Audición automática");
+                    ui.add_enabled_ui(self.audition.enabled, |ui| {
+                        ui.label("Nota");
+                        ui.add(egui::widgets::Slider::new(&mut self.audition.note, 24..=108));
+                        ui.label("Velocidad");
+                        ui.add(egui::widgets::Slider::new(&mut self.audition.velocity, 0.0..=1.0));
+                        ui.checkbox(&mut self.audition.chord, "Acorde");
+                        if ui.button("Probar").clicked() {
+                            self.start_audition();
                         }
                     });
-                
-                if host_changed {
-                    let mut config = self.config.lock().unwrap();
-                    config.host_index = new_host_index;
-                    drop(config);
-                    self.update_devices();
+                });
+            });
+            
+            ui.add_space(10.0);
+            
+            // Morph entre dos presets con un solo control
+            ui.group(|ui| {
+                ui.heading("Morph de Presets");
+                let morph = self.config.lock().unwrap().morph.clone();
+                let (name_a, name_b) = {
+                    let morph = morph.lock().unwrap();
+                    let name = |preset: &Option<Preset>| preset.as_ref().map_or(String::from("-"), |p| p.name.clone());
+                    (name(&morph.a), name(&morph.b))
+                };
+                let mut selected = None;
+                ui.horizontal(|ui| {
+                    for (label, current, is_b) in [("A", &name_a, false), ("B", &name_b, true)] {
+                        egui::ComboBox::from_label(label)
+                            .selected_text(current.clone())
+                            .show_ui(ui, |ui| {
+                                for name in &self.available_presets {
+                                    if ui.selectable_label(name == current, name).clicked() {
+                                        selected = Some((name.clone(), is_b));
+                                    }
+                                }
+                            });
+                        ui.add_space(20.0);
+                    }
+                });
+                if let Some((name, is_b)) = selected {
+                    self.load_morph_preset(&name, is_b);
                 }
                 
-                // Selección de dispositivo
-                let mut device_changed = false;
-                let mut new_device_index = device_index;
+                // Los parámetros continuos se interpolan; los discretos cambian a mitad de recorrido
+                ui.horizontal(|ui| {
+                    ui.label("A");
+                    let mut amount = morph.lock().unwrap().amount;
+                    if ui.add(egui::widgets::Slider::new(&mut amount, 0.0..=1.0).show_value(false)).changed() {
+                        morph.lock().unwrap().amount = amount;
+                    }
+                    ui.label("B");
+                });
+            });
+            
+            ui.add_space(10.0);
+            
+            // La grabación y el audio los lleva el host cuando es un plugin
+            if !self.plugin {
+                // Grabación de la sesión MIDI
+                ui.group(|ui| {
+                    ui.heading("Grabación MIDI");
+                    let recorder = self.config.lock().unwrap().midi_recorder.clone();
+                    let (is_recording, event_count) = {
+                        let recorder = recorder.lock().unwrap();
+                        (recorder.is_recording(), recorder.event_count())
+                    };
                 
-                egui::ComboBox::from_label("Dispositivo de Salida")
-                    .selected_text(device_text)
-                    .show_ui(ui, |ui| {
-                        for (i, device) in available_devices.iter().enumerate() {
-                            let is_selected = i == device_index;
-                            if ui.selectable_label(is_selected, device).clicked() {
-                                new_device_index = i;
-                                device_changed = true;
+                    ui.horizontal(|ui| {
+                        if is_recording {
+                            if ui.button("Detener grabación").clicked() {
+                                recorder.lock().unwrap().stop();
                             }
+                        } else if ui.button("Grabar MIDI").clicked() {
+                            recorder.lock().unwrap().start();
+                        }
+                    
+                        ui.label(format!("Eventos: {}", event_count));
+                    
+                        ui.add_space(20.0);
+                    
+                        ui.label("Nombre");
+                        ui.text_edit_singleline(&mut self.recording_name);
+                        let can_export = !is_recording && event_count > 0 && is_valid_file_name(self.recording_name.trim());
+                        if ui.add_enabled(can_export, egui::Button::new("Exportar .mid")).clicked() {
+                            self.export_recording();
                         }
                     });
+                });
+            
+                ui.add_space(10.0);
+            
+                // Configuración de audio
+                ui.group(|ui| {
+                    ui.heading("Configuración de Audio");
                 
-                if device_changed {
-                    let mut config = self.config.lock().unwrap();
-                    config.device_index = new_device_index;
-                    drop(config);
-                    self.update_sample_rates();
-                }
+                    // Obtener datos para mostrar en la UI
+                    let host_text;
+                    let device_text;
+                    let rate_text;
+                    let volume;
+                    let available_hosts;
+                    let available_devices;
+                    let available_sample_rates;
+                    let host_index;
+                    let device_index;
+                    let sample_rate_index;
+                
+                    {
+                        let config = self.config.lock().unwrap();
+                        host_text = config.available_hosts.get(config.host_index)
+                            .cloned().unwrap_or_else(|| "Ninguno".to_string());
+                        device_text = config.available_devices.get(config.device_index)
+                            .cloned().unwrap_or_else(|| "Ninguno".to_string());
+                        rate_text = config.available_sample_rates.get(config.sample_rate_index)
+                            .map(|rate| format!("{} Hz", rate))
+                            .unwrap_or_else(|| "Ninguna".to_string());
+                        volume = config.volume.clone();
+                    
+                        // Clonar las colecciones para evitar problemas de préstamo
+                        available_hosts = config.available_hosts.clone();
+                        available_devices = config.available_devices.clone();
+                        available_sample_rates = config.available_sample_rates.clone();
+                        host_index = config.host_index;
+                        device_index = config.device_index;
+                        sample_rate_index = config.sample_rate_index;
+                    }
                 
-                // Selección de frecuencia de muestreo
-                let mut rate_changed = false;
-                let mut new_rate_index = sample_rate_index;
-                let mut new_rate = 0u32;
+                    // Selección de host
+                    let mut host_changed = false;
+                    let mut new_host_index = host_index;
                 
-                egui::ComboBox::from_label("Frecuencia de Muestreo")
-                    .selected_text(rate_text)
-                    .show_ui(ui, |ui| {
-                        for (i, rate) in available_sample_rates.iter().enumerate() {
-                            let is_selected = i == sample_rate_index;
-                            if ui.selectable_label(is_selected, format!("{} Hz", rate)).clicked() {
-                                new_rate_index = i;
-                                new_rate = *rate;
-                                rate_changed = true;
+                    egui::ComboBox::from_label("Host de Audio")
+                        .selected_text(host_text)
+                        .show_ui(ui, |ui| {
+                            for (i, host) in available_hosts.iter().enumerate() {
+                                let is_selected = i == host_index;
+                                if ui.selectable_label(is_selected, host).clicked() {
+                                    new_host_index = i;
+                                    host_changed = true;
+                                }
                             }
-                        }
-                    });
+                        });
                 
-                if rate_changed {
-                    let mut config = self.config.lock().unwrap();
-                    config.sample_rate_index = new_rate_index;
-                    drop(config);
-                    *self.sample_rate.lock().unwrap() = new_rate as f32;
-                }
-            });
+                    if host_changed {
+                        let mut config = self.config.lock().unwrap();
+                        config.host_index = new_host_index;
+                        drop(config);
+                        self.update_devices();
+                        self.save_session();
+                    }
+                
+                    // Selección de dispositivo
+                    let mut device_changed = false;
+                    let mut new_device_index = device_index;
+                
+                    egui::ComboBox::from_label("Dispositivo de Salida")
+                        .selected_text(device_text)
+                        .show_ui(ui, |ui| {
+                            for (i, device) in available_devices.iter().enumerate() {
+                                let is_selected = i == device_index;
+                                if ui.selectable_label(is_selected, device).clicked() {
+                                    new_device_index = i;
+                                    device_changed = true;
+                                }
+                            }
+                        });
+                
+                    if device_changed {
+                        let mut config = self.config.lock().unwrap();
+                        config.device_index = new_device_index;
+                        drop(config);
+                        self.update_sample_rates();
+                        self.save_session();
+                    }
+                
+                    // Selección de frecuencia de muestreo
+                    let mut rate_changed = false;
+                    let mut new_rate_index = sample_rate_index;
+                    let mut new_rate = 0u32;
+                
+                    egui::ComboBox::from_label("Frecuencia de Muestreo")
+                        .selected_text(rate_text)
+                        .show_ui(ui, |ui| {
+                            for (i, rate) in available_sample_rates.iter().enumerate() {
+                                let is_selected = i == sample_rate_index;
+                                if ui.selectable_label(is_selected, format!("{} Hz", rate)).clicked() {
+                                    new_rate_index = i;
+                                    new_rate = *rate;
+                                    rate_changed = true;
+                                }
+                            }
+                        });
+                
+                    if rate_changed {
+                        let mut config = self.config.lock().unwrap();
+                        config.sample_rate_index = new_rate_index;
+                        drop(config);
+                        *self.sample_rate.lock().unwrap() = new_rate as f32;
+                        self.save_session();
+                    }
+                
+                    // Más pequeño, menos latencia y más carga; se aplica al volver a iniciar
+                    let buffer_size = self.config.lock().unwrap().buffer_size;
+                    let mut new_buffer_size = buffer_size;
+                    egui::ComboBox::from_label("Tamaño del Buffer")
+                        .selected_text(format!("{} frames", buffer_size))
+                        .show_ui(ui, |ui| {
+                            for size in BUFFER_SIZES {
+                                ui.selectable_value(&mut new_buffer_size, size, format!("{} frames", size));
+                            }
+                        });
+                    if new_buffer_size != buffer_size {
+                        self.config.lock().unwrap().buffer_size = new_buffer_size;
+                        self.save_session();
+                    }
+                
+                    // Calidad del antialiasing, se puede cambiar en vivo
+                    let current_quality = *self.config.lock().unwrap().quality.lock().unwrap();
+                    let mut new_quality = current_quality;
+                    egui::ComboBox::from_label("Calidad")
+                        .selected_text(current_quality.as_str())
+                        .show_ui(ui, |ui| {
+                            for quality in Quality::ALL {
+                                ui.selectable_value(&mut new_quality, quality, quality.as_str());
+                            }
+                        });
+                    if new_quality != current_quality {
+                        *self.config.lock().unwrap().quality.lock().unwrap() = new_quality;
+                    }
+                });
             
-            ui.add_space(10.0);
+                ui.add_space(10.0);
+            }
             
             // Control de volumen y forma de onda en una sección separada
             ui.group(|ui| {
                 ui.heading("Controles de Sonido");
                 
-                // Oscilador 1
+                // Volumen general y número de osciladores por voz
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        ui.label("Volumen general");
+                        let mut volume_value = *self.config.lock().unwrap().volume.lock().unwrap();
+                        let volume_response = ui.add(egui::widgets::Slider::new(&mut volume_value, 0.0..=1.0)
+                            .show_value(true)
+                            .text(""));
+                        if volume_response.changed() {
+                            *self.config.lock().unwrap().volume.lock().unwrap() = volume_value;
+                        }
+                    });
+
+                    ui.add_space(20.0);
+
+                    ui.vertical(|ui| {
+                        ui.label("Osciladores");
+                        let mut count_value = *self.config.lock().unwrap().oscillator_count.lock().unwrap();
+                        let count_response = ui.add(egui::widgets::Slider::new(&mut count_value, 1..=MAX_OSCILLATORS)
+                            .show_value(true)
+                            .text(""));
+                        if count_response.changed() {
+                            *self.config.lock().unwrap().oscillator_count.lock().unwrap() = count_value;
+                        }
+                    });
+                });
+
+                ui.add_space(10.0);
+
+                let oscillator_count = *self.config.lock().unwrap().oscillator_count.lock().unwrap();
+                for i in 0..oscillator_count {
+                    ui.group(|ui| {
+                        ui.heading(format!("Oscilador {}", i + 1));
+                        let oscillators = self.config.lock().unwrap().oscillators.clone();
+                        let mut settings = oscillators.lock().unwrap()[i];
+                        let mut changed = false;
+
+                        ui.horizontal(|ui| {
+                            // Control de volumen
+                            ui.vertical(|ui| {
+                                ui.label("Volumen");
+                                changed |= ui.add(egui::widgets::Slider::new(&mut settings.volume, 0.0..=1.0)
+                                    .show_value(true)
+                                    .text(""))
+                                    .changed();
+                            });
+
+                            ui.add_space(20.0);
+
+                            // Control de detune
+                            ui.vertical(|ui| {
+                                ui.label("Detune (semitonos)");
+                                changed |= ui.add(egui::widgets::Slider::new(&mut settings.detune, -MAX_OSCILLATOR_DETUNE..=MAX_OSCILLATOR_DETUNE)
+                                    .show_value(true)
+                                    .text(""))
+                                    .changed();
+                            });
+
+                            ui.add_space(20.0);
+
+                            // Selector de octava
+                            ui.vertical(|ui| {
+                                ui.label("Octava");
+                                changed |= ui.add(egui::widgets::Slider::new(&mut settings.octave, -2..=2)
+                                    .show_value(true)
+                                    .text(""))
+                                    .changed();
+                            });
+
+                            ui.add_space(20.0);
+
+                            // Drift analógico
+                            ui.vertical(|ui| {
+                                ui.label("Drift");
+                                changed |= ui.add(egui::widgets::Slider::new(&mut settings.drift, 0.0..=1.0)
+                                    .show_value(true)
+                                    .text(""))
+                                    .changed();
+                            });
+
+                            ui.add_space(20.0);
+
+                            // Wavefolder
+                            ui.vertical(|ui| {
+                                ui.label("Plegado");
+                                changed |= ui.add(egui::widgets::Slider::new(&mut settings.fold, 0.0..=1.0)
+                                    .show_value(true)
+                                    .text(""))
+                                    .changed();
+                            });
+
+                            ui.add_space(20.0);
+
+                            // Panorama estéreo
+                            ui.vertical(|ui| {
+                                ui.label("Panorama");
+                                changed |= ui.add(egui::widgets::Slider::new(&mut settings.pan, -1.0..=1.0)
+                                    .show_value(true)
+                                    .text(""))
+                                    .changed();
+                            });
+
+                            ui.add_space(20.0);
+
+                            // Modo de fase al comenzar cada nota
+                            ui.vertical(|ui| {
+                                ui.label("Fase");
+                                let current_mode = settings.phase_mode;
+                                egui::ComboBox::from_id_source(("phase_mode", i))
+                                    .selected_text(current_mode.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for mode in PhaseMode::ALL {
+                                            ui.selectable_value(&mut settings.phase_mode, mode, mode.as_str());
+                                        }
+                                    });
+                                changed |= settings.phase_mode != current_mode;
+                            });
+
+                            ui.add_space(20.0);
+
+                            // Control de tipo de onda
+                            ui.vertical(|ui| {
+                                ui.label("Tipo de Onda");
+                                ui.horizontal(|ui| {
+                                    let button_size = egui::vec2(40.0, 40.0);
+                                    for wave_type in WaveType::ALL {
+                                        let selected = settings.wave_type == wave_type;
+                                        let response = ui.add(egui::Button::new("")
+                                            .min_size(button_size)
+                                            .selected(selected))
+                                            .on_hover_text(wave_type.as_str());
+                                        if response.clicked() {
+                                            settings.wave_type = wave_type;
+                                            changed = true;
+                                        }
+                                        draw_wave_icon(ui.painter(), response.rect, wave_type, selected);
+                                    }
+                                });
+                            });
+                        });
+
+                        // Ajustes del modo chiptune
+                        if settings.wave_type == WaveType::Chip {
+                            ui.horizontal(|ui| {
+                                ui.label("Canal");
+                                let current_channel = settings.chip_channel;
+                                egui::ComboBox::from_id_source(("chip_channel", i))
+                                    .selected_text(current_channel.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for channel in ChipChannel::ALL {
+                                            ui.selectable_value(&mut settings.chip_channel, channel, channel.as_str());
+                                        }
+                                    });
+                                changed |= settings.chip_channel != current_channel;
+
+                                ui.add_space(20.0);
+
+                                ui.label("Ciclo de trabajo");
+                                let current_duty = settings.duty_cycle;
+                                egui::ComboBox::from_id_source(("duty_cycle", i))
+                                    .selected_text(current_duty.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for duty in DutyCycle::ALL {
+                                            ui.selectable_value(&mut settings.duty_cycle, duty, duty.as_str());
+                                        }
+                                    });
+                                changed |= settings.duty_cycle != current_duty;
+
+                                ui.add_space(20.0);
+
+                                ui.label("Bits");
+                                changed |= ui.add(egui::widgets::Slider::new(&mut settings.bit_depth, 1..=MAX_CHIP_BIT_DEPTH))
+                                    .changed();
+                            });
+                        }
+
+                        if changed {
+                            oscillators.lock().unwrap()[i] = settings;
+                        }
+                    });
+
+                    ui.add_space(10.0);
+                }
+
+                // Tabla de ondas morfable
+                ui.group(|ui| {
+                    ui.heading("Tabla de Ondas");
+                    ui.horizontal(|ui| {
+                        // Posición en la tabla
+                        ui.vertical(|ui| {
+                            ui.label("Posición");
+                            let mut position_value = *self.config.lock().unwrap().wavetable_position.lock().unwrap();
+                            let position_response = ui.add(egui::widgets::Slider::new(&mut position_value, 0.0..=1.0)
+                                .show_value(true)
+                                .text(""));
+                            if position_response.changed() {
+                                *self.config.lock().unwrap().wavetable_position.lock().unwrap() = position_value;
+                            }
+                        });
+
+                        ui.add_space(20.0);
+
+                        // Barrido de la posición en el tiempo
+                        ui.vertical(|ui| {
+                            ui.label("Barrido (pos/s)");
+                            let mut sweep_value = *self.config.lock().unwrap().wavetable_sweep.lock().unwrap();
+                            let sweep_response = ui.add(egui::widgets::Slider::new(&mut sweep_value, -2.0..=2.0)
+                                .show_value(true)
+                                .text(""));
+                            if sweep_response.changed() {
+                                *self.config.lock().unwrap().wavetable_sweep.lock().unwrap() = sweep_value;
+                            }
+                        });
+                    });
+                });
+
+                ui.add_space(10.0);
+
+                // Síntesis aditiva: nivel de cada armónico al estilo drawbar
+                ui.group(|ui| {
+                    ui.heading("Síntesis Aditiva");
+                    let mut levels = self.config.lock().unwrap().harmonic_levels;
+                    let mut levels_changed = false;
+
+                    egui::ScrollArea::horizontal().show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            for (i, level) in levels.iter_mut().enumerate() {
+                                ui.vertical(|ui| {
+                                    if ui.add(egui::widgets::Slider::new(level, 0.0..=1.0)
+                                        .vertical()
+                                        .show_value(false))
+                                        .changed()
+                                    {
+                                        levels_changed = true;
+                                    }
+                                    ui.label(format!("{}", i + 1));
+                                });
+                            }
+                        });
+                    });
+
+                    // Regenerar la tabla solo cuando cambian los niveles
+                    if levels_changed {
+                        let table = build_additive_table(&levels);
+                        let mut config = self.config.lock().unwrap();
+                        config.harmonic_levels = levels;
+                        *config.additive_table.lock().unwrap() = (levels, table);
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // Envolvente de amplitud: los controles van de 0 a 1 y se muestran los
+                // tiempos reales que resultan de la escala logarítmica
+                ui.group(|ui| {
+                    ui.heading("Envolvente");
+                    let envelope = self.config.lock().unwrap().envelope.clone();
+                    let mut settings = *envelope.lock().unwrap();
+                    let changed = adsr_editor(ui, &mut settings);
+
+                    ui.label(format!(
+                        "Nota mínima: {}  ·  Cola: {}",
+                        format_time(settings.min_note_length()),
+                        format_time(settings.tail()),
+                    ));
+
+                    if changed {
+                        *envelope.lock().unwrap() = settings;
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // Filtro resonante de la voz, tras la mezcla de osciladores
+                ui.group(|ui| {
+                    ui.heading("Filtro");
+                    let filter = self.config.lock().unwrap().filter.clone();
+                    let mut settings = *filter.lock().unwrap();
+                    let mut changed = false;
+
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label("Tipo");
+                            let current_type = settings.filter_type;
+                            egui::ComboBox::from_id_source("filter_type")
+                                .selected_text(current_type.as_str())
+                                .show_ui(ui, |ui| {
+                                    for filter_type in FilterType::ALL {
+                                        ui.selectable_value(&mut settings.filter_type, filter_type, filter_type.as_str());
+                                    }
+                                });
+                            changed |= settings.filter_type != current_type;
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Pendiente");
+                            let current_slope = settings.slope;
+                            egui::ComboBox::from_id_source("filter_slope")
+                                .selected_text(current_slope.as_str())
+                                .show_ui(ui, |ui| {
+                                    for slope in FilterSlope::ALL {
+                                        ui.selectable_value(&mut settings.slope, slope, slope.as_str());
+                                    }
+                                });
+                            changed |= settings.slope != current_slope;
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Corte (Hz)");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.cutoff, MIN_CUTOFF..=MAX_CUTOFF)
+                                .logarithmic(true)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Resonancia");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.resonance, 0.0..=1.0)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+
+                        ui.add_space(20.0);
+
+                        // Bipolar: en negativo la envolvente cierra el filtro
+                        ui.vertical(|ui| {
+                            ui.label("Cantidad env.");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.env_amount, -1.0..=1.0)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+
+                        ui.add_space(20.0);
+
+                        // Bipolar: en negativo las notas fuertes cierran el filtro
+                        ui.vertical(|ui| {
+                            ui.label("Velocidad");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.velocity_amount, -1.0..=1.0)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("LFO");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.lfo_amount, 0.0..=1.0)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+                    });
+
+                    // Filtro de banda tras el resonante: notch para quitar una frecuencia
+                    // concreta o paso banda para aislarla
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        changed |= ui.checkbox(&mut settings.band.enabled, "Filtro de banda").changed();
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Modo");
+                            let current_mode = settings.band.mode;
+                            egui::ComboBox::from_id_source("band_mode")
+                                .selected_text(current_mode.as_str())
+                                .show_ui(ui, |ui| {
+                                    for mode in BandMode::ALL {
+                                        ui.selectable_value(&mut settings.band.mode, mode, mode.as_str());
+                                    }
+                                });
+                            changed |= settings.band.mode != current_mode;
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Frecuencia (Hz)");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.band.frequency, MIN_CUTOFF..=MAX_CUTOFF)
+                                .logarithmic(true)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Q");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.band.q, MIN_BAND_Q..=MAX_BAND_Q)
+                                .logarithmic(true)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+                    });
+
+                    // Formantes al final de la cadena: el morph recorre A-E-I-O-U
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        changed |= ui.checkbox(&mut settings.formant.enabled, "Formantes").changed();
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label(format!("Vocal: {}", Vowel::nearest(settings.formant.morph).as_str()));
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.formant.morph, 0.0..=1.0)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+                    });
+
+                    if changed {
+                        *filter.lock().unwrap() = settings;
+                    }
+
+                    ui.separator();
+                    ui.label("Envolvente del filtro");
+                    let filter_envelope = self.config.lock().unwrap().filter_envelope.clone();
+                    let mut envelope_settings = *filter_envelope.lock().unwrap();
+                    if adsr_editor(ui, &mut envelope_settings) {
+                        *filter_envelope.lock().unwrap() = envelope_settings;
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // LFO de cada voz; su fase arranca con la nota, al azar o continúa la común
+                ui.group(|ui| {
+                    ui.heading("LFO");
+                    let lfo = self.config.lock().unwrap().lfo.clone();
+                    let mut settings = *lfo.lock().unwrap();
+                    let mut changed = false;
+
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label("Forma");
+                            let current_shape = settings.shape;
+                            egui::ComboBox::from_id_source("lfo_shape")
+                                .selected_text(current_shape.as_str())
+                                .show_ui(ui, |ui| {
+                                    for shape in LfoShape::ALL {
+                                        ui.selectable_value(&mut settings.shape, shape, shape.as_str());
+                                    }
+                                });
+                            changed |= settings.shape != current_shape;
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Velocidad (Hz)");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.rate, MIN_LFO_RATE..=MAX_LFO_RATE)
+                                .logarithmic(true)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Vibrato (semitonos)");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.pitch_depth, 0.0..=MAX_LFO_PITCH_DEPTH)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label("Disparo");
+                            let current_trigger = settings.trigger;
+                            egui::ComboBox::from_id_source("lfo_trigger")
+                                .selected_text(current_trigger.as_str())
+                                .show_ui(ui, |ui| {
+                                    for trigger in LfoTrigger::ALL {
+                                        ui.selectable_value(&mut settings.trigger, trigger, trigger.as_str());
+                                    }
+                                });
+                            changed |= settings.trigger != current_trigger;
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Fase inicial");
+                            changed |= ui.add_enabled(settings.trigger == LfoTrigger::Key,
+                                egui::widgets::Slider::new(&mut settings.start_phase, 0.0..=1.0)
+                                    .show_value(true)
+                                    .text(""))
+                                .changed();
+                        });
+
+                        ui.add_space(20.0);
+
+                        // Un ciclo por pulsación y después se queda quieto
+                        changed |= ui.checkbox(&mut settings.one_shot, "Un solo ciclo").changed();
+                    });
+
+                    if changed {
+                        *lfo.lock().unwrap() = settings;
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // Envolvente multisegmento: arrastrar los puntos, doble clic para añadir uno
+                ui.group(|ui| {
+                    ui.heading("MSEG");
+                    let mseg = self.config.lock().unwrap().mseg.clone();
+                    let mut settings = *mseg.lock().unwrap();
+
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut settings.enabled, "Activo");
+                        egui::ComboBox::from_id_source("mseg_destination")
+                            .selected_text(settings.destination.as_str())
+                            .show_ui(ui, |ui| {
+                                for destination in MsegDestination::ALL {
+                                    ui.selectable_value(&mut settings.destination, destination, destination.as_str());
+                                }
+                            });
+                        ui.add(egui::widgets::Slider::new(&mut settings.amount, -1.0..=1.0)
+                            .text("Cantidad"));
+                    });
+
+                    mseg_editor(ui, egui::vec2(400.0, 120.0), &mut settings, &mut self.mseg_point);
+
+                    let last = settings.point_count - 1;
+                    self.mseg_point = self.mseg_point.min(last);
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Punto {}", self.mseg_point + 1));
+                        ui.add_enabled(self.mseg_point > 0, egui::widgets::Slider::new(
+                            &mut settings.points[self.mseg_point].curve, -1.0..=1.0)
+                            .text("Curva"));
+                        let can_add = self.mseg_point < last && settings.point_count < MAX_MSEG_POINTS;
+                        if ui.add_enabled(can_add, egui::Button::new("Añadir punto")).clicked() {
+                            // A medio camino entre el punto seleccionado y el siguiente
+                            let (from, to) = (settings.points[self.mseg_point], settings.points[self.mseg_point + 1]);
+                            let point = MsegPoint::new((from.time + to.time) / 2.0, (from.level + to.level) / 2.0, 0.0);
+                            if let Some(index) = settings.insert_point(point) {
+                                self.mseg_point = index;
+                            }
+                        }
+                        let can_remove = self.mseg_point > 0 && self.mseg_point < last;
+                        if ui.add_enabled(can_remove, egui::Button::new("Quitar punto")).clicked() {
+                            settings.remove_point(self.mseg_point);
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        let last = settings.point_count - 1;
+                        ui.checkbox(&mut settings.looping, "Bucle");
+                        ui.label("Desde");
+                        ui.add(egui::DragValue::new(&mut settings.loop_start).clamp_range(0..=last));
+                        ui.label("hasta");
+                        ui.add(egui::DragValue::new(&mut settings.loop_end).clamp_range(0..=last));
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut settings.tempo_sync, "Sincronizar al tempo");
+                        ui.add_enabled(settings.tempo_sync, egui::widgets::Slider::new(&mut settings.bpm, 30.0..=300.0)
+                            .text("BPM"));
+                        egui::ComboBox::from_label("Rejilla")
+                            .selected_text(format!("1/{}", settings.grid))
+                            .show_ui(ui, |ui| {
+                                for grid in MSEG_GRIDS {
+                                    ui.selectable_value(&mut settings.grid, grid, format!("1/{}", grid));
+                                }
+                            });
+                    });
+
+                    if settings != *mseg.lock().unwrap() {
+                        *mseg.lock().unwrap() = settings;
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // Matriz de modulación: cada ruta lleva una fuente a un destino de la voz
+                ui.group(|ui| {
+                    ui.heading("Matriz de modulación");
+                    let mod_matrix = self.config.lock().unwrap().mod_matrix.clone();
+                    let mut matrix = *mod_matrix.lock().unwrap();
+                    let mut remove = None;
+
+                    for (index, route) in matrix.routes[..matrix.route_count].iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source(("mod_source", index))
+                                .selected_text(route.source.as_str())
+                                .show_ui(ui, |ui| {
+                                    for source in ModSource::ALL {
+                                        ui.selectable_value(&mut route.source, source, source.as_str());
+                                    }
+                                });
+                            if route.source == ModSource::Cc {
+                                ui.add(egui::DragValue::new(&mut route.cc).clamp_range(0..=127));
+                            }
+                            egui::ComboBox::from_id_source(("mod_destination", index))
+                                .selected_text(route.destination.as_str())
+                                .show_ui(ui, |ui| {
+                                    for destination in ModDestination::ALL {
+                                        ui.selectable_value(&mut route.destination, destination, destination.as_str());
+                                    }
+                                });
+                            ui.add(egui::widgets::Slider::new(&mut route.amount, -1.0..=1.0)
+                                .text("Cantidad"));
+                            egui::ComboBox::from_id_source(("mod_curve", index))
+                                .selected_text(route.curve.as_str())
+                                .show_ui(ui, |ui| {
+                                    for curve in ModCurve::ALL {
+                                        ui.selectable_value(&mut route.curve, curve, curve.as_str());
+                                    }
+                                });
+                            if ui.button("Quitar").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        matrix.remove_route(index);
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(matrix.route_count < MAX_MOD_ROUTES, egui::Button::new("Añadir ruta")).clicked() {
+                            matrix.add_route(ModRoute::default());
+                        }
+                        // Escala a la vez todas las rutas con la fuente aleatoria por nota
+                        ui.add(egui::widgets::Slider::new(&mut matrix.random_depth, 0.0..=1.0)
+                            .text("Profundidad aleatoria"));
+                    });
+
+                    if matrix != *mod_matrix.lock().unwrap() {
+                        *mod_matrix.lock().unwrap() = matrix;
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // Filtro maestro sobre la mezcla de todas las voces, antes de la saturación de salida
+                ui.group(|ui| {
+                    ui.heading("Filtro maestro");
+                    let master_filter = self.config.lock().unwrap().master_filter.clone();
+                    let mut settings = *master_filter.lock().unwrap();
+                    let mut changed = false;
+
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label("Paso bajo (Hz)");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.lowpass_cutoff, MIN_CUTOFF..=MAX_CUTOFF)
+                                .logarithmic(true)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Paso alto (Hz)");
+                            changed |= ui.add(egui::widgets::Slider::new(&mut settings.highpass_cutoff, MIN_CUTOFF..=MAX_CUTOFF)
+                                .logarithmic(true)
+                                .show_value(true)
+                                .text(""))
+                                .changed();
+                        });
+                    });
+
+                    if changed {
+                        *master_filter.lock().unwrap() = settings;
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // Rack de efectos sobre la mezcla, en el orden de la lista
+                ui.group(|ui| {
+                    ui.heading("Rack de efectos");
+                    let fx = self.config.lock().unwrap().fx.clone();
+                    let mut settings = fx.lock().unwrap().clone();
+                    let slot_count = settings.slots.len();
+                    let mut moved = None;
+                    let mut remove = None;
+
+                    for (index, slot) in settings.slots.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}. {}", index + 1, slot.effect.as_str()));
+                            ui.checkbox(&mut slot.bypass, "Bypass");
+                            ui.add(egui::widgets::Slider::new(&mut slot.mix, 0.0..=1.0)
+                                .text("Mezcla"));
+                            if ui.add_enabled(index > 0, egui::Button::new("⬆")).clicked() {
+                                moved = Some((index, index - 1));
+                            }
+                            if ui.add_enabled(index + 1 < slot_count, egui::Button::new("⬇")).clicked() {
+                                moved = Some((index, index + 1));
+                            }
+                            if ui.button("Quitar").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some((from, to)) = moved {
+                        settings.move_slot(from, to);
+                    }
+                    if let Some(index) = remove {
+                        settings.remove_slot(index);
+                    }
+
+                    ui.add_enabled_ui(slot_count < MAX_FX_SLOTS, |ui| {
+                        egui::ComboBox::from_id_source("fx_add")
+                            .selected_text("Añadir efecto")
+                            .show_ui(ui, |ui| {
+                                // Cada efecto va en una sola ranura
+                                for effect in EffectType::ALL {
+                                    let label = egui::SelectableLabel::new(false, effect.as_str());
+                                    if ui.add_enabled(!settings.contains(effect), label).clicked() {
+                                        settings.add_slot(effect);
+                                    }
+                                }
+                            });
+                    });
+
+                    if settings.contains(EffectType::Delay) {
+                        ui.separator();
+                        ui.label("Delay");
+                        let delay = &mut settings.delay;
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut delay.tempo_sync, "Sincronizar al tempo");
+                            if delay.tempo_sync {
+                                egui::ComboBox::from_id_source("delay_division")
+                                    .selected_text(delay.division.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for division in DelayDivision::ALL {
+                                            ui.selectable_value(&mut delay.division, division, division.as_str());
+                                        }
+                                    });
+                                ui.add(egui::widgets::Slider::new(&mut delay.bpm, 30.0..=300.0)
+                                    .text("BPM"));
+                            } else {
+                                ui.add(egui::widgets::Slider::new(&mut delay.time, MIN_DELAY_TIME..=MAX_DELAY_TIME)
+                                    .logarithmic(true)
+                                    .text("Tiempo (ms)"));
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(egui::widgets::Slider::new(&mut delay.feedback, 0.0..=MAX_DELAY_FEEDBACK)
+                                .text("Realimentación"));
+                            ui.add(egui::widgets::Slider::new(&mut delay.damping, 0.0..=1.0)
+                                .text("Amortiguación"));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut delay.ping_pong, "Ping-pong");
+                            ui.add(egui::widgets::Slider::new(&mut delay.mix, 0.0..=1.0)
+                                .text("Mezcla"));
+                        });
+                    }
+
+                    if settings.contains(EffectType::Distortion) {
+                        ui.separator();
+                        ui.label("Distorsión");
+                        let distortion = &mut settings.distortion;
+                        egui::ComboBox::from_label("Curva de distorsión")
+                            .selected_text(distortion.curve.as_str())
+                            .show_ui(ui, |ui| {
+                                for curve in DistortionCurve::ALL {
+                                    ui.selectable_value(&mut distortion.curve, curve, curve.as_str());
+                                }
+                            });
+                        ui.horizontal(|ui| {
+                            ui.add(egui::widgets::Slider::new(&mut distortion.drive, 0.0..=MAX_DISTORTION_DRIVE)
+                                .text("Ganancia de entrada (dB)"));
+                            ui.add(egui::widgets::Slider::new(&mut distortion.trim, -MAX_DISTORTION_TRIM..=MAX_DISTORTION_TRIM)
+                                .text("Salida (dB)"));
+                        });
+                        ui.add(egui::widgets::Slider::new(&mut distortion.mix, 0.0..=1.0)
+                            .text("Mezcla"));
+                    }
+
+                    if settings.contains(EffectType::Eq) {
+                        ui.separator();
+                        ui.label("Ecualizador");
+                        let eq = &mut settings.eq;
+                        ui.horizontal(|ui| {
+                            ui.add(egui::widgets::Slider::new(&mut eq.low_frequency, MIN_CUTOFF..=MAX_CUTOFF)
+                                .logarithmic(true)
+                                .text("Graves (Hz)"));
+                            ui.add(egui::widgets::Slider::new(&mut eq.low_gain, -MAX_EQ_GAIN..=MAX_EQ_GAIN)
+                                .text("Ganancia (dB)"));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(egui::widgets::Slider::new(&mut eq.mid_frequency, MIN_CUTOFF..=MAX_CUTOFF)
+                                .logarithmic(true)
+                                .text("Medios (Hz)"));
+                            ui.add(egui::widgets::Slider::new(&mut eq.mid_gain, -MAX_EQ_GAIN..=MAX_EQ_GAIN)
+                                .text("Ganancia (dB)"));
+                            ui.add(egui::widgets::Slider::new(&mut eq.mid_q, MIN_BAND_Q..=MAX_BAND_Q)
+                                .logarithmic(true)
+                                .text("Q"));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(egui::widgets::Slider::new(&mut eq.high_frequency, MIN_CUTOFF..=MAX_CUTOFF)
+                                .logarithmic(true)
+                                .text("Agudos (Hz)"));
+                            ui.add(egui::widgets::Slider::new(&mut eq.high_gain, -MAX_EQ_GAIN..=MAX_EQ_GAIN)
+                                .text("Ganancia (dB)"));
+                        });
+                    }
+
+                    if settings.contains(EffectType::Compressor) {
+                        ui.separator();
+                        ui.label("Compresor");
+                        let compressor = &mut settings.compressor;
+                        ui.horizontal(|ui| {
+                            ui.add(egui::widgets::Slider::new(&mut compressor.threshold, MIN_COMPRESSOR_THRESHOLD..=0.0)
+                                .text("Umbral (dB)"));
+                            ui.add(egui::widgets::Slider::new(&mut compressor.ratio, 1.0..=MAX_COMPRESSOR_RATIO)
+                                .logarithmic(true)
+                                .text("Relación"));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(egui::widgets::Slider::new(&mut compressor.attack, MIN_COMPRESSOR_ATTACK..=MAX_COMPRESSOR_ATTACK)
+                                .logarithmic(true)
+                                .text("Ataque (ms)"));
+                            ui.add(egui::widgets::Slider::new(&mut compressor.release, MIN_COMPRESSOR_RELEASE..=MAX_COMPRESSOR_RELEASE)
+                                .logarithmic(true)
+                                .text("Relajación (ms)"));
+                        });
+                        ui.add(egui::widgets::Slider::new(&mut compressor.makeup, 0.0..=MAX_COMPRESSOR_MAKEUP)
+                            .text("Ganancia de compensación (dB)"));
+                    }
+
+                    if settings.contains(EffectType::Reverb) {
+                        ui.separator();
+                        ui.label("Reverb");
+                        let reverb = &mut settings.reverb;
+                        ui.horizontal(|ui| {
+                            ui.label("Respuesta al impulso (WAV)");
+                            ui.text_edit_singleline(&mut self.impulse_path);
+                            if ui.button("Cargar").clicked() {
+                                if let Err(err) = reverb.load_impulse(self.impulse_path.trim()) {
+                                    eprintln!("Error al cargar la respuesta al impulso {}: {}", self.impulse_path, err);
+                                }
+                            }
+                            if ui.button("Sintética").clicked() {
+                                self.impulse_path.clear();
+                                let _ = reverb.load_impulse("");
+                            }
+                        });
+                        ui.label(match &reverb.impulse {
+                            Some(impulse) => format!("{} ({:.2} s)", reverb.impulse_path, impulse.length()),
+                            None => String::from("Sala sintética"),
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(egui::widgets::Slider::new(&mut reverb.pre_delay, 0.0..=MAX_PRE_DELAY)
+                                .text("Pre-delay (ms)"));
+                            ui.add(egui::widgets::Slider::new(&mut reverb.mix, 0.0..=1.0)
+                                .text("Mezcla"));
+                        });
+                    }
+
+                    if settings != *fx.lock().unwrap() {
+                        *fx.lock().unwrap() = settings;
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // Última etapa de la mezcla, después del rack
+                ui.group(|ui| {
+                    ui.heading("Saturación de salida");
+                    let saturation = self.config.lock().unwrap().saturation.clone();
+                    let mut settings = *saturation.lock().unwrap();
+
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_label("Algoritmo")
+                            .selected_text(settings.mode.as_str())
+                            .show_ui(ui, |ui| {
+                                for mode in SaturationMode::ALL {
+                                    ui.selectable_value(&mut settings.mode, mode, mode.as_str());
+                                }
+                            });
+                        ui.add_enabled(settings.mode != SaturationMode::None,
+                            egui::widgets::Slider::new(&mut settings.drive, 0.0..=MAX_SATURATION_DRIVE)
+                                .text("Ganancia de entrada (dB)"));
+                    });
+
+                    if settings != *saturation.lock().unwrap() {
+                        *saturation.lock().unwrap() = settings;
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // Efectos por voz, antes de la mezcla
+                ui.group(|ui| {
+                    ui.heading("Efectos por Voz");
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label("Drive");
+                            let mut drive_value = *self.config.lock().unwrap().voice_drive.lock().unwrap();
+                            let drive_response = ui.add(egui::widgets::Slider::new(&mut drive_value, 0.0..=1.0)
+                                .show_value(true)
+                                .text(""));
+                            if drive_response.changed() {
+                                *self.config.lock().unwrap().voice_drive.lock().unwrap() = drive_value;
+                            }
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Drive por velocidad");
+                            let mut velocity_value = *self.config.lock().unwrap().voice_drive_velocity.lock().unwrap();
+                            let velocity_response = ui.add(egui::widgets::Slider::new(&mut velocity_value, 0.0..=1.0)
+                                .show_value(true)
+                                .text(""));
+                            if velocity_response.changed() {
+                                *self.config.lock().unwrap().voice_drive_velocity.lock().unwrap() = velocity_value;
+                            }
+                        });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            ui.label("Envolvente de filtro");
+                            let mut depth_value = *self.config.lock().unwrap().voice_filter_env_depth.lock().unwrap();
+                            let depth_response = ui.add(egui::widgets::Slider::new(&mut depth_value, 0.0..=1.0)
+                                .show_value(true)
+                                .text(""));
+                            if depth_response.changed() {
+                                *self.config.lock().unwrap().voice_filter_env_depth.lock().unwrap() = depth_value;
+                            }
+                        });
+                    });
+                });
+
+                ui.add_space(10.0);
+
+                // Modo de voz mono/legato
+                ui.group(|ui| {
+                    ui.heading("Modo de Voz");
+                    ui.horizontal(|ui| {
+                        let mut legato_value = *self.config.lock().unwrap().legato.lock().unwrap();
+                        if ui.checkbox(&mut legato_value, "Mono/Legato").changed() {
+                            *self.config.lock().unwrap().legato.lock().unwrap() = legato_value;
+                        }
+
+                        ui.add_space(20.0);
+
+                        let mut retrigger_amp_value = *self.config.lock().unwrap().legato_retrigger_amp.lock().unwrap();
+                        if ui.add_enabled(legato_value, egui::Checkbox::new(&mut retrigger_amp_value, "Redisparar envolvente de amplitud")).changed() {
+                            *self.config.lock().unwrap().legato_retrigger_amp.lock().unwrap() = retrigger_amp_value;
+                        }
+
+                        let mut retrigger_mod_value = *self.config.lock().unwrap().legato_retrigger_mod.lock().unwrap();
+                        if ui.add_enabled(legato_value, egui::Checkbox::new(&mut retrigger_mod_value, "Redisparar modulación")).changed() {
+                            *self.config.lock().unwrap().legato_retrigger_mod.lock().unwrap() = retrigger_mod_value;
+                        }
+                    });
+                    // Glide: desde la nota anterior, continuo o por semitonos
+                    ui.horizontal(|ui| {
+                        let glide = self.config.lock().unwrap().glide.clone();
+                        let mut settings = *glide.lock().unwrap();
+                        ui.label("Glide");
+                        egui::ComboBox::from_id_source("glide_mode")
+                            .selected_text(settings.mode.as_str())
+                            .show_ui(ui, |ui| {
+                                for mode in GlideMode::ALL {
+                                    ui.selectable_value(&mut settings.mode, mode, mode.as_str());
+                                }
+                            });
+                        ui.add_enabled_ui(settings.mode != GlideMode::Off, |ui| {
+                            ui.label("Tiempo");
+                            ui.add(egui::widgets::Slider::new(&mut settings.time, 0.0..=MAX_GLIDE_TIME).suffix(" s"));
+                        });
+                        *glide.lock().unwrap() = settings;
+                    });
+                });
+
+                ui.add_space(10.0);
+
+                // Unísono: varias voces completas por tecla, desafinadas y abiertas en estéreo
                 ui.group(|ui| {
-                    ui.heading("Oscilador 1");
+                    ui.heading("Unísono");
+                    let unison = self.config.lock().unwrap().unison.clone();
+                    let mut settings = *unison.lock().unwrap();
                     ui.horizontal(|ui| {
-                        // Control de volumen
-                        ui.vertical(|ui| {
-                            ui.label("Volumen");
-                            let mut volume_value = *self.config.lock().unwrap().volume.lock().unwrap();
-                            let volume_response = ui.add(egui::widgets::Slider::new(&mut volume_value, 0.0..=1.0)
-                                .show_value(true)
-                                .text(""));
-                            if volume_response.changed() {
-                                *self.config.lock().unwrap().volume.lock().unwrap() = volume_value;
-                            }
+                        ui.label("Voces");
+                        ui.add(egui::widgets::Slider::new(&mut settings.voices, 1..=MAX_UNISON_VOICES));
+                        ui.add_space(20.0);
+                        ui.add_enabled_ui(settings.voices > 1, |ui| {
+                            ui.label("Desafinación");
+                            ui.add(egui::widgets::Slider::new(&mut settings.detune, 0.0..=MAX_UNISON_DETUNE).suffix(" st"));
+                            ui.label("Apertura");
+                            ui.add(egui::widgets::Slider::new(&mut settings.spread, 0.0..=1.0));
                         });
+                    });
+                    *unison.lock().unwrap() = settings;
+                });
 
-                        ui.add_space(20.0);
+                ui.add_space(10.0);
 
-                        // Control de tipo de onda
-                        ui.vertical(|ui| {
-                            ui.label("Tipo de Onda");
-                            ui.horizontal(|ui| {
-                                let button_size = egui::vec2(40.0, 40.0);
-                                let mut current_wave = *self.config.lock().unwrap().wave_type1.lock().unwrap();
-                                
-                                // Botón Sine
-                                let sine_response = ui.add(egui::Button::new("")
-                                    .min_size(button_size)
-                                    .selected(current_wave == WaveType::Sine));
-                                if sine_response.clicked() {
-                                    *self.config.lock().unwrap().wave_type1.lock().unwrap() = WaveType::Sine;
-                                }
-                                draw_sine_wave(ui.painter(), sine_response.rect, current_wave == WaveType::Sine);
-
-                                // Botón Square
-                                let square_response = ui.add(egui::Button::new("")
-                                    .min_size(button_size)
-                                    .selected(current_wave == WaveType::Square));
-                                if square_response.clicked() {
-                                    *self.config.lock().unwrap().wave_type1.lock().unwrap() = WaveType::Square;
-                                }
-                                draw_square_wave(ui.painter(), square_response.rect, current_wave == WaveType::Square);
-
-                                // Botón Triangle
-                                let triangle_response = ui.add(egui::Button::new("")
-                                    .min_size(button_size)
-                                    .selected(current_wave == WaveType::Triangle));
-                                if triangle_response.clicked() {
-                                    *self.config.lock().unwrap().wave_type1.lock().unwrap() = WaveType::Triangle;
-                                }
-                                draw_triangle_wave(ui.painter(), triangle_response.rect, current_wave == WaveType::Triangle);
-
-                                // Botón Sawtooth
-                                let saw_response = ui.add(egui::Button::new("")
-                                    .min_size(button_size)
-                                    .selected(current_wave == WaveType::Sawtooth));
-                                if saw_response.clicked() {
-                                    *self.config.lock().unwrap().wave_type1.lock().unwrap() = WaveType::Sawtooth;
+                // Panorama de voces: fijo, o alternando / repartiendo las notas por el estéreo
+                ui.group(|ui| {
+                    ui.heading("Panorama de Voces");
+                    let voice_pan = self.config.lock().unwrap().voice_pan.clone();
+                    let mut settings = *voice_pan.lock().unwrap();
+                    ui.horizontal(|ui| {
+                        ui.label("Modo");
+                        egui::ComboBox::from_id_source("voice_pan_mode")
+                            .selected_text(settings.mode.as_str())
+                            .show_ui(ui, |ui| {
+                                for mode in VoicePanMode::ALL {
+                                    ui.selectable_value(&mut settings.mode, mode, mode.as_str());
                                 }
-                                draw_sawtooth_wave(ui.painter(), saw_response.rect, current_wave == WaveType::Sawtooth);
                             });
+                        ui.add_space(20.0);
+                        ui.label("Panorama");
+                        ui.add(egui::widgets::Slider::new(&mut settings.pan, -1.0..=1.0));
+                        ui.add_enabled_ui(settings.mode != VoicePanMode::Fixed, |ui| {
+                            ui.label("Anchura");
+                            ui.add(egui::widgets::Slider::new(&mut settings.spread, 0.0..=1.0));
                         });
                     });
+                    *voice_pan.lock().unwrap() = settings;
                 });
 
                 ui.add_space(10.0);
 
-                // Oscilador 2
+                // Modo parafónico: una envolvente y un filtro para todas las notas
                 ui.group(|ui| {
-                    ui.heading("Oscilador 2");
+                    ui.heading("Modo Parafónico");
+                    let paraphonic = self.config.lock().unwrap().paraphonic.clone();
+                    let mut settings = *paraphonic.lock().unwrap();
                     ui.horizontal(|ui| {
-                        // Control de volumen
-                        ui.vertical(|ui| {
-                            ui.label("Volumen");
-                            let mut volume_value = *self.config.lock().unwrap().osc2_volume.lock().unwrap();
-                            let volume_response = ui.add(egui::widgets::Slider::new(&mut volume_value, 0.0..=1.0)
-                                .show_value(true)
-                                .text(""));
-                            if volume_response.changed() {
-                                *self.config.lock().unwrap().osc2_volume.lock().unwrap() = volume_value;
-                            }
+                        ui.checkbox(&mut settings.enabled, "Envolvente y filtro comunes");
+                        ui.add_space(20.0);
+                        ui.add_enabled_ui(settings.enabled, |ui| {
+                            ui.label("Redisparo");
+                            egui::ComboBox::from_id_source("paraphonic_trigger")
+                                .selected_text(settings.trigger.as_str())
+                                .show_ui(ui, |ui| {
+                                    for trigger in ParaphonicTrigger::ALL {
+                                        ui.selectable_value(&mut settings.trigger, trigger, trigger.as_str());
+                                    }
+                                });
                         });
+                    });
+                    *paraphonic.lock().unwrap() = settings;
+                });
 
-                        ui.add_space(20.0);
+                ui.add_space(10.0);
 
-                        // Control de detune
-                        ui.vertical(|ui| {
-                            ui.label("Detune (semitonos)");
-                            let mut detune_value = *self.config.lock().unwrap().osc2_detune.lock().unwrap();
-                            let detune_response = ui.add(egui::widgets::Slider::new(&mut detune_value, -12.0..=12.0)
-                                .show_value(true)
-                                .text(""));
-                            if detune_response.changed() {
-                                *self.config.lock().unwrap().osc2_detune.lock().unwrap() = detune_value;
-                            }
+                // Teclado dividido: la zona grave con su propia onda, envolvente y volumen
+                ui.group(|ui| {
+                    ui.heading("Teclado Dividido");
+                    let split = self.config.lock().unwrap().split.clone();
+                    let mut settings = *split.lock().unwrap();
+
+                    ui.checkbox(&mut settings.enabled, "Dividir el teclado (grave abajo, sonido principal arriba)");
+                    ui.add_enabled_ui(settings.enabled, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Punto de división");
+                            ui.add(egui::widgets::Slider::new(&mut settings.split_note, 1..=127)
+                                .custom_formatter(|value, _| note_name(value as u8)));
+                            ui.add_space(20.0);
+                            ui.label("Volumen grave");
+                            ui.add(egui::widgets::Slider::new(&mut settings.lower_volume, 0.0..=1.0));
+                            ui.label("Volumen agudo");
+                            ui.add(egui::widgets::Slider::new(&mut settings.upper_volume, 0.0..=1.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Onda de la zona grave");
+                            egui::ComboBox::from_id_source("split_lower_wave_type")
+                                .selected_text(settings.lower_wave_type.as_str())
+                                .show_ui(ui, |ui| {
+                                    for wave_type in WaveType::ALL {
+                                        ui.selectable_value(&mut settings.lower_wave_type, wave_type, wave_type.as_str());
+                                    }
+                                });
                         });
+                        ui.label("Envolvente de la zona grave");
+                        adsr_editor(ui, &mut settings.lower_envelope);
+                    });
 
-                        ui.add_space(20.0);
+                    *split.lock().unwrap() = settings;
+                });
 
-                        // Control de tipo de onda
-                        ui.vertical(|ui| {
-                            ui.label("Tipo de Onda");
-                            ui.horizontal(|ui| {
-                                let button_size = egui::vec2(40.0, 40.0);
-                                let mut current_wave = *self.config.lock().unwrap().wave_type2.lock().unwrap();
-                                
-                                // Botón Sine
-                                let sine_response = ui.add(egui::Button::new("")
-                                    .min_size(button_size)
-                                    .selected(current_wave == WaveType::Sine));
-                                if sine_response.clicked() {
-                                    *self.config.lock().unwrap().wave_type2.lock().unwrap() = WaveType::Sine;
-                                }
-                                draw_sine_wave(ui.painter(), sine_response.rect, current_wave == WaveType::Sine);
-
-                                // Botón Square
-                                let square_response = ui.add(egui::Button::new("")
-                                    .min_size(button_size)
-                                    .selected(current_wave == WaveType::Square));
-                                if square_response.clicked() {
-                                    *self.config.lock().unwrap().wave_type2.lock().unwrap() = WaveType::Square;
-                                }
-                                draw_square_wave(ui.painter(), square_response.rect, current_wave == WaveType::Square);
-
-                                // Botón Triangle
-                                let triangle_response = ui.add(egui::Button::new("")
-                                    .min_size(button_size)
-                                    .selected(current_wave == WaveType::Triangle));
-                                if triangle_response.clicked() {
-                                    *self.config.lock().unwrap().wave_type2.lock().unwrap() = WaveType::Triangle;
-                                }
-                                draw_triangle_wave(ui.painter(), triangle_response.rect, current_wave == WaveType::Triangle);
-
-                                // Botón Sawtooth
-                                let saw_response = ui.add(egui::Button::new("")
-                                    .min_size(button_size)
-                                    .selected(current_wave == WaveType::Sawtooth));
-                                if saw_response.clicked() {
-                                    *self.config.lock().unwrap().wave_type2.lock().unwrap() = WaveType::Sawtooth;
-                                }
-                                draw_sawtooth_wave(ui.painter(), saw_response.rect, current_wave == WaveType::Sawtooth);
+                ui.add_space(10.0);
+
+                // Síntesis vectorial: mezcla de los cuatro osciladores con una posición X/Y
+                ui.group(|ui| {
+                    ui.heading("Síntesis Vectorial");
+                    let vector = self.config.lock().unwrap().vector.clone();
+                    let mut settings = *vector.lock().unwrap();
+
+                    if ui.checkbox(&mut settings.enabled, "Modo vectorial (A, B, C, D en las esquinas)").changed() && settings.enabled {
+                        // El modo vectorial necesita los cuatro osciladores
+                        *self.config.lock().unwrap().oscillator_count.lock().unwrap() = MAX_OSCILLATORS;
+                    }
+
+                    ui.add_enabled_ui(settings.enabled, |ui| {
+                        ui.horizontal(|ui| {
+                            let mut position = egui::vec2(settings.x, settings.y);
+                            if xy_pad(ui, VECTOR_PAD_SIZE, &mut position).changed() {
+                                settings.x = position.x;
+                                settings.y = position.y;
+                            }
+
+                            ui.add_space(20.0);
+
+                            ui.vertical(|ui| {
+                                ui.label("Envolvente X");
+                                ui.add(egui::widgets::Slider::new(&mut settings.env_x, -1.0..=1.0));
+                                ui.label("Envolvente Y");
+                                ui.add(egui::widgets::Slider::new(&mut settings.env_y, -1.0..=1.0));
                             });
                         });
                     });
+
+                    *vector.lock().unwrap() = settings;
                 });
             });
             
             ui.add_space(10.0);
             
+            // Asignaciones de CC MIDI
+            ui.group(|ui| {
+                ui.heading("Asignaciones MIDI CC");
+                let cc_mapper = self.config.lock().unwrap().cc_mapper.clone();
+                let mut mapper = cc_mapper.lock().unwrap();
+                let mut remove_index = None;
+                
+                for i in 0..mapper.mappings.len() {
+                    let lsb_detected = mapper.lsb_detected(mapper.mappings[i].cc);
+                    let mapping = &mut mapper.mappings[i];
+                    ui.horizontal(|ui| {
+                        ui.label("CC");
+                        ui.add(egui::DragValue::new(&mut mapping.cc).clamp_range(0..=127));
+                        
+                        egui::ComboBox::from_id_source(("cc_target", i))
+                            .selected_text(mapping.target.as_str())
+                            .show_ui(ui, |ui| {
+                                for target in CcTarget::ALL {
+                                    ui.selectable_value(&mut mapping.target, target, target.as_str());
+                                }
+                            });
+                        
+                        // Solo los CC 0-31 tienen pareja LSB
+                        ui.add_enabled(mapping.cc < 32, egui::Checkbox::new(&mut mapping.high_resolution, "14 bits"));
+                        if mapping.cc < 32 && mapping.high_resolution {
+                            ui.label(if lsb_detected { "LSB detectado" } else { "Sin LSB" });
+                        }
+                        
+                        if ui.button("Quitar").clicked() {
+                            remove_index = Some(i);
+                        }
+                    });
+                }
+                
+                if let Some(i) = remove_index {
+                    mapper.mappings.remove(i);
+                }
+                
+                if ui.button("Añadir asignación").clicked() {
+                    mapper.mappings.push(CcMapping { cc: 74, target: CcTarget::Volume, high_resolution: false });
+                }
+            });
+            
+            ui.add_space(10.0);
+            
             // Controles del sintetizador
             ui.group(|ui| {
                 ui.heading("Controles");
@@ -842,28 +3320,191 @@ impl eframe::App for SynthApp {
                 {
                     let config = self.config.lock().unwrap();
                     is_running = config.running;
-                    is_midi_connected = self.midi_connection.is_some();
+                    is_midi_connected = !self.midi_connections.is_empty();
                 }
                 
-                ui.horizontal(|ui| {
-                    if is_running {
-                        if ui.button("Detener Sintetizador").clicked() {
-                            self.stop_synth();
+                // Arranque, puertos MIDI y archivos: en el plugin los pone el host
+                if !self.plugin {
+                    ui.horizontal(|ui| {
+                        if is_running {
+                            if ui.button("Detener Sintetizador").clicked() {
+                                self.stop_synth();
+                            }
+                        } else {
+                            if ui.button("Iniciar Sintetizador").clicked() {
+                                self.start_synth();
+                            }
                         }
-                    } else {
-                        if ui.button("Iniciar Sintetizador").clicked() {
-                            self.start_synth();
+                    
+                        if is_midi_connected {
+                            if ui.button("Desconectar MIDI").clicked() {
+                                self.disconnect_midi();
+                            }
+                        } else {
+                            if ui.button("Conectar MIDI").clicked() {
+                                self.connect_midi();
+                            }
                         }
+                    });
+                
+                    // Tempo del delay y del MSEG sincronizados desde un reloj MIDI externo
+                    let follow_midi_clock = self.config.lock().unwrap().follow_midi_clock.clone();
+                    let mut follow = *follow_midi_clock.lock().unwrap();
+                    if ui.checkbox(&mut follow, "Seguir el reloj MIDI").changed() {
+                        *follow_midi_clock.lock().unwrap() = follow;
                     }
+                
+                    // Archivo MIDI estándar, para probar sonidos sin controlador
+                    ui.horizontal(|ui| {
+                        ui.label("Archivo MIDI:");
+                        ui.text_edit_singleline(&mut self.midi_file_path);
+                        if self.is_midi_file_playing() {
+                            if ui.button("Detener archivo").clicked() {
+                                self.stop_midi_file();
+                            }
+                        } else if ui.add_enabled(is_running, egui::Button::new("Reproducir archivo MIDI")).clicked() {
+                            self.play_midi_file();
+                        }
+                    });
+                
+                    // Entradas MIDI que se abren juntas al conectar
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Entradas:");
+                        for (name, selected) in self.midi_input_ports.iter().zip(self.midi_input_selected.iter_mut()) {
+                            ui.add_enabled(!is_midi_connected, egui::Checkbox::new(selected, name));
+                        }
+                        if ui.add_enabled(!is_midi_connected, egui::Button::new("Actualizar entradas")).clicked() {
+                            self.refresh_midi_inputs();
+                        }
+                    });
+                
+                    // MIDI thru hacia un puerto de salida
+                    ui.horizontal(|ui| {
+                        let mut thru_enabled = self.config.lock().unwrap().midi_thru.lock().unwrap().is_some();
+                        if ui.checkbox(&mut thru_enabled, "MIDI Thru").changed() {
+                            if thru_enabled {
+                                self.connect_midi_thru();
+                            } else {
+                                self.disconnect_midi_thru();
+                            }
+                        }
+                    
+                        let selected_text = self.midi_output_ports.get(self.midi_thru_port)
+                            .cloned()
+                            .unwrap_or_else(|| "Ninguna".to_string());
+                        let previous_port = self.midi_thru_port;
+                        egui::ComboBox::from_label("Salida")
+                            .selected_text(selected_text)
+                            .show_ui(ui, |ui| {
+                                for (i, name) in self.midi_output_ports.iter().enumerate() {
+                                    ui.selectable_value(&mut self.midi_thru_port, i, name);
+                                }
+                            });
+                        // Cambiar de puerto con el thru activo reconecta a la nueva salida
+                        if self.midi_thru_port != previous_port && thru_enabled {
+                            self.disconnect_midi_thru();
+                            self.connect_midi_thru();
+                        }
                     
-                    if is_midi_connected {
-                        if ui.button("Desconectar MIDI").clicked() {
-                            self.disconnect_midi();
+                        if ui.button("Actualizar").clicked() {
+                            self.refresh_midi_outputs();
+                        }
+                    });
+                
+                    // Salida de las notas generadas por el sintetizador hacia un equipo externo
+                    ui.horizontal(|ui| {
+                        let midi_out = self.config.lock().unwrap().midi_out.clone();
+                        let mut out_enabled = midi_out.lock().unwrap().is_connected();
+                        if ui.checkbox(&mut out_enabled, "Salida MIDI").changed() {
+                            if out_enabled {
+                                self.connect_midi_out();
+                            } else {
+                                self.disconnect_midi_out();
+                            }
+                        }
+                    
+                        let selected_text = self.midi_output_ports.get(self.midi_out_port)
+                            .cloned()
+                            .unwrap_or_else(|| "Ninguna".to_string());
+                        let previous_port = self.midi_out_port;
+                        egui::ComboBox::from_id_source("midi_out_port")
+                            .selected_text(selected_text)
+                            .show_ui(ui, |ui| {
+                                for (i, name) in self.midi_output_ports.iter().enumerate() {
+                                    ui.selectable_value(&mut self.midi_out_port, i, name);
+                                }
+                            });
+                        if self.midi_out_port != previous_port && out_enabled {
+                            self.connect_midi_out();
                         }
-                    } else {
-                        if ui.button("Conectar MIDI").clicked() {
-                            self.connect_midi();
+                    
+                        let mut channel = midi_out.lock().unwrap().channel + 1;
+                        if ui.add(egui::DragValue::new(&mut channel).clamp_range(1..=16).prefix("Canal ")).changed() {
+                            midi_out.lock().unwrap().channel = channel - 1;
                         }
+                    });
+                }
+                
+                // Ajuste de velocidad por canal, p. ej. para pads de batería en el canal 10
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Canal")
+                        .selected_text((self.trim_channel + 1).to_string())
+                        .show_ui(ui, |ui| {
+                            for channel in 0..MIDI_CHANNELS {
+                                ui.selectable_value(&mut self.trim_channel, channel, (channel + 1).to_string());
+                            }
+                        });
+                    
+                    let velocity_trims = self.config.lock().unwrap().velocity_trims.clone();
+                    let mut trim = velocity_trims.lock().unwrap()[self.trim_channel];
+                    let mut changed = false;
+                    changed |= ui.add(egui::widgets::Slider::new(&mut trim.gain, 0.0..=2.0)
+                        .text("Ganancia vel."))
+                        .changed();
+                    changed |= ui.add(egui::widgets::Slider::new(&mut trim.offset, -1.0..=1.0)
+                        .text("Desplazamiento vel."))
+                        .changed();
+                    changed |= ui.add(egui::widgets::Slider::new(&mut trim.min, 0.0..=1.0)
+                        .text("Vel. mínima"))
+                        .changed();
+                    changed |= ui.add(egui::widgets::Slider::new(&mut trim.max, 0.0..=1.0)
+                        .text("Vel. máxima"))
+                        .changed();
+                    
+                    // Velocidad fija para todo el canal, p. ej. con un teclado de respuesta brusca
+                    let mut fixed_enabled = trim.fixed.is_some();
+                    if ui.checkbox(&mut fixed_enabled, "Vel. fija").changed() {
+                        trim.fixed = fixed_enabled.then_some(0.8);
+                        changed = true;
+                    }
+                    if let Some(fixed) = trim.fixed.as_mut() {
+                        changed |= ui.add(egui::widgets::Slider::new(fixed, 0.0..=1.0))
+                            .changed();
+                    }
+                    
+                    if changed {
+                        velocity_trims.lock().unwrap()[self.trim_channel] = trim;
+                        self.save_session();
+                    }
+                });
+                
+                // Curva de respuesta del teclado, común a todos los canales
+                ui.horizontal(|ui| {
+                    let velocity_response = self.config.lock().unwrap().velocity_response.clone();
+                    let mut response = *velocity_response.lock().unwrap();
+                    egui::ComboBox::from_label("Curva de velocidad")
+                        .selected_text(response.curve.as_str())
+                        .show_ui(ui, |ui| {
+                            for curve in VelocityCurve::ALL {
+                                ui.selectable_value(&mut response.curve, curve, curve.as_str());
+                            }
+                        });
+                    ui.add(egui::widgets::Slider::new(&mut response.sensitivity, 0.0..=1.0)
+                        .text("Sensibilidad"));
+                    
+                    if response != *velocity_response.lock().unwrap() {
+                        *velocity_response.lock().unwrap() = response;
+                        self.save_session();
                     }
                 });
             });
@@ -875,22 +3516,75 @@ impl eframe::App for SynthApp {
                 ui.heading("Estado");
                 
                 let is_running;
-                let is_midi_connected;
+                let midi_input_count;
                 let sample_rate;
                 let active_note_count;
                 
                 {
                     let config = self.config.lock().unwrap();
                     is_running = config.running;
-                    is_midi_connected = self.midi_connection.is_some();
+                    midi_input_count = self.midi_connections.len();
                     sample_rate = *self.sample_rate.lock().unwrap();
-                    active_note_count = self.active_notes.lock().unwrap().len();
+                    active_note_count = self.voices.lock().unwrap().active_voices();
                 }
                 
                 ui.label(format!("Estado del sintetizador: {}", if is_running { "Ejecutando" } else { "Detenido" }));
-                ui.label(format!("Conexión MIDI: {}", if is_midi_connected { "Conectado" } else { "Desconectado" }));
+                if midi_input_count == 0 {
+                    ui.label("Conexión MIDI: Desconectado");
+                } else {
+                    ui.label(format!("Conexión MIDI: {} entrada(s) conectada(s)", midi_input_count));
+                }
+                let midi_clock = self.config.lock().unwrap().midi_clock.clone();
+                let clock = midi_clock.lock().unwrap();
+                match clock.bpm() {
+                    Some(bpm) => ui.label(format!("Reloj MIDI: {:.1} BPM ({})", bpm, if clock.running { "en marcha" } else { "parado" })),
+                    None => ui.label("Reloj MIDI: sin señal"),
+                };
+                drop(clock);
+                // Lo que hayan fijado los RPN 0,0 y 0,2 desde el controlador o el DAW
+                let sources = *self.voices.lock().unwrap().mod_sources();
+                ui.label(format!("Pitch bend: ±{:.2} semitonos, transposición: {:+} semitonos", sources.bend_range, sources.coarse_tune));
                 ui.label(format!("Frecuencia de muestreo actual: {:.1} Hz", sample_rate));
                 ui.label(format!("Notas activas: {}", active_note_count));
+                let stats = self.config.lock().unwrap().stats.snapshot();
+                ui.label(format!(
+                    "Audio: {} voces, {} robadas, {:.0} µs por bloque ({:.0}% de carga), pico {:.1} dBFS",
+                    stats.active_voices,
+                    stats.stolen_voices,
+                    stats.average_callback_time.as_secs_f32() * 1_000_000.0,
+                    stats.load * 100.0,
+                    stats.peak_db(),
+                ));
+            });
+            
+            ui.add_space(10.0);
+            
+            // Diagnóstico
+            ui.group(|ui| {
+                ui.heading("Diagnóstico");
+                // El vigilante solo anota tecla y segundos; el texto se compone aquí
+                let watchdog = self.config.lock().unwrap().voice_watchdog.clone();
+                self.stale_voices.update(&watchdog);
+                
+                ui.horizontal(|ui| {
+                    ui.label("Liberar voces colgadas tras (s, 0 = nunca)");
+                    let mut timeout = watchdog.timeout();
+                    if ui.add(egui::widgets::Slider::new(&mut timeout, 0.0..=120.0)).changed() {
+                        watchdog.set_timeout(timeout);
+                    }
+                    if ui.button("Limpiar registro").clicked() {
+                        self.stale_voices.clear();
+                    }
+                });
+                
+                egui::ScrollArea::vertical()
+                    .id_source("diagnostics_log")
+                    .max_height(80.0)
+                    .show(ui, |ui| {
+                        for entry in self.stale_voices.entries() {
+                            ui.label(entry);
+                        }
+                    });
             });
             
             // Información
@@ -901,8 +3595,134 @@ impl eframe::App for SynthApp {
             });
         });
         
-        // Solicitar repintado continuo para actualizar el estado
-        ctx.request_repaint();
+        // Los cambios de parámetros ya repintan por sí solos al llegar la entrada del usuario;
+        // mientras suena, el estado (notas activas) se refresca a ritmo fijo
+        if self.config.lock().unwrap().running {
+            ctx.request_repaint_after(METER_REFRESH_INTERVAL);
+        }
+    }
+}
+
+fn target_selector(ui: &mut egui::Ui, id: &str, label: &str, target: &mut CcTarget) {
+    ui.label(label);
+    egui::ComboBox::from_id_source(id)
+        .selected_text(target.as_str())
+        .show_ui(ui, |ui| {
+            for option in CcTarget::ALL {
+                ui.selectable_value(target, option, option.as_str());
+            }
+        });
+}
+
+// Pad XY: la posición va de (0, 0) abajo a la izquierda a (1, 1) arriba a la derecha
+fn xy_pad(ui: &mut egui::Ui, size: f32, position: &mut egui::Vec2) -> egui::Response {
+    let (rect, mut response) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click_and_drag());
+    
+    if let Some(pointer) = response.interact_pointer_pos() {
+        let x = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+        let y = ((rect.bottom() - pointer.y) / rect.height()).clamp(0.0, 1.0);
+        if x != position.x || y != position.y {
+            *position = egui::vec2(x, y);
+            response.mark_changed();
+        }
+    }
+    
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 8.0, ui.visuals().extreme_bg_color);
+    let handle = egui::pos2(
+        rect.left() + position.x * rect.width(),
+        rect.bottom() - position.y * rect.height(),
+    );
+    let guide = egui::Stroke::new(1.0, egui::Color32::GRAY);
+    painter.line_segment([egui::pos2(rect.left(), handle.y), egui::pos2(rect.right(), handle.y)], guide);
+    painter.line_segment([egui::pos2(handle.x, rect.top()), egui::pos2(handle.x, rect.bottom())], guide);
+    painter.circle_filled(handle, (size * 0.04).max(6.0), ui.visuals().selection.bg_fill);
+    
+    response
+}
+
+// Texto emergente de un preset en el navegador, con los metadatos que tenga
+fn preset_info(metadata: &PresetMetadata) -> String {
+    let mut lines = Vec::new();
+    if !metadata.description.is_empty() {
+        lines.push(metadata.description.clone());
+    }
+    if !metadata.author.is_empty() {
+        lines.push(format!("Autor: {}", metadata.author));
+    }
+    if !metadata.category.is_empty() {
+        lines.push(format!("Categoría: {}", metadata.category));
+    }
+    let tags: Vec<&str> = metadata.tags().collect();
+    if !tags.is_empty() {
+        lines.push(format!("Etiquetas: {}", tags.join(", ")));
+    }
+    lines.join("\n")
+}
+
+// Nombre de una nota MIDI con su octava, con C4 en la nota 60
+fn note_name(key: u8) -> String {
+    const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+    format!("{}{}", NAMES[(key % 12) as usize], key as i32 / 12 - 1)
+}
+
+// Teclado de piano desde `first_note` (un do) con `octaves` octavas y el do final.
+// Devuelve la tecla bajo el puntero mientras está pulsado y una velocidad según la
+// altura: cuanto más abajo en la tecla, más fuerte.
+fn piano_keyboard(ui: &mut egui::Ui, first_note: u8, octaves: usize, held: &[bool; 128]) -> Option<(u8, f32)> {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(ui.available_width(), KEYBOARD_HEIGHT), egui::Sense::click_and_drag());
+    let white_width = rect.width() / (octaves * 7 + 1) as f32;
+    let black_size = egui::vec2(white_width * 0.6, rect.height() * 0.6);
+    let key_count = octaves * 12 + 1;
+    let key_rect = |offset: usize| {
+        let white_index = offset / 12 * 7 + WHITE_KEY_INDEX[offset % 12];
+        if BLACK_KEYS[offset % 12] {
+            let center = rect.left() + (white_index + 1) as f32 * white_width;
+            egui::Rect::from_min_size(egui::pos2(center - black_size.x / 2.0, rect.top()), black_size)
+        } else {
+            egui::Rect::from_min_size(
+                egui::pos2(rect.left() + white_index as f32 * white_width, rect.top()),
+                egui::vec2(white_width, rect.height()),
+            )
+        }
+    };
+    let is_black = |offset: usize| BLACK_KEYS[offset % 12];
+    let note = |offset: usize| first_note.saturating_add(offset as u8).min(127);
+    
+    // Las negras están encima, así que se miran primero
+    let pressed = response.interact_pointer_pos().filter(|_| response.is_pointer_button_down_on()).and_then(|pointer| {
+        let offset = (0..key_count).filter(|&offset| is_black(offset))
+            .chain((0..key_count).filter(|&offset| !is_black(offset)))
+            .find(|&offset| key_rect(offset).contains(pointer))?;
+        let key_rect = key_rect(offset);
+        let velocity = ((pointer.y - key_rect.top()) / key_rect.height()).clamp(0.1, 1.0);
+        Some((note(offset), velocity))
+    });
+    
+    let painter = ui.painter_at(rect);
+    let held_color = ui.visuals().selection.bg_fill;
+    let outline = egui::Stroke::new(1.0, egui::Color32::DARK_GRAY);
+    for offset in (0..key_count).filter(|&offset| !is_black(offset)) {
+        let fill = if held[note(offset) as usize] { held_color } else { egui::Color32::WHITE };
+        painter.rect(key_rect(offset), 2.0, fill, outline);
+    }
+    for offset in (0..key_count).filter(|&offset| is_black(offset)) {
+        let fill = if held[note(offset) as usize] { held_color } else { egui::Color32::BLACK };
+        painter.rect(key_rect(offset), 2.0, fill, outline);
+    }
+    
+    pressed
+}
+
+fn draw_wave_icon(painter: &egui::Painter, rect: egui::Rect, wave_type: WaveType, selected: bool) {
+    match wave_type {
+        WaveType::Sine => draw_sine_wave(painter, rect, selected),
+        WaveType::Square => draw_square_wave(painter, rect, selected),
+        WaveType::Triangle => draw_triangle_wave(painter, rect, selected),
+        WaveType::Sawtooth => draw_sawtooth_wave(painter, rect, selected),
+        WaveType::Wavetable => draw_wavetable_wave(painter, rect, selected),
+        WaveType::Additive => draw_additive_wave(painter, rect, selected),
+        WaveType::Chip => draw_chip_wave(painter, rect, selected),
     }
 }
 
@@ -958,4 +3778,285 @@ fn draw_sawtooth_wave(painter: &egui::Painter, rect: egui::Rect, selected: bool)
         egui::pos2(rect.right(), rect.center().y - rect.height() * 0.3),
     ];
     painter.add(egui::Shape::line(points, stroke));
-} 
\ No newline at end of file
+}
+
+fn draw_wavetable_wave(painter: &egui::Painter, rect: egui::Rect, selected: bool) {
+    let color = if selected { egui::Color32::WHITE } else { egui::Color32::GRAY };
+    let stroke = egui::Stroke::new(2.0, color);
+    // Dos ciclos: el primero senoidal y el segundo con forma de sierra
+    let mut points = Vec::new();
+    let width = rect.width();
+    let height = rect.height();
+    let center_y = rect.center().y;
+
+    for i in 0..=10 {
+        let x = rect.left() + (i as f32 * width / 20.0);
+        let y = center_y - (i as f32 * std::f32::consts::TAU / 10.0).sin() * height * 0.3;
+        points.push(egui::pos2(x, y));
+    }
+    points.push(egui::pos2(rect.left() + width * 0.5, center_y + height * 0.3));
+    points.push(egui::pos2(rect.right(), center_y - height * 0.3));
+    points.push(egui::pos2(rect.right(), center_y + height * 0.3));
+    painter.add(egui::Shape::line(points, stroke));
+}
+
+// Controles ADSR con el tiempo real de cada etapa bajo su control
+fn adsr_editor(ui: &mut egui::Ui, settings: &mut AdsrSettings) -> bool {
+    let mut changed = adsr_graph(ui, egui::vec2(320.0, 100.0), settings).changed();
+    ui.horizontal(|ui| {
+        for (label, time, max_time) in [
+            ("Ataque", &mut settings.attack, MAX_ATTACK_TIME),
+            ("Decaimiento", &mut settings.decay, MAX_DECAY_TIME),
+            ("Liberación", &mut settings.release, MAX_RELEASE_TIME),
+        ] {
+            ui.vertical(|ui| {
+                ui.label(label);
+                let mut value = stage_time_to_normalized(*time, max_time);
+                if ui.add(egui::widgets::Slider::new(&mut value, 0.0..=1.0)
+                    .show_value(true)
+                    .text(""))
+                    .changed()
+                {
+                    *time = stage_time_from_normalized(value, max_time);
+                    changed = true;
+                }
+                ui.label(format_time(*time));
+            });
+            ui.add_space(20.0);
+        }
+
+        ui.vertical(|ui| {
+            ui.label("Sostenido");
+            changed |= ui.add(egui::widgets::Slider::new(&mut settings.sustain, 0.0..=1.0)
+                .show_value(true)
+                .text(""))
+                .changed();
+        });
+        ui.add_space(20.0);
+
+        // Acorta el decaimiento y la liberación en las notas agudas
+        ui.vertical(|ui| {
+            ui.label("Seguimiento de teclado");
+            changed |= ui.add(egui::widgets::Slider::new(&mut settings.key_tracking, 0.0..=1.0)
+                .show_value(true)
+                .text(""))
+                .changed();
+        });
+    });
+    changed
+}
+
+// Contorno de la envolvente con un punto arrastrable por etapa: el pico del ataque
+// (tiempo), el final del decaimiento (tiempo y sostenido), el final del sostenido
+// (nivel) y el final de la liberación (tiempo). Cada etapa de tiempo ocupa hasta un
+// cuarto del ancho según su posición en el slider, y el sostenido un cuarto fijo.
+fn adsr_graph(ui: &mut egui::Ui, size: egui::Vec2, settings: &mut AdsrSettings) -> egui::Response {
+    let (rect, mut response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+    let segment = rect.width() / 4.0;
+    let level_y = |level: f32| rect.bottom() - level * rect.height();
+    
+    let points = |settings: &AdsrSettings| {
+        let attack_x = rect.left() + stage_time_to_normalized(settings.attack, MAX_ATTACK_TIME) * segment;
+        let decay_x = attack_x + stage_time_to_normalized(settings.decay, MAX_DECAY_TIME) * segment;
+        let sustain_x = decay_x + segment;
+        let release_x = sustain_x + stage_time_to_normalized(settings.release, MAX_RELEASE_TIME) * segment;
+        [
+            egui::pos2(rect.left(), rect.bottom()),
+            egui::pos2(attack_x, rect.top()),
+            egui::pos2(decay_x, level_y(settings.sustain)),
+            egui::pos2(sustain_x, level_y(settings.sustain)),
+            egui::pos2(release_x, rect.bottom()),
+        ]
+    };
+    
+    // Punto que se está arrastrando, guardado entre fotogramas
+    let drag_id = response.id.with("punto");
+    if response.drag_started() {
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let nearest = points(settings)[1..]
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.distance(pointer).total_cmp(&b.1.distance(pointer)))
+                .map(|(i, _)| i);
+            ui.data_mut(|data| data.insert_temp(drag_id, nearest));
+        }
+    }
+    
+    let dragged = ui.data(|data| data.get_temp::<Option<usize>>(drag_id)).flatten();
+    if let (Some(handle), Some(pointer)) = (dragged, response.interact_pointer_pos()) {
+        let [_, attack, _, sustain, _] = points(settings);
+        let level = ((rect.bottom() - pointer.y) / rect.height()).clamp(0.0, 1.0);
+        let before = *settings;
+        match handle {
+            0 => {
+                let value = ((pointer.x - rect.left()) / segment).clamp(0.0, 1.0);
+                settings.attack = stage_time_from_normalized(value, MAX_ATTACK_TIME);
+            }
+            1 => {
+                let value = ((pointer.x - attack.x) / segment).clamp(0.0, 1.0);
+                settings.decay = stage_time_from_normalized(value, MAX_DECAY_TIME);
+                settings.sustain = level;
+            }
+            2 => settings.sustain = level,
+            _ => {
+                let value = ((pointer.x - sustain.x) / segment).clamp(0.0, 1.0);
+                settings.release = stage_time_from_normalized(value, MAX_RELEASE_TIME);
+            }
+        }
+        if *settings != before {
+            response.mark_changed();
+        }
+    }
+    if response.drag_released() {
+        ui.data_mut(|data| data.remove::<Option<usize>>(drag_id));
+    }
+    
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+    let points = points(settings);
+    painter.add(egui::Shape::line(points.to_vec(), egui::Stroke::new(2.0, egui::Color32::WHITE)));
+    for (i, point) in points[1..].iter().enumerate() {
+        let color = if dragged == Some(i) { egui::Color32::WHITE } else { ui.visuals().selection.bg_fill };
+        painter.circle_filled(*point, 5.0, color);
+    }
+    
+    response
+}
+
+// Editor de la curva del MSEG. Los puntos se arrastran ajustados a la rejilla, un
+// doble clic añade un punto y el clic secundario quita el más cercano. El eje
+// horizontal cubre la curva redondeada al pulso (o segundo) siguiente.
+fn mseg_editor(ui: &mut egui::Ui, size: egui::Vec2, settings: &mut MsegSettings, selected: &mut usize) -> egui::Response {
+    let (rect, mut response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+    let view_length = settings.length().ceil().max(1.0);
+    let to_screen = |time: f32, level: f32| {
+        egui::pos2(
+            rect.left() + time / view_length * rect.width(),
+            rect.bottom() - level * rect.height(),
+        )
+    };
+    let from_screen = |pos: egui::Pos2| {
+        (
+            (pos.x - rect.left()) / rect.width() * view_length,
+            ((rect.bottom() - pos.y) / rect.height()).clamp(0.0, 1.0),
+        )
+    };
+    let nearest = |settings: &MsegSettings, pos: egui::Pos2| {
+        settings.active_points()
+            .iter()
+            .enumerate()
+            .min_by(|a, b| {
+                let a = to_screen(a.1.time, a.1.level).distance(pos);
+                let b = to_screen(b.1.time, b.1.level).distance(pos);
+                a.total_cmp(&b)
+            })
+            .map(|(i, _)| i)
+    };
+    let before = *settings;
+
+    let drag_id = response.id.with("punto");
+    if response.drag_started() {
+        if let Some(index) = response.interact_pointer_pos().and_then(|pos| nearest(settings, pos)) {
+            *selected = index;
+            ui.data_mut(|data| data.insert_temp(drag_id, true));
+        }
+    }
+    let dragging = ui.data(|data| data.get_temp::<bool>(drag_id)).unwrap_or(false);
+    if let (true, Some(pos)) = (dragging, response.interact_pointer_pos()) {
+        let (time, level) = from_screen(pos);
+        settings.move_point(*selected, settings.snap(time), level);
+    }
+    if response.drag_released() {
+        ui.data_mut(|data| data.remove::<bool>(drag_id));
+    }
+    if response.double_clicked() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            let (time, level) = from_screen(pos);
+            if time > 0.0 && time < settings.length() {
+                if let Some(index) = settings.insert_point(MsegPoint::new(settings.snap(time), level, 0.0)) {
+                    *selected = index;
+                }
+            }
+        }
+    } else if response.secondary_clicked() {
+        if let Some(index) = response.interact_pointer_pos().and_then(|pos| nearest(settings, pos)) {
+            settings.remove_point(index);
+        }
+    } else if response.clicked() {
+        if let Some(index) = response.interact_pointer_pos().and_then(|pos| nearest(settings, pos)) {
+            *selected = index;
+        }
+    }
+    if *settings != before {
+        response.mark_changed();
+    }
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+    // Rejilla: líneas finas en cada subdivisión y más marcadas en cada pulso
+    let steps = (view_length * settings.grid.max(1) as f32) as u32;
+    for step in 0..=steps {
+        let time = step as f32 / settings.grid.max(1) as f32;
+        let x = to_screen(time, 0.0).x;
+        let color = if step % settings.grid.max(1) == 0 { egui::Color32::GRAY } else { egui::Color32::from_gray(60) };
+        painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], egui::Stroke::new(1.0, color));
+    }
+    if let Some((start, end)) = settings.loop_region() {
+        let points = settings.active_points();
+        let region = egui::Rect::from_x_y_ranges(
+            to_screen(points[start].time, 0.0).x..=to_screen(points[end].time, 0.0).x,
+            rect.y_range(),
+        );
+        painter.rect_filled(region, 0.0, ui.visuals().selection.bg_fill.gamma_multiply(0.3));
+    }
+    let curve: Vec<egui::Pos2> = (0..=200)
+        .map(|i| {
+            let time = i as f32 / 200.0 * view_length;
+            to_screen(time, settings.level_at(time))
+        })
+        .collect();
+    painter.add(egui::Shape::line(curve, egui::Stroke::new(2.0, egui::Color32::WHITE)));
+    for (i, point) in settings.active_points().iter().enumerate() {
+        let color = if i == *selected { egui::Color32::WHITE } else { ui.visuals().selection.bg_fill };
+        painter.circle_filled(to_screen(point.time, point.level), 5.0, color);
+    }
+
+    response
+}
+
+// Tiempo legible: milisegundos por debajo de un segundo
+fn format_time(seconds: f32) -> String {
+    if seconds < 1.0 {
+        format!("{:.0} ms", seconds * 1000.0)
+    } else {
+        format!("{:.2} s", seconds)
+    }
+}
+
+fn draw_chip_wave(painter: &egui::Painter, rect: egui::Rect, selected: bool) {
+    let color = if selected { egui::Color32::WHITE } else { egui::Color32::GRAY };
+    let stroke = egui::Stroke::new(2.0, color);
+    // Triangular en escalones, como la de 4 bits de la NES
+    let steps = 8;
+    let mut points = Vec::new();
+    for i in 0..steps {
+        let phase = i as f32 / steps as f32;
+        let level = 1.0 - 4.0 * (phase - 0.5).abs();
+        let y = rect.center().y - level * rect.height() * 0.3;
+        points.push(egui::pos2(rect.left() + rect.width() * phase, y));
+        points.push(egui::pos2(rect.left() + rect.width() * (i + 1) as f32 / steps as f32, y));
+    }
+    painter.add(egui::Shape::line(points, stroke));
+}
+
+fn draw_additive_wave(painter: &egui::Painter, rect: egui::Rect, selected: bool) {
+    let color = if selected { egui::Color32::WHITE } else { egui::Color32::GRAY };
+    let stroke = egui::Stroke::new(2.0, color);
+    // Barras verticales de altura decreciente, como drawbars
+    let bottom = rect.center().y + rect.height() * 0.3;
+    for i in 0..5 {
+        let x = rect.left() + rect.width() * (0.2 + i as f32 * 0.15);
+        let height = rect.height() * 0.6 / (i + 1) as f32;
+        painter.line_segment([egui::pos2(x, bottom), egui::pos2(x, bottom - height)], stroke);
+    }
+}
@@ -1,38 +1,157 @@
-use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 use vst3_com::sys::GUID;
 use vst3_plugin::{
-    audio_processor::{AudioProcessor, Process, ProcessConfig, ProcessData, ProcessStatus},
+    audio_processor::{AudioProcessor, Process, ProcessConfig, ProcessData, ProcessStatus, SymbolicSampleSize},
     base::{Plugin, ThreadingModel},
     prelude::*,
     util::ProcessSetup,
 };
 
 mod audio;
+mod dsp;
 mod midi;
 mod structs;
 mod gui;
+mod parameters;
+mod editor;
+pub mod api;
+#[cfg(feature = "lv2")]
+pub mod lv2_plugin;
+#[cfg(all(feature = "au", target_os = "macos"))]
+pub mod au_plugin;
 
-use crate::audio::{Note, soft_clip};
-use crate::gui::WaveType;
-use crate::structs::envelope::Envelope;
+// API pública para incrustar y extender el motor desde otros crates
+pub use crate::api::{Effect, Patch, SynthEngine, VoiceManager};
+pub use crate::dsp::{Note, OscillatorSource, VoiceId, VoiceMap};
 
+use crate::audio::engine::LayeredFrame;
+use crate::audio::stats::EngineStats;
+use crate::audio::watchdog::VoiceWatchdog;
+use crate::audio::stereo_channel_sample;
+use crate::api::{fx_slot_of, ParameterGroup, ParameterKind, PARAMETERS};
+use crate::parameters::parameter_scale;
+use crate::editor::{EditorShared, PluginEditor};
+use crate::gui::SynthConfig;
+use crate::dsp::wavetable::build_additive_table;
+use crate::midi::parser::MidiParser;
+use crate::midi::{ALL_NOTES_OFF_CC, ALL_SOUND_OFF_CC, SUSTAIN_PEDAL_CC};
+
+// Parámetros del plugin: el patch completo del motor. Arranca con el sonido del preset
+// Init, con dos osciladores como la aplicación
 #[derive(Default)]
 struct RustSynthController {
-    wave_type: Arc<Mutex<WaveType>>,
-    attack: f32,
-    decay: f32,
-    sustain: f32,
-    release: f32,
+    patch: Patch,
+}
+
+// Unidad VST3 de un grupo de parámetros; el host muestra los grupos como árbol bajo la
+// unidad raíz, en los editores genéricos y en las superficies de control
+fn unit_info(group: ParameterGroup) -> UnitInfo {
+    UnitInfo {
+        id: group as i32,
+        parent_unit_id: kRootUnitId,
+        name: group.as_str().into(),
+        program_list_id: kNoProgramListId,
+    }
+}
+
+// Salidas de audio del plugin. La principal lleva la mezcla completa; las auxiliares,
+// desactivadas hasta que el host las pida, separan capas para tratarlas en el DAW
+#[derive(Clone, Copy, PartialEq)]
+enum OutputBus {
+    Main,
+    // Voces tras el filtro maestro, antes del rack de efectos y la saturación
+    Dry,
+    // Solo lo que añade el rack: su salida menos la señal seca
+    Fx,
+    // Voces alternas por pulsación (con sus copias del unísono), tomadas antes del filtro
+    // maestro y los efectos; en silencio en modo parafónico
+    EvenVoices,
+    OddVoices,
+}
+
+impl OutputBus {
+    const ALL: [OutputBus; 5] = [
+        OutputBus::Main,
+        OutputBus::Dry,
+        OutputBus::Fx,
+        OutputBus::EvenVoices,
+        OutputBus::OddVoices,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            OutputBus::Main => "Salida",
+            OutputBus::Dry => "Seco",
+            OutputBus::Fx => "Solo FX",
+            OutputBus::EvenVoices => "Voces pares",
+            OutputBus::OddVoices => "Voces impares",
+        }
+    }
+
+    fn info(&self) -> BusInfo {
+        BusInfo {
+            media_type: MediaType::Audio,
+            direction: BusDirection::Output,
+            channel_count: 2,
+            name: self.as_str().into(),
+            bus_type: if *self == OutputBus::Main { BusType::Main } else { BusType::Aux },
+            flags: if *self == OutputBus::Main { BusFlags::DEFAULT_ACTIVE } else { BusFlags::empty() },
+        }
+    }
+
+    // Frame estéreo de esta salida
+    fn frame(&self, frame: &LayeredFrame) -> (f32, f32) {
+        let layers = &frame.layers;
+        match self {
+            OutputBus::Main => layers.output,
+            OutputBus::Dry => layers.dry,
+            OutputBus::Fx => (layers.wet.0 - layers.dry.0, layers.wet.1 - layers.dry.1),
+            OutputBus::EvenVoices => frame.even_voices,
+            OutputBus::OddVoices => frame.odd_voices,
+        }
+    }
+}
+
+const OUTPUT_BUS_COUNT: usize = OutputBus::ALL.len();
+
+// Ganancia de cada voz con el volumen al máximo; con el volumen por defecto (0.5) cada
+// voz entra a 0.15 en la mezcla, con margen para varias notas antes de saturar
+const VOICE_GAIN: f32 = 0.3;
+
+// IDs de todos los parámetros registrados, en el orden del registro
+fn parameter_ids() -> impl Iterator<Item = u32> {
+    PARAMETERS.iter().map(|info| info.id)
 }
 
 #[derive(Default)]
 struct RustSynth {
-    active_notes: Arc<Mutex<HashMap<u8, Note>>>,
+    // Voces, filtro maestro, rack de efectos y saturación, igual que en la aplicación:
+    // glide, unísono, panorama por voz, teclado dividido, legato y modo parafónico
+    voices: VoiceManager,
     sample_rate: f32,
-    wave_type: Arc<Mutex<WaveType>>,
     process_config: ProcessConfig,
     controller: RustSynthController,
+    // Flujo MIDI del host, por si agrupa o parte los mensajes
+    midi_parser: MidiParser,
+    // Carga del callback de process(), legible sin bloquear el audio
+    stats: Arc<EngineStats>,
+    // Protección contra Note Off perdidos; el panel de diagnóstico del editor la comparte
+    voice_watchdog: Arc<VoiceWatchdog>,
+    // Cambios de parámetros del bloque en curso como (muestra, ID, valor), guardados
+    // aquí para no reservar memoria en cada process()
+    parameter_changes: Vec<(usize, u32, f64)>,
+    // Capas de la salida en cada muestra del bloque, en f32; se copian a cada salida en
+    // la precisión que pida el host (32 o 64 bits), reservado en set_process_config
+    frames: Vec<LayeredFrame>,
+    // Salidas auxiliares que ha activado el host; empiezan apagadas y la principal
+    // se escribe siempre
+    active_buses: [bool; OUTPUT_BUS_COUNT],
+    // Liberación que les queda a las voces que suenan, en segundos (bits de un f32);
+    // se publica al final de process() para que el host la consulte sin tocar las notas
+    voice_tail: AtomicU32,
+    // Ajustes y avisos que comparte con la ventana del editor
+    editor: Arc<EditorShared>,
 }
 
 impl Plugin for RustSynth {
@@ -59,93 +178,252 @@ impl Plugin for RustSynth {
 }
 
 impl AudioProcessor for RustSynth {
+    fn get_bus_count(&self, media_type: MediaType, direction: BusDirection) -> i32 {
+        match (media_type, direction) {
+            (MediaType::Audio, BusDirection::Output) => OUTPUT_BUS_COUNT as i32,
+            (MediaType::Event, BusDirection::Input) => 1,
+            _ => 0,
+        }
+    }
+
+    fn get_bus_info(&self, media_type: MediaType, direction: BusDirection, index: i32) -> Option<BusInfo> {
+        match (media_type, direction) {
+            (MediaType::Audio, BusDirection::Output) => OutputBus::ALL.get(index as usize).map(OutputBus::info),
+            (MediaType::Event, BusDirection::Input) if index == 0 => Some(BusInfo {
+                media_type: MediaType::Event,
+                direction: BusDirection::Input,
+                channel_count: 16,
+                name: "MIDI".into(),
+                bus_type: BusType::Main,
+                flags: BusFlags::DEFAULT_ACTIVE,
+            }),
+            _ => None,
+        }
+    }
+
+    fn activate_bus(&mut self, media_type: MediaType, direction: BusDirection, index: i32, state: bool) -> tresult {
+        match (media_type, direction) {
+            (MediaType::Audio, BusDirection::Output) if (index as usize) < OUTPUT_BUS_COUNT => {
+                self.active_buses[index as usize] = state;
+                kResultOk
+            }
+            (MediaType::Event, BusDirection::Input) if index == 0 => kResultOk,
+            _ => kInvalidArgument,
+        }
+    }
+
+    // Sin entradas de audio; cada salida puede ser estéreo o mono (la mezcla de ambos lados)
+    fn set_bus_arrangements(&mut self, inputs: &[SpeakerArrangement], outputs: &[SpeakerArrangement]) -> tresult {
+        let supported = inputs.is_empty()
+            && outputs.len() <= OUTPUT_BUS_COUNT
+            && outputs.iter().all(|arrangement| matches!(*arrangement, SpeakerArrangement::STEREO | SpeakerArrangement::MONO));
+        if supported { kResultOk } else { kResultFalse }
+    }
+
+    // El motor calcula siempre en f32 y convierte al escribir la salida
+    fn can_process_sample_size(&self, sample_size: SymbolicSampleSize) -> bool {
+        matches!(sample_size, SymbolicSampleSize::Sample32 | SymbolicSampleSize::Sample64)
+    }
+
+    // El host puede cambiar la frecuencia y el tamaño de bloque sin recrear el plugin:
+    // las voces que suenan, el filtro maestro y el rack pasan a los coeficientes nuevos
     fn set_process_config(&mut self, config: ProcessConfig) {
         self.process_config = config;
-        self.sample_rate = config.sample_rate;
+        if config.sample_rate != self.sample_rate {
+            self.sample_rate = config.sample_rate;
+            self.voices.set_sample_rate(config.sample_rate);
+        }
+        // La reverb recibe sus particiones ya calculadas, fuera del hilo de audio
+        self.controller.patch.fx.reverb.prepare(config.sample_rate);
+        self.frames = vec![LayeredFrame::default(); config.max_samples_per_block.max(0) as usize];
     }
 
     fn process(&mut self, data: ProcessData<'_>) -> ProcessStatus {
+        let started = std::time::Instant::now();
+        // Automatización del host: cada cola trae los cambios de un parámetro durante el
+        // bloque; se juntan todos ordenados por la muestra en la que tocan
+        let mut changes = std::mem::take(&mut self.parameter_changes);
+        changes.clear();
+        if let Some(parameter_changes) = data.inputs.parameter_changes {
+            for queue in parameter_changes.queues() {
+                let id = queue.parameter_id();
+                changes.extend(queue.points().map(|point| (point.sample_offset.max(0) as usize, id, point.value)));
+            }
+        }
+        // Ordenación estable: los cambios de un mismo parámetro conservan su orden
+        changes.sort_by_key(|change| change.0);
+        let mut next_change = 0;
+
+        // Lo que el editor cambia sin parámetro del host (orden del rack, respuesta al
+        // impulso, armónicos con su tabla ya calculada...); los valores de los
+        // parámetros siguen siendo los del host
+        self.editor.take_patch(|patch, additive_table| {
+            self.controller.patch.merge_unregistered(patch);
+            self.voices.set_additive_table(patch.harmonic_levels, additive_table);
+        });
+
         // Procesar eventos MIDI
         if let Some(events) = data.inputs.events {
             for event in events.events() {
                 if let Some(midi_event) = event.try_as_midi() {
-                    self.handle_midi_event(midi_event);
+                    // El parser se saca un momento para poder llamar a `self` desde el callback
+                    let mut parser = std::mem::take(&mut self.midi_parser);
+                    parser.feed(&midi_event.data[..], |message| self.handle_midi_message(message));
+                    self.midi_parser = parser;
                 }
             }
         }
 
         // Procesar audio
-        if let Some(mut output) = data.outputs.first_mut() {
-            let num_samples = output.samples_per_channel() as usize;
-            let mut notes = self.active_notes.lock().unwrap();
-
-            for frame_idx in 0..num_samples {
-                let mut mix = 0.0;
+        if let Some(num_samples) = data.outputs.first().map(|output| output.samples_per_channel() as usize) {
+            // Solo reserva si el host pasa un bloque mayor del que anunció
+            let mut frames = std::mem::take(&mut self.frames);
+            if frames.len() < num_samples {
+                frames.resize(num_samples, LayeredFrame::default());
+            }
 
-                for note in notes.values_mut() {
-                    let envelope_amp = note.envelope.next_sample();
-                    let sine_value = note.get_sample();
-                    mix += sine_value * envelope_amp * 0.15;
+            // El bloque se parte en cada cambio de parámetro, para que la automatización
+            // rápida (p. ej. del corte) no suene a escalones del tamaño del buffer
+            self.voices.apply_patch(&self.controller.patch);
+            let mut stolen = 0;
+            let mut start = 0;
+            while start < num_samples {
+                if changes.get(next_change).is_some_and(|change| change.0 <= start) {
+                    // El bypass y la mezcla de las ranuras van directos al rack; el resto
+                    // vuelve a aplicar el patch
+                    let mut patch_changed = false;
+                    while let Some(&(_, id, value)) = changes.get(next_change).filter(|change| change.0 <= start) {
+                        self.controller.set_normalized(id, value);
+                        match fx_slot_of(id) {
+                            Some(slot) => if let Some(&settings) = self.controller.patch.fx.slots.get(slot) {
+                                self.voices.set_fx_slot(slot, settings);
+                            },
+                            None => patch_changed = true,
+                        }
+                        next_change += 1;
+                    }
+                    if patch_changed {
+                        self.voices.apply_patch(&self.controller.patch);
+                    }
                 }
+                let end = changes.get(next_change).map_or(num_samples, |change| change.0.min(num_samples));
+                let gain = self.controller.patch.volume * VOICE_GAIN;
+                stolen += self.voices.render_layers(gain, &mut frames[start..end]);
+                start = end;
+            }
 
-                // Aplicar soft clip
-                let processed = soft_clip(mix);
-
-                // Escribir a todos los canales de salida
-                for channel in output.channels_mut() {
-                    channel[frame_idx] = processed;
+            // Repartir cada frame estéreo entre los canales de cada salida activa, en f32 o en f64
+            let frames_in_block = &frames[..num_samples];
+            for (bus, output) in data.outputs.iter_mut().enumerate().take(OUTPUT_BUS_COUNT) {
+                if bus != OutputBus::Main as usize && !self.active_buses[bus] {
+                    continue;
+                }
+                let bus = OutputBus::ALL[bus];
+                match self.process_config.symbolic_sample_size {
+                    SymbolicSampleSize::Sample64 => {
+                        let channel_count = output.channels_mut_f64().count();
+                        for (c, channel) in output.channels_mut_f64().enumerate() {
+                            for (sample, frame) in channel.iter_mut().zip(frames_in_block) {
+                                *sample = stereo_channel_sample(bus.frame(frame), c, channel_count) as f64;
+                            }
+                        }
+                    }
+                    SymbolicSampleSize::Sample32 => {
+                        let channel_count = output.channels_mut().count();
+                        for (c, channel) in output.channels_mut().enumerate() {
+                            for (sample, frame) in channel.iter_mut().zip(frames_in_block) {
+                                *sample = stereo_channel_sample(bus.frame(frame), c, channel_count);
+                            }
+                        }
+                    }
                 }
             }
+            let peak = frames_in_block.iter()
+                .map(|frame| frame.layers.output.0.abs().max(frame.layers.output.1.abs()))
+                .fold(0.0f32, f32::max);
+            self.frames = frames;
+
+            // Vigilar voces colgadas por Note Off perdidos
+            self.voices.check_watchdog(&self.voice_watchdog);
+            
+            // Las voces terminadas ya se retiraron al renderizar; se cuentan las que se
+            // cortaron a la fuerza
+            self.stats.add_stolen(stolen);
+            self.voice_tail.store(self.voices.remaining_tail().to_bits(), Ordering::Relaxed);
+            self.stats.record_callback(started.elapsed(), num_samples, self.sample_rate, self.voices.active_voices(), peak);
+        }
 
-            // Eliminar notas terminadas
-            notes.retain(|_, note| !note.envelope.is_finished());
+        // Los cambios que no llegaron a sonar (sin salida o fuera del bloque) se aplican igual
+        for &(_, id, value) in &changes[next_change..] {
+            self.controller.set_normalized(id, value);
         }
+        self.parameter_changes = changes;
 
         ProcessStatus::Normal
     }
 
+    // Cola tras el último Note Off: la liberación más larga, la de una nota nueva o la
+    // que les queda a las voces que ya suenan, más lo que tardan en apagarse los efectos
+    // del rack (repeticiones del delay, reverb). Se calcula en cada consulta, así que
+    // sigue a los cambios de release y de efectos; lo de las voces es lo que publicó el
+    // último process(), porque el host puede preguntar desde otro hilo
     fn get_tail_samples(&self) -> u32 {
-        0
+        let voices = f32::from_bits(self.voice_tail.load(Ordering::Relaxed));
+        let release = self.controller.patch.envelope.max_tail().max(voices);
+        ((release + self.controller.patch.fx.tail_time()) * self.sample_rate).ceil() as u32
     }
 }
 
 impl RustSynth {
-    fn handle_midi_event(&mut self, event: MidiEvent<'_>) {
-        let status = event.data[0] & 0xF0;
+    // Mensaje completo del parser, con su byte de estado
+    fn handle_midi_message(&mut self, data: &[u8]) {
+        let status = data[0] & 0xF0;
         match status {
             0x90 => { // Note On
-                let note = event.data[1];
-                let velocity = event.data[2] as f32 / 127.0;
+                let note = data[1];
+                let velocity = self.controller.patch.velocity_response.apply(data[2] as f32 / 127.0);
                 if velocity > 0.0 {
-                    let freq = midi::midi_note_to_freq(note);
-                    let mut envelope = Envelope::new(self.sample_rate);
-                    envelope.set_adsr(
-                        self.controller.attack,
-                        self.controller.decay,
-                        self.controller.sustain,
-                        self.controller.release
-                    );
-                    envelope.set_velocity(velocity);
-                    let current_wave_type = *self.wave_type.lock().unwrap();
-                    let new_note = Note::new(
-                        freq,
-                        envelope,
-                        self.sample_rate,
-                        current_wave_type,
-                        current_wave_type,
-                    );
-                    self.active_notes.lock().unwrap().insert(note, new_note);
+                    self.voice_watchdog.key_down(note);
+                    self.voices.note_on(note, velocity, &self.controller.patch);
                 } else {
-                    if let Some(note) = self.active_notes.lock().unwrap().get_mut(&note) {
-                        note.envelope.note_off();
-                    }
+                    self.voice_watchdog.key_up(note);
+                    self.voices.note_off(note);
                 }
             },
             0x80 => { // Note Off
-                let note = event.data[1];
-                if let Some(note) = self.active_notes.lock().unwrap().get_mut(&note) {
-                    note.envelope.note_off();
-                }
+                let note = data[1];
+                self.voice_watchdog.key_up(note);
+                self.voices.note_off(note);
+            },
+            0xB0 => match data[1] {
+                // All Notes Off: todas las voces pasan a la liberación
+                ALL_NOTES_OFF_CC => {
+                    self.voice_watchdog.release_all();
+                    self.voices.all_notes_off();
+                },
+                // All Sound Off: silencio inmediato, también en las colas de los efectos
+                ALL_SOUND_OFF_CC => {
+                    self.voice_watchdog.release_all();
+                    self.voices.reset();
+                },
+                // Control Change: fuente de la matriz de modulación (CC1 va al vibrato por
+                // defecto) o parte de un RPN (rango del pitch bend, transposición). Con el
+                // pedal de sustain pisado el vigilante no libera voces.
+                _ => {
+                    if data[1] == SUSTAIN_PEDAL_CC {
+                        self.voice_watchdog.set_sustain_pedal(data[2] >= 64);
+                    }
+                    self.voices.control_change(data[1], data[2]);
+                },
+            },
+            0xE0 => { // Pitch Bend, con el rango que fije el RPN 0,0
+                self.voices.pitch_bend(((data[2] as u16) << 7) | data[1] as u16);
+            },
+            0xA0 => { // Polyphonic Key Pressure: presión propia de la voz de esa tecla
+                self.voices.poly_pressure(data[1], data[2]);
+            },
+            0xD0 => { // Channel Pressure: aftertouch de canal, fuente de la matriz
+                self.voices.channel_pressure(data[1]);
             },
             _ => (),
         }
@@ -165,94 +443,177 @@ impl EditController for RustSynth {
         String::new()
     }
 
+    // La unidad raíz y un grupo por sección del sintetizador
+    fn get_unit_count(&self) -> i32 {
+        ParameterGroup::ALL.len() as i32 + 1
+    }
+
+    fn get_unit_info(&self, unit_index: i32) -> Option<UnitInfo> {
+        if unit_index == 0 {
+            return Some(UnitInfo {
+                id: kRootUnitId,
+                parent_unit_id: kNoParentUnitId,
+                name: "Rust Synth".into(),
+                program_list_id: kNoProgramListId,
+            });
+        }
+        ParameterGroup::ALL.get((unit_index - 1) as usize).copied().map(unit_info)
+    }
+
     fn get_parameter_count(&self) -> i32 {
-        5
+        PARAMETERS.len() as i32
     }
 
+    // Las unidades van en el texto de cada valor (get_parameter_string_by_value),
+    // que las adapta a la escala: 120 ms o 1.20 s, 800 Hz o 2.4 kHz
     fn get_parameter_info(&self, param_index: i32) -> ParameterInfo {
-        match param_index {
-            0 => ParameterInfo {
-                id: 0,
-                title: String::from("Wave Type"),
-                short_title: String::from("Wave"),
-                units: String::new(),
-                step_count: 3,
-                default_normalized_value: 0.0,
-                unit_id: 0,
-                parameter_flags: ParameterFlags::empty(),
-            },
-            1 => ParameterInfo {
-                id: 1,
-                title: String::from("Attack"),
-                short_title: String::from("Atk"),
-                units: String::from("s"),
-                step_count: 0,
-                default_normalized_value: 0.01,
-                unit_id: 0,
-                parameter_flags: ParameterFlags::empty(),
-            },
-            2 => ParameterInfo {
-                id: 2,
-                title: String::from("Decay"),
-                short_title: String::from("Dec"),
-                units: String::from("s"),
-                step_count: 0,
-                default_normalized_value: 0.1,
-                unit_id: 0,
-                parameter_flags: ParameterFlags::empty(),
+        let Some(info) = usize::try_from(param_index).ok().and_then(|index| PARAMETERS.get(index)) else {
+            return Default::default();
+        };
+        ParameterInfo {
+            id: info.id,
+            title: info.name.into(),
+            short_title: info.short_name.into(),
+            units: String::new(),
+            step_count: info.step_count() as i32,
+            default_normalized_value: info.to_normalized(info.default) as f64,
+            unit_id: info.group as i32,
+            // Las listas muestran el nombre de cada opción en get_parameter_string_by_value
+            parameter_flags: match info.kind {
+                ParameterKind::Choice(_) => ParameterFlags::IS_LIST,
+                _ => ParameterFlags::empty(),
             },
-            3 => ParameterInfo {
-                id: 3,
-                title: String::from("Sustain"),
-                short_title: String::from("Sus"),
-                units: String::new(),
-                step_count: 0,
-                default_normalized_value: 0.7,
-                unit_id: 0,
-                parameter_flags: ParameterFlags::empty(),
-            },
-            4 => ParameterInfo {
-                id: 4,
-                title: String::from("Release"),
-                short_title: String::from("Rel"),
-                units: String::from("s"),
-                step_count: 0,
-                default_normalized_value: 0.3,
-                unit_id: 0,
-                parameter_flags: ParameterFlags::empty(),
-            },
-            _ => Default::default(),
         }
     }
 
     fn get_parameter_normalized(&self, id: u32) -> f64 {
-        match id {
-            0 => *self.wave_type.lock().unwrap() as u8 as f64 / 3.0,
-            1 => self.controller.attack as f64,
-            2 => self.controller.decay as f64,
-            3 => self.controller.sustain as f64,
-            4 => self.controller.release as f64,
+        self.controller.normalized(id)
+    }
+
+    fn set_parameter_normalized(&mut self, id: u32, value: f64) {
+        self.controller.set_normalized(id, value);
+        // Con el editor abierto, sus controles siguen a la automatización y a la lista
+        // genérica de parámetros del host
+        self.editor.push_host_change(id, value);
+    }
+
+    fn set_component_handler(&mut self, handler: Option<ComponentHandler>) -> tresult {
+        self.editor.set_handler(handler);
+        kResultOk
+    }
+
+    // La misma interfaz que la aplicación, incrustada en la ventana del host
+    fn create_view(&mut self, name: &str) -> Option<Box<dyn IPlugView>> {
+        if name != ViewType::EDITOR {
+            return None;
+        }
+        // Los controles empiezan con los valores actuales del plugin
+        self.controller.store_config(&self.editor.config());
+        self.editor.config().voice_watchdog = self.voice_watchdog.clone();
+        Some(Box::new(PluginEditor::new(self.editor.clone())))
+    }
+
+    fn get_parameter_string_by_value(&self, id: u32, value_normalized: f64) -> String {
+        parameter_scale(id).map_or_else(String::new, |scale| scale.format(value_normalized))
+    }
+
+    fn get_parameter_value_by_string(&self, id: u32, string: &str) -> Option<f64> {
+        parameter_scale(id)?.parse(string)
+    }
+
+    fn normalized_parameter_to_plain(&self, id: u32, value_normalized: f64) -> f64 {
+        parameter_scale(id).map_or(value_normalized, |scale| scale.to_plain(value_normalized))
+    }
+
+    fn plain_parameter_to_normalized(&self, id: u32, plain_value: f64) -> f64 {
+        parameter_scale(id).map_or(plain_value, |scale| scale.to_normalized(plain_value))
+    }
+}
+
+impl RustSynthController {
+    // Valor normalizado (0-1) de un parámetro por su ID
+    fn normalized(&self, id: u32) -> f64 {
+        match (parameter_scale(id), self.patch.parameter(id)) {
+            (Some(scale), Ok(value)) => scale.to_normalized(value as f64),
             _ => 0.0,
         }
     }
 
-    fn set_parameter_normalized(&mut self, id: u32, value: f64) {
-        match id {
-            0 => {
-                let wave_type = match (value * 3.0).round() as u8 {
-                    0 => WaveType::Sine,
-                    1 => WaveType::Square,
-                    2 => WaveType::Triangle,
-                    _ => WaveType::Sawtooth,
-                };
-                *self.wave_type.lock().unwrap() = wave_type;
-            }
-            1 => self.controller.attack = value as f32,
-            2 => self.controller.decay = value as f32,
-            3 => self.controller.sustain = value as f32,
-            4 => self.controller.release = value as f32,
-            _ => (),
+    fn set_normalized(&mut self, id: u32, value: f64) {
+        if let Some(scale) = parameter_scale(id) {
+            let _ = self.patch.set_parameter(id, scale.to_plain(value) as f32);
+        }
+    }
+
+    // Copiar los ajustes de la interfaz
+    fn load_config(&mut self, config: &SynthConfig) {
+        self.patch.volume = *config.volume.lock().unwrap();
+        self.patch.oscillators = *config.oscillators.lock().unwrap();
+        self.patch.oscillator_count = *config.oscillator_count.lock().unwrap();
+        self.patch.wavetable_position = *config.wavetable_position.lock().unwrap();
+        self.patch.wavetable_sweep = *config.wavetable_sweep.lock().unwrap();
+        self.patch.harmonic_levels = config.additive_table.lock().unwrap().0;
+        self.patch.quality = *config.quality.lock().unwrap();
+        self.patch.legato = *config.legato.lock().unwrap();
+        self.patch.legato_retrigger_amp = *config.legato_retrigger_amp.lock().unwrap();
+        self.patch.legato_retrigger_mod = *config.legato_retrigger_mod.lock().unwrap();
+        self.patch.voice_drive = *config.voice_drive.lock().unwrap();
+        self.patch.voice_drive_velocity = *config.voice_drive_velocity.lock().unwrap();
+        self.patch.voice_filter_env_depth = *config.voice_filter_env_depth.lock().unwrap();
+        self.patch.vector = *config.vector.lock().unwrap();
+        self.patch.lfo = *config.lfo.lock().unwrap();
+        self.patch.mseg = *config.mseg.lock().unwrap();
+        self.patch.mod_matrix = *config.mod_matrix.lock().unwrap();
+        self.patch.filter = *config.filter.lock().unwrap();
+        self.patch.envelope = *config.envelope.lock().unwrap();
+        self.patch.filter_envelope = *config.filter_envelope.lock().unwrap();
+        self.patch.split = *config.split.lock().unwrap();
+        self.patch.unison = *config.unison.lock().unwrap();
+        self.patch.voice_pan = *config.voice_pan.lock().unwrap();
+        self.patch.paraphonic = *config.paraphonic.lock().unwrap();
+        self.patch.glide = *config.glide.lock().unwrap();
+        self.patch.master_filter = *config.master_filter.lock().unwrap();
+        self.patch.fx = config.fx.lock().unwrap().clone();
+        self.patch.saturation = *config.saturation.lock().unwrap();
+        self.patch.velocity_response = *config.velocity_response.lock().unwrap();
+    }
+
+    // Llevar los parámetros a la interfaz
+    fn store_config(&self, config: &SynthConfig) {
+        *config.volume.lock().unwrap() = self.patch.volume;
+        *config.oscillators.lock().unwrap() = self.patch.oscillators;
+        *config.oscillator_count.lock().unwrap() = self.patch.oscillator_count;
+        *config.wavetable_position.lock().unwrap() = self.patch.wavetable_position;
+        *config.wavetable_sweep.lock().unwrap() = self.patch.wavetable_sweep;
+        // La tabla aditiva solo se reconstruye si cambian los armónicos
+        let mut additive_table = config.additive_table.lock().unwrap();
+        if additive_table.0 != self.patch.harmonic_levels {
+            *additive_table = (self.patch.harmonic_levels, build_additive_table(&self.patch.harmonic_levels));
         }
+        drop(additive_table);
+        *config.quality.lock().unwrap() = self.patch.quality;
+        *config.legato.lock().unwrap() = self.patch.legato;
+        *config.legato_retrigger_amp.lock().unwrap() = self.patch.legato_retrigger_amp;
+        *config.legato_retrigger_mod.lock().unwrap() = self.patch.legato_retrigger_mod;
+        *config.voice_drive.lock().unwrap() = self.patch.voice_drive;
+        *config.voice_drive_velocity.lock().unwrap() = self.patch.voice_drive_velocity;
+        *config.voice_filter_env_depth.lock().unwrap() = self.patch.voice_filter_env_depth;
+        *config.vector.lock().unwrap() = self.patch.vector;
+        *config.lfo.lock().unwrap() = self.patch.lfo;
+        *config.mseg.lock().unwrap() = self.patch.mseg;
+        *config.mod_matrix.lock().unwrap() = self.patch.mod_matrix;
+        *config.filter.lock().unwrap() = self.patch.filter;
+        *config.envelope.lock().unwrap() = self.patch.envelope;
+        *config.filter_envelope.lock().unwrap() = self.patch.filter_envelope;
+        *config.split.lock().unwrap() = self.patch.split;
+        *config.unison.lock().unwrap() = self.patch.unison;
+        *config.voice_pan.lock().unwrap() = self.patch.voice_pan;
+        *config.paraphonic.lock().unwrap() = self.patch.paraphonic;
+        *config.glide.lock().unwrap() = self.patch.glide;
+        *config.master_filter.lock().unwrap() = self.patch.master_filter;
+        *config.fx.lock().unwrap() = self.patch.fx.clone();
+        *config.saturation.lock().unwrap() = self.patch.saturation;
+        *config.velocity_response.lock().unwrap() = self.patch.velocity_response;
     }
 }
 
@@ -0,0 +1,175 @@
+// Plugin LV2 (feature "lv2") sobre el mismo motor que la API: entrada MIDI por un
+// puerto de átomos, salida estéreo y un puerto de control por cada parámetro de
+// `PARAMETERS`, en sus unidades reales. Los .ttl del bundle los genera `ttl`:
+//
+//     cargo build --release --features lv2
+//     cargo run --release --features lv2 --bin lv2-bundle -- ~/.lv2/rust-synth.lv2
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use lv2::lv2_core::port::{PortHandle, PortPointerCache};
+use lv2::prelude::*;
+use crate::api::{SynthEngine, PARAMETERS};
+
+pub mod ttl;
+
+// URI con la que el host identifica el plugin, la misma en la biblioteca y en los .ttl
+macro_rules! plugin_uri {
+    () => {
+        "urn:rust-synth:synth"
+    };
+}
+
+pub const PLUGIN_URI: &str = plugin_uri!();
+
+// Índices de los puertos; los de control siguen el orden de `PARAMETERS`
+pub const MIDI_PORT: usize = 0;
+pub const LEFT_PORT: usize = 1;
+pub const RIGHT_PORT: usize = 2;
+pub const FIRST_CONTROL_PORT: usize = 3;
+const CONTROL_COUNT: usize = PARAMETERS.len();
+
+// Puertos de un bloque de run(): los de control se leen una vez al principio
+struct Ports {
+    midi: InputPort<AtomPort>,
+    left: OutputPort<Audio>,
+    right: OutputPort<Audio>,
+    controls: [f32; CONTROL_COUNT],
+}
+
+// Punteros que conecta el host con connect_port, válidos hasta que los cambie
+struct PortPointers {
+    midi: *mut c_void,
+    left: *mut c_void,
+    right: *mut c_void,
+    controls: [*mut c_void; CONTROL_COUNT],
+}
+
+impl Default for PortPointers {
+    fn default() -> Self {
+        Self {
+            midi: std::ptr::null_mut(),
+            left: std::ptr::null_mut(),
+            right: std::ptr::null_mut(),
+            controls: [std::ptr::null_mut(); CONTROL_COUNT],
+        }
+    }
+}
+
+impl PortPointerCache for PortPointers {
+    fn connect(&mut self, index: u32, pointer: *mut c_void) {
+        match index as usize {
+            MIDI_PORT => self.midi = pointer,
+            LEFT_PORT => self.left = pointer,
+            RIGHT_PORT => self.right = pointer,
+            index => {
+                if let Some(control) = self.controls.get_mut(index - FIRST_CONTROL_PORT) {
+                    *control = pointer;
+                }
+            }
+        }
+    }
+}
+
+impl PortCollection for Ports {
+    type Cache = PortPointers;
+
+    // Sin todos los puertos conectados el bloque no se procesa
+    unsafe fn from_connections(cache: &PortPointers, sample_count: u32) -> Option<Self> {
+        let mut controls = [0.0; CONTROL_COUNT];
+        for (value, pointer) in controls.iter_mut().zip(cache.controls.iter()) {
+            *value = *pointer.cast::<f32>().as_ref()?;
+        }
+        Some(Self {
+            midi: InputPort::from_raw(NonNull::new(cache.midi)?, sample_count),
+            left: OutputPort::from_raw(NonNull::new(cache.left)?, sample_count),
+            right: OutputPort::from_raw(NonNull::new(cache.right)?, sample_count),
+            controls,
+        })
+    }
+}
+
+#[derive(FeatureCollection)]
+struct Features<'a> {
+    map: LV2Map<'a>,
+}
+
+#[derive(URIDCollection)]
+struct Urids {
+    atom: AtomURIDCollection,
+    midi: MidiURIDCollection,
+    unit: UnitURIDCollection,
+}
+
+struct RustSynthLv2 {
+    engine: SynthEngine,
+    urids: Urids,
+    // Último valor de cada puerto de control, para aplicar solo los que cambian
+    controls: [f32; CONTROL_COUNT],
+}
+
+unsafe impl UriBound for RustSynthLv2 {
+    const URI: &'static [u8] = concat!(plugin_uri!(), "\0").as_bytes();
+}
+
+impl RustSynthLv2 {
+    fn apply_controls(&mut self, controls: &[f32; CONTROL_COUNT]) {
+        for ((info, last), &value) in PARAMETERS.iter().zip(self.controls.iter_mut()).zip(controls.iter()) {
+            if value != *last {
+                *last = value;
+                // Los identificadores salen del propio registro, así que siempre existen
+                let _ = self.engine.set_parameter(info.id, value.clamp(info.min, info.max));
+            }
+        }
+    }
+}
+
+impl Plugin for RustSynthLv2 {
+    type Ports = Ports;
+    type InitFeatures = Features<'static>;
+    type AudioFeatures = ();
+
+    fn new(plugin_info: &PluginInfo, features: &mut Features<'static>) -> Option<Self> {
+        let engine = SynthEngine::new(plugin_info.sample_rate() as f32);
+        let controls = std::array::from_fn(|i| engine.parameter(PARAMETERS[i].id).unwrap_or(PARAMETERS[i].default));
+        Some(Self {
+            engine,
+            urids: features.map.populate_collection()?,
+            controls,
+        })
+    }
+
+    // Al reactivar el plugin no deben quedar voces ni colas del uso anterior
+    fn activate(&mut self, _features: &mut ()) {
+        self.engine.reset();
+    }
+
+    // El bloque se parte en cada evento MIDI, para que las notas empiecen en su muestra
+    fn run(&mut self, ports: &mut Ports, _features: &mut (), sample_count: u32) {
+        self.apply_controls(&ports.controls);
+        let frames = sample_count as usize;
+        let left: &mut [f32] = &mut ports.left;
+        let right: &mut [f32] = &mut ports.right;
+        let mut start = 0;
+
+        if let Some(sequence) = ports.midi.read(self.urids.atom.sequence, self.urids.unit.beat) {
+            for (timestamp, atom) in sequence {
+                let Some(message) = atom.read(self.urids.midi.raw, ()) else {
+                    continue;
+                };
+                // Los eventos con la posición en pulsos en lugar de muestras suenan al
+                // principio del bloque
+                let frame = (timestamp.as_frames().unwrap_or(0).max(0) as usize).clamp(start, frames);
+                if frame > start {
+                    self.engine.process(&mut left[start..frame], &mut right[start..frame]);
+                    start = frame;
+                }
+                self.engine.midi_message(message);
+            }
+        }
+        if frames > start {
+            self.engine.process(&mut left[start..frames], &mut right[start..frames]);
+        }
+    }
+}
+
+lv2_descriptors!(RustSynthLv2);
@@ -0,0 +1,142 @@
+// Archivos .ttl del bundle LV2: manifest.ttl apunta a la biblioteca y a la descripción
+// del plugin, que lista los puertos. Los puertos de control salen de `PARAMETERS`, así
+// que el bundle se vuelve a generar con cada versión que añada parámetros.
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::api::{ParameterInfo, ParameterKind, PARAMETERS};
+use super::{FIRST_CONTROL_PORT, LEFT_PORT, MIDI_PORT, PLUGIN_URI, RIGHT_PORT};
+
+pub const MANIFEST_TTL: &str = "manifest.ttl";
+pub const PLUGIN_TTL: &str = "rust-synth.ttl";
+
+const PREFIXES: &str = "@prefix atom:   <http://lv2plug.in/ns/ext/atom#> .
+@prefix doap:   <http://usefulinc.com/ns/doap#> .
+@prefix lv2:    <http://lv2plug.in/ns/lv2core#> .
+@prefix midi:   <http://lv2plug.in/ns/ext/midi#> .
+@prefix pprops: <http://lv2plug.in/ns/ext/port-props#> .
+@prefix rdf:    <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .
+@prefix rdfs:   <http://www.w3.org/2000/01/rdf-schema#> .
+@prefix units:  <http://lv2plug.in/ns/extensions/units#> .
+@prefix urid:   <http://lv2plug.in/ns/ext/urid#> .
+";
+
+// Nombre del archivo de la biblioteca que genera cargo en la plataforma actual
+pub fn library_name() -> String {
+    format!("{}rust_synth{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX)
+}
+
+pub fn manifest_ttl(library: &str) -> String {
+    format!(
+        "{PREFIXES}
+<{PLUGIN_URI}>
+    a lv2:Plugin, lv2:InstrumentPlugin ;
+    lv2:binary <{library}> ;
+    rdfs:seeAlso <{PLUGIN_TTL}> .
+"
+    )
+}
+
+pub fn plugin_ttl() -> String {
+    let mut ttl = format!(
+        "{PREFIXES}
+<{PLUGIN_URI}>
+    a lv2:Plugin, lv2:InstrumentPlugin ;
+    doap:name \"Rust Synth\" ;
+    lv2:requiredFeature urid:map ;
+    lv2:optionalFeature lv2:hardRTCapable ;
+    lv2:port [
+        a lv2:InputPort, atom:AtomPort ;
+        atom:bufferType atom:Sequence ;
+        atom:supports midi:MidiEvent ;
+        lv2:designation lv2:control ;
+        lv2:index {MIDI_PORT} ;
+        lv2:symbol \"midi_in\" ;
+        lv2:name \"MIDI\" ;
+    ] , [
+        a lv2:OutputPort, lv2:AudioPort ;
+        lv2:index {LEFT_PORT} ;
+        lv2:symbol \"out_l\" ;
+        lv2:name \"Izquierda\" ;
+    ] , [
+        a lv2:OutputPort, lv2:AudioPort ;
+        lv2:index {RIGHT_PORT} ;
+        lv2:symbol \"out_r\" ;
+        lv2:name \"Derecha\" ;
+    ]"
+    );
+    for (i, info) in PARAMETERS.iter().enumerate() {
+        ttl.push_str(" , ");
+        write_control_port(&mut ttl, FIRST_CONTROL_PORT + i, info);
+    }
+    ttl.push_str(" .\n");
+    ttl
+}
+
+// El símbolo sale del identificador, que es estable entre versiones, para que las
+// sesiones guardadas sigan encontrando sus puertos
+fn write_control_port(ttl: &mut String, index: usize, info: &ParameterInfo) {
+    let _ = write!(
+        ttl,
+        "[
+        a lv2:InputPort, lv2:ControlPort ;
+        lv2:index {index} ;
+        lv2:symbol \"param_{}\" ;
+        lv2:name \"{}\" ;
+        lv2:default {:?} ;
+        lv2:minimum {:?} ;
+        lv2:maximum {:?} ;",
+        info.id,
+        info.name.replace('"', "\\\""),
+        info.default,
+        info.min,
+        info.max,
+    );
+    if info.logarithmic {
+        ttl.push_str("\n        lv2:portProperty pprops:logarithmic ;");
+    }
+    // Interruptores, enteros y listas para que el host muestre el control adecuado
+    match info.kind {
+        ParameterKind::Toggle => ttl.push_str("\n        lv2:portProperty lv2:toggled ;"),
+        ParameterKind::Integer => ttl.push_str("\n        lv2:portProperty lv2:integer ;"),
+        ParameterKind::Choice(name) => {
+            ttl.push_str("\n        lv2:portProperty lv2:integer, lv2:enumeration ;");
+            for index in 0..=info.max as usize {
+                let _ = write!(
+                    ttl,
+                    "\n        lv2:scalePoint [ rdfs:label \"{}\" ; rdf:value {} ] ;",
+                    name(index).replace('"', "\\\""),
+                    index,
+                );
+            }
+        }
+        _ => (),
+    }
+    if let Some(unit) = unit_uri(info.unit) {
+        let _ = write!(ttl, "\n        units:unit {unit} ;");
+    }
+    ttl.push_str("\n    ]");
+}
+
+// Unidad de LV2 para las unidades del registro de parámetros; sin unidad, el host
+// muestra el número tal cual
+fn unit_uri(unit: &str) -> Option<&'static str> {
+    match unit {
+        "s" => Some("units:s"),
+        "ms" => Some("units:ms"),
+        "Hz" => Some("units:hz"),
+        "dB" => Some("units:db"),
+        "BPM" => Some("units:bpm"),
+        "st" => Some("units:semitone12TET"),
+        "°" => Some("units:degree"),
+        _ => None,
+    }
+}
+
+// Escribir los dos .ttl en la carpeta del bundle, creándola si no existe
+pub fn write_bundle(dir: &Path, library: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(MANIFEST_TTL), manifest_ttl(library))?;
+    fs::write(dir.join(PLUGIN_TTL), plugin_ttl())
+}
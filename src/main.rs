@@ -1,41 +1,73 @@
+mod api;
 mod audio;
+mod dsp;
 mod midi;
+mod parameters;
 mod structs;
 mod gui;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use std::collections::HashMap;
 use std::sync::{Mutex, Condvar};
 use std::env;
+use std::fs;
+use std::path::Path;
 use egui::ViewportBuilder;
 
 // Importaciones del módulo de audio
 use crate::audio::{
-    soft_clip,
-    Note,
+    stereo_channel_sample,
     create_audio_config,
     list_audio_hosts,
+    DEFAULT_BUFFER_SIZE,
 };
+use crate::api::{Patch, SynthEngine, VoiceManager};
+use crate::dsp::{ModDestination, ModMatrix, ModRoute, ModSource, SaturationMode};
+use crate::audio::pipe::{run_pipe_output, PipeFormat};
+use crate::audio::watchdog::{StaleVoiceLog, VoiceWatchdog, DEFAULT_STALE_VOICE_TIMEOUT};
 
 // Importaciones del módulo MIDI
-use crate::midi::{midi_note_to_freq, connect_midi};
+use crate::midi::{midi_note_to_freq, connect_midi, handle_midi_message};
+use crate::midi::smf::{MidiFile, MidiFilePlayer};
+use crate::midi::velocity::{VelocityCurve, VelocityResponse};
+use crate::structs::session::{Session, SESSION_FILE};
 
 // Importaciones de GUI y estructuras
-use crate::gui::{SynthApp, SynthConfig, WaveType};
+use crate::gui::{SynthApp, SynthConfig};
+
+// Ganancia por voz sobre el volumen del patch, la misma que en el plugin
+const VOICE_GAIN: f32 = 0.3;
+// Cada cuánto se revisa el registro del vigilante de voces colgadas
+const STALE_VOICE_POLL: Duration = Duration::from_millis(500);
 
 fn main() {
     // Verificar si se debe usar la interfaz gráfica
     let args: Vec<String> = env::args().collect();
     let use_gui = args.len() > 1 && args[1] == "--gui";
     
-    if use_gui {
+    // Salida a stdout o tubería: --out <ruta|-> [--format raw|wav] [--sample-rate N]
+    let out_target = arg_value(&args, "--out");
+    
+    if let Some(target) = out_target {
+        let format = match arg_value(&args, "--format") {
+            Some(name) => PipeFormat::from_name(&name).unwrap_or_else(|| {
+                eprintln!("Formato desconocido: {}, usando wav", name);
+                PipeFormat::Wav
+            }),
+            None => PipeFormat::Wav,
+        };
+        let sample_rate = arg_value(&args, "--sample-rate")
+            .and_then(|rate| rate.parse().ok())
+            .unwrap_or(44100);
+        run_pipe_version(&target, format, sample_rate, patch_from_args(&args));
+    } else if use_gui {
         // Inicializar la configuración compartida
         let config = Arc::new(Mutex::new(SynthConfig::default()));
         
-        // Inicializar las notas activas compartidas
-        let active_notes = Arc::new(Mutex::new(HashMap::new()));
+        // Inicializar las voces compartidas
+        let voices = Arc::new(Mutex::new(VoiceManager::default()));
         
         // Inicializar la frecuencia de muestreo compartida
         let sample_rate_shared = Arc::new(Mutex::new(44100.0f32));
@@ -43,7 +75,7 @@ fn main() {
         // Crear la aplicación
         let app = SynthApp::new(
             config,
-            active_notes,
+            voices,
             sample_rate_shared,
         );
         
@@ -60,36 +92,181 @@ fn main() {
             Box::new(|_cc| Box::new(app)),
         ).unwrap();
     } else {
+        // Reproducir un archivo MIDI estándar en lugar de (o además de) tocar: --play archivo.mid
+        let player = arg_value(&args, "--play").and_then(|path| match MidiFile::load(Path::new(&path)) {
+            Ok(file) => {
+                println!("Reproduciendo {} ({} eventos, {:.1} s)", path, file.event_count(), file.duration());
+                Some(MidiFilePlayer::new(file))
+            },
+            Err(err) => {
+                eprintln!("No se pudo leer el archivo MIDI {}: {}", path, err);
+                None
+            },
+        });
+        // Volver a preguntar por el host y el dispositivo aunque estén guardados: --choose-audio
+        let choose_audio = args.iter().any(|arg| arg == "--choose-audio");
         // Versión de consola original
-        run_console_version();
+        run_console_version(patch_from_args(&args), player, choose_audio);
     }
 }
 
-fn run_console_version() {
-    // Reemplazar el HashSet por un HashMap
-    let active_notes = Arc::new(Mutex::new(HashMap::new()));
+// Valor que sigue a una opción de la línea de comandos, p. ej. "--out -"
+fn arg_value(args: &[String], name: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+// Patch de la consola y de la tubería: el preset de --preset archivo (o el de inicio)
+// con las opciones de la línea de comandos encima
+fn patch_from_args(args: &[String]) -> Patch {
+    let mut patch = match arg_value(args, "--preset") {
+        Some(path) => match fs::read_to_string(&path) {
+            Ok(text) => Patch::from_preset_text(&text),
+            Err(err) => {
+                eprintln!("No se pudo leer el preset {}: {}, usando el de inicio", path, err);
+                Patch::default()
+            },
+        },
+        None => Patch::default(),
+    };
+    patch.volume *= VOICE_GAIN;
     
-    // Usar un Arc<Mutex<f32>> para la frecuencia de muestreo
-    let sample_rate_shared = Arc::new(Mutex::new(44100.0f32));
+    // Respuesta a la velocidad: --velocity-curve linear|soft|hard|fixed [--velocity-sensitivity 0..1]
+    patch.velocity_response = velocity_response_from_args(args);
     
-    // Tipo de onda compartido
-    let wave_type_shared = Arc::new(Mutex::new(WaveType::Sine));
+    // Rutas de modulación por CC: --cc-mod CC:pitch|cutoff|wavetable|pan|vibrato:cantidad (repetible),
+    // añadidas a las del preset (en el de inicio, la rueda de modulación al vibrato)
+    add_mod_routes_from_args(args, &mut patch.mod_matrix);
     
-    // Configurar entrada MIDI
-    let midi_in = connect_midi(
-        active_notes.clone(),
-        sample_rate_shared.clone(),
-        wave_type_shared.clone(),
-    ).expect("No se pudo conectar al dispositivo MIDI");
+    // Filtro maestro opcional: --master-lowpass N / --master-highpass N (Hz)
+    if let Some(cutoff) = arg_value(args, "--master-lowpass").and_then(|value| value.parse().ok()) {
+        patch.master_filter.lowpass_cutoff = cutoff;
+    }
+    if let Some(cutoff) = arg_value(args, "--master-highpass").and_then(|value| value.parse().ok()) {
+        patch.master_filter.highpass_cutoff = cutoff;
+    }
+    // Saturación de salida opcional: --saturation tanh|hard|cubic|sine|none / --drive N (dB)
+    if let Some(mode) = arg_value(args, "--saturation").and_then(|value| SaturationMode::from_name(&value)) {
+        patch.saturation.mode = mode;
+    }
+    if let Some(drive) = arg_value(args, "--drive").and_then(|value| value.parse().ok()) {
+        patch.saturation.drive = drive;
+    }
+    patch
+}
+
+fn velocity_response_from_args(args: &[String]) -> VelocityResponse {
+    let mut response = VelocityResponse::default();
+    if let Some(name) = arg_value(args, "--velocity-curve") {
+        match VelocityCurve::from_name(&name) {
+            Some(curve) => response.curve = curve,
+            None => eprintln!("Curva de velocidad desconocida: {}, usando lineal", name),
+        }
+    }
+    if let Some(sensitivity) = arg_value(args, "--velocity-sensitivity").and_then(|value| value.parse::<f32>().ok()) {
+        response.sensitivity = sensitivity.clamp(0.0, 1.0);
+    }
+    response
+}
 
-    // Listar hosts de audio disponibles
-    println!("\nHosts de audio disponibles:");
-    let available_hosts = list_audio_hosts();
+fn add_mod_routes_from_args(args: &[String], matrix: &mut ModMatrix) {
+    let values = args.windows(2).filter(|pair| pair[0] == "--cc-mod").map(|pair| &pair[1]);
+    for value in values {
+        let parts: Vec<&str> = value.split(':').collect();
+        let route = match parts.as_slice() {
+            [cc, destination, amount] => cc.parse::<u8>().ok().filter(|cc| *cc < 128).and_then(|cc| {
+                Some(ModRoute {
+                    source: ModSource::Cc,
+                    cc,
+                    destination: ModDestination::from_name(destination)?,
+                    amount: amount.parse::<f32>().ok()?.clamp(-1.0, 1.0),
+                    ..ModRoute::default()
+                })
+            }),
+            _ => None,
+        };
+        match route {
+            Some(route) => {
+                if !matrix.add_route(route) {
+                    eprintln!("Demasiadas rutas de modulación, se ignora {}", value);
+                }
+            },
+            None => eprintln!("Ruta de modulación no válida: {}", value),
+        }
+    }
+}
+
+fn run_pipe_version(target: &str, format: PipeFormat, sample_rate: u32, patch: Patch) {
+    let mut engine = SynthEngine::new(sample_rate as f32);
+    engine.set_patch(patch);
+    let engine = Arc::new(Mutex::new(engine));
+    // Liberar las voces colgadas con el tiempo de la sesión de la aplicación
+    let timeout = Session::load(Path::new(SESSION_FILE)).stale_voice_timeout;
+    let watchdog = Arc::new(VoiceWatchdog::with_timeout(timeout.unwrap_or(DEFAULT_STALE_VOICE_TIMEOUT)));
+    
+    // Todos los mensajes van por stderr para no mezclarse con el audio
+    let midi_in = connect_midi(engine.clone(), watchdog.clone());
+    if midi_in.is_none() {
+        eprintln!("No se pudo conectar al dispositivo MIDI, se enviará silencio");
+    }
+    
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::Relaxed);
+    }).expect("Error al configurar el manejador de Ctrl+C");
+    
+    eprintln!("Enviando audio a {} ({} Hz)", if target == "-" { "stdout" } else { target }, sample_rate);
+    if let Err(err) = run_pipe_output(target, format, sample_rate, engine, watchdog, running) {
+        eprintln!("Error en la salida de audio: {}", err);
+    }
+}
 
-    println!("\nSelecciona un host (0-{}): ", available_hosts.len() - 1);
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    let host_index: usize = input.trim().parse().unwrap_or(0);
+fn run_console_version(patch: Patch, mut player: Option<MidiFilePlayer>, choose_audio: bool) {
+    // Host, dispositivo, frecuencia y buffer de la última vez
+    let mut session = Session::load(Path::new(SESSION_FILE));
+    
+
+    // Motor con el patch de la línea de comandos; la frecuencia de muestreo se ajusta al
+    // dispositivo más abajo
+    let mut engine = SynthEngine::new(44100.0);
+    engine.set_patch(patch);
+    let engine = Arc::new(Mutex::new(engine));
+    
+    // Protección contra Note Off perdidos, con el tiempo guardado en la sesión
+    let watchdog = Arc::new(VoiceWatchdog::with_timeout(
+        session.stale_voice_timeout.unwrap_or(DEFAULT_STALE_VOICE_TIMEOUT),
+    ));
+    
+    // Configurar entrada MIDI; con un archivo que reproducir no hace falta controlador
+    let midi_in = connect_midi(engine.clone(), watchdog.clone());
+    if midi_in.is_none() {
+        if player.is_none() {
+            panic!("No se pudo conectar al dispositivo MIDI");
+        }
+        println!("Sin dispositivo MIDI: solo sonará el archivo");
+    }
+
+    // Host guardado en la sesión; si ya no existe (o con --choose-audio) se pregunta
+    let saved_host = cpal::available_hosts()
+        .into_iter()
+        .position(|host_id| Some(host_id.name()) == session.audio_host.as_deref())
+        .filter(|_| !choose_audio);
+    let (available_hosts, host_index) = match saved_host {
+        Some(index) => (cpal::available_hosts(), index),
+        None => {
+            // Listar hosts de audio disponibles
+            println!("\nHosts de audio disponibles:");
+            let available_hosts = list_audio_hosts();
+
+            println!("\nSelecciona un host (0-{}): ", available_hosts.len() - 1);
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input).unwrap();
+            (available_hosts, input.trim().parse().unwrap_or(0))
+        }
+    };
 
     let host = if host_index < available_hosts.len() {
         cpal::host_from_id(available_hosts[host_index])
@@ -109,23 +286,31 @@ fn run_console_version() {
     // Get sample rate before MIDI callback
     let device = host.default_output_device().expect("No se encontró dispositivo de audio");
     let default_config = device.default_output_config().unwrap();
-    *sample_rate_shared.lock().unwrap() = default_config.sample_rate().0 as f32;
+    engine.lock().unwrap().set_sample_rate(default_config.sample_rate().0 as f32);
     
-    // Listar dispositivos de salida disponibles
-    println!("\nDispositivos de salida disponibles:");
     let output_devices = host.output_devices()
         .expect("Error al obtener dispositivos de salida");
+    let devices_vec: Vec<_> = output_devices.collect();
     
-    let mut devices_vec = Vec::new();
-    for (idx, device) in output_devices.enumerate() {
-        println!("{}. {}", idx, device.name().unwrap_or_else(|_| "Nombre desconocido".into()));
-        devices_vec.push(device);
-    }
+    // Dispositivo guardado en la sesión, igual que el host
+    let saved_device = devices_vec.iter()
+        .position(|device| device.name().ok() == session.audio_device)
+        .filter(|_| saved_host.is_some());
+    let device_index = match saved_device {
+        Some(index) => index,
+        None => {
+            // Listar dispositivos de salida disponibles
+            println!("\nDispositivos de salida disponibles:");
+            for (idx, device) in devices_vec.iter().enumerate() {
+                println!("{}. {}", idx, device.name().unwrap_or_else(|_| "Nombre desconocido".into()));
+            }
 
-    println!("\nSelecciona un dispositivo (0-{}): ", devices_vec.len() - 1);
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    let device_index: usize = input.trim().parse().unwrap_or(0);
+            println!("\nSelecciona un dispositivo (0-{}): ", devices_vec.len() - 1);
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input).unwrap();
+            input.trim().parse().unwrap_or(0)
+        }
+    };
     
     let device = devices_vec.get(device_index).cloned().unwrap_or_else(|| {
         println!("Índice inválido, usando dispositivo por defecto");
@@ -135,6 +320,10 @@ fn run_console_version() {
 
     println!("Usando host de audio: {}", host.id().name());
     println!("Dispositivo de salida: {}", device.name().unwrap());
+    
+    // Recordar la elección para no preguntar en el próximo arranque
+    session.audio_host = Some(host.id().name().to_string());
+    session.audio_device = device.name().ok();
 
     let config = device.default_output_config().unwrap();
     println!("Configuración por defecto: {:?}", config);
@@ -177,8 +366,8 @@ fn run_console_version() {
             asio_sample_rate);
         
         // Actualizar la variable sample_rate para que coincida con la configuración de ASIO
-        *sample_rate_shared.lock().unwrap() = asio_sample_rate.0 as f32;
-        let current_sample_rate = *sample_rate_shared.lock().unwrap();
+        engine.lock().unwrap().set_sample_rate(asio_sample_rate.0 as f32);
+        let current_sample_rate = asio_sample_rate.0 as f32;
         
         println!("Usando frecuencia de muestreo para ASIO: {} Hz", current_sample_rate);
         println!("Nota A4 (MIDI 69) = {} Hz", midi_note_to_freq(69));
@@ -193,9 +382,16 @@ fn run_console_version() {
             buffer_size: cpal::BufferSize::Default,
         }
     } else {
-        create_audio_config(&device)
+        let config = create_audio_config(&device, session.sample_rate, session.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE));
+        engine.lock().unwrap().set_sample_rate(config.sample_rate.0 as f32);
+        config
     };
     
+    session.sample_rate = Some(config.sample_rate.0);
+    if let Err(err) = session.save(Path::new(SESSION_FILE)) {
+        eprintln!("Error al guardar la sesión: {}", err);
+    }
+    
     // Get the sample format before creating the stream
     let sample_format = device.default_output_config()
         .expect("Failed to get default output config")
@@ -203,116 +399,65 @@ fn run_console_version() {
 
     println!("Configuración optimizada: {:?}", config);
     
-    let sample_rate_for_audio = sample_rate_shared.clone();
-    let notes_for_audio = active_notes.clone();
+    let engine_for_audio = engine.clone();
+    let watchdog_for_audio = watchdog.clone();
+    // Muestras generadas desde el inicio: el reloj con el que avanza el archivo MIDI
+    let mut played_frames: u64 = 0;
+    // Canales del motor, que solo crecen si el dispositivo pide más frames
+    let mut left = Vec::new();
+    let mut right = Vec::new();
     
-    // Tamaño del buffer de audio para reducir las operaciones de bloqueo
-    const BUFFER_SIZE: usize = 64;
+    // Render en f32, común a todos los formatos de muestra del dispositivo
+    let mut render = move |data: &mut [f32]| {
+        let channels = config.channels as usize;
+        let frames = data.len() / channels;
+        
+        // Eventos del archivo MIDI que tocan hasta el principio de este buffer
+        if let Some(player) = player.as_mut() {
+            let time = played_frames as f64 / engine_for_audio.lock().unwrap().sample_rate() as f64;
+            player.advance_to(time, |message| {
+                handle_midi_message(message, &engine_for_audio, &watchdog_for_audio);
+            });
+            played_frames += frames as u64;
+        }
+        
+        left.resize(frames, 0.0);
+        right.resize(frames, 0.0);
+        {
+            let mut engine = engine_for_audio.lock().unwrap();
+            // Vigilar voces colgadas por Note Off perdidos
+            engine.voices_mut().check_watchdog(&watchdog_for_audio);
+            engine.process(&mut left, &mut right);
+        }
+        
+        // Repartir cada frame estéreo entre los canales de salida
+        for (i, frame) in data.chunks_mut(channels).enumerate() {
+            for (c, channel) in frame.iter_mut().enumerate() {
+                *channel = stereo_channel_sample((left[i], right[i]), c, channels);
+            }
+        }
+    };
     
     let stream = match sample_format {
-        cpal::SampleFormat::I32 => device.build_output_stream(
-            &config,
-            move |data: &mut [i32], _: &cpal::OutputCallbackInfo| {
-                // Adquirir el bloqueo una vez por buffer en lugar de por muestra
-                let mut notes_guard = notes_for_audio.lock().unwrap();
-                let current_sample_rate = *sample_rate_for_audio.lock().unwrap();
-                
-                // Actualizar las frecuencias de muestreo si es necesario
-                for note in notes_guard.values_mut() {
-                    if note.sample_rate != current_sample_rate {
-                        note.sample_rate = current_sample_rate;
-                        note.update_frequency(note.frequency);
+        cpal::SampleFormat::I32 => {
+            // Buffer intermedio en f32, que solo crece si el dispositivo pide más frames
+            let mut buffer = Vec::new();
+            device.build_output_stream(
+                &config,
+                move |data: &mut [i32], _: &cpal::OutputCallbackInfo| {
+                    buffer.resize(data.len(), 0.0);
+                    render(&mut buffer);
+                    for (sample, value) in data.iter_mut().zip(buffer.iter()) {
+                        *sample = (value * i32::MAX as f32) as i32;
                     }
-                }
-                
-                let channels = config.channels as usize;
-                
-                // Procesar el audio en bloques para mejorar la eficiencia
-                for chunk in data.chunks_mut(channels * BUFFER_SIZE).filter(|c| !c.is_empty()) {
-                    // Generar un buffer temporal de muestras
-                    let mut temp_buffer = [0.0f32; BUFFER_SIZE];
-                    
-                    // Generar todas las muestras para este bloque
-                    for (i, frame) in chunk.chunks_mut(channels).enumerate() {
-                        if i < BUFFER_SIZE {
-                            let sample = {
-                                let mut mix = 0.0;
-                                
-                                for note in notes_guard.values_mut() {
-                                    let envelope_amp = note.envelope.next_sample();
-                                    let sine_value = note.get_sample();
-                                    mix += sine_value * envelope_amp * 0.15;
-                                }
-                                
-                                // Aplicar soft clip y convertir a i32
-                                temp_buffer[i] = soft_clip(mix);
-                                (temp_buffer[i] * i32::MAX as f32) as i32
-                            };
-                            
-                            // Copiar la muestra a todos los canales
-                            for channel in frame.iter_mut() {
-                                *channel = sample;
-                            }
-                        }
-                    }
-                }
-                
-                // Eliminar las notas terminadas
-                notes_guard.retain(|_, note| !note.envelope.is_finished());
-            },
-            |err| eprintln!("Error en el stream: {}", err),
-            Some(Duration::from_millis(100))
-        ),
+                },
+                |err| eprintln!("Error en el stream: {}", err),
+                Some(Duration::from_millis(100))
+            )
+        }
         _ => device.build_output_stream(
             &config,
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                // Adquirir el bloqueo una vez por buffer en lugar de por muestra
-                let mut notes_guard = notes_for_audio.lock().unwrap();
-                let current_sample_rate = *sample_rate_for_audio.lock().unwrap();
-                
-                // Actualizar las frecuencias de muestreo si es necesario
-                for note in notes_guard.values_mut() {
-                    if note.sample_rate != current_sample_rate {
-                        note.sample_rate = current_sample_rate;
-                        note.update_frequency(note.frequency);
-                    }
-                }
-                
-                let channels = config.channels as usize;
-                
-                // Procesar el audio en bloques para mejorar la eficiencia
-                for chunk in data.chunks_mut(channels * BUFFER_SIZE).filter(|c| !c.is_empty()) {
-                    // Generar un buffer temporal de muestras
-                    let mut temp_buffer = [0.0f32; BUFFER_SIZE];
-                    
-                    // Generar todas las muestras para este bloque
-                    for (i, frame) in chunk.chunks_mut(channels).enumerate() {
-                        if i < BUFFER_SIZE {
-                            let sample = {
-                                let mut mix = 0.0;
-                                
-                                for note in notes_guard.values_mut() {
-                                    let envelope_amp = note.envelope.next_sample();
-                                    let sine_value = note.get_sample();
-                                    mix += sine_value * envelope_amp * 0.15;
-                                }
-                                
-                                // Aplicar soft clip
-                                temp_buffer[i] = soft_clip(mix);
-                                temp_buffer[i]
-                            };
-                            
-                            // Copiar la muestra a todos los canales
-                            for channel in frame.iter_mut() {
-                                *channel = sample;
-                            }
-                        }
-                    }
-                }
-                
-                // Eliminar las notas terminadas
-                notes_guard.retain(|_, note| !note.envelope.is_finished());
-            },
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| render(data),
             |err| eprintln!("Error en el stream: {}", err),
             Some(Duration::from_millis(100))
         )
@@ -331,11 +476,14 @@ fn run_console_version() {
         cvar.notify_one();
     }).expect("Error al configurar el manejador de Ctrl+C");
 
-    // Esperar a que el usuario presione Ctrl+C
+    // Esperar a que el usuario presione Ctrl+C, mostrando mientras tanto las voces que
+    // haya liberado el vigilante
+    let mut stale_voices = StaleVoiceLog::new();
     let (lock, cvar) = &*running;
     let mut running = lock.lock().unwrap();
     while *running {
-        running = cvar.wait(running).unwrap();
+        running = cvar.wait_timeout(running, STALE_VOICE_POLL).unwrap().0;
+        stale_voices.update(&watchdog);
     }
     
     println!("Saliendo...");
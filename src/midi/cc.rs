@@ -0,0 +1,146 @@
+use crate::dsp::filters::{cutoff_from_normalized, cutoff_to_normalized};
+use crate::dsp::MAX_OSCILLATOR_DETUNE;
+
+// Parámetros que se pueden controlar con un CC MIDI o con el pad XY
+#[derive(Clone, Copy, PartialEq)]
+pub enum CcTarget {
+    Volume,
+    Osc2Volume,
+    Osc2Detune,
+    Osc1Fold,
+    WavetablePosition,
+    WavetableSweep,
+    VoiceFilterEnvDepth,
+    VoiceDrive,
+    FilterCutoff,
+    FilterResonance,
+    MasterCutoff,
+    FormantMorph,
+    // Posición entre los presets A y B del morph
+    Morph,
+}
+
+impl CcTarget {
+    pub const ALL: [CcTarget; 13] = [
+        CcTarget::Volume,
+        CcTarget::Osc2Volume,
+        CcTarget::Osc2Detune,
+        CcTarget::Osc1Fold,
+        CcTarget::WavetablePosition,
+        CcTarget::WavetableSweep,
+        CcTarget::VoiceFilterEnvDepth,
+        CcTarget::VoiceDrive,
+        CcTarget::FilterCutoff,
+        CcTarget::FilterResonance,
+        CcTarget::MasterCutoff,
+        CcTarget::FormantMorph,
+        CcTarget::Morph,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CcTarget::Volume => "Volumen",
+            CcTarget::Osc2Volume => "Volumen Osc 2",
+            CcTarget::Osc2Detune => "Detune Osc 2",
+            CcTarget::Osc1Fold => "Plegado Osc 1",
+            CcTarget::WavetablePosition => "Posición tabla",
+            CcTarget::WavetableSweep => "Barrido tabla",
+            CcTarget::VoiceFilterEnvDepth => "Filtro por voz",
+            CcTarget::VoiceDrive => "Drive por voz",
+            CcTarget::FilterCutoff => "Corte del filtro",
+            CcTarget::FilterResonance => "Resonancia",
+            CcTarget::MasterCutoff => "Corte maestro",
+            CcTarget::FormantMorph => "Morph de vocal",
+            CcTarget::Morph => "Morph de presets",
+        }
+    }
+
+    fn range(&self) -> (f32, f32) {
+        match self {
+            CcTarget::Osc2Detune => (-MAX_OSCILLATOR_DETUNE, MAX_OSCILLATOR_DETUNE),
+            CcTarget::WavetableSweep => (-2.0, 2.0),
+            _ => (0.0, 1.0),
+        }
+    }
+
+    // Convertir un valor normalizado (0..1) al rango del parámetro
+    pub fn denormalize(&self, value: f32) -> f32 {
+        if matches!(self, CcTarget::FilterCutoff | CcTarget::MasterCutoff) {
+            return cutoff_from_normalized(value);
+        }
+        let (min, max) = self.range();
+        min + value * (max - min)
+    }
+
+    pub fn normalize(&self, value: f32) -> f32 {
+        if matches!(self, CcTarget::FilterCutoff | CcTarget::MasterCutoff) {
+            return cutoff_to_normalized(value);
+        }
+        let (min, max) = self.range();
+        ((value - min) / (max - min)).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct CcMapping {
+    pub cc: u8,
+    pub target: CcTarget,
+    // Combinar este CC (MSB) con su pareja CC+32 (LSB) para obtener 14 bits
+    pub high_resolution: bool,
+}
+
+// Traduce mensajes CC a valores de parámetros, incluyendo pares MSB/LSB de 14 bits
+pub struct CcMapper {
+    pub mappings: Vec<CcMapping>,
+    msb: [u8; 32],
+    // Se marca al recibir por primera vez el LSB de un controlador
+    lsb_detected: [bool; 32],
+}
+
+impl Default for CcMapper {
+    fn default() -> Self {
+        Self {
+            // La rueda de modulación (CC1) no se asigna: va al vibrato por la matriz de
+            // modulación del patch
+            mappings: vec![
+                CcMapping { cc: 7, target: CcTarget::Volume, high_resolution: true },
+            ],
+            msb: [0; 32],
+            lsb_detected: [false; 32],
+        }
+    }
+}
+
+impl CcMapper {
+    pub fn lsb_detected(&self, cc: u8) -> bool {
+        cc < 32 && self.lsb_detected[cc as usize]
+    }
+
+    // Procesar un mensaje CC y devolver el parámetro afectado con su valor normalizado
+    pub fn process(&mut self, cc: u8, value: u8) -> Option<(CcTarget, f32)> {
+        let value = value & 0x7F;
+
+        // CC 32-63: LSB del controlador cc-32
+        if (32..64).contains(&cc) {
+            let msb_cc = cc - 32;
+            let mapping = self.mappings.iter().find(|m| m.cc == msb_cc && m.high_resolution)?;
+            let index = msb_cc as usize;
+            self.lsb_detected[index] = true;
+            let combined = ((self.msb[index] as u16) << 7) | value as u16;
+            return Some((mapping.target, combined as f32 / 16383.0));
+        }
+
+        let mapping = self.mappings.iter().find(|m| m.cc == cc)?;
+        if cc < 32 && mapping.high_resolution {
+            // Un MSB nuevo reinicia el LSB a 0, como indica la especificación MIDI, así
+            // que el valor combinado es el MSB solo hasta que llegue su LSB
+            let index = cc as usize;
+            self.msb[index] = value;
+            if self.lsb_detected[index] {
+                let combined = (value as u16) << 7;
+                return Some((mapping.target, combined as f32 / 16383.0));
+            }
+        }
+        Some((mapping.target, value as f32 / 127.0))
+    }
+}
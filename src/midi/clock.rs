@@ -0,0 +1,82 @@
+use std::time::{Duration, Instant};
+
+// Pulsos de reloj MIDI por negra
+const PULSES_PER_QUARTER: usize = 24;
+// Sin pulsos durante este tiempo se da el reloj por perdido (menos de 2.5 BPM)
+const CLOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Mensajes de tiempo real
+pub const CLOCK_TICK: u8 = 0xF8;
+pub const CLOCK_START: u8 = 0xFA;
+pub const CLOCK_CONTINUE: u8 = 0xFB;
+pub const CLOCK_STOP: u8 = 0xFC;
+
+// Tempo de un reloj MIDI externo, medido a partir del intervalo entre pulsos. El tempo
+// es la media de la última negra para que la fluctuación de los pulsos no se note.
+pub struct MidiClock {
+    last_tick: Option<Instant>,
+    // Intervalos de los últimos pulsos en segundos, en anillo
+    intervals: [f64; PULSES_PER_QUARTER],
+    interval_count: usize,
+    interval_index: usize,
+    // Entre Start/Continue y Stop
+    pub running: bool,
+}
+
+impl Default for MidiClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MidiClock {
+    pub fn new() -> Self {
+        Self {
+            last_tick: None,
+            intervals: [0.0; PULSES_PER_QUARTER],
+            interval_count: 0,
+            interval_index: 0,
+            running: false,
+        }
+    }
+
+    // Atender un mensaje de tiempo real del reloj; devuelve false si es otro mensaje
+    pub fn process(&mut self, message: &[u8]) -> bool {
+        match message {
+            [CLOCK_TICK] => self.tick(Instant::now()),
+            [CLOCK_START] | [CLOCK_CONTINUE] => self.running = true,
+            [CLOCK_STOP] => self.running = false,
+            _ => return false,
+        }
+        true
+    }
+
+    fn tick(&mut self, now: Instant) {
+        if let Some(last) = self.last_tick {
+            let interval = now - last;
+            if interval < CLOCK_TIMEOUT {
+                self.intervals[self.interval_index] = interval.as_secs_f64();
+                self.interval_index = (self.interval_index + 1) % PULSES_PER_QUARTER;
+                self.interval_count = (self.interval_count + 1).min(PULSES_PER_QUARTER);
+            } else {
+                // Tras una pausa larga se vuelve a medir desde cero
+                self.interval_count = 0;
+                self.interval_index = 0;
+            }
+        }
+        self.last_tick = Some(now);
+    }
+
+    // Tempo actual, o None si no llegan pulsos
+    pub fn bpm(&self) -> Option<f32> {
+        let last = self.last_tick?;
+        if self.interval_count == 0 || last.elapsed() > CLOCK_TIMEOUT {
+            return None;
+        }
+        let average = self.intervals[..self.interval_count].iter().sum::<f64>() / self.interval_count as f64;
+        if average <= 0.0 {
+            return None;
+        }
+        Some((60.0 / (average * PULSES_PER_QUARTER as f64)) as f32)
+    }
+}
@@ -1,9 +1,23 @@
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
 use midir::{MidiInput, MidiInputConnection};
-use crate::audio::Note;
-use crate::gui::WaveType;
-use crate::structs::envelope::Envelope;
+use crate::api::SynthEngine;
+use crate::audio::watchdog::VoiceWatchdog;
+use parser::MidiParser;
+
+pub mod cc;
+pub mod clock;
+pub mod output;
+pub mod parser;
+pub mod recorder;
+pub mod rpn;
+pub mod smf;
+pub mod velocity;
+
+// Mensajes de modo de canal que silencian el sintetizador
+pub const ALL_SOUND_OFF_CC: u8 = 120;
+pub const ALL_NOTES_OFF_CC: u8 = 123;
+// Pedal de sustain, que el pánico deja levantado
+pub const SUSTAIN_PEDAL_CC: u8 = 64;
 
 pub fn midi_note_to_freq(note: u8) -> f32 {
     // La fórmula correcta para MIDI a frecuencia es:
@@ -16,65 +30,69 @@ pub fn midi_note_to_freq(note: u8) -> f32 {
     let multiplier = 2.0f32.powf(power);
     let freq = 440.0 * multiplier;
     
-    // Imprimir para depuración
-    println!("Nota MIDI {} -> Frecuencia {} Hz", note, freq);
+    // Imprimir para depuración (por stderr, stdout puede llevar audio)
+    eprintln!("Nota MIDI {} -> Frecuencia {} Hz", note, freq);
     
     freq
 }
 
-pub fn handle_midi_message(
-    msg: &[u8], 
-    active_notes: Arc<Mutex<HashMap<u8, Note>>>, 
-    sample_rate: Arc<Mutex<f32>>, 
-    wave_type: Arc<Mutex<WaveType>>
-) {
-    match msg[0] & 0xF0 {
-        0x90 => { // Note On
-            let note = msg[1];
-            let velocity = msg[2] as f32 / 127.0;
-            if velocity > 0.0 {
-                let freq = midi_note_to_freq(note);
-                let mut envelope = Envelope::new(*sample_rate.lock().unwrap());
-                envelope.set_adsr(0.01, 0.1, 0.7, 0.3);
-                envelope.set_velocity(velocity);
-                let current_wave_type = *wave_type.lock().unwrap();
-                let new_note = Note::new(freq, envelope, *sample_rate.lock().unwrap(), current_wave_type, current_wave_type);
-                active_notes.lock().unwrap().insert(note, new_note);
-            } else {
-                if let Some(note) = active_notes.lock().unwrap().get_mut(&note) {
-                    note.envelope.note_off();
-                }
-            }
-        },
-        0x80 => { // Note Off
-            let note = msg[1];
-            if let Some(note) = active_notes.lock().unwrap().get_mut(&note) {
-                note.envelope.note_off();
-            }
-        },
+// `msg` es un mensaje completo, tal como lo entrega `MidiParser`. El motor reparte las
+// notas entre las voces; aquí solo se lleva la cuenta de las teclas pulsadas y del pedal
+// de sustain para el vigilante de voces colgadas.
+pub fn handle_midi_message(msg: &[u8], engine: &Mutex<SynthEngine>, watchdog: &VoiceWatchdog) {
+    match (msg[0] & 0xF0, msg.get(1).copied(), msg.get(2).copied()) {
+        (0x90, Some(note), Some(velocity)) if velocity > 0 => watchdog.key_down(note),
+        (0x80 | 0x90, Some(note), _) => watchdog.key_up(note),
+        (0xB0, Some(SUSTAIN_PEDAL_CC), Some(value)) => watchdog.set_sustain_pedal(value >= 64),
+        (0xB0, Some(ALL_NOTES_OFF_CC | ALL_SOUND_OFF_CC), _) => watchdog.release_all(),
         _ => (),
     }
+    engine.lock().unwrap().midi_message(msg);
 }
 
+// Abrir todos los puertos de entrada MIDI a la vez (p. ej. un teclado y un controlador
+// de mandos). Los mensajes de todos llegan al mismo motor y comparten las fuentes de
+// modulación. Devuelve None si no se pudo abrir ninguno.
 pub fn connect_midi(
-    active_notes: Arc<Mutex<HashMap<u8, Note>>>, 
-    sample_rate: Arc<Mutex<f32>>, 
-    wave_type: Arc<Mutex<WaveType>>
-) -> Option<MidiInputConnection<()>> {
-    let midi_in = MidiInput::new("rust-synth").ok()?;
-    let ports = midi_in.ports();
-    let port = ports.get(0)?;
+    engine: Arc<Mutex<SynthEngine>>,
+    watchdog: Arc<VoiceWatchdog>,
+) -> Option<Vec<MidiInputConnection<()>>> {
+    let port_count = MidiInput::new("rust-synth").ok()?.port_count();
+    let mut connections = Vec::new();
 
-    let notes = active_notes.clone();
-    let sr = sample_rate.clone();
-    let wt = wave_type.clone();
-    
-    midi_in.connect(
-        port,
-        "rust-synth",
-        move |_stamp, message, _| {
-            handle_midi_message(message, notes.clone(), sr.clone(), wt.clone());
-        },
-        (),
-    ).ok()
+    for index in 0..port_count {
+        // Cada conexión consume su MidiInput
+        let Ok(midi_in) = MidiInput::new("rust-synth") else { continue };
+        let ports = midi_in.ports();
+        let Some(port) = ports.get(index) else { continue };
+        let name = midi_in.port_name(port).unwrap_or_else(|_| "Puerto desconocido".into());
+
+        let engine = engine.clone();
+        let watchdog = watchdog.clone();
+        // Cada puerto es un flujo aparte, con su propio estado implícito
+        let mut parser = MidiParser::new();
+        let connection = midi_in.connect(
+            port,
+            "rust-synth",
+            move |_stamp, bytes, _| {
+                parser.feed(bytes, |message| {
+                    handle_midi_message(message, &engine, &watchdog);
+                });
+            },
+            (),
+        );
+        match connection {
+            Ok(connection) => {
+                eprintln!("Entrada MIDI conectada: {}", name);
+                connections.push(connection);
+            },
+            Err(err) => eprintln!("No se pudo abrir la entrada MIDI {}: {}", name, err),
+        }
+    }
+
+    if connections.is_empty() {
+        None
+    } else {
+        Some(connections)
+    }
 }
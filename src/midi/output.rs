@@ -0,0 +1,75 @@
+use midir::{MidiOutput, MidiOutputConnection};
+use super::{ALL_NOTES_OFF_CC, SUSTAIN_PEDAL_CC};
+
+// Salida MIDI para las notas que genera el propio sintetizador (audición de presets,
+// teclado en pantalla...), para tocar con ellas un equipo externo. El MIDI que llega
+// del controlador va por el thru, no por aquí.
+pub struct MidiOut {
+    connection: Option<MidiOutputConnection>,
+    // Canal de 0 a 15 de los mensajes enviados
+    pub channel: u8,
+}
+
+impl Default for MidiOut {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MidiOut {
+    pub fn new() -> Self {
+        Self {
+            connection: None,
+            channel: 0,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    // Abrir el puerto de salida `port_index`, cerrando antes el que estuviera abierto
+    pub fn connect(&mut self, port_index: usize) -> Result<(), String> {
+        self.disconnect();
+        let midi_out = MidiOutput::new("rust-synth-out").map_err(|err| err.to_string())?;
+        let ports = midi_out.ports();
+        let port = ports.get(port_index).ok_or("el puerto de salida ya no está disponible")?;
+        let connection = midi_out.connect(port, "midi-out").map_err(|err| err.to_string())?;
+        self.connection = Some(connection);
+        Ok(())
+    }
+
+    pub fn disconnect(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            connection.close();
+        }
+    }
+
+    // Enviar un mensaje completo; sin puerto abierto no hace nada
+    pub fn send(&mut self, message: &[u8]) {
+        if let Some(connection) = self.connection.as_mut() {
+            if let Err(err) = connection.send(message) {
+                eprintln!("Error al enviar MIDI: {}", err);
+            }
+        }
+    }
+
+    // Velocidad de 0.0 a 1.0; se envía al menos 1 para que no cuente como Note Off
+    pub fn note_on(&mut self, note: u8, velocity: f32) {
+        let velocity = (velocity.clamp(0.0, 1.0) * 127.0).round().max(1.0) as u8;
+        self.send(&[0x90 | (self.channel & 0x0F), note & 0x7F, velocity]);
+    }
+
+    pub fn note_off(&mut self, note: u8) {
+        self.send(&[0x80 | (self.channel & 0x0F), note & 0x7F, 0]);
+    }
+
+    // Botón de pánico: pedal de sustain levantado, pitch bend al centro y todas las
+    // notas del canal soltadas en el equipo externo
+    pub fn panic(&mut self) {
+        let channel = self.channel & 0x0F;
+        self.send(&[0xB0 | channel, SUSTAIN_PEDAL_CC, 0]);
+        self.send(&[0xE0 | channel, 0x00, 0x40]);
+        self.send(&[0xB0 | channel, ALL_NOTES_OFF_CC, 0]);
+    }
+}
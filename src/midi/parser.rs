@@ -0,0 +1,162 @@
+// Lectura de un flujo MIDI byte a byte, común al modo standalone y al plugin. Devuelve
+// mensajes completos con su byte de estado aunque lleguen partidos entre varios
+// paquetes, con estado implícito (running status) o con bytes de tiempo real en medio.
+
+// Tamaño máximo de un SysEx; lo que pase de aquí se descarta junto con el mensaje
+pub const MAX_SYSEX_LENGTH: usize = 4096;
+
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+
+// Bytes de datos que siguen a un byte de estado, o None si no inicia un mensaje
+fn data_length(status: u8) -> Option<usize> {
+    match status & 0xF0 {
+        0x80 | 0x90 | 0xA0 | 0xB0 | 0xE0 => Some(2),
+        0xC0 | 0xD0 => Some(1),
+        _ => match status {
+            // Código de tiempo MTC y selección de canción
+            0xF1 | 0xF3 => Some(1),
+            // Posición de la canción
+            0xF2 => Some(2),
+            // Petición de afinación
+            0xF6 => Some(0),
+            // 0xF4 y 0xF5 no están definidos
+            _ => None,
+        },
+    }
+}
+
+pub struct MidiParser {
+    // Estado del mensaje de canal en curso, que se repite mientras lleguen solo datos
+    running_status: Option<u8>,
+    message: [u8; 3],
+    length: usize,
+    expected: usize,
+    sysex: Vec<u8>,
+    in_sysex: bool,
+    // El SysEx en curso pasó de MAX_SYSEX_LENGTH y se descartará al cerrarse
+    sysex_overflow: bool,
+}
+
+impl Default for MidiParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MidiParser {
+    pub fn new() -> Self {
+        Self {
+            running_status: None,
+            message: [0; 3],
+            length: 0,
+            expected: 0,
+            sysex: Vec::new(),
+            in_sysex: false,
+            sysex_overflow: false,
+        }
+    }
+
+    // Olvidar el mensaje a medias, p. ej. al reconectar el puerto
+    pub fn reset(&mut self) {
+        self.running_status = None;
+        self.length = 0;
+        self.expected = 0;
+        self.sysex.clear();
+        self.in_sysex = false;
+        self.sysex_overflow = false;
+    }
+
+    // Leer `bytes` y llamar a `on_message` con cada mensaje completo: los de canal y de
+    // sistema común con su byte de estado, los de tiempo real de un solo byte y los
+    // SysEx enteros, de F0 a F7
+    pub fn feed(&mut self, bytes: &[u8], mut on_message: impl FnMut(&[u8])) {
+        for &byte in bytes {
+            self.push(byte, &mut on_message);
+        }
+    }
+
+    fn push(&mut self, byte: u8, on_message: &mut impl FnMut(&[u8])) {
+        // Tiempo real: se entrega en el acto sin tocar el mensaje en curso
+        if byte >= 0xF8 {
+            on_message(&[byte]);
+            return;
+        }
+
+        if byte & 0x80 != 0 {
+            // Cualquier otro byte de estado cierra un SysEx abierto
+            if self.in_sysex {
+                self.finish_sysex(on_message);
+                if byte == SYSEX_END {
+                    return;
+                }
+            }
+            match byte {
+                SYSEX_START => {
+                    self.running_status = None;
+                    self.length = 0;
+                    self.in_sysex = true;
+                    self.sysex_overflow = false;
+                    self.sysex.clear();
+                    self.sysex.push(byte);
+                },
+                // F7 suelto, sin SysEx abierto
+                SYSEX_END => self.running_status = None,
+                _ => match data_length(byte) {
+                    Some(expected) => {
+                        // Los mensajes de sistema común anulan el estado implícito
+                        self.running_status = if byte < 0xF0 { Some(byte) } else { None };
+                        self.message[0] = byte;
+                        self.length = 1;
+                        self.expected = expected;
+                        if expected == 0 {
+                            on_message(&self.message[..1]);
+                            self.length = 0;
+                        }
+                    },
+                    None => {
+                        self.running_status = None;
+                        self.length = 0;
+                    },
+                },
+            }
+            return;
+        }
+
+        // Byte de datos
+        if self.in_sysex {
+            if self.sysex.len() < MAX_SYSEX_LENGTH {
+                self.sysex.push(byte);
+            } else {
+                self.sysex_overflow = true;
+            }
+            return;
+        }
+        if self.length == 0 {
+            // Sin estado explícito se repite el último de canal; sin él, el byte se pierde
+            let Some(status) = self.running_status else {
+                return;
+            };
+            self.message[0] = status;
+            self.length = 1;
+            self.expected = data_length(status).unwrap_or(0);
+        }
+        self.message[self.length] = byte;
+        self.length += 1;
+        if self.length > self.expected {
+            on_message(&self.message[..self.length]);
+            self.length = 0;
+        }
+    }
+
+    fn finish_sysex(&mut self, on_message: &mut impl FnMut(&[u8])) {
+        self.in_sysex = false;
+        if self.sysex_overflow {
+            eprintln!("SysEx de más de {} bytes descartado", MAX_SYSEX_LENGTH);
+        } else {
+            self.sysex.push(SYSEX_END);
+            on_message(&self.sysex);
+        }
+        self.sysex.clear();
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use crate::structs::preset::is_valid_file_name;
+
+// Carpeta donde se exportan las grabaciones del modo standalone
+pub const RECORDINGS_DIR: &str = "recordings";
+// Resolución del archivo MIDI (ticks por negra)
+const TICKS_PER_QUARTER: u16 = 480;
+// Tempo fijo de 120 BPM (microsegundos por negra)
+const TEMPO_MICROS: u32 = 500_000;
+
+struct RecordedEvent {
+    // Microsegundos desde el inicio de la grabación
+    time_micros: u64,
+    message: Vec<u8>,
+}
+
+// Captura los mensajes MIDI de la sesión con su instante de llegada
+pub struct MidiRecorder {
+    start: Option<Instant>,
+    events: Vec<RecordedEvent>,
+}
+
+impl Default for MidiRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MidiRecorder {
+    pub fn new() -> Self {
+        Self {
+            start: None,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.start.is_some()
+    }
+
+    // Empezar una toma nueva descartando la anterior
+    pub fn start(&mut self) {
+        self.events.clear();
+        self.start = Some(Instant::now());
+    }
+
+    pub fn stop(&mut self) {
+        self.start = None;
+    }
+
+    pub fn event_count(&self) -> usize {
+        self.events.len()
+    }
+
+    // Registrar un mensaje de canal, ya venga del controlador o generado por el propio sintetizador
+    pub fn record(&mut self, message: &[u8]) {
+        let Some(start) = self.start else {
+            return;
+        };
+        // Solo mensajes de canal completos; los de sistema no van en la pista
+        if message.is_empty() || message[0] < 0x80 || message[0] >= 0xF0 {
+            return;
+        }
+        self.events.push(RecordedEvent {
+            time_micros: start.elapsed().as_micros() as u64,
+            message: message.to_vec(),
+        });
+    }
+
+    // Archivo MIDI estándar de formato 0 con una única pista
+    pub fn to_smf(&self) -> Vec<u8> {
+        let mut track = Vec::new();
+
+        // Meta evento de tempo al inicio
+        track.extend_from_slice(&[0x00, 0xFF, 0x51, 0x03]);
+        track.extend_from_slice(&TEMPO_MICROS.to_be_bytes()[1..]);
+
+        let mut last_tick = 0;
+        for event in &self.events {
+            let tick = event.time_micros * TICKS_PER_QUARTER as u64 / TEMPO_MICROS as u64;
+            write_variable_length(&mut track, (tick - last_tick) as u32);
+            track.extend_from_slice(&event.message);
+            last_tick = tick;
+        }
+
+        // Fin de pista
+        track.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+
+        let mut data = Vec::with_capacity(track.len() + 22);
+        data.extend_from_slice(b"MThd");
+        data.extend_from_slice(&6u32.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&TICKS_PER_QUARTER.to_be_bytes());
+        data.extend_from_slice(b"MTrk");
+        data.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        data.extend_from_slice(&track);
+        data
+    }
+
+    // El nombre es el del archivo dentro de `dir`; no puede llevar separadores de ruta
+    pub fn export(&self, dir: &Path, name: &str) -> io::Result<PathBuf> {
+        if !is_valid_file_name(name) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("nombre de grabación no válido: {:?}", name)));
+        }
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.mid", name));
+        fs::write(&path, self.to_smf())?;
+        Ok(path)
+    }
+}
+
+// Cantidad de longitud variable: 7 bits por byte, el bit alto indica que sigue otro
+fn write_variable_length(buffer: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = [0u8; 4];
+    let mut count = 0;
+    loop {
+        bytes[count] = (value & 0x7F) as u8;
+        count += 1;
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+    }
+    for (i, byte) in bytes[..count].iter().enumerate().rev() {
+        let continuation = if i > 0 { 0x80 } else { 0x00 };
+        buffer.push(byte | continuation);
+    }
+}
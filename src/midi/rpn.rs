@@ -0,0 +1,99 @@
+// Parámetros registrados (RPN): CC101/CC100 eligen el parámetro y CC6/CC38 (entrada de
+// datos) le dan valor. Así un DAW o un controlador puede fijar el rango del pitch bend
+// o transponer el sintetizador con mensajes MIDI estándar.
+
+pub const DATA_ENTRY_MSB_CC: u8 = 6;
+pub const DATA_ENTRY_LSB_CC: u8 = 38;
+pub const NRPN_LSB_CC: u8 = 98;
+pub const NRPN_MSB_CC: u8 = 99;
+pub const RPN_LSB_CC: u8 = 100;
+pub const RPN_MSB_CC: u8 = 101;
+
+// Parámetros atendidos, como (MSB << 7) | LSB
+const RPN_PITCH_BEND_SENSITIVITY: u16 = 0;
+const RPN_COARSE_TUNING: u16 = 2;
+// RPN nulo (127, 127): desactiva la entrada de datos
+const RPN_NULL: u16 = 0x3FFF;
+
+// Cambio que resulta de la entrada de datos sobre un RPN
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RpnChange {
+    // Semitonos de pitch bend en cada sentido (MSB semitonos, LSB centésimas)
+    BendRange(f32),
+    // Transposición en semitonos; el MSB 64 es el centro
+    CoarseTune(f32),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RpnReceiver {
+    // Parámetro elegido, o RPN_NULL sin ninguno (también tras elegir un NRPN)
+    selected: u16,
+    data_msb: u8,
+    data_lsb: u8,
+}
+
+impl Default for RpnReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RpnReceiver {
+    pub fn new() -> Self {
+        Self {
+            selected: RPN_NULL,
+            data_msb: 0,
+            data_lsb: 0,
+        }
+    }
+
+    // Si el CC pertenece a un RPN y no debe usarse como fuente de modulación. La
+    // entrada de datos solo cuenta con un RPN elegido; si no, CC6 y CC38 quedan libres.
+    pub fn handles(&self, cc: u8) -> bool {
+        match cc {
+            RPN_LSB_CC | RPN_MSB_CC | NRPN_LSB_CC | NRPN_MSB_CC => true,
+            DATA_ENTRY_MSB_CC | DATA_ENTRY_LSB_CC => self.selected != RPN_NULL,
+            _ => false,
+        }
+    }
+
+    pub fn control_change(&mut self, cc: u8, value: u8) -> Option<RpnChange> {
+        let value = value & 0x7F;
+        match cc {
+            RPN_MSB_CC => self.select((value as u16) << 7 | (self.selected & 0x7F)),
+            RPN_LSB_CC => self.select((self.selected & !0x7F) | value as u16),
+            // Los NRPN no se usan: la entrada de datos que los siga se ignora
+            NRPN_LSB_CC | NRPN_MSB_CC => self.selected = RPN_NULL,
+            DATA_ENTRY_MSB_CC => {
+                self.data_msb = value;
+                // El MSB basta; el LSB puede llegar detrás para afinar
+                self.data_lsb = 0;
+                return self.change();
+            },
+            DATA_ENTRY_LSB_CC => {
+                self.data_lsb = value;
+                return self.change();
+            },
+            _ => (),
+        }
+        None
+    }
+
+    fn select(&mut self, parameter: u16) {
+        if parameter != self.selected {
+            self.selected = parameter;
+            self.data_msb = 0;
+            self.data_lsb = 0;
+        }
+    }
+
+    fn change(&self) -> Option<RpnChange> {
+        match self.selected {
+            RPN_PITCH_BEND_SENSITIVITY => {
+                Some(RpnChange::BendRange(self.data_msb as f32 + self.data_lsb.min(99) as f32 / 100.0))
+            },
+            RPN_COARSE_TUNING => Some(RpnChange::CoarseTune(self.data_msb as f32 - 64.0)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use super::ALL_NOTES_OFF_CC;
+
+// Tempo por defecto de un archivo MIDI sin meta evento de tempo: 120 BPM
+const DEFAULT_TEMPO_MICROS: u32 = 500_000;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Mensaje de canal con el instante en que suena, en segundos desde el principio
+struct TimedEvent {
+    time: f64,
+    message: Vec<u8>,
+}
+
+// Archivo MIDI estándar (formato 0 o 1) con los mensajes de canal de todas las pistas
+// mezclados y ya pasados a segundos con el mapa de tempo
+pub struct MidiFile {
+    events: Vec<TimedEvent>,
+}
+
+impl MidiFile {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(4)? != b"MThd" {
+            return Err(invalid("no es un archivo MIDI estándar"));
+        }
+        let header_length = reader.u32()? as usize;
+        let header = reader.take(header_length)?;
+        if header.len() < 6 {
+            return Err(invalid("cabecera MThd demasiado corta"));
+        }
+        let format = u16::from_be_bytes([header[0], header[1]]);
+        let track_count = u16::from_be_bytes([header[2], header[3]]);
+        let division = u16::from_be_bytes([header[4], header[5]]);
+        if format > 1 {
+            return Err(invalid("solo se admiten archivos de formato 0 y 1"));
+        }
+
+        // Eventos de canal (tick, orden de llegada, mensaje) y cambios de tempo (tick, µs por negra)
+        let mut events: Vec<(u64, usize, Vec<u8>)> = Vec::new();
+        let mut tempos: Vec<(u64, u32)> = Vec::new();
+        for _ in 0..track_count {
+            let id = reader.take(4)?;
+            let length = reader.u32()? as usize;
+            let data = reader.take(length)?;
+            // Se ignoran los bloques desconocidos entre pistas
+            if id == b"MTrk" {
+                read_track(data, &mut events, &mut tempos)?;
+            }
+        }
+        // A igual tick se conserva el orden del archivo
+        events.sort_by_key(|(tick, order, _)| (*tick, *order));
+        tempos.sort_by_key(|(tick, _)| *tick);
+
+        let events = if division & 0x8000 != 0 {
+            // División SMPTE: fotogramas por segundo y ticks por fotograma, sin tempo. El
+            // byte alto solo puede ser -24, -25, -29 (30 con pérdida de fotogramas) o -30.
+            let frames = match (division >> 8) as i8 {
+                -24 => 24.0,
+                -25 => 25.0,
+                -29 => 29.97,
+                -30 => 30.0,
+                _ => return Err(invalid("división SMPTE no válida")),
+            };
+            let ticks_per_frame = (division & 0xFF) as f64;
+            let ticks_per_second = (frames * ticks_per_frame).max(1.0);
+            events.into_iter()
+                .map(|(tick, _, message)| TimedEvent { time: tick as f64 / ticks_per_second, message })
+                .collect()
+        } else {
+            let ticks_per_quarter = division.max(1) as f64;
+            // Recorrer los eventos acumulando el tiempo de cada tramo de tempo
+            let mut tempo_index = 0;
+            let (mut last_tick, mut last_time) = (0u64, 0.0f64);
+            let mut tempo = DEFAULT_TEMPO_MICROS;
+            events.into_iter()
+                .map(|(tick, _, message)| {
+                    while tempo_index < tempos.len() && tempos[tempo_index].0 <= tick {
+                        let (change_tick, change_tempo) = tempos[tempo_index];
+                        last_time += (change_tick - last_tick) as f64 * tempo as f64 / 1e6 / ticks_per_quarter;
+                        last_tick = change_tick;
+                        tempo = change_tempo;
+                        tempo_index += 1;
+                    }
+                    let time = last_time + (tick - last_tick) as f64 * tempo as f64 / 1e6 / ticks_per_quarter;
+                    TimedEvent { time, message }
+                })
+                .collect()
+        };
+        Ok(Self { events })
+    }
+
+    pub fn event_count(&self) -> usize {
+        self.events.len()
+    }
+
+    // Segundos hasta el último evento
+    pub fn duration(&self) -> f64 {
+        self.events.last().map_or(0.0, |event| event.time)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let end = self.offset.checked_add(count).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| invalid("el archivo está truncado"))?;
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // Cantidad de longitud variable: 7 bits por byte, el bit alto indica que sigue otro
+    fn variable_length(&mut self) -> io::Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("cantidad de longitud variable demasiado larga"))
+    }
+}
+
+fn read_track(data: &[u8], events: &mut Vec<(u64, usize, Vec<u8>)>, tempos: &mut Vec<(u64, u32)>) -> io::Result<()> {
+    let mut reader = Reader { bytes: data, offset: 0 };
+    let mut tick = 0u64;
+    let mut running_status: Option<u8> = None;
+    while reader.offset < data.len() {
+        tick += reader.variable_length()? as u64;
+        let first = reader.byte()?;
+        match first {
+            // Meta evento: solo interesan el tempo y el fin de pista
+            0xFF => {
+                let kind = reader.byte()?;
+                let length = reader.variable_length()? as usize;
+                let payload = reader.take(length)?;
+                match kind {
+                    0x51 if payload.len() == 3 => {
+                        tempos.push((tick, u32::from_be_bytes([0, payload[0], payload[1], payload[2]])));
+                    },
+                    0x2F => break,
+                    _ => (),
+                }
+            },
+            // SysEx: no se reproduce
+            0xF0 | 0xF7 => {
+                let length = reader.variable_length()? as usize;
+                reader.take(length)?;
+                running_status = None;
+            },
+            _ => {
+                // Con estado implícito el primer byte ya es un dato
+                let (status, first_data) = if first & 0x80 != 0 {
+                    running_status = Some(first);
+                    (first, None)
+                } else {
+                    let status = running_status.ok_or_else(|| invalid("dato sin byte de estado"))?;
+                    (status, Some(first))
+                };
+                let data_length = match status & 0xF0 {
+                    0xC0 | 0xD0 => 1,
+                    0x80..=0xE0 => 2,
+                    _ => return Err(invalid("byte de estado no válido en la pista")),
+                };
+                let mut message = vec![status];
+                message.extend(first_data);
+                while message.len() <= data_length {
+                    message.push(reader.byte()?);
+                }
+                let order = events.len();
+                events.push((tick, order, message));
+            },
+        }
+    }
+    Ok(())
+}
+
+// Reproducción de un MidiFile al ritmo que marque quien la llama, normalmente el reloj
+// del audio (muestras generadas / frecuencia de muestreo)
+pub struct MidiFilePlayer {
+    file: MidiFile,
+    position: usize,
+}
+
+impl MidiFilePlayer {
+    pub fn new(file: MidiFile) -> Self {
+        Self { file, position: 0 }
+    }
+
+    // Entregar los mensajes que tocan hasta `time` segundos desde el principio
+    pub fn advance_to(&mut self, time: f64, mut on_message: impl FnMut(&[u8])) {
+        while let Some(event) = self.file.events.get(self.position) {
+            if event.time > time {
+                break;
+            }
+            on_message(&event.message);
+            self.position += 1;
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.file.events.len()
+    }
+
+    // Soltar las notas que sigan sonando con un All Notes Off en cada canal
+    pub fn stop(&mut self, mut on_message: impl FnMut(&[u8])) {
+        self.position = self.file.events.len();
+        for channel in 0..16u8 {
+            on_message(&[0xB0 | channel, ALL_NOTES_OFF_CC, 0]);
+        }
+    }
+}
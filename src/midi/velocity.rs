@@ -0,0 +1,126 @@
+// Canales de un puerto MIDI
+pub const MIDI_CHANNELS: usize = 16;
+
+// Velocidad normalizada más baja de una nota tocada
+const MIN_NOTE_VELOCITY: f32 = 1.0 / 127.0;
+
+// Ajuste de la velocidad de entrada de un canal, para igualar pads de batería
+// (normalmente en el canal 10) con el teclado o domar teclados de respuesta brusca
+#[derive(Clone, Copy, PartialEq)]
+pub struct VelocityTrim {
+    pub gain: f32,
+    // Desplazamiento que se suma tras la ganancia, de -1.0 a 1.0
+    pub offset: f32,
+    // Límites del resultado, de 0.0 a 1.0
+    pub min: f32,
+    pub max: f32,
+    // Si tiene valor, todas las notas del canal llegan con esta velocidad
+    pub fixed: Option<f32>,
+}
+
+impl Default for VelocityTrim {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            offset: 0.0,
+            min: 0.0,
+            max: 1.0,
+            fixed: None,
+        }
+    }
+}
+
+impl VelocityTrim {
+    // Velocidad normalizada (0..1) ya ajustada. Una nota tocada nunca baja de la
+    // velocidad mínima para que el ajuste no la convierta en un Note Off.
+    pub fn apply(&self, velocity: f32) -> f32 {
+        if velocity <= 0.0 {
+            return 0.0;
+        }
+        let velocity = match self.fixed {
+            Some(fixed) => fixed,
+            None => velocity * self.gain + self.offset,
+        };
+        let max = self.max.clamp(MIN_NOTE_VELOCITY, 1.0);
+        let min = self.min.clamp(MIN_NOTE_VELOCITY, max);
+        velocity.clamp(min, max)
+    }
+}
+
+// Curva de respuesta de la velocidad del teclado
+#[derive(Clone, Copy, PartialEq)]
+pub enum VelocityCurve {
+    Linear,
+    // Más volumen con poca fuerza
+    Soft,
+    // Hace falta tocar más fuerte para llegar arriba
+    Hard,
+    // Todas las notas al máximo, como un órgano
+    Fixed,
+}
+
+impl VelocityCurve {
+    pub const ALL: [VelocityCurve; 4] = [
+        VelocityCurve::Linear,
+        VelocityCurve::Soft,
+        VelocityCurve::Hard,
+        VelocityCurve::Fixed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VelocityCurve::Linear => "Lineal",
+            VelocityCurve::Soft => "Suave",
+            VelocityCurve::Hard => "Dura",
+            VelocityCurve::Fixed => "Fija",
+        }
+    }
+
+    // Nombre en la línea de comandos
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(VelocityCurve::Linear),
+            "soft" => Some(VelocityCurve::Soft),
+            "hard" => Some(VelocityCurve::Hard),
+            "fixed" => Some(VelocityCurve::Fixed),
+            _ => None,
+        }
+    }
+
+    fn shape(&self, velocity: f32) -> f32 {
+        match self {
+            VelocityCurve::Linear => velocity,
+            VelocityCurve::Soft => velocity.sqrt(),
+            VelocityCurve::Hard => velocity * velocity,
+            VelocityCurve::Fixed => 1.0,
+        }
+    }
+}
+
+// Respuesta del teclado a la velocidad: curva y sensibilidad. Con sensibilidad 0
+// todas las notas suenan al máximo; con 1 se usa la curva completa.
+#[derive(Clone, Copy, PartialEq)]
+pub struct VelocityResponse {
+    pub curve: VelocityCurve,
+    pub sensitivity: f32,
+}
+
+impl Default for VelocityResponse {
+    fn default() -> Self {
+        Self {
+            curve: VelocityCurve::Linear,
+            sensitivity: 1.0,
+        }
+    }
+}
+
+impl VelocityResponse {
+    // Velocidad normalizada (0..1) de entrada; una velocidad nula sigue siendo Note Off
+    pub fn apply(&self, velocity: f32) -> f32 {
+        if velocity <= 0.0 {
+            return 0.0;
+        }
+        let shaped = self.curve.shape(velocity.min(1.0));
+        (1.0 - self.sensitivity.clamp(0.0, 1.0) * (1.0 - shaped)).clamp(MIN_NOTE_VELOCITY, 1.0)
+    }
+}
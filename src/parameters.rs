@@ -0,0 +1,182 @@
+use crate::api::{ParameterInfo, ParameterKind};
+
+// Escala de un parámetro del plugin: cómo pasa del valor normalizado (0..1) que usa el
+// host al valor real, y cómo se muestra ese valor en el DAW ("120 ms", "2.4 kHz"...).
+// Las conversiones son las mismas que aplica set_parameter_normalized.
+#[derive(Clone, Copy)]
+pub enum ParameterScale {
+    // Lista de opciones: nombre de cada índice y número de opciones
+    Choice(fn(usize) -> &'static str, usize),
+    Toggle,
+    // De 0.0 al máximo dado, mostrado en porcentaje
+    Percent(f32),
+    // De -1.0 a 1.0, mostrado en porcentaje con signo
+    Bipolar,
+    // Panorama de -1.0 (izquierda) a 1.0 (derecha)
+    Pan,
+    // Entero de min a max
+    Integer(i32, i32, &'static str),
+    // Lineal de min a max, con su unidad
+    Linear(f32, f32, &'static str),
+    // Exponencial de min a max, como los tiempos y las frecuencias
+    Exponential(f32, f32, &'static str),
+}
+
+// Opción de una lista de `count` a partir del valor normalizado: cada opción ocupa un
+// paso igual de 0.0 a 1.0, como espera el host de un parámetro con step_count = count - 1
+pub fn choice_index(normalized: f64, count: usize) -> usize {
+    let last = count.saturating_sub(1);
+    ((normalized.clamp(0.0, 1.0) * last as f64).round() as usize).min(last)
+}
+
+pub fn choice_normalized(index: usize, count: usize) -> f64 {
+    (index as f64 / count.saturating_sub(1).max(1) as f64).clamp(0.0, 1.0)
+}
+
+impl ParameterScale {
+    pub fn to_plain(&self, normalized: f64) -> f64 {
+        let value = normalized.clamp(0.0, 1.0);
+        match *self {
+            ParameterScale::Choice(_, count) => choice_index(value, count) as f64,
+            ParameterScale::Toggle => if value >= 0.5 { 1.0 } else { 0.0 },
+            ParameterScale::Percent(max) => value * max as f64,
+            ParameterScale::Bipolar | ParameterScale::Pan => value * 2.0 - 1.0,
+            ParameterScale::Integer(min, max, _) => min as f64 + (value * (max - min) as f64).round(),
+            ParameterScale::Linear(min, max, _) => min as f64 + value * (max - min) as f64,
+            ParameterScale::Exponential(min, max, _) => min as f64 * (max as f64 / min as f64).powf(value),
+        }
+    }
+
+    pub fn to_normalized(&self, plain: f64) -> f64 {
+        let value = match *self {
+            ParameterScale::Choice(_, count) => choice_normalized(plain.max(0.0).round() as usize, count),
+            ParameterScale::Toggle => if plain >= 0.5 { 1.0 } else { 0.0 },
+            ParameterScale::Percent(max) => plain / max as f64,
+            ParameterScale::Bipolar | ParameterScale::Pan => (plain + 1.0) / 2.0,
+            ParameterScale::Integer(min, max, _) => (plain.round() - min as f64) / (max - min) as f64,
+            ParameterScale::Linear(min, max, _) => (plain - min as f64) / (max - min) as f64,
+            ParameterScale::Exponential(min, max, _) => {
+                (plain.max(min as f64) / min as f64).ln() / (max as f64 / min as f64).ln()
+            }
+        };
+        value.clamp(0.0, 1.0)
+    }
+
+    // Texto del valor con su unidad, que se adapta a la escala (ms o s, Hz o kHz)
+    pub fn format(&self, normalized: f64) -> String {
+        let plain = self.to_plain(normalized);
+        match *self {
+            ParameterScale::Choice(name, _) => name(plain as usize).to_string(),
+            ParameterScale::Toggle => String::from(if plain >= 0.5 { "On" } else { "Off" }),
+            ParameterScale::Percent(_) => format!("{:.0} %", plain * 100.0),
+            ParameterScale::Bipolar => format!("{:+.0} %", plain * 100.0),
+            ParameterScale::Pan => {
+                let percent = (plain * 100.0).round();
+                if percent == 0.0 {
+                    String::from("C")
+                } else if percent < 0.0 {
+                    format!("L{:.0}", -percent)
+                } else {
+                    format!("R{:.0}", percent)
+                }
+            }
+            ParameterScale::Integer(_, _, "oct") => format!("{:+.0} oct", plain),
+            ParameterScale::Integer(_, _, "") => format!("{:.0}", plain),
+            ParameterScale::Integer(_, _, unit) => format!("{:.0} {}", plain, unit),
+            ParameterScale::Linear(_, _, unit) | ParameterScale::Exponential(_, _, unit) => format_value(plain, unit),
+        }
+    }
+
+    // Valor normalizado a partir del texto que escribe el usuario en el host. Acepta
+    // la unidad que muestra format() o ninguna ("120 ms", "0.12 s", "2.4 kHz", "L30").
+    pub fn parse(&self, text: &str) -> Option<f64> {
+        let text = text.trim().to_lowercase();
+        let plain = match *self {
+            ParameterScale::Choice(name, count) => (0..count).find(|&index| name(index).to_lowercase() == text)? as f64,
+            ParameterScale::Toggle => match text.as_str() {
+                "on" | "1" => 1.0,
+                "off" | "0" => 0.0,
+                _ => return None,
+            },
+            ParameterScale::Percent(_) | ParameterScale::Bipolar => parse_number(&text)?.0 / 100.0,
+            ParameterScale::Pan => {
+                if text == "c" {
+                    0.0
+                } else if let Some(rest) = text.strip_prefix('l') {
+                    -parse_number(rest)?.0 / 100.0
+                } else if let Some(rest) = text.strip_prefix('r') {
+                    parse_number(rest)?.0 / 100.0
+                } else {
+                    parse_number(&text)?.0 / 100.0
+                }
+            }
+            ParameterScale::Integer(_, _, unit)
+            | ParameterScale::Linear(_, _, unit)
+            | ParameterScale::Exponential(_, _, unit) => {
+                let (value, suffix) = parse_number(&text)?;
+                value * unit_factor(suffix, unit)
+            }
+        };
+        Some(self.to_normalized(plain))
+    }
+}
+
+// La escala sale del tipo y el rango del parámetro en el registro
+impl From<&ParameterInfo> for ParameterScale {
+    fn from(info: &ParameterInfo) -> Self {
+        match info.kind {
+            ParameterKind::Choice(name) => ParameterScale::Choice(name, info.max as usize + 1),
+            ParameterKind::Toggle => ParameterScale::Toggle,
+            ParameterKind::Percent => ParameterScale::Percent(info.max),
+            ParameterKind::Bipolar => ParameterScale::Bipolar,
+            ParameterKind::Pan => ParameterScale::Pan,
+            ParameterKind::Integer => ParameterScale::Integer(info.min as i32, info.max as i32, info.unit),
+            ParameterKind::Continuous if info.logarithmic => ParameterScale::Exponential(info.min, info.max, info.unit),
+            ParameterKind::Continuous => ParameterScale::Linear(info.min, info.max, info.unit),
+        }
+    }
+}
+
+// Escala de un parámetro por su ID, o None si el ID no existe
+pub fn parameter_scale(id: u32) -> Option<ParameterScale> {
+    ParameterInfo::find(id).map(ParameterScale::from)
+}
+
+fn format_value(value: f64, unit: &str) -> String {
+    match unit {
+        "s" if value < 1.0 => format_value(value * 1000.0, "ms"),
+        "ms" if value >= 1000.0 => format!("{:.2} s", value / 1000.0),
+        "Hz" if value >= 1000.0 => format!("{:.1} kHz", value / 1000.0),
+        "dB" => format!("{:.1} dB", value),
+        ":1" => format!("{:.1}:1", value),
+        _ => {
+            // Tres cifras significativas más o menos, sin decimales de sobra en los valores grandes
+            let decimals = if value.abs() >= 100.0 { 0 } else if value.abs() >= 10.0 { 1 } else { 2 };
+            if unit.is_empty() {
+                format!("{:.*}", decimals, value)
+            } else {
+                format!("{:.*} {}", decimals, value, unit)
+            }
+        }
+    }
+}
+
+// Número al principio del texto (admite coma decimal) y lo que queda detrás
+fn parse_number(text: &str) -> Option<(f64, &str)> {
+    let text = text.trim();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | ',')))
+        .unwrap_or(text.len());
+    let value = text[..end].replace(',', ".").parse().ok()?;
+    Some((value, text[end..].trim()))
+}
+
+// Factor para pasar de la unidad escrita a la del parámetro; sin unidad o con la misma, 1
+fn unit_factor(suffix: &str, unit: &str) -> f64 {
+    match (suffix, unit) {
+        ("ms", "s") => 0.001,
+        ("s", "ms") => 1000.0,
+        ("khz" | "k", "Hz") => 1000.0,
+        _ => 1.0,
+    }
+}
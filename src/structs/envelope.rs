@@ -1,3 +1,5 @@
+use crate::dsp::smoother::GAIN_SMOOTHING_TIME;
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum EnvelopeState {
     Idle,
@@ -7,6 +9,70 @@ pub enum EnvelopeState {
     Release,
 }
 
+// Rango de los tiempos de etapa (segundos), el mismo que declara el plugin
+pub const MIN_STAGE_TIME: f32 = 0.001;
+pub const MAX_ATTACK_TIME: f32 = 2.0;
+pub const MAX_DECAY_TIME: f32 = 2.0;
+pub const MAX_RELEASE_TIME: f32 = 5.0;
+
+// Tiempo de una etapa a partir de un valor normalizado (0..1) en escala logarítmica
+pub fn stage_time_from_normalized(value: f32, max_time: f32) -> f32 {
+    MIN_STAGE_TIME * (max_time / MIN_STAGE_TIME).powf(value.clamp(0.0, 1.0))
+}
+
+pub fn stage_time_to_normalized(time: f32, max_time: f32) -> f32 {
+    ((time / MIN_STAGE_TIME).ln() / (max_time / MIN_STAGE_TIME).ln()).clamp(0.0, 1.0)
+}
+
+// Fundido con el que se corta una voz a la fuerza (segundos), para que no haya chasquido
+pub const KILL_FADE_TIME: f32 = 0.005;
+
+// Nota MIDI en la que el seguimiento de teclado no cambia los tiempos (C4)
+pub const KEY_TRACKING_CENTER: u8 = 60;
+
+// Parámetros ADSR de una envolvente, tiempos en segundos
+#[derive(Clone, Copy, PartialEq)]
+pub struct AdsrSettings {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+    // Seguimiento de teclado del decaimiento y la liberación (0.0 a 1.0): con 1.0
+    // cada octava por encima de C4 divide esos tiempos por dos, como en un piano
+    pub key_tracking: f32,
+}
+
+impl Default for AdsrSettings {
+    fn default() -> Self {
+        Self {
+            attack: 0.01,
+            decay: 0.1,
+            sustain: 0.7,
+            release: 0.3,
+            key_tracking: 0.0,
+        }
+    }
+}
+
+impl AdsrSettings {
+    // Duración mínima de una nota hasta llegar al sostenido
+    pub fn min_note_length(&self) -> f32 {
+        self.attack + self.decay
+    }
+
+    // Cola que queda sonando tras soltar la tecla
+    pub fn tail(&self) -> f32 {
+        self.release
+    }
+
+    // Cola más larga de todo el teclado: con seguimiento de teclado la liberación se
+    // alarga en las notas graves, hasta cinco octavas por debajo de C4 en la nota 0
+    pub fn max_tail(&self) -> f32 {
+        let octaves = KEY_TRACKING_CENTER as f32 / 12.0;
+        self.release * 2.0f32.powf(octaves * self.key_tracking.clamp(0.0, 1.0))
+    }
+}
+
 pub struct Envelope {
     pub sample_rate: f32,
     pub state: EnvelopeState,
@@ -16,6 +82,13 @@ pub struct Envelope {
     pub sustain_level: f32,
     pub release_time: f32,
     pub velocity: f32,
+    pub key_tracking: f32,
+    // Nota MIDI que toca la envolvente, para el seguimiento de teclado
+    key: u8,
+    // Cortada con kill(): la liberación dura KILL_FADE_TIME sea cual sea el ajuste
+    killed: bool,
+    // Muestras transcurridas en la fase de sustain desde el último note_on
+    sustain_samples: u64,
     attack_increment: f32,
     decay_increment: f32,
     release_increment: f32,
@@ -32,6 +105,10 @@ impl Envelope {
             sustain_level: 0.7,
             release_time: 0.3,
             velocity: 1.0,
+            key_tracking: 0.0,
+            key: KEY_TRACKING_CENTER,
+            killed: false,
+            sustain_samples: 0,
             attack_increment: 0.0,
             decay_increment: 0.0,
             release_increment: 0.0,
@@ -46,28 +123,90 @@ impl Envelope {
         self.recalculate_increments();
     }
 
+    pub fn set_settings(&mut self, settings: &AdsrSettings) {
+        self.key_tracking = settings.key_tracking;
+        self.set_adsr(settings.attack, settings.decay, settings.sustain, settings.release);
+    }
+
+    pub fn set_key(&mut self, key: u8) {
+        self.key = key;
+        self.recalculate_increments();
+    }
+
+    pub fn key(&self) -> u8 {
+        self.key
+    }
+
+    // Factor por el que se multiplican el decaimiento y la liberación en esta nota
+    fn key_scale(&self) -> f32 {
+        let octaves = (self.key as f32 - KEY_TRACKING_CENTER as f32) / 12.0;
+        2.0f32.powf(-octaves * self.key_tracking.clamp(0.0, 1.0))
+    }
+
     pub fn set_velocity(&mut self, velocity: f32) {
         self.velocity = velocity;
         self.recalculate_increments();
     }
 
+    // Mantiene la etapa y el nivel actuales; solo cambian los incrementos por muestra.
+    // Un fundido de kill() conserva la duración que le quedaba
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if self.killed {
+            self.release_increment *= self.sample_rate / sample_rate;
+        }
+        self.sample_rate = sample_rate;
+        self.recalculate_increments();
+    }
+
     fn recalculate_increments(&mut self) {
         self.attack_increment = 1.0 / (self.attack_time * self.sample_rate);
-        self.decay_increment = (1.0 - self.sustain_level) / (self.decay_time * self.sample_rate);
-        self.release_increment = self.sustain_level / (self.release_time * self.sample_rate);
+        let key_scale = self.key_scale();
+        self.decay_increment = (1.0 - self.sustain_level) / (self.decay_time * key_scale * self.sample_rate);
+        if !self.killed {
+            self.release_increment = self.sustain_level / (self.release_time * key_scale * self.sample_rate);
+        }
     }
 
     pub fn note_on(&mut self) {
         self.state = EnvelopeState::Attack;
+        self.sustain_samples = 0;
+        self.killed = false;
         self.recalculate_increments();
     }
 
+    // Volver a pulsar una nota que aún suena: el ataque arranca desde el nivel actual
+    // y el nivel se reescala a la nueva velocidad para que la salida no salte
+    pub fn retrigger(&mut self, velocity: f32) {
+        let output = self.current_level * self.velocity;
+        self.velocity = velocity;
+        self.current_level = if velocity > 0.0 { (output / velocity).min(1.0) } else { 0.0 };
+        self.note_on();
+    }
+
+    // Segundos hasta que la envolvente se apague si se suelta ahora la tecla
+    pub fn remaining_tail(&self) -> f32 {
+        if self.state == EnvelopeState::Idle || self.release_increment <= 0.0 {
+            return 0.0;
+        }
+        self.current_level / (self.release_increment * self.sample_rate)
+    }
+
     pub fn note_off(&mut self) {
         if self.state != EnvelopeState::Idle {
             self.state = EnvelopeState::Release;
         }
     }
 
+    // Terminar la nota con un fundido de KILL_FADE_TIME desde el nivel actual, para
+    // voces que se quitan antes de tiempo (robo de voces, cortes de todo el sonido...)
+    pub fn kill(&mut self) {
+        if self.state != EnvelopeState::Idle {
+            self.state = EnvelopeState::Release;
+            self.killed = true;
+            self.release_increment = (self.current_level / (KILL_FADE_TIME * self.sample_rate)).max(f32::EPSILON);
+        }
+    }
+
     pub fn next_sample(&mut self) -> f32 {
         match self.state {
             EnvelopeState::Idle => 0.0,
@@ -82,12 +221,21 @@ impl Envelope {
             EnvelopeState::Decay => {
                 self.current_level -= self.decay_increment;
                 if self.current_level <= self.sustain_level {
-                    self.current_level = self.sustain_level;
+                    // Si el sostenido ha subido a media caída, el sustain llega a él en rampa
+                    if self.sustain_level - self.current_level <= self.decay_increment {
+                        self.current_level = self.sustain_level;
+                    }
                     self.state = EnvelopeState::Sustain;
                 }
                 self.current_level * self.velocity
             }
             EnvelopeState::Sustain => {
+                // Un cambio del sostenido con la tecla pulsada se desliza en lugar de saltar
+                if self.current_level != self.sustain_level {
+                    let step = 1.0 / (GAIN_SMOOTHING_TIME * self.sample_rate).max(1.0);
+                    self.current_level += (self.sustain_level - self.current_level).clamp(-step, step);
+                }
+                self.sustain_samples += 1;
                 self.current_level * self.velocity
             }
             EnvelopeState::Release => {
@@ -101,7 +249,16 @@ impl Envelope {
         }
     }
 
+    pub fn sustain_seconds(&self) -> f32 {
+        self.sustain_samples as f32 / self.sample_rate
+    }
+
     pub fn is_finished(&self) -> bool {
         self.state == EnvelopeState::Idle
     }
+
+    // Cortada con kill() en lugar de terminar su liberación normal
+    pub fn is_killed(&self) -> bool {
+        self.killed
+    }
 }
\ No newline at end of file
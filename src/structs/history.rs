@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+use super::preset::Preset;
+
+// Pasos que se pueden deshacer; los más antiguos se descartan
+const MAX_UNDO_STEPS: usize = 100;
+// Los cambios separados por menos de este tiempo forman un solo paso (p. ej. varios
+// clics seguidos en un DragValue o un mando girado por CC)
+const COALESCE_TIME: Duration = Duration::from_millis(500);
+
+// Historial de deshacer/rehacer de los cambios del sonido. Cada paso guarda el estado
+// completo de antes del cambio, así que sirve igual para un parámetro suelto que para
+// cargar un preset entero.
+pub struct UndoHistory {
+    undo: Vec<Preset>,
+    redo: Vec<Preset>,
+    // Último estado registrado, con el que se compara el actual
+    current: Preset,
+    // Momento del último cambio del paso abierto; sin valor no hay ninguno abierto
+    last_change: Option<Instant>,
+}
+
+impl UndoHistory {
+    pub fn new(current: Preset) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            current,
+            last_change: None,
+        }
+    }
+
+    // Anotar el estado actual. Con `editing` hay un gesto en curso (arrastrar un
+    // slider) y todo lo que cambie hasta soltarlo se añade al mismo paso. Devuelve true
+    // si el estado ha cambiado desde el último registrado.
+    pub fn record(&mut self, state: Preset, editing: bool) -> bool {
+        let now = Instant::now();
+        let open = self.last_change
+            .is_some_and(|last| editing || now.duration_since(last) < COALESCE_TIME);
+        if !open {
+            self.last_change = None;
+        }
+        if state == self.current {
+            return false;
+        }
+        if open {
+            self.current = state;
+        } else {
+            self.undo.push(std::mem::replace(&mut self.current, state));
+            if self.undo.len() > MAX_UNDO_STEPS {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.last_change = Some(now);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // Estado al que hay que volver, si queda alguno
+    pub fn undo(&mut self) -> Option<Preset> {
+        let previous = self.undo.pop()?;
+        self.redo.push(std::mem::replace(&mut self.current, previous));
+        self.last_change = None;
+        Some(self.current.clone())
+    }
+
+    pub fn redo(&mut self) -> Option<Preset> {
+        let next = self.redo.pop()?;
+        self.undo.push(std::mem::replace(&mut self.current, next));
+        self.last_change = None;
+        Some(self.current.clone())
+    }
+}
@@ -1,2 +1,6 @@
 pub mod envelope;
-pub mod note;
\ No newline at end of file
+pub mod history;
+pub mod mseg;
+pub mod preset;
+pub mod session;
+pub mod split;
\ No newline at end of file
@@ -0,0 +1,230 @@
+use crate::dsp::modulation::ModDestination;
+
+// Generador de envolvente multisegmento (MSEG): una curva libre de puntos que se
+// recorre desde la pulsación de la nota y modula el tono, el corte del filtro o la
+// posición de la tabla de ondas
+
+// Número máximo de puntos de la curva
+pub const MAX_MSEG_POINTS: usize = 16;
+
+// Subdivisiones por pulso disponibles para la rejilla del editor
+pub const MSEG_GRIDS: [u32; 5] = [1, 2, 4, 8, 16];
+
+// El MSEG modula los mismos destinos que la matriz de modulación, con el mismo alcance
+pub type MsegDestination = ModDestination;
+
+// Punto de la curva. `curve` da forma al segmento que llega a este punto:
+// 0.0 es una recta, los valores positivos empiezan despacio y los negativos deprisa.
+#[derive(Clone, Copy, PartialEq)]
+pub struct MsegPoint {
+    // En pulsos con sincronización al tempo, en segundos sin ella
+    pub time: f32,
+    // Nivel de 0.0 a 1.0
+    pub level: f32,
+    // De -1.0 a 1.0
+    pub curve: f32,
+}
+
+impl MsegPoint {
+    pub const fn new(time: f32, level: f32, curve: f32) -> Self {
+        Self { time, level, curve }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct MsegSettings {
+    pub enabled: bool,
+    // Puntos ordenados por tiempo; el primero está siempre en el instante 0
+    pub points: [MsegPoint; MAX_MSEG_POINTS],
+    pub point_count: usize,
+    // Con la tecla pulsada se repite el tramo entre estos dos puntos
+    pub looping: bool,
+    pub loop_start: usize,
+    pub loop_end: usize,
+    // Tiempos en pulsos a `bpm` en lugar de en segundos
+    pub tempo_sync: bool,
+    pub bpm: f32,
+    // Subdivisiones por pulso a las que se ajustan los puntos en el editor
+    pub grid: u32,
+    pub destination: MsegDestination,
+    // De -1.0 a 1.0
+    pub amount: f32,
+}
+
+impl Default for MsegSettings {
+    fn default() -> Self {
+        let mut points = [MsegPoint::new(0.0, 0.0, 0.0); MAX_MSEG_POINTS];
+        points[1] = MsegPoint::new(0.25, 1.0, 0.0);
+        points[2] = MsegPoint::new(1.0, 0.5, 0.5);
+        points[3] = MsegPoint::new(2.0, 0.0, 0.0);
+        Self {
+            enabled: false,
+            points,
+            point_count: 4,
+            looping: false,
+            loop_start: 1,
+            loop_end: 2,
+            tempo_sync: false,
+            bpm: 120.0,
+            grid: 4,
+            destination: MsegDestination::Cutoff,
+            amount: 0.5,
+        }
+    }
+}
+
+// Forma del segmento: `t` de 0.0 a 1.0 dentro del tramo
+#[inline]
+fn shape(t: f32, curve: f32) -> f32 {
+    t.powf(2.0f32.powf(curve.clamp(-1.0, 1.0) * 3.0))
+}
+
+impl MsegSettings {
+    pub fn active_points(&self) -> &[MsegPoint] {
+        &self.points[..self.point_count.clamp(2, MAX_MSEG_POINTS)]
+    }
+
+    // Duración total de la curva en sus propias unidades
+    pub fn length(&self) -> f32 {
+        self.active_points().last().map_or(0.0, |point| point.time)
+    }
+
+    // Unidades de tiempo de la curva que avanzan por segundo
+    pub fn units_per_second(&self) -> f32 {
+        if self.tempo_sync {
+            self.bpm.max(1.0) / 60.0
+        } else {
+            1.0
+        }
+    }
+
+    // Tiempo ajustado a la rejilla; sin sincronización la rejilla divide el segundo
+    pub fn snap(&self, time: f32) -> f32 {
+        let step = 1.0 / self.grid.max(1) as f32;
+        (time / step).round() * step
+    }
+
+    // Tramo de repetición válido (inicio, fin) o None si no se repite
+    pub fn loop_region(&self) -> Option<(usize, usize)> {
+        let last = self.active_points().len() - 1;
+        let (start, end) = (self.loop_start.min(last), self.loop_end.min(last));
+        (self.looping && start < end).then_some((start, end))
+    }
+
+    // Nivel de la curva en un instante; fuera de ella se mantiene el extremo más cercano
+    pub fn level_at(&self, time: f32) -> f32 {
+        let points = self.active_points();
+        if time <= points[0].time {
+            return points[0].level;
+        }
+        for pair in points.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if time < to.time {
+                let span = to.time - from.time;
+                if span <= 0.0 {
+                    return to.level;
+                }
+                let t = shape((time - from.time) / span, to.curve);
+                return from.level + (to.level - from.level) * t;
+            }
+        }
+        points[points.len() - 1].level
+    }
+
+    // Añade un punto manteniendo el orden por tiempo; devuelve su índice
+    pub fn insert_point(&mut self, point: MsegPoint) -> Option<usize> {
+        if self.point_count >= MAX_MSEG_POINTS {
+            return None;
+        }
+        let index = self.active_points().iter().position(|p| p.time > point.time).unwrap_or(self.point_count).max(1);
+        self.points.copy_within(index..self.point_count, index + 1);
+        self.points[index] = point;
+        self.point_count += 1;
+        if self.loop_start >= index {
+            self.loop_start += 1;
+        }
+        if self.loop_end >= index {
+            self.loop_end += 1;
+        }
+        Some(index)
+    }
+
+    // El primer y el último punto no se pueden quitar
+    pub fn remove_point(&mut self, index: usize) {
+        if index == 0 || index + 1 >= self.point_count || self.point_count <= 2 {
+            return;
+        }
+        self.points.copy_within(index + 1..self.point_count, index);
+        self.point_count -= 1;
+        if self.loop_start > index {
+            self.loop_start -= 1;
+        }
+        if self.loop_end > index {
+            self.loop_end -= 1;
+        }
+    }
+
+    // Mover un punto sin que adelante al anterior ni sobrepase al siguiente
+    pub fn move_point(&mut self, index: usize, time: f32, level: f32) {
+        let count = self.active_points().len();
+        if index >= count {
+            return;
+        }
+        let time = if index == 0 {
+            0.0
+        } else {
+            let min = self.points[index - 1].time;
+            let max = if index + 1 < count { self.points[index + 1].time } else { f32::MAX };
+            time.clamp(min, max)
+        };
+        self.points[index].time = time;
+        self.points[index].level = level.clamp(0.0, 1.0);
+    }
+}
+
+// Lector de la curva para una voz
+pub struct Mseg {
+    pub settings: MsegSettings,
+    position: f32,
+    released: bool,
+}
+
+impl Mseg {
+    pub fn new(settings: MsegSettings) -> Self {
+        Self {
+            settings,
+            position: 0.0,
+            released: false,
+        }
+    }
+
+    pub fn note_on(&mut self) {
+        self.position = 0.0;
+        self.released = false;
+    }
+
+    // Al soltar la tecla se sale del bucle y se recorre el resto de la curva
+    pub fn note_off(&mut self) {
+        self.released = true;
+    }
+
+    // Nivel actual (0.0 a 1.0) y avance de una muestra
+    #[inline]
+    pub fn next_sample(&mut self, sample_rate: f32) -> f32 {
+        let level = self.settings.level_at(self.position);
+        let length = self.settings.length();
+        if self.position < length {
+            self.position += self.settings.units_per_second() / sample_rate;
+            if !self.released {
+                if let Some((start, end)) = self.settings.loop_region() {
+                    let points = self.settings.active_points();
+                    let (start, end) = (points[start].time, points[end].time);
+                    if end > start && self.position >= end {
+                        self.position = start + (self.position - end) % (end - start);
+                    }
+                }
+            }
+        }
+        level
+    }
+}
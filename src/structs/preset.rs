@@ -0,0 +1,747 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::dsp::filters::{BandMode, FilterSettings, FilterSlope, FilterType, MasterFilterSettings};
+use crate::dsp::wavetable::ADDITIVE_HARMONICS;
+use crate::dsp::fx::delay::DelayDivision;
+use crate::dsp::fx::distortion::DistortionCurve;
+use crate::dsp::saturation::{SaturationMode, SaturationSettings};
+use crate::dsp::{ChipChannel, DutyCycle, EffectType, FxSettings, OscillatorSettings, PhaseMode, Quality, UnisonSettings, VectorSettings, VoicePanMode, VoicePanSettings, MAX_OSCILLATORS};
+use crate::dsp::{GlideMode, GlideSettings, ParaphonicSettings, ParaphonicTrigger};
+use crate::dsp::{LfoSettings, LfoShape, LfoTrigger, ModCurve, ModDestination, ModMatrix, ModRoute, ModSource, WaveType, MAX_UNISON_VOICES};
+use crate::structs::envelope::AdsrSettings;
+use crate::structs::mseg::{MsegDestination, MsegPoint, MsegSettings, MAX_MSEG_POINTS};
+use crate::structs::split::SplitSettings;
+
+// Carpeta donde se guardan los presets del modo standalone
+pub const PRESETS_DIR: &str = "presets";
+const PRESET_EXTENSION: &str = "preset";
+
+// Datos descriptivos para el navegador de presets; no afectan al sonido
+#[derive(Clone, PartialEq, Default)]
+pub struct PresetMetadata {
+    pub author: String,
+    // Familia del sonido: bajo, pad, lead...
+    pub category: String,
+    // Etiquetas separadas por comas
+    pub tags: String,
+    // Una sola línea, como el resto de valores del archivo
+    pub description: String,
+}
+
+impl PresetMetadata {
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.tags.split(',').map(str::trim).filter(|tag| !tag.is_empty())
+    }
+
+    // El preset `name` encaja con la búsqueda si cada palabra aparece en el nombre o
+    // en alguno de los metadatos, sin distinguir mayúsculas
+    pub fn matches(&self, name: &str, query: &str) -> bool {
+        let text = [name, &self.author, &self.category, &self.tags, &self.description]
+            .join(" ")
+            .to_lowercase();
+        query.to_lowercase().split_whitespace().all(|word| text.contains(word))
+    }
+
+    // Leer solo los metadatos de un preset, sin el sonido (ni la respuesta al impulso)
+    pub fn from_text(text: &str) -> Self {
+        let mut metadata = PresetMetadata::default();
+        for line in text.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                metadata.parse_key(key.trim(), value.trim());
+            }
+        }
+        metadata
+    }
+
+    // Devuelve si la clave era de los metadatos
+    fn parse_key(&mut self, key: &str, value: &str) -> bool {
+        let field = match key {
+            "author" => &mut self.author,
+            "category" => &mut self.category,
+            "tags" => &mut self.tags,
+            "description" => &mut self.description,
+            _ => return false,
+        };
+        *field = value.to_string();
+        true
+    }
+}
+
+// Estado completo del sonido que se guarda en un preset
+#[derive(Clone, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub metadata: PresetMetadata,
+    pub volume: f32,
+    pub oscillators: [OscillatorSettings; MAX_OSCILLATORS],
+    pub oscillator_count: usize,
+    pub wavetable_position: f32,
+    pub wavetable_sweep: f32,
+    pub harmonic_levels: [f32; ADDITIVE_HARMONICS],
+    pub legato: bool,
+    pub legato_retrigger_amp: bool,
+    pub legato_retrigger_mod: bool,
+    pub quality: Quality,
+    pub voice_drive: f32,
+    pub voice_drive_velocity: f32,
+    pub voice_filter_env_depth: f32,
+    pub vector: VectorSettings,
+    pub lfo: LfoSettings,
+    pub mseg: MsegSettings,
+    pub mod_matrix: ModMatrix,
+    pub filter: FilterSettings,
+    pub envelope: AdsrSettings,
+    pub filter_envelope: AdsrSettings,
+    // Zona grave del teclado dividido
+    pub split: SplitSettings,
+    pub unison: UnisonSettings,
+    pub voice_pan: VoicePanSettings,
+    pub paraphonic: ParaphonicSettings,
+    pub glide: GlideSettings,
+    pub master_filter: MasterFilterSettings,
+    pub fx: FxSettings,
+    // Saturación final de la mezcla, tras el rack
+    pub saturation: SaturationSettings,
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        let mut harmonic_levels = [0.0; ADDITIVE_HARMONICS];
+        harmonic_levels[0] = 1.0;
+        let mut oscillators = [OscillatorSettings::default(); MAX_OSCILLATORS];
+        for settings in oscillators.iter_mut().skip(1) {
+            settings.volume = 0.5;
+        }
+        Self {
+            name: String::from("Init"),
+            metadata: PresetMetadata::default(),
+            volume: 0.5,
+            oscillators,
+            oscillator_count: 2,
+            wavetable_position: 0.0,
+            wavetable_sweep: 0.0,
+            harmonic_levels,
+            legato: false,
+            legato_retrigger_amp: false,
+            legato_retrigger_mod: true,
+            quality: Quality::Normal,
+            voice_drive: 0.0,
+            voice_drive_velocity: 0.0,
+            voice_filter_env_depth: 0.0,
+            vector: VectorSettings::default(),
+            lfo: LfoSettings::default(),
+            mseg: MsegSettings::default(),
+            mod_matrix: ModMatrix::default(),
+            filter: FilterSettings::default(),
+            envelope: AdsrSettings::default(),
+            filter_envelope: AdsrSettings::default(),
+            split: SplitSettings::default(),
+            unison: UnisonSettings::default(),
+            voice_pan: VoicePanSettings::default(),
+            paraphonic: ParaphonicSettings::default(),
+            glide: GlideSettings::default(),
+            master_filter: MasterFilterSettings::default(),
+            fx: FxSettings::default(),
+            saturation: SaturationSettings::default(),
+        }
+    }
+}
+
+impl Preset {
+    // Formato de texto simple: una línea "clave=valor" por parámetro
+    pub fn to_text(&self) -> String {
+        let levels: Vec<String> = self.harmonic_levels.iter().map(|l| l.to_string()).collect();
+        let mut text = String::new();
+        text.push_str(&format!("name={}\n", self.name));
+        text.push_str(&format!("author={}\n", self.metadata.author));
+        text.push_str(&format!("category={}\n", self.metadata.category));
+        text.push_str(&format!("tags={}\n", self.metadata.tags));
+        text.push_str(&format!("description={}\n", self.metadata.description));
+        text.push_str(&format!("volume={}\n", self.volume));
+        text.push_str(&format!("oscillator_count={}\n", self.oscillator_count));
+        for (i, settings) in self.oscillators.iter().enumerate() {
+            // Claves con el número de oscilador empezando en 1: osc1_volume, osc2_detune...
+            let n = i + 1;
+            text.push_str(&format!("osc{}_wave_type={}\n", n, settings.wave_type as u8));
+            text.push_str(&format!("osc{}_volume={}\n", n, settings.volume));
+            text.push_str(&format!("osc{}_detune={}\n", n, settings.detune));
+            text.push_str(&format!("osc{}_octave={}\n", n, settings.octave));
+            text.push_str(&format!("osc{}_drift={}\n", n, settings.drift));
+            text.push_str(&format!("osc{}_phase_mode={}\n", n, settings.phase_mode as u8));
+            text.push_str(&format!("osc{}_pan={}\n", n, settings.pan));
+            text.push_str(&format!("osc{}_fold={}\n", n, settings.fold));
+            text.push_str(&format!("osc{}_chip_channel={}\n", n, settings.chip_channel as u8));
+            text.push_str(&format!("osc{}_duty_cycle={}\n", n, settings.duty_cycle as u8));
+            text.push_str(&format!("osc{}_bit_depth={}\n", n, settings.bit_depth));
+        }
+        text.push_str(&format!("wavetable_position={}\n", self.wavetable_position));
+        text.push_str(&format!("wavetable_sweep={}\n", self.wavetable_sweep));
+        text.push_str(&format!("harmonic_levels={}\n", levels.join(",")));
+        text.push_str(&format!("legato={}\n", self.legato));
+        text.push_str(&format!("legato_retrigger_amp={}\n", self.legato_retrigger_amp));
+        text.push_str(&format!("legato_retrigger_mod={}\n", self.legato_retrigger_mod));
+        text.push_str(&format!("quality={}\n", self.quality as u8));
+        text.push_str(&format!("voice_drive={}\n", self.voice_drive));
+        text.push_str(&format!("voice_drive_velocity={}\n", self.voice_drive_velocity));
+        text.push_str(&format!("voice_filter_env_depth={}\n", self.voice_filter_env_depth));
+        text.push_str(&format!("vector_enabled={}\n", self.vector.enabled));
+        text.push_str(&format!("vector_x={}\n", self.vector.x));
+        text.push_str(&format!("vector_y={}\n", self.vector.y));
+        text.push_str(&format!("vector_env_x={}\n", self.vector.env_x));
+        text.push_str(&format!("vector_env_y={}\n", self.vector.env_y));
+        text.push_str(&format!("filter_type={}\n", self.filter.filter_type as u8));
+        text.push_str(&format!("filter_cutoff={}\n", self.filter.cutoff));
+        text.push_str(&format!("filter_resonance={}\n", self.filter.resonance));
+        text.push_str(&format!("filter_env_amount={}\n", self.filter.env_amount));
+        text.push_str(&format!("filter_velocity_amount={}\n", self.filter.velocity_amount));
+        text.push_str(&format!("filter_lfo_amount={}\n", self.filter.lfo_amount));
+        text.push_str(&format!("lfo_shape={}\n", self.lfo.shape as u8));
+        text.push_str(&format!("lfo_rate={}\n", self.lfo.rate));
+        text.push_str(&format!("lfo_pitch_depth={}\n", self.lfo.pitch_depth));
+        text.push_str(&format!("lfo_trigger={}\n", self.lfo.trigger as u8));
+        text.push_str(&format!("lfo_start_phase={}\n", self.lfo.start_phase));
+        text.push_str(&format!("lfo_one_shot={}\n", self.lfo.one_shot));
+        // Cada punto del MSEG como tiempo:nivel:curva
+        let points: Vec<String> = self.mseg.active_points().iter()
+            .map(|p| format!("{}:{}:{}", p.time, p.level, p.curve))
+            .collect();
+        text.push_str(&format!("mseg_enabled={}\n", self.mseg.enabled));
+        text.push_str(&format!("mseg_points={}\n", points.join(",")));
+        text.push_str(&format!("mseg_looping={}\n", self.mseg.looping));
+        text.push_str(&format!("mseg_loop_start={}\n", self.mseg.loop_start));
+        text.push_str(&format!("mseg_loop_end={}\n", self.mseg.loop_end));
+        text.push_str(&format!("mseg_tempo_sync={}\n", self.mseg.tempo_sync));
+        text.push_str(&format!("mseg_bpm={}\n", self.mseg.bpm));
+        text.push_str(&format!("mseg_grid={}\n", self.mseg.grid));
+        text.push_str(&format!("mseg_destination={}\n", self.mseg.destination as u8));
+        text.push_str(&format!("mseg_amount={}\n", self.mseg.amount));
+        // Cada ruta de la matriz como fuente:cc:destino:cantidad:curva
+        let routes: Vec<String> = self.mod_matrix.active_routes().iter()
+            .map(|route| {
+                format!(
+                    "{}:{}:{}:{}:{}",
+                    route.source as u8, route.cc, route.destination as u8, route.amount, route.curve as u8
+                )
+            })
+            .collect();
+        text.push_str(&format!("mod_routes={}\n", routes.join(",")));
+        text.push_str(&format!("mod_random_depth={}\n", self.mod_matrix.random_depth));
+        text.push_str(&format!("filter_slope={}\n", self.filter.slope as u8));
+        text.push_str(&format!("band_enabled={}\n", self.filter.band.enabled));
+        text.push_str(&format!("band_mode={}\n", self.filter.band.mode as u8));
+        text.push_str(&format!("band_frequency={}\n", self.filter.band.frequency));
+        text.push_str(&format!("band_q={}\n", self.filter.band.q));
+        text.push_str(&format!("formant_enabled={}\n", self.filter.formant.enabled));
+        text.push_str(&format!("formant_morph={}\n", self.filter.formant.morph));
+        text.push_str(&format!("env_attack={}\n", self.envelope.attack));
+        text.push_str(&format!("env_decay={}\n", self.envelope.decay));
+        text.push_str(&format!("env_sustain={}\n", self.envelope.sustain));
+        text.push_str(&format!("env_release={}\n", self.envelope.release));
+        text.push_str(&format!("env_key_tracking={}\n", self.envelope.key_tracking));
+        text.push_str(&format!("filter_env_attack={}\n", self.filter_envelope.attack));
+        text.push_str(&format!("filter_env_decay={}\n", self.filter_envelope.decay));
+        text.push_str(&format!("filter_env_sustain={}\n", self.filter_envelope.sustain));
+        text.push_str(&format!("filter_env_release={}\n", self.filter_envelope.release));
+        text.push_str(&format!("filter_env_key_tracking={}\n", self.filter_envelope.key_tracking));
+        text.push_str(&format!("split_enabled={}\n", self.split.enabled));
+        text.push_str(&format!("split_note={}\n", self.split.split_note));
+        text.push_str(&format!("split_lower_wave_type={}\n", self.split.lower_wave_type as u8));
+        text.push_str(&format!("split_lower_attack={}\n", self.split.lower_envelope.attack));
+        text.push_str(&format!("split_lower_decay={}\n", self.split.lower_envelope.decay));
+        text.push_str(&format!("split_lower_sustain={}\n", self.split.lower_envelope.sustain));
+        text.push_str(&format!("split_lower_release={}\n", self.split.lower_envelope.release));
+        text.push_str(&format!("split_lower_volume={}\n", self.split.lower_volume));
+        text.push_str(&format!("split_upper_volume={}\n", self.split.upper_volume));
+        text.push_str(&format!("unison_voices={}\n", self.unison.voices));
+        text.push_str(&format!("unison_detune={}\n", self.unison.detune));
+        text.push_str(&format!("unison_spread={}\n", self.unison.spread));
+        text.push_str(&format!("voice_pan_mode={}\n", self.voice_pan.mode as u8));
+        text.push_str(&format!("voice_pan={}\n", self.voice_pan.pan));
+        text.push_str(&format!("voice_pan_spread={}\n", self.voice_pan.spread));
+        text.push_str(&format!("paraphonic={}\n", self.paraphonic.enabled));
+        text.push_str(&format!("paraphonic_trigger={}\n", self.paraphonic.trigger as u8));
+        text.push_str(&format!("glide_mode={}\n", self.glide.mode as u8));
+        text.push_str(&format!("glide_time={}\n", self.glide.time));
+        text.push_str(&format!("master_lowpass={}\n", self.master_filter.lowpass_cutoff));
+        text.push_str(&format!("master_highpass={}\n", self.master_filter.highpass_cutoff));
+        // Ranuras del rack en orden, cada una como efecto:bypass:mezcla
+        let slots: Vec<String> = self.fx.slots.iter()
+            .map(|slot| format!("{}:{}:{}", slot.effect as u8, slot.bypass, slot.mix))
+            .collect();
+        text.push_str(&format!("fx_slots={}\n", slots.join(",")));
+        text.push_str(&format!("delay_time={}\n", self.fx.delay.time));
+        text.push_str(&format!("delay_tempo_sync={}\n", self.fx.delay.tempo_sync));
+        text.push_str(&format!("delay_division={}\n", self.fx.delay.division as u8));
+        text.push_str(&format!("delay_bpm={}\n", self.fx.delay.bpm));
+        text.push_str(&format!("delay_feedback={}\n", self.fx.delay.feedback));
+        text.push_str(&format!("delay_damping={}\n", self.fx.delay.damping));
+        text.push_str(&format!("delay_ping_pong={}\n", self.fx.delay.ping_pong));
+        text.push_str(&format!("delay_mix={}\n", self.fx.delay.mix));
+        text.push_str(&format!("distortion_curve={}\n", self.fx.distortion.curve as u8));
+        text.push_str(&format!("distortion_drive={}\n", self.fx.distortion.drive));
+        text.push_str(&format!("distortion_trim={}\n", self.fx.distortion.trim));
+        text.push_str(&format!("distortion_mix={}\n", self.fx.distortion.mix));
+        text.push_str(&format!("eq_low_frequency={}\n", self.fx.eq.low_frequency));
+        text.push_str(&format!("eq_low_gain={}\n", self.fx.eq.low_gain));
+        text.push_str(&format!("eq_mid_frequency={}\n", self.fx.eq.mid_frequency));
+        text.push_str(&format!("eq_mid_gain={}\n", self.fx.eq.mid_gain));
+        text.push_str(&format!("eq_mid_q={}\n", self.fx.eq.mid_q));
+        text.push_str(&format!("eq_high_frequency={}\n", self.fx.eq.high_frequency));
+        text.push_str(&format!("eq_high_gain={}\n", self.fx.eq.high_gain));
+        text.push_str(&format!("compressor_threshold={}\n", self.fx.compressor.threshold));
+        text.push_str(&format!("compressor_ratio={}\n", self.fx.compressor.ratio));
+        text.push_str(&format!("compressor_attack={}\n", self.fx.compressor.attack));
+        text.push_str(&format!("compressor_release={}\n", self.fx.compressor.release));
+        text.push_str(&format!("compressor_makeup={}\n", self.fx.compressor.makeup));
+        text.push_str(&format!("reverb_impulse={}\n", self.fx.reverb.impulse_path));
+        text.push_str(&format!("reverb_pre_delay={}\n", self.fx.reverb.pre_delay));
+        text.push_str(&format!("reverb_mix={}\n", self.fx.reverb.mix));
+        text.push_str(&format!("saturation_mode={}\n", self.saturation.mode as u8));
+        text.push_str(&format!("saturation_drive={}\n", self.saturation.drive));
+        text
+    }
+
+    // Las claves desconocidas se ignoran y las que faltan conservan su valor por defecto
+    pub fn from_text(text: &str) -> Self {
+        let mut preset = Preset::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            if preset.metadata.parse_key(key.trim(), value) {
+                continue;
+            }
+            match key.trim() {
+                "name" => preset.name = value.to_string(),
+                "volume" => parse_into(value, &mut preset.volume),
+                // Presets de antes de los osciladores configurables; el resto de claves
+                // antiguas (osc2_volume, osc1_octave...) ya tienen la forma osc{n}_{campo}
+                "wave_type1" => parse_enum(value, &WaveType::ALL, &mut preset.oscillators[0].wave_type),
+                "wave_type2" => parse_enum(value, &WaveType::ALL, &mut preset.oscillators[1].wave_type),
+                "oscillator_count" => {
+                    parse_into(value, &mut preset.oscillator_count);
+                    preset.oscillator_count = preset.oscillator_count.clamp(1, MAX_OSCILLATORS);
+                }
+                "wavetable_position" => parse_into(value, &mut preset.wavetable_position),
+                "wavetable_sweep" => parse_into(value, &mut preset.wavetable_sweep),
+                "harmonic_levels" => {
+                    for (level, text) in preset.harmonic_levels.iter_mut().zip(value.split(',')) {
+                        parse_into(text.trim(), level);
+                    }
+                }
+                "legato" => parse_into(value, &mut preset.legato),
+                "legato_retrigger_amp" => parse_into(value, &mut preset.legato_retrigger_amp),
+                "legato_retrigger_mod" => parse_into(value, &mut preset.legato_retrigger_mod),
+                "quality" => parse_enum(value, &Quality::ALL, &mut preset.quality),
+                "voice_drive" => parse_into(value, &mut preset.voice_drive),
+                "voice_drive_velocity" => parse_into(value, &mut preset.voice_drive_velocity),
+                "voice_filter_env_depth" => parse_into(value, &mut preset.voice_filter_env_depth),
+                "vector_enabled" => parse_into(value, &mut preset.vector.enabled),
+                "vector_x" => parse_into(value, &mut preset.vector.x),
+                "vector_y" => parse_into(value, &mut preset.vector.y),
+                "vector_env_x" => parse_into(value, &mut preset.vector.env_x),
+                "vector_env_y" => parse_into(value, &mut preset.vector.env_y),
+                "filter_type" => parse_enum(value, &FilterType::ALL, &mut preset.filter.filter_type),
+                "filter_cutoff" => parse_into(value, &mut preset.filter.cutoff),
+                "filter_resonance" => parse_into(value, &mut preset.filter.resonance),
+                "filter_env_amount" => parse_into(value, &mut preset.filter.env_amount),
+                "filter_velocity_amount" => parse_into(value, &mut preset.filter.velocity_amount),
+                "filter_lfo_amount" => parse_into(value, &mut preset.filter.lfo_amount),
+                "lfo_shape" => parse_enum(value, &LfoShape::ALL, &mut preset.lfo.shape),
+                "lfo_rate" => parse_into(value, &mut preset.lfo.rate),
+                "lfo_pitch_depth" => parse_into(value, &mut preset.lfo.pitch_depth),
+                "lfo_trigger" => parse_enum(value, &LfoTrigger::ALL, &mut preset.lfo.trigger),
+                "lfo_start_phase" => parse_into(value, &mut preset.lfo.start_phase),
+                "lfo_one_shot" => parse_into(value, &mut preset.lfo.one_shot),
+                "mseg_enabled" => parse_into(value, &mut preset.mseg.enabled),
+                "mseg_points" => parse_mseg_points(value, &mut preset.mseg),
+                "mseg_looping" => parse_into(value, &mut preset.mseg.looping),
+                "mseg_loop_start" => parse_into(value, &mut preset.mseg.loop_start),
+                "mseg_loop_end" => parse_into(value, &mut preset.mseg.loop_end),
+                "mseg_tempo_sync" => parse_into(value, &mut preset.mseg.tempo_sync),
+                "mseg_bpm" => parse_into(value, &mut preset.mseg.bpm),
+                "mseg_grid" => parse_into(value, &mut preset.mseg.grid),
+                "mseg_destination" => parse_enum(value, &MsegDestination::ALL, &mut preset.mseg.destination),
+                "mseg_amount" => parse_into(value, &mut preset.mseg.amount),
+                "mod_routes" => parse_mod_routes(value, &mut preset.mod_matrix),
+                "mod_random_depth" => parse_into(value, &mut preset.mod_matrix.random_depth),
+                "filter_slope" => parse_enum(value, &FilterSlope::ALL, &mut preset.filter.slope),
+                "band_enabled" => parse_into(value, &mut preset.filter.band.enabled),
+                "band_mode" => parse_enum(value, &BandMode::ALL, &mut preset.filter.band.mode),
+                "band_frequency" => parse_into(value, &mut preset.filter.band.frequency),
+                "band_q" => parse_into(value, &mut preset.filter.band.q),
+                "formant_enabled" => parse_into(value, &mut preset.filter.formant.enabled),
+                "formant_morph" => parse_into(value, &mut preset.filter.formant.morph),
+                "env_attack" => parse_into(value, &mut preset.envelope.attack),
+                "env_decay" => parse_into(value, &mut preset.envelope.decay),
+                "env_sustain" => parse_into(value, &mut preset.envelope.sustain),
+                "env_release" => parse_into(value, &mut preset.envelope.release),
+                "env_key_tracking" => parse_into(value, &mut preset.envelope.key_tracking),
+                "filter_env_attack" => parse_into(value, &mut preset.filter_envelope.attack),
+                "filter_env_decay" => parse_into(value, &mut preset.filter_envelope.decay),
+                "filter_env_sustain" => parse_into(value, &mut preset.filter_envelope.sustain),
+                "filter_env_release" => parse_into(value, &mut preset.filter_envelope.release),
+                "filter_env_key_tracking" => parse_into(value, &mut preset.filter_envelope.key_tracking),
+                "split_enabled" => parse_into(value, &mut preset.split.enabled),
+                "split_note" => {
+                    parse_into(value, &mut preset.split.split_note);
+                    preset.split.split_note = preset.split.split_note.min(127);
+                }
+                "split_lower_wave_type" => parse_enum(value, &WaveType::ALL, &mut preset.split.lower_wave_type),
+                "split_lower_attack" => parse_into(value, &mut preset.split.lower_envelope.attack),
+                "split_lower_decay" => parse_into(value, &mut preset.split.lower_envelope.decay),
+                "split_lower_sustain" => parse_into(value, &mut preset.split.lower_envelope.sustain),
+                "split_lower_release" => parse_into(value, &mut preset.split.lower_envelope.release),
+                "split_lower_volume" => parse_into(value, &mut preset.split.lower_volume),
+                "split_upper_volume" => parse_into(value, &mut preset.split.upper_volume),
+                "unison_voices" => {
+                    parse_into(value, &mut preset.unison.voices);
+                    preset.unison.voices = preset.unison.voices.clamp(1, MAX_UNISON_VOICES);
+                }
+                "unison_detune" => parse_into(value, &mut preset.unison.detune),
+                "unison_spread" => parse_into(value, &mut preset.unison.spread),
+                "voice_pan_mode" => parse_enum(value, &VoicePanMode::ALL, &mut preset.voice_pan.mode),
+                "voice_pan" => parse_into(value, &mut preset.voice_pan.pan),
+                "voice_pan_spread" => parse_into(value, &mut preset.voice_pan.spread),
+                "paraphonic" => parse_into(value, &mut preset.paraphonic.enabled),
+                "paraphonic_trigger" => parse_enum(value, &ParaphonicTrigger::ALL, &mut preset.paraphonic.trigger),
+                "glide_mode" => parse_enum(value, &GlideMode::ALL, &mut preset.glide.mode),
+                "glide_time" => parse_into(value, &mut preset.glide.time),
+                "master_lowpass" => parse_into(value, &mut preset.master_filter.lowpass_cutoff),
+                "master_highpass" => parse_into(value, &mut preset.master_filter.highpass_cutoff),
+                "fx_slots" => parse_fx_slots(value, &mut preset.fx),
+                "delay_time" => parse_into(value, &mut preset.fx.delay.time),
+                "delay_tempo_sync" => parse_into(value, &mut preset.fx.delay.tempo_sync),
+                "delay_division" => parse_enum(value, &DelayDivision::ALL, &mut preset.fx.delay.division),
+                "delay_bpm" => parse_into(value, &mut preset.fx.delay.bpm),
+                "delay_feedback" => parse_into(value, &mut preset.fx.delay.feedback),
+                "delay_damping" => parse_into(value, &mut preset.fx.delay.damping),
+                "delay_ping_pong" => parse_into(value, &mut preset.fx.delay.ping_pong),
+                "delay_mix" => parse_into(value, &mut preset.fx.delay.mix),
+                "distortion_curve" => parse_enum(value, &DistortionCurve::ALL, &mut preset.fx.distortion.curve),
+                "distortion_drive" => parse_into(value, &mut preset.fx.distortion.drive),
+                "distortion_trim" => parse_into(value, &mut preset.fx.distortion.trim),
+                "distortion_mix" => parse_into(value, &mut preset.fx.distortion.mix),
+                "eq_low_frequency" => parse_into(value, &mut preset.fx.eq.low_frequency),
+                "eq_low_gain" => parse_into(value, &mut preset.fx.eq.low_gain),
+                "eq_mid_frequency" => parse_into(value, &mut preset.fx.eq.mid_frequency),
+                "eq_mid_gain" => parse_into(value, &mut preset.fx.eq.mid_gain),
+                "eq_mid_q" => parse_into(value, &mut preset.fx.eq.mid_q),
+                "eq_high_frequency" => parse_into(value, &mut preset.fx.eq.high_frequency),
+                "eq_high_gain" => parse_into(value, &mut preset.fx.eq.high_gain),
+                "compressor_threshold" => parse_into(value, &mut preset.fx.compressor.threshold),
+                "compressor_ratio" => parse_into(value, &mut preset.fx.compressor.ratio),
+                "compressor_attack" => parse_into(value, &mut preset.fx.compressor.attack),
+                "compressor_release" => parse_into(value, &mut preset.fx.compressor.release),
+                "compressor_makeup" => parse_into(value, &mut preset.fx.compressor.makeup),
+                "reverb_impulse" => {
+                    if let Err(err) = preset.fx.reverb.load_impulse(value) {
+                        eprintln!("Error al cargar la respuesta al impulso {}: {}", value, err);
+                    }
+                }
+                "reverb_pre_delay" => parse_into(value, &mut preset.fx.reverb.pre_delay),
+                "reverb_mix" => parse_into(value, &mut preset.fx.reverb.mix),
+                "saturation_mode" => parse_enum(value, &SaturationMode::ALL, &mut preset.saturation.mode),
+                "saturation_drive" => parse_into(value, &mut preset.saturation.drive),
+                key => parse_oscillator_key(key, value, &mut preset.oscillators),
+            }
+        }
+        preset
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let path = preset_path(dir, &self.name)?;
+        fs::create_dir_all(dir)?;
+        fs::write(path, self.to_text())
+    }
+
+    pub fn load(dir: &Path, name: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(preset_path(dir, name)?)?;
+        let mut preset = Preset::from_text(&text);
+        preset.name = name.to_string();
+        Ok(preset)
+    }
+
+    // Mezcla de dos presets: los parámetros continuos se interpolan según `amount`
+    // (0.0 = A, 1.0 = B) y los discretos (formas de onda, modos, rutas, ranuras del
+    // rack...) pasan de A a B al llegar a la mitad. Frecuencias y tiempos se
+    // interpolan en escala logarítmica para que el recorrido suene uniforme.
+    pub fn morph(a: &Preset, b: &Preset, amount: f32) -> Preset {
+        let t = amount.clamp(0.0, 1.0);
+        let mut preset = if t < 0.5 { a.clone() } else { b.clone() };
+        preset.volume = lerp(a.volume, b.volume, t);
+        for (i, osc) in preset.oscillators.iter_mut().enumerate() {
+            let (osc_a, osc_b) = (&a.oscillators[i], &b.oscillators[i]);
+            osc.volume = lerp(osc_a.volume, osc_b.volume, t);
+            osc.detune = lerp(osc_a.detune, osc_b.detune, t);
+            osc.drift = lerp(osc_a.drift, osc_b.drift, t);
+            osc.pan = lerp(osc_a.pan, osc_b.pan, t);
+            osc.fold = lerp(osc_a.fold, osc_b.fold, t);
+        }
+        preset.wavetable_position = lerp(a.wavetable_position, b.wavetable_position, t);
+        preset.wavetable_sweep = lerp(a.wavetable_sweep, b.wavetable_sweep, t);
+        for (i, level) in preset.harmonic_levels.iter_mut().enumerate() {
+            *level = lerp(a.harmonic_levels[i], b.harmonic_levels[i], t);
+        }
+        preset.voice_drive = lerp(a.voice_drive, b.voice_drive, t);
+        preset.voice_drive_velocity = lerp(a.voice_drive_velocity, b.voice_drive_velocity, t);
+        preset.voice_filter_env_depth = lerp(a.voice_filter_env_depth, b.voice_filter_env_depth, t);
+        preset.vector.x = lerp(a.vector.x, b.vector.x, t);
+        preset.vector.y = lerp(a.vector.y, b.vector.y, t);
+        preset.vector.env_x = lerp(a.vector.env_x, b.vector.env_x, t);
+        preset.vector.env_y = lerp(a.vector.env_y, b.vector.env_y, t);
+        preset.lfo.rate = lerp_log(a.lfo.rate, b.lfo.rate, t);
+        preset.lfo.pitch_depth = lerp(a.lfo.pitch_depth, b.lfo.pitch_depth, t);
+        preset.lfo.start_phase = lerp(a.lfo.start_phase, b.lfo.start_phase, t);
+        preset.mseg.amount = lerp(a.mseg.amount, b.mseg.amount, t);
+        preset.mod_matrix.random_depth = lerp(a.mod_matrix.random_depth, b.mod_matrix.random_depth, t);
+        preset.filter.cutoff = lerp_log(a.filter.cutoff, b.filter.cutoff, t);
+        preset.filter.resonance = lerp(a.filter.resonance, b.filter.resonance, t);
+        preset.filter.env_amount = lerp(a.filter.env_amount, b.filter.env_amount, t);
+        preset.filter.velocity_amount = lerp(a.filter.velocity_amount, b.filter.velocity_amount, t);
+        preset.filter.lfo_amount = lerp(a.filter.lfo_amount, b.filter.lfo_amount, t);
+        preset.filter.band.frequency = lerp_log(a.filter.band.frequency, b.filter.band.frequency, t);
+        preset.filter.band.q = lerp(a.filter.band.q, b.filter.band.q, t);
+        preset.filter.formant.morph = lerp(a.filter.formant.morph, b.filter.formant.morph, t);
+        preset.envelope = morph_envelope(&a.envelope, &b.envelope, t);
+        preset.filter_envelope = morph_envelope(&a.filter_envelope, &b.filter_envelope, t);
+        preset.split.lower_envelope = morph_envelope(&a.split.lower_envelope, &b.split.lower_envelope, t);
+        preset.split.lower_volume = lerp(a.split.lower_volume, b.split.lower_volume, t);
+        preset.split.upper_volume = lerp(a.split.upper_volume, b.split.upper_volume, t);
+        preset.unison.detune = lerp(a.unison.detune, b.unison.detune, t);
+        preset.unison.spread = lerp(a.unison.spread, b.unison.spread, t);
+        preset.voice_pan.pan = lerp(a.voice_pan.pan, b.voice_pan.pan, t);
+        preset.voice_pan.spread = lerp(a.voice_pan.spread, b.voice_pan.spread, t);
+        preset.glide.time = lerp(a.glide.time, b.glide.time, t);
+        preset.master_filter.lowpass_cutoff = lerp_log(a.master_filter.lowpass_cutoff, b.master_filter.lowpass_cutoff, t);
+        preset.master_filter.highpass_cutoff = lerp_log(a.master_filter.highpass_cutoff, b.master_filter.highpass_cutoff, t);
+        // La mezcla de cada ranura solo se interpola si las dos tienen el mismo efecto
+        for (i, slot) in preset.fx.slots.iter_mut().enumerate() {
+            if let (Some(slot_a), Some(slot_b)) = (a.fx.slots.get(i), b.fx.slots.get(i)) {
+                if slot_a.effect == slot_b.effect {
+                    slot.mix = lerp(slot_a.mix, slot_b.mix, t);
+                }
+            }
+        }
+        let (fx_a, fx_b) = (&a.fx, &b.fx);
+        preset.fx.delay.time = lerp_log(fx_a.delay.time, fx_b.delay.time, t);
+        preset.fx.delay.bpm = lerp(fx_a.delay.bpm, fx_b.delay.bpm, t);
+        preset.fx.delay.feedback = lerp(fx_a.delay.feedback, fx_b.delay.feedback, t);
+        preset.fx.delay.damping = lerp(fx_a.delay.damping, fx_b.delay.damping, t);
+        preset.fx.delay.mix = lerp(fx_a.delay.mix, fx_b.delay.mix, t);
+        preset.fx.distortion.drive = lerp(fx_a.distortion.drive, fx_b.distortion.drive, t);
+        preset.fx.distortion.trim = lerp(fx_a.distortion.trim, fx_b.distortion.trim, t);
+        preset.fx.distortion.mix = lerp(fx_a.distortion.mix, fx_b.distortion.mix, t);
+        preset.fx.eq.low_frequency = lerp_log(fx_a.eq.low_frequency, fx_b.eq.low_frequency, t);
+        preset.fx.eq.low_gain = lerp(fx_a.eq.low_gain, fx_b.eq.low_gain, t);
+        preset.fx.eq.mid_frequency = lerp_log(fx_a.eq.mid_frequency, fx_b.eq.mid_frequency, t);
+        preset.fx.eq.mid_gain = lerp(fx_a.eq.mid_gain, fx_b.eq.mid_gain, t);
+        preset.fx.eq.mid_q = lerp(fx_a.eq.mid_q, fx_b.eq.mid_q, t);
+        preset.fx.eq.high_frequency = lerp_log(fx_a.eq.high_frequency, fx_b.eq.high_frequency, t);
+        preset.fx.eq.high_gain = lerp(fx_a.eq.high_gain, fx_b.eq.high_gain, t);
+        preset.fx.compressor.threshold = lerp(fx_a.compressor.threshold, fx_b.compressor.threshold, t);
+        preset.fx.compressor.ratio = lerp(fx_a.compressor.ratio, fx_b.compressor.ratio, t);
+        preset.fx.compressor.attack = lerp_log(fx_a.compressor.attack, fx_b.compressor.attack, t);
+        preset.fx.compressor.release = lerp_log(fx_a.compressor.release, fx_b.compressor.release, t);
+        preset.fx.compressor.makeup = lerp(fx_a.compressor.makeup, fx_b.compressor.makeup, t);
+        preset.fx.reverb.pre_delay = lerp(fx_a.reverb.pre_delay, fx_b.reverb.pre_delay, t);
+        preset.fx.reverb.mix = lerp(fx_a.reverb.mix, fx_b.reverb.mix, t);
+        preset.saturation.drive = lerp(a.saturation.drive, b.saturation.drive, t);
+        preset
+    }
+}
+
+// Dos presets entre los que se mueve un único control de morph
+#[derive(Clone, Default)]
+pub struct PresetMorph {
+    pub a: Option<Preset>,
+    pub b: Option<Preset>,
+    // Posición del control: 0.0 = preset A, 1.0 = preset B
+    pub amount: f32,
+}
+
+impl PresetMorph {
+    // Sonido en la posición actual, si ya hay dos presets elegidos
+    pub fn current(&self) -> Option<Preset> {
+        match (&self.a, &self.b) {
+            (Some(a), Some(b)) => Some(Preset::morph(a, b, self.amount)),
+            _ => None,
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// Interpolación geométrica para frecuencias y tiempos; con valores no positivos
+// vuelve a la lineal
+fn lerp_log(a: f32, b: f32, t: f32) -> f32 {
+    if a > 0.0 && b > 0.0 {
+        a * (b / a).powf(t)
+    } else {
+        lerp(a, b, t)
+    }
+}
+
+fn morph_envelope(a: &AdsrSettings, b: &AdsrSettings, t: f32) -> AdsrSettings {
+    AdsrSettings {
+        attack: lerp_log(a.attack, b.attack, t),
+        decay: lerp_log(a.decay, b.decay, t),
+        sustain: lerp(a.sustain, b.sustain, t),
+        release: lerp_log(a.release, b.release, t),
+        key_tracking: lerp(a.key_tracking, b.key_tracking, t),
+    }
+}
+
+// Nombres de los presets disponibles en la carpeta, ordenados alfabéticamente
+pub fn list_presets(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |ext| ext == PRESET_EXTENSION))
+                .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+// Nombre y metadatos de cada preset de la carpeta, en el orden de list_presets
+pub fn list_preset_metadata(dir: &Path) -> Vec<(String, PresetMetadata)> {
+    list_presets(dir)
+        .into_iter()
+        .map(|name| {
+            let metadata = preset_path(dir, &name)
+                .and_then(fs::read_to_string)
+                .map(|text| PresetMetadata::from_text(&text))
+                .unwrap_or_default();
+            (name, metadata)
+        })
+        .collect()
+}
+
+// Un nombre escrito por el usuario solo puede ser un archivo dentro de la carpeta:
+// sin separadores de ruta ni "..", que lo sacarían de ella
+pub fn is_valid_file_name(name: &str) -> bool {
+    !name.trim().is_empty() && !name.contains(['/', '\\', '\0']) && !name.contains("..")
+}
+
+fn preset_path(dir: &Path, name: &str) -> io::Result<PathBuf> {
+    if !is_valid_file_name(name) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("nombre de preset no válido: {:?}", name)));
+    }
+    Ok(dir.join(format!("{}.{}", name, PRESET_EXTENSION)))
+}
+
+// Claves "osc{n}_{campo}" de los ajustes de cada oscilador
+fn parse_oscillator_key(key: &str, value: &str, oscillators: &mut [OscillatorSettings; MAX_OSCILLATORS]) {
+    let Some((number, field)) = key.strip_prefix("osc").and_then(|rest| rest.split_once('_')) else {
+        return;
+    };
+    let Some(settings) = number.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|i| oscillators.get_mut(i)) else {
+        return;
+    };
+    match field {
+        "wave_type" => parse_enum(value, &WaveType::ALL, &mut settings.wave_type),
+        "volume" => parse_into(value, &mut settings.volume),
+        "detune" => parse_into(value, &mut settings.detune),
+        "octave" => parse_into(value, &mut settings.octave),
+        "drift" => parse_into(value, &mut settings.drift),
+        "phase_mode" => parse_enum(value, &PhaseMode::ALL, &mut settings.phase_mode),
+        "pan" => parse_into(value, &mut settings.pan),
+        "fold" => parse_into(value, &mut settings.fold),
+        "chip_channel" => parse_enum(value, &ChipChannel::ALL, &mut settings.chip_channel),
+        "duty_cycle" => parse_enum(value, &DutyCycle::ALL, &mut settings.duty_cycle),
+        "bit_depth" => parse_into(value, &mut settings.bit_depth),
+        _ => (),
+    }
+}
+
+pub(crate) fn parse_into<T: std::str::FromStr>(text: &str, target: &mut T) {
+    if let Ok(value) = text.parse() {
+        *target = value;
+    }
+}
+
+// Puntos del MSEG separados por comas, cada uno como tiempo:nivel:curva. Con menos
+// de dos puntos válidos se mantiene la curva por defecto.
+fn parse_mseg_points(text: &str, mseg: &mut MsegSettings) {
+    let mut points = [MsegPoint::new(0.0, 0.0, 0.0); MAX_MSEG_POINTS];
+    let mut count = 0;
+    for item in text.split(',') {
+        let mut fields = item.split(':').map(|field| field.trim().parse::<f32>());
+        if let (Some(Ok(time)), Some(Ok(level)), Some(Ok(curve))) = (fields.next(), fields.next(), fields.next()) {
+            if count < MAX_MSEG_POINTS {
+                points[count] = MsegPoint::new(time, level.clamp(0.0, 1.0), curve.clamp(-1.0, 1.0));
+                count += 1;
+            }
+        }
+    }
+    if count >= 2 {
+        mseg.points = points;
+        mseg.point_count = count;
+    }
+}
+
+// Rutas de la matriz separadas por comas, cada una como fuente:cc:destino:cantidad:curva.
+// Los presets anteriores guardan rutas sin curva (lineal) o sin fuente (leen un CC).
+fn parse_mod_routes(text: &str, matrix: &mut ModMatrix) {
+    matrix.route_count = 0;
+    for item in text.split(',') {
+        let fields: Vec<&str> = item.split(':').map(str::trim).collect();
+        let (source, cc, destination, amount, curve) = match fields.as_slice() {
+            [source, cc, destination, amount, curve] => (Some(*source), *cc, *destination, *amount, Some(*curve)),
+            [source, cc, destination, amount] => (Some(*source), *cc, *destination, *amount, None),
+            [cc, destination, amount] => (None, *cc, *destination, *amount, None),
+            _ => continue,
+        };
+        let (Ok(cc), Ok(amount)) = (cc.parse::<u8>(), amount.parse::<f32>()) else {
+            continue;
+        };
+        let mut route = ModRoute::default();
+        if let Some(source) = source {
+            parse_enum(source, &ModSource::ALL, &mut route.source);
+        }
+        route.cc = cc & 0x7F;
+        parse_enum(destination, &ModDestination::ALL, &mut route.destination);
+        route.amount = amount.clamp(-1.0, 1.0);
+        if let Some(curve) = curve {
+            parse_enum(curve, &ModCurve::ALL, &mut route.curve);
+        }
+        matrix.add_route(route);
+    }
+}
+
+// Ranuras del rack separadas por comas, cada una como efecto:bypass:mezcla; las de
+// efectos desconocidos se descartan. Los presets anteriores no guardan la mezcla.
+fn parse_fx_slots(text: &str, fx: &mut FxSettings) {
+    fx.slots.clear();
+    for item in text.split(',') {
+        let mut fields = item.split(':').map(str::trim);
+        let effect = fields.next()
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| EffectType::ALL.get(index).copied());
+        if let Some(effect) = effect {
+            let bypass = fields.next().and_then(|bypass| bypass.parse().ok()).unwrap_or(false);
+            let mix = fields.next().and_then(|mix| mix.parse::<f32>().ok()).unwrap_or(1.0);
+            if fx.add_slot(effect) {
+                let slot = fx.slots.last_mut().unwrap();
+                slot.bypass = bypass;
+                slot.mix = mix.clamp(0.0, 1.0);
+            }
+        }
+    }
+}
+
+// Los enums se guardan por su índice dentro de la lista de variantes
+pub(crate) fn parse_enum<T: Copy>(text: &str, variants: &[T], target: &mut T) {
+    if let Some(variant) = text.parse::<usize>().ok().and_then(|i| variants.get(i)) {
+        *target = *variant;
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::midi::velocity::{VelocityCurve, VelocityResponse, VelocityTrim, MIDI_CHANNELS};
+use super::preset::{parse_enum, parse_into};
+
+// Archivo con los ajustes de la sesión del modo standalone, ajenos al sonido
+pub const SESSION_FILE: &str = "session.txt";
+
+// Ajustes que dependen del equipo conectado y no del preset
+#[derive(Clone, PartialEq, Default)]
+pub struct Session {
+    pub velocity_trims: [VelocityTrim; MIDI_CHANNELS],
+    pub velocity_response: VelocityResponse,
+    // Host, dispositivo y entradas MIDI por nombre: los índices cambian al conectar
+    // o desconectar equipos
+    pub audio_host: Option<String>,
+    pub audio_device: Option<String>,
+    pub sample_rate: Option<u32>,
+    // Frames por buffer; sin valor se usa el tamaño por defecto
+    pub buffer_size: Option<u32>,
+    pub midi_inputs: Vec<String>,
+    pub volume: Option<f32>,
+    // Segundos en sustain antes de liberar una voz sin tecla pulsada (0 = nunca)
+    pub stale_voice_timeout: Option<f32>,
+    // Último preset cargado o guardado, que se vuelve a cargar al arrancar
+    pub last_preset: Option<String>,
+}
+
+impl Session {
+    // Mismo formato que los presets, con canales numerados desde 1: ch10_velocity_gain...
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        // Los ajustes sin valor no se escriben
+        let optional = [
+            ("audio_host", self.audio_host.clone()),
+            ("audio_device", self.audio_device.clone()),
+            ("sample_rate", self.sample_rate.map(|rate| rate.to_string())),
+            ("buffer_size", self.buffer_size.map(|size| size.to_string())),
+            ("volume", self.volume.map(|volume| volume.to_string())),
+            ("stale_voice_timeout", self.stale_voice_timeout.map(|timeout| timeout.to_string())),
+            ("last_preset", self.last_preset.clone()),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                text.push_str(&format!("{}={}\n", key, value));
+            }
+        }
+        // Una línea por entrada MIDI, porque los nombres pueden llevar comas
+        for name in &self.midi_inputs {
+            text.push_str(&format!("midi_input={}\n", name));
+        }
+        text.push_str(&format!("velocity_curve={}\n", self.velocity_response.curve as u8));
+        text.push_str(&format!("velocity_sensitivity={}\n", self.velocity_response.sensitivity));
+        for (i, trim) in self.velocity_trims.iter().enumerate() {
+            let n = i + 1;
+            text.push_str(&format!("ch{}_velocity_gain={}\n", n, trim.gain));
+            text.push_str(&format!("ch{}_velocity_offset={}\n", n, trim.offset));
+            text.push_str(&format!("ch{}_velocity_min={}\n", n, trim.min));
+            text.push_str(&format!("ch{}_velocity_max={}\n", n, trim.max));
+            // Sin la clave la velocidad no es fija
+            if let Some(fixed) = trim.fixed {
+                text.push_str(&format!("ch{}_velocity_fixed={}\n", n, fixed));
+            }
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        let mut session = Session::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key.trim() {
+                "audio_host" => session.audio_host = Some(value.trim().to_string()),
+                "audio_device" => session.audio_device = Some(value.trim().to_string()),
+                "sample_rate" => session.sample_rate = value.trim().parse().ok(),
+                "buffer_size" => session.buffer_size = value.trim().parse().ok(),
+                "midi_input" => session.midi_inputs.push(value.trim().to_string()),
+                "volume" => session.volume = value.trim().parse().ok(),
+                "stale_voice_timeout" => session.stale_voice_timeout = value.trim().parse().ok(),
+                "last_preset" => session.last_preset = Some(value.trim().to_string()),
+                "velocity_curve" => parse_enum(value.trim(), &VelocityCurve::ALL, &mut session.velocity_response.curve),
+                "velocity_sensitivity" => parse_into(value.trim(), &mut session.velocity_response.sensitivity),
+                _ => (),
+            }
+            let Some((number, field)) = key.trim().strip_prefix("ch").and_then(|rest| rest.split_once('_')) else {
+                continue;
+            };
+            let Some(trim) = number
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| session.velocity_trims.get_mut(i))
+            else {
+                continue;
+            };
+            match field {
+                "velocity_gain" => parse_into(value.trim(), &mut trim.gain),
+                "velocity_offset" => parse_into(value.trim(), &mut trim.offset),
+                "velocity_min" => parse_into(value.trim(), &mut trim.min),
+                "velocity_max" => parse_into(value.trim(), &mut trim.max),
+                "velocity_fixed" => trim.fixed = value.trim().parse().ok(),
+                _ => (),
+            }
+        }
+        session
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    // Sin archivo se empieza con los ajustes por defecto
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .map(|text| Session::from_text(&text))
+            .unwrap_or_default()
+    }
+}
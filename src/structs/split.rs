@@ -0,0 +1,64 @@
+use crate::dsp::{OscillatorSettings, WaveType, MAX_OSCILLATORS};
+use crate::structs::envelope::AdsrSettings;
+
+// Teclado dividido: las notas por debajo del punto de división suenan con su propia
+// forma de onda, envolvente y volumen (p. ej. un bajo), y las demás con el sonido
+// principal del preset (p. ej. un solo), cada zona con su volumen.
+#[derive(Clone, Copy, PartialEq)]
+pub struct SplitSettings {
+    pub enabled: bool,
+    // Primera nota de la zona aguda
+    pub split_note: u8,
+    // Forma de onda de todos los osciladores en la zona grave
+    pub lower_wave_type: WaveType,
+    pub lower_envelope: AdsrSettings,
+    pub lower_volume: f32,
+    pub upper_volume: f32,
+}
+
+impl Default for SplitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            split_note: 60,
+            lower_wave_type: WaveType::Sawtooth,
+            lower_envelope: AdsrSettings::default(),
+            lower_volume: 1.0,
+            upper_volume: 1.0,
+        }
+    }
+}
+
+impl SplitSettings {
+    pub fn is_lower(&self, key: u8) -> bool {
+        self.enabled && key < self.split_note
+    }
+
+    // Ajustes de los osciladores de una voz nueva o ya sonando en la tecla `key`
+    pub fn oscillators(&self, key: u8, settings: &[OscillatorSettings; MAX_OSCILLATORS]) -> [OscillatorSettings; MAX_OSCILLATORS] {
+        let mut settings = *settings;
+        if self.is_lower(key) {
+            for osc in settings.iter_mut() {
+                osc.wave_type = self.lower_wave_type;
+            }
+        }
+        settings
+    }
+
+    pub fn envelope(&self, key: u8, envelope: &AdsrSettings) -> AdsrSettings {
+        if self.is_lower(key) {
+            self.lower_envelope
+        } else {
+            *envelope
+        }
+    }
+
+    // Ganancia de la zona de la tecla; sin división, 1.0
+    pub fn volume(&self, key: u8) -> f32 {
+        match (self.enabled, self.is_lower(key)) {
+            (false, _) => 1.0,
+            (true, true) => self.lower_volume,
+            (true, false) => self.upper_volume,
+        }
+    }
+}